@@ -0,0 +1,61 @@
+//! Interpreter throughput: `cargo bench`, reported in instructions per second.
+
+use std::path::PathBuf;
+
+use chip_8_emulator::emulator::Emulator;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+/// Instructions run per iteration.
+const CYCLES: u64 = 100_000;
+
+/// ROMs from `resources/roms` that keep running without input.
+const ROMS: [&str; 3] = [
+    "IBM Logo.ch8",
+    "Trip8 Demo (2008) [Revival Studios].ch8",
+    "br8kout.ch8",
+];
+
+/// Arithmetic and memory without drawing: V0 += 1, V1 += V0, V2 ^= V1, I = 0x300,
+/// FX55/FX65 with V0..V2, then loops.
+const ALU_LOOP: [u16; 7] = [0x7001, 0x8104, 0x8213, 0xA300, 0xF255, 0xF265, 0x1200];
+
+fn rom(name: &str) -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("resources")
+        .join("roms")
+        .join(name);
+    std::fs::read(&path).unwrap_or_else(|err| panic!("Failed to read {path:?}: {err}"))
+}
+
+fn load(name: &str, data: &[u8]) -> Emulator {
+    let mut emulator = Emulator::with_seed(0);
+    emulator.load_font();
+    emulator.load_rom_data(name, data).unwrap();
+    emulator
+}
+
+fn interpreter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("interpreter");
+    group.throughput(Throughput::Elements(CYCLES));
+    let alu: Vec<u8> = ALU_LOOP
+        .iter()
+        .flat_map(|inst| inst.to_be_bytes())
+        .collect();
+    let roms = ROMS
+        .iter()
+        .map(|name| (*name, rom(name)))
+        .chain([("ALU loop", alu)]);
+    for (name, data) in roms {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &data, |b, data| {
+            b.iter_batched(
+                || load("bench.ch8", data),
+                |mut emulator| emulator.run_until(|_| false, CYCLES),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, interpreter);
+criterion_main!(benches);
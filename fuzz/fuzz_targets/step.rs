@@ -0,0 +1,46 @@
+//! Runs the input as a ROM, with the first byte picking the quirks, and checks the CPU
+//! either keeps going or halts cleanly on every instruction.
+
+#![no_main]
+
+use chip_8_emulator::{
+    emulator::{Emulator, STACK_SIZE},
+    error::EmulatorError,
+    quirks::{QuirkPreset, RngMode},
+};
+use libfuzzer_sys::fuzz_target;
+
+const STEPS: usize = 10_000;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&setup, rom)) = data.split_first() else {
+        return;
+    };
+    let mut emulator = Emulator::with_seed(0);
+    QuirkPreset::ALL[setup as usize % QuirkPreset::ALL.len()].apply(&mut emulator);
+    emulator.rng_mode = RngMode::ALL[(setup >> 4) as usize % RngMode::ALL.len()];
+    emulator.load_font();
+    if emulator.load_rom_data("fuzz.ch8", rom).is_err() {
+        return;
+    }
+
+    for step in 0..STEPS {
+        // Keys follow the step count, so FX0A and the skips see presses and releases.
+        if step % 64 == 0 {
+            let key = (step / 64 % 16) as u8;
+            emulator.press_key(key);
+            emulator.release_key(key.wrapping_sub(1) & 0xF);
+        }
+        let pc = emulator.pc();
+        emulator.step_instruction();
+        assert!(emulator.stack().len() <= STACK_SIZE);
+        if let Some(halt) = emulator.halted() {
+            assert!(!matches!(
+                halt.error,
+                EmulatorError::IoError(_) | EmulatorError::RomTooLarge { .. }
+            ));
+            assert_eq!(emulator.pc(), pc);
+            return;
+        }
+    }
+});
@@ -0,0 +1,44 @@
+// Phosphor pass: decodes the packed display and ages the pixels that are off, one drawn
+// frame at a time, into the texture shader.wgsl samples. See DisplayRenderer::update.
+
+struct Frame {
+    // x, y: display resolution in CHIP-8 pixels.
+    size: vec4<u32>,
+    // Pixels two bits each, 16 to a word starting from the lowest bits, row by row.
+    pixels: array<vec4<u32>, 128>,
+};
+
+// Last pass's output, at high resolution like the target.
+@group(0) @binding(0)
+var previous: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> frame: Frame;
+
+// One triangle covering the whole target.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn pixel(x: u32, y: u32) -> u32 {
+    let index = y * frame.size.x + x;
+    let word = frame.pixels[index / 64u][(index / 16u) % 4u];
+    return (word >> ((index % 16u) * 2u)) & 3u;
+}
+
+// Red holds the pixel value the texel was last lit with, out of 255, and green the drawn
+// frames since, up to 255. Low resolution pixels cover two by two texels, so switching
+// resolution fades the old picture out too.
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = vec2<u32>(position.xy);
+    let scale = textureDimensions(previous).x / i32(frame.size.x);
+    let value = pixel(texel.x / u32(scale), texel.y / u32(scale));
+    if (value != 0u) {
+        return vec4<f32>(f32(value) / 255.0, 0.0, 0.0, 1.0);
+    }
+    let last = textureLoad(previous, vec2<i32>(texel), 0);
+    let age = min(round(last.g * 255.0) + 1.0, 255.0);
+    return vec4<f32>(last.r, age / 255.0, 0.0, 1.0);
+}
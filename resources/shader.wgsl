@@ -21,12 +21,74 @@ fn vs_main(
 
 // Fragment shader
 
+struct Effects {
+    // Strength of the scanlines, curvature, vignette and glow, 0 turns each off.
+    strength: vec4<f32>,
+    // x, y: display resolution in CHIP-8 pixels. z: 1 when post-processing is on.
+    // w: 1 when the texture holds MegaChip8 colors instead of CHIP-8 pixels.
+    display: vec4<f32>,
+    // x: drawn frames pixels take to fade out after turning off, 0 at once.
+    fade: vec4<f32>,
+    // Color of each pixel value, the background first.
+    palette: array<vec4<f32>, 4>,
+};
+
 @group(0) @binding(0)
 var t_diffuse: texture_2d<f32>;
 @group(0)@binding(1)
 var s_diffuse: sampler;
+@group(0) @binding(2)
+var<uniform> effects: Effects;
+
+// CHIP-8 texels hold the pixel value they were lit with in red, out of 255, and the drawn
+// frames since in green, see phosphor.wgsl. The color fades from that value's into the
+// background.
+fn sample(uvs: vec2<f32>) -> vec3<f32> {
+    let texel = textureSampleLevel(t_diffuse, s_diffuse, uvs, 0.0);
+    if (effects.display.w != 0.0) {
+        return texel.rgb;
+    }
+    let lit = effects.palette[u32(round(texel.r * 255.0)) & 3u].rgb;
+    let age = round(texel.g * 255.0);
+    let intensity = clamp(1.0 - age / max(effects.fade.x, 1.0), 0.0, 1.0);
+    return mix(effects.palette[0].rgb, lit, intensity);
+}
+
+// Bends the picture like a CRT tube, texture coordinates outside 0..1 are off the tube.
+fn curve(uvs: vec2<f32>, amount: f32) -> vec2<f32> {
+    let centered = uvs * 2.0 - 1.0;
+    let bent = centered * (1.0 + amount * centered.yx * centered.yx);
+    return bent * 0.5 + 0.5;
+}
+
+// Average of the pixels around, lit pixels bleed light into their neighbours.
+fn glow(uvs: vec2<f32>) -> vec3<f32> {
+    let pixel = 1.0 / effects.display.xy;
+    var sum = vec3<f32>(0.0);
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            sum += sample(uvs + vec2<f32>(f32(x), f32(y)) * pixel);
+        }
+    }
+    return sum / 9.0;
+}
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.uvs);
+    if (effects.display.z == 0.0) {
+        return vec4<f32>(sample(in.uvs), 1.0);
+    }
+
+    let uvs = curve(in.uvs, effects.strength.y);
+    var color = sample(uvs) + glow(uvs) * effects.strength.w;
+
+    // Dark gaps between the rows of pixels.
+    let row = fract(uvs.y * effects.display.y);
+    color *= mix(1.0, sin(row * 3.14159265), effects.strength.x);
+
+    let centered = uvs * 2.0 - 1.0;
+    color *= 1.0 - effects.strength.z * dot(centered, centered) * 0.5;
+
+    let inside = all(uvs >= vec2<f32>(0.0)) && all(uvs <= vec2<f32>(1.0));
+    return vec4<f32>(select(vec3<f32>(0.0), color, inside), 1.0);
 }
\ No newline at end of file
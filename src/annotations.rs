@@ -0,0 +1,346 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use imgui::Ui;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    rom::RomInfo,
+    symbols::{self, SymbolFormat},
+};
+
+/// Labels and comments attached to addresses of one ROM, stored by ROM hash so they
+/// come back automatically whenever the same ROM is loaded. They live in
+/// [`crate::resources::ResourceLocator::annotations`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Annotations {
+    #[serde(skip)]
+    pub rom_hash: Option<String>,
+    pub labels: BTreeMap<u16, String>,
+    pub comments: BTreeMap<u16, String>,
+    #[serde(default)]
+    pub bookmarks: BTreeMap<u16, String>,
+}
+
+impl Annotations {
+    fn path(dir: &Path, rom_hash: &str) -> PathBuf {
+        dir.join(format!("{rom_hash}.json"))
+    }
+
+    /// Loads the annotations for a ROM from `dir`, or an empty set if none were saved yet.
+    pub fn load(dir: &Path, rom_hash: &str) -> io::Result<Self> {
+        let mut annotations: Annotations = match fs::read_to_string(Self::path(dir, rom_hash)) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Annotations::default(),
+            Err(err) => return Err(err),
+        };
+        annotations.rom_hash = Some(rom_hash.to_string());
+        Ok(annotations)
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let Some(rom_hash) = &self.rom_hash else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)?;
+        fs::write(
+            Self::path(dir, rom_hash),
+            serde_json::to_string_pretty(self)?,
+        )
+    }
+
+    /// Makes sure the loaded annotations belong to the given ROM, switching if needed.
+    pub fn sync_rom(&mut self, dir: &Path, rom_hash: Option<&str>) {
+        if self.rom_hash.as_deref() == rom_hash {
+            return;
+        }
+        *self = match rom_hash {
+            Some(hash) => Self::load(dir, hash).unwrap_or_else(|err| {
+                log::error!("Failed to load annotations for {hash}: {err}");
+                Annotations {
+                    rom_hash: Some(hash.to_string()),
+                    ..Default::default()
+                }
+            }),
+            None => Annotations::default(),
+        };
+    }
+
+    pub fn label(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    /// `0x2A4 draw_player`, or just the address when it has no label.
+    pub fn address_name(&self, address: u16) -> String {
+        match self.label(address) {
+            Some(label) => format!("0x{address:03X} {label}"),
+            None => format!("0x{address:03X}"),
+        }
+    }
+
+    pub fn comment(&self, address: u16) -> Option<&str> {
+        self.comments.get(&address).map(String::as_str)
+    }
+
+    /// Sets or, when empty, removes the label at an address.
+    pub fn set_label(&mut self, address: u16, label: String) {
+        set_or_remove(&mut self.labels, address, label);
+    }
+
+    /// Adds labels from a symbol file. With `replace` they overwrite labels already set,
+    /// otherwise they only fill in unlabeled addresses. Returns how many were added.
+    pub fn import_labels(&mut self, labels: BTreeMap<u16, String>, replace: bool) -> usize {
+        let mut added = 0;
+        for (address, label) in labels {
+            if replace || !self.labels.contains_key(&address) {
+                self.labels.insert(address, label);
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Reads a symbol file into the labels, see [`Annotations::import_labels`].
+    pub fn import_symbols(&mut self, path: impl AsRef<Path>, replace: bool) -> io::Result<usize> {
+        let text = fs::read_to_string(path)?;
+        let labels =
+            symbols::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(self.import_labels(labels, replace))
+    }
+
+    /// Writes the labels as a symbol file, in Octo order for `.sym` files.
+    pub fn export_symbols(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        fs::write(
+            path,
+            symbols::write(&self.labels, SymbolFormat::for_path(path)),
+        )
+    }
+
+    /// Adds a named bookmark, replacing any bookmark already at the address.
+    pub fn add_bookmark(&mut self, address: u16, name: String) {
+        self.bookmarks.insert(address, name);
+    }
+
+    pub fn remove_bookmark(&mut self, address: u16) {
+        self.bookmarks.remove(&address);
+    }
+
+    /// Sets or, when empty, removes the comment at an address.
+    pub fn set_comment(&mut self, address: u16, comment: String) {
+        set_or_remove(&mut self.comments, address, comment);
+    }
+}
+
+fn set_or_remove(map: &mut BTreeMap<u16, String>, address: u16, value: String) {
+    if value.trim().is_empty() {
+        map.remove(&address);
+    } else {
+        map.insert(address, value);
+    }
+}
+
+/// Selection and edit buffers for annotating the address picked in the memory view.
+pub struct AnnotationEditor {
+    pub annotations: Annotations,
+    /// Where annotations are loaded from and saved to.
+    dir: PathBuf,
+    selected: Option<u16>,
+    label: String,
+    comment: String,
+    bookmark_name: String,
+    scroll_to: Option<u16>,
+    symbols_path: String,
+    symbols_status: String,
+}
+
+impl AnnotationEditor {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            annotations: Annotations::default(),
+            dir: dir.into(),
+            selected: None,
+            label: String::new(),
+            comment: String::new(),
+            bookmark_name: String::new(),
+            scroll_to: None,
+            symbols_path: String::new(),
+            symbols_status: String::new(),
+        }
+    }
+
+    /// Switches to the annotations of the loaded ROM. Labels from a symbol file next to it,
+    /// see [`symbols::sidecar`], fill in the addresses that have none yet, so renames made
+    /// in the debugger stick.
+    pub fn sync_rom(&mut self, rom: Option<&RomInfo>) {
+        let rom_hash = rom.map(|rom| rom.hash.as_str());
+        if self.annotations.rom_hash.as_deref() == rom_hash {
+            return;
+        }
+        self.annotations.sync_rom(&self.dir, rom_hash);
+        self.selected = None;
+        let Some(rom) = rom else {
+            return;
+        };
+        let sidecar = symbols::sidecar(&rom.path);
+        self.symbols_path = sidecar
+            .as_deref()
+            .unwrap_or(&rom.path.with_extension(symbols::SYMBOL_EXTENSION))
+            .display()
+            .to_string();
+        if let Some(path) = sidecar {
+            self.symbols_status = match self.annotations.import_symbols(&path, false) {
+                Ok(added) => format!("Loaded {added} labels from {}.", path.display()),
+                Err(err) => format!("Failed to load {}: {err}", path.display()),
+            };
+        } else {
+            self.symbols_status.clear();
+        }
+    }
+
+    pub fn selected(&self) -> Option<u16> {
+        self.selected
+    }
+
+    pub fn select(&mut self, address: u16) {
+        self.selected = Some(address);
+        self.label = self
+            .annotations
+            .label(address)
+            .unwrap_or_default()
+            .to_string();
+        self.comment = self
+            .annotations
+            .comment(address)
+            .unwrap_or_default()
+            .to_string();
+    }
+
+    /// Address the listing should scroll to this frame, set when jumping to a bookmark.
+    pub fn take_scroll_to(&mut self) -> Option<u16> {
+        self.scroll_to.take()
+    }
+
+    /// Text shown next to an address in listings, e.g. `main: clears the screen`.
+    pub fn summary(&self, address: u16) -> Option<String> {
+        match (
+            self.annotations.label(address),
+            self.annotations.comment(address),
+        ) {
+            (Some(label), Some(comment)) => Some(format!("{label}: {comment}")),
+            (Some(label), None) => Some(format!("{label}:")),
+            (None, Some(comment)) => Some(format!("; {comment}")),
+            (None, None) => None,
+        }
+    }
+
+    /// Draws the label and comment fields for the selected address, saving on every edit.
+    pub fn draw(&mut self, ui: &Ui) {
+        let Some(address) = self.selected else {
+            ui.text_disabled("Click an address to annotate it.");
+            return;
+        };
+        if self.annotations.rom_hash.is_none() {
+            ui.text_disabled("Load a ROM to annotate it.");
+            return;
+        }
+        ui.text(format!("0x{address:03X}"));
+        ui.same_line();
+        ui.set_next_item_width(100.0);
+        let mut changed = ui.input_text("Label", &mut self.label).build();
+        ui.same_line();
+        changed |= ui.input_text("Comment", &mut self.comment).build();
+        if changed {
+            self.annotations.set_label(address, self.label.clone());
+            self.annotations.set_comment(address, self.comment.clone());
+            self.save();
+        }
+    }
+
+    /// Draws the bookmark list with jump and remove buttons, plus a field to bookmark the
+    /// selected address.
+    pub fn draw_bookmarks(&mut self, ui: &Ui) {
+        if self.annotations.rom_hash.is_none() {
+            return;
+        }
+        if let Some(address) = self.selected {
+            ui.set_next_item_width(100.0);
+            ui.input_text("##bookmark_name", &mut self.bookmark_name)
+                .hint(&self.label)
+                .build();
+            ui.same_line();
+            if ui.button(format!("Bookmark 0x{address:03X}")) {
+                let name = match self.bookmark_name.trim() {
+                    "" if !self.label.trim().is_empty() => self.label.clone(),
+                    "" => format!("0x{address:03X}"),
+                    name => name.to_string(),
+                };
+                self.annotations.add_bookmark(address, name);
+                self.bookmark_name.clear();
+                self.save();
+            }
+        }
+        let mut removed = None;
+        for (address, name) in &self.annotations.bookmarks {
+            let _id = ui.push_id_int(*address as i32);
+            if ui.small_button("x") {
+                removed = Some(*address);
+            }
+            ui.same_line();
+            if ui.small_button(format!("0x{address:03X}  {name}")) {
+                self.scroll_to = Some(*address);
+            }
+        }
+        if let Some(address) = self.scroll_to {
+            self.select(address);
+        }
+        if let Some(address) = removed {
+            self.annotations.remove_bookmark(address);
+            self.save();
+        }
+    }
+
+    /// Draws the symbol file path with buttons to import labels from it, replacing the
+    /// current ones, and to export the labels to it.
+    pub fn draw_symbols(&mut self, ui: &Ui) {
+        if self.annotations.rom_hash.is_none() {
+            return;
+        }
+        ui.input_text("##symbols_path", &mut self.symbols_path)
+            .hint("game.sym")
+            .build();
+        ui.same_line();
+        if ui.button("Import") {
+            self.symbols_status = match self.annotations.import_symbols(&self.symbols_path, true) {
+                Ok(added) => {
+                    self.save();
+                    format!("Imported {added} labels.")
+                }
+                Err(err) => format!("Import failed: {err}"),
+            };
+        }
+        ui.same_line();
+        if ui.button("Export") {
+            self.symbols_status = match self.annotations.export_symbols(&self.symbols_path) {
+                Ok(()) => format!(
+                    "Exported {} labels to {}.",
+                    self.annotations.labels.len(),
+                    self.symbols_path
+                ),
+                Err(err) => format!("Export failed: {err}"),
+            };
+        }
+        if !self.symbols_status.is_empty() {
+            ui.text_wrapped(&self.symbols_status);
+        }
+    }
+
+    fn save(&self) {
+        if let Err(err) = self.annotations.save(&self.dir) {
+            log::error!("Failed to save annotations: {err}");
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use imgui::Ui;
+use rodio::{OutputStream, Sink, Source};
+
+pub use crate::buzzer::{AudioSettings, Waveform};
+use crate::buzzer::{BuzzerQueue, Synth, SAMPLE_RATE};
+
+impl Source for Synth {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Plays the CHIP-8 buzzer on the default output device, from the ticks the emulator
+/// queues in [`Beeper::queue`].
+pub struct Beeper {
+    pub settings: AudioSettings,
+    /// Settings the audio thread reads, updated from `settings` when they're drawn.
+    shared: Arc<Mutex<AudioSettings>>,
+    queue: BuzzerQueue,
+    _sink: Sink,
+    // Dropping the stream stops all playback, it must live as long as the sink.
+    _stream: OutputStream,
+}
+
+impl Beeper {
+    /// Opens the default output device, fails if the system has no audio output.
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let (stream, handle) = OutputStream::try_default()?;
+        let settings = AudioSettings::default();
+        let shared = Arc::new(Mutex::new(settings));
+        let queue = BuzzerQueue::default();
+        let sink = Sink::try_new(&handle)?;
+        sink.append(Synth::new(queue.clone(), shared.clone()));
+        Ok(Self {
+            settings,
+            shared,
+            queue,
+            _sink: sink,
+            _stream: stream,
+        })
+    }
+
+    /// Replaces the settings, e.g. with the ones saved in [`crate::config::Config`].
+    pub fn set_settings(&mut self, settings: AudioSettings) {
+        self.settings = settings;
+        *self.shared.lock().expect("Audio thread panicked.") = settings;
+    }
+
+    /// Queue to install as [`crate::emulator::Emulator::buzzer`].
+    pub fn queue(&self) -> BuzzerQueue {
+        self.queue.clone()
+    }
+
+    /// Adds the sound settings to the "Emulator" window.
+    pub fn draw_settings(&mut self, ui: &Ui) {
+        ui.window("Emulator").build(|| {
+            if !ui.collapsing_header("Sound", imgui::TreeNodeFlags::empty()) {
+                return;
+            }
+            if let Some(_combo) = ui.begin_combo("Waveform", self.settings.waveform.name()) {
+                for waveform in Waveform::ALL {
+                    if ui.selectable(waveform.name()) {
+                        self.settings.waveform = waveform;
+                    }
+                }
+            }
+            ui.slider("Frequency (Hz)", 50.0, 2000.0, &mut self.settings.frequency);
+            ui.slider("Volume", 0.0, 1.0, &mut self.settings.volume);
+        });
+        *self.shared.lock().expect("Audio thread panicked.") = self.settings;
+    }
+}
@@ -0,0 +1,217 @@
+//! Terminal frontend: runs a ROM in the terminal, drawing the display with half-block
+//! characters. Needs nothing but a terminal, so it works over SSH and on servers, and
+//! only uses the emulation core.
+
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use chip_8_emulator::{
+    compat::{CompatReport, CompatWarning},
+    emulator::Emulator,
+    metadata::RomDatabase,
+    terminal,
+};
+use clap::Parser;
+use ratatui::{
+    crossterm::{
+        event::{
+            self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+            PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+        },
+        execute, terminal as crossterm_terminal,
+    },
+    layout::{Constraint, Layout},
+    text::Line,
+    widgets::{Block, Paragraph},
+    DefaultTerminal, Frame,
+};
+use web_time::Instant;
+
+/// How often the screen is redrawn.
+const FRAME: Duration = Duration::from_micros(16_667);
+/// Most terminals only report presses, so a key counts as held for this long after each
+/// press or key repeat. Unused when the terminal reports releases.
+const KEY_HOLD: Duration = Duration::from_millis(200);
+
+/// CHIP-8 emulator in the terminal. Keypad on 1234/QWER/ASDF/ZXCV, Esc quits and F5
+/// restarts the ROM.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// ROM to run.
+    rom: PathBuf,
+    /// Keep the current quirks even if the compatibility database says the ROM needs
+    /// others.
+    #[arg(long)]
+    keep_quirks: bool,
+}
+
+/// Keypad keys pressed on a terminal that doesn't report releases, with when each one
+/// should be let go.
+#[derive(Default)]
+struct HeldKeys {
+    release_at: [Option<Instant>; 16],
+}
+
+impl HeldKeys {
+    fn press(&mut self, emulator: &mut Emulator, key: u8, now: Instant) {
+        emulator.press_key(key);
+        self.release_at[key as usize] = Some(now + KEY_HOLD);
+    }
+
+    fn release_expired(&mut self, emulator: &mut Emulator, now: Instant) {
+        for (key, release_at) in self.release_at.iter_mut().enumerate() {
+            if release_at.is_some_and(|at| at <= now) {
+                *release_at = None;
+                emulator.release_key(key as u8);
+            }
+        }
+    }
+}
+
+struct App {
+    emulator: Emulator,
+    title: String,
+    /// Whether key releases are reported, see [`KEY_HOLD`].
+    releases: bool,
+    held: HeldKeys,
+    buzzing: bool,
+    /// Shown in the status line until the next restart.
+    error: Option<String>,
+    quit: bool,
+}
+
+impl App {
+    fn handle_key(&mut self, key: KeyEvent, now: Instant) {
+        let ctrl_c =
+            key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc if key.kind == KeyEventKind::Press => self.quit = true,
+            _ if ctrl_c => self.quit = true,
+            KeyCode::F(5) if key.kind == KeyEventKind::Press => {
+                self.held = HeldKeys::default();
+                self.error = self
+                    .emulator
+                    .reload_rom()
+                    .err()
+                    .map(|err| format!("Failed to reload the ROM: {err}"));
+            }
+            KeyCode::Char(c) => {
+                let Some(value) = terminal::keypad_key(c) else {
+                    return;
+                };
+                match key.kind {
+                    KeyEventKind::Release => self.emulator.release_key(value),
+                    _ if self.releases => self.emulator.press_key(value),
+                    _ => self.held.press(&mut self.emulator, value, now),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn update(&mut self, elapsed: Duration, now: Instant) {
+        self.held.release_expired(&mut self.emulator, now);
+        self.emulator.run_for(elapsed);
+
+        // The terminal bell is the only sound there is, rung when the buzzer starts.
+        let buzzing = self.emulator.sound_active();
+        if buzzing && !self.buzzing {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(b"\x07").and_then(|()| stdout.flush());
+        }
+        self.buzzing = buzzing;
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [screen, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let lines: Vec<Line> = terminal::half_blocks(&self.emulator.display)
+            .into_iter()
+            .map(Line::from)
+            .collect();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(self.title.as_str())),
+            screen,
+        );
+        let status_text = match (&self.error, self.emulator.halted()) {
+            (Some(error), _) => error.clone(),
+            (None, Some(halt)) => halt.to_string(),
+            (None, None) => String::from("Esc: quit  F5: restart"),
+        };
+        frame.render_widget(Paragraph::new(status_text), status);
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let data = match std::fs::read(&cli.rom) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("{}: {err}", cli.rom.display());
+            std::process::exit(1);
+        }
+    };
+
+    let mut emulator = Emulator::new();
+    let report = CompatReport::check(&cli.rom, &data, &emulator, &RomDatabase::bundled());
+    for warning in &report.warnings {
+        match warning {
+            CompatWarning::Quirks { quirks, .. } if !cli.keep_quirks => {
+                emulator.quirks = *quirks;
+            }
+            _ => eprintln!("{warning}"),
+        }
+    }
+    emulator.load_font();
+    if let Err(err) = emulator.load_rom_data(&cli.rom, &data) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+
+    let mut terminal = ratatui::init();
+    let releases = crossterm_terminal::supports_keyboard_enhancement().unwrap_or(false)
+        && execute!(
+            io::stdout(),
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+        )
+        .is_ok();
+    let mut app = App {
+        emulator,
+        title: report.title(),
+        releases,
+        held: HeldKeys::default(),
+        buzzing: false,
+        error: None,
+        quit: false,
+    };
+    let result = run(&mut terminal, &mut app);
+    if releases {
+        let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+    }
+    ratatui::restore();
+    if let Err(err) = result {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    let mut last = Instant::now();
+    while !app.quit {
+        let deadline = last + FRAME;
+        while event::poll(deadline.saturating_duration_since(Instant::now()))? {
+            if let Event::Key(key) = event::read()? {
+                app.handle_key(key, Instant::now());
+            }
+        }
+        let now = Instant::now();
+        app.update(now - last, now);
+        last = now;
+        terminal.draw(|frame| app.draw(frame))?;
+    }
+    Ok(())
+}
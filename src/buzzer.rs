@@ -0,0 +1,186 @@
+//! The buzzer, driven by emulated time. Every 60 Hz timer tick the emulator queues whether
+//! the sound timer was running (see [`crate::emulator::Emulator::buzzer`]), and the audio
+//! callback turns each queued tick into its share of samples. Pausing or stepping frames
+//! plays exactly what was emulated, silence included, and fast-forward shortens the ticks
+//! without raising the pitch.
+
+use std::{
+    collections::VecDeque,
+    f32::consts::TAU,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{emulator::AudioPattern, timing::TIMER_HZ};
+
+pub const SAMPLE_RATE: u32 = 44_100;
+/// Ticks the queue holds before dropping the oldest, about 100 ms of latency.
+pub const MAX_QUEUED_TICKS: usize = 6;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Sine,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 2] = [Waveform::Square, Waveform::Sine];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Waveform::Square => "Square",
+            Waveform::Sine => "Sine",
+        }
+    }
+}
+
+/// How the buzzer sounds while the sound timer is running.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AudioSettings {
+    pub waveform: Waveform,
+    /// Tone frequency in Hz.
+    pub frequency: f32,
+    /// 0.0 (muted) to 1.0.
+    pub volume: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Square,
+            frequency: 440.0,
+            volume: 0.25,
+        }
+    }
+}
+
+/// The buzzer during one timer tick.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuzzerTick {
+    /// Whether the sound timer was running.
+    pub active: bool,
+    /// XO-CHIP pattern played instead of the tone.
+    pub pattern: Option<AudioPattern>,
+    /// Emulation speed the tick ran at, see [`crate::timing::Clock::speed`].
+    pub speed: f64,
+    /// MegaChip8 digitized sound for the tick, stretched over its length and played instead
+    /// of the buzzer, see [`crate::megachip::Sample`].
+    pub sample: Option<Arc<[f32]>>,
+}
+
+/// Ticks handed from the emulation thread to the audio callback. When the callback falls
+/// behind, the oldest ticks are dropped so latency stays bounded.
+#[derive(Clone, Debug, Default)]
+pub struct BuzzerQueue {
+    ticks: Arc<Mutex<VecDeque<BuzzerTick>>>,
+}
+
+impl BuzzerQueue {
+    pub fn push(&self, tick: BuzzerTick) {
+        let mut ticks = self.ticks.lock().expect("Audio thread panicked.");
+        if ticks.len() == MAX_QUEUED_TICKS {
+            ticks.pop_front();
+        }
+        ticks.push_back(tick);
+    }
+
+    pub fn pop(&self) -> Option<BuzzerTick> {
+        self.ticks
+            .lock()
+            .expect("Emulation thread panicked.")
+            .pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ticks.lock().expect("Emulation thread panicked.").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Endless mono samples at [`SAMPLE_RATE`] for the ticks in a [`BuzzerQueue`], silence
+/// while it's empty.
+pub struct Synth {
+    queue: BuzzerQueue,
+    settings: Arc<Mutex<AudioSettings>>,
+    /// Tick being played and the samples left of it.
+    current: Option<(BuzzerTick, AudioSettings)>,
+    remaining: usize,
+    /// Samples the current tick started with.
+    length: usize,
+    /// Fraction of a sample carried over between ticks, so ticks average out to their exact
+    /// length at any speed.
+    carry: f64,
+    /// Position within the current period, 0.0 to 1.0.
+    phase: f32,
+}
+
+impl Synth {
+    /// `settings` are read again at the start of every tick.
+    pub fn new(queue: BuzzerQueue, settings: Arc<Mutex<AudioSettings>>) -> Self {
+        Self {
+            queue,
+            settings,
+            current: None,
+            remaining: 0,
+            length: 0,
+            carry: 0.0,
+            phase: 0.0,
+        }
+    }
+
+    fn next_tick(&mut self) {
+        self.current = self.queue.pop().map(|tick| {
+            let samples = SAMPLE_RATE as f64 / TIMER_HZ / tick.speed.max(0.01) + self.carry;
+            self.remaining = samples as usize;
+            self.length = self.remaining;
+            self.carry = samples.fract();
+            let settings = *self.settings.lock().expect("UI thread panicked.");
+            (tick, settings)
+        });
+    }
+}
+
+impl Iterator for Synth {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.remaining == 0 {
+            self.next_tick();
+        }
+        let position = self.length - self.remaining;
+        // A tick shorter than a sample at high speeds still plays one.
+        self.remaining = self.remaining.saturating_sub(1);
+        let Some((tick, settings)) = &self.current else {
+            self.phase = 0.0;
+            return Some(0.0);
+        };
+        if let Some(sample) = tick.sample.as_ref().filter(|sample| !sample.is_empty()) {
+            let index = position * sample.len() / self.length.max(1);
+            return Some(sample[index.min(sample.len() - 1)] * settings.volume);
+        }
+        if !tick.active {
+            // Silence restarts the tone, like the buzzer turning on again.
+            self.phase = 0.0;
+            return Some(0.0);
+        }
+        let (value, frequency) = match &tick.pattern {
+            // A period covers the whole 128 bit pattern.
+            Some(pattern) => {
+                let bit = pattern.bit((self.phase * 128.0) as usize % 128);
+                (if bit { 1.0 } else { -1.0 }, pattern.rate() / 128.0)
+            }
+            None => match settings.waveform {
+                Waveform::Square if self.phase < 0.5 => (1.0, settings.frequency),
+                Waveform::Square => (-1.0, settings.frequency),
+                Waveform::Sine => ((self.phase * TAU).sin(), settings.frequency),
+            },
+        };
+        self.phase = (self.phase + frequency / SAMPLE_RATE as f32).fract();
+        Some(value * settings.volume)
+    }
+}
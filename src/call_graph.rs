@@ -0,0 +1,97 @@
+use std::{collections::BTreeMap, fmt::Write, fs, io, path::Path};
+
+use crate::disassembler::{disassemble_at, Instruction};
+
+/// A call on the CPU stack, rebuilt from the return address alone so it stays right after
+/// PC or the stack were edited.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StackFrame {
+    pub return_address: u16,
+    /// The instruction just before the return address, normally the 2NNN that made the call.
+    pub call_site: Instruction,
+    /// Subroutine the call went to, `None` if the call site isn't a 2NNN.
+    pub subroutine: Option<u16>,
+}
+
+/// Frames of `stack`, innermost call first.
+pub fn stack_frames(memory: &[u8], stack: &[u16]) -> Vec<StackFrame> {
+    stack
+        .iter()
+        .rev()
+        .map(|&return_address| {
+            let call_site = disassemble_at(memory, return_address.wrapping_sub(2));
+            let subroutine =
+                (call_site.opcode & 0xF000 == 0x2000).then_some(call_site.opcode & 0x0FFF);
+            StackFrame {
+                return_address,
+                call_site,
+                subroutine,
+            }
+        })
+        .collect()
+}
+
+/// Subroutine call edges observed while running, keyed by (caller, callee) entry address.
+/// The caller is the subroutine the CALL was executed from, the ROM entry point at top level.
+#[derive(Clone, Debug, Default)]
+pub struct CallGraph {
+    edges: BTreeMap<(u16, u16), u64>,
+    /// Entry addresses of the subroutines currently executing, mirrors the CPU stack.
+    frames: Vec<u16>,
+    entry: u16,
+}
+
+impl CallGraph {
+    pub fn new(entry: u16) -> Self {
+        Self {
+            entry,
+            ..Default::default()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.edges.clear();
+        self.frames.clear();
+    }
+
+    pub fn on_call(&mut self, callee: u16) {
+        *self.edges.entry((self.current(), callee)).or_default() += 1;
+        self.frames.push(callee);
+    }
+
+    pub fn on_return(&mut self) {
+        self.frames.pop();
+    }
+
+    /// Address execution started from, the root of the graph.
+    pub fn entry(&self) -> u16 {
+        self.entry
+    }
+
+    /// Entry address of the subroutine currently executing.
+    pub fn current(&self) -> u16 {
+        self.frames.last().copied().unwrap_or(self.entry)
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (u16, u16, u64)> + '_ {
+        self.edges
+            .iter()
+            .map(|((caller, callee), count)| (*caller, *callee, *count))
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph calls {\n    node [shape=box, fontname=monospace];\n");
+        for (caller, callee, count) in self.edges() {
+            let _ = writeln!(
+                dot,
+                "    \"0x{caller:03X}\" -> \"0x{callee:03X}\" [label=\"{count}\"];"
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn export_dot(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_dot())
+    }
+}
@@ -0,0 +1,324 @@
+//! Screenshots and clips of the emulated display, rendered straight from the framebuffer
+//! without the GPU post-processing.
+//!
+//! Images are always 64x32 CHIP-8 pixels times the scale, hires frames use half-size
+//! pixels, so a clip keeps one size when a game switches resolution.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, Rgba, RgbaImage,
+};
+use imgui::Ui;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    emulator::{Display, DISPLAY_SIZE},
+    palette::Palette,
+};
+
+pub const DEFAULT_CAPTURE_SCALE: u32 = 8;
+/// Scales offered in the "Capture" window. Hires needs at least 2 to keep every pixel.
+pub const CAPTURE_SCALES: std::ops::RangeInclusive<u32> = 2..=16;
+/// Recording stops by itself past this, so a forgotten recording doesn't eat all memory.
+pub const MAX_CLIP_LENGTH: Duration = Duration::from_secs(300);
+
+/// Expands the display into an image of `width` by `height` pixels, nearest neighbour.
+pub fn render(display: &Display, palette: &Palette, width: u32, height: u32) -> RgbaImage {
+    let (columns, rows) = display.size();
+    RgbaImage::from_fn(width, height, |x, y| {
+        let column = x as usize * columns / width as usize;
+        let row = y as usize * rows / height as usize;
+        Rgba(palette.color(display[column][row]))
+    })
+}
+
+/// Size of the images captured at `scale`, see the module docs.
+pub fn capture_size(scale: u32) -> (u32, u32) {
+    (DISPLAY_SIZE.0 as u32 * scale, DISPLAY_SIZE.1 as u32 * scale)
+}
+
+pub fn save_screenshot(
+    display: &Display,
+    palette: &Palette,
+    scale: u32,
+    path: impl AsRef<Path>,
+) -> io::Result<()> {
+    let (width, height) = capture_size(scale);
+    render(display, palette, width, height)
+        .save(path)
+        .map_err(io::Error::other)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClipFormat {
+    Gif,
+    /// Animated PNG, exact colors and millisecond frame timing.
+    Apng,
+}
+
+impl ClipFormat {
+    pub const ALL: [ClipFormat; 2] = [ClipFormat::Gif, ClipFormat::Apng];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ClipFormat::Gif => "GIF",
+            ClipFormat::Apng => "APNG",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ClipFormat::Gif => "gif",
+            ClipFormat::Apng => "png",
+        }
+    }
+
+    /// Format for a file name, `None` if the extension isn't one of ours.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gif" => Some(ClipFormat::Gif),
+            "png" | "apng" => Some(ClipFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// Frame of a clip and how long it stayed on screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClipFrame {
+    pub display: Display,
+    pub duration: Duration,
+}
+
+/// Recorded frames. Frames that don't change the display only make the previous one last
+/// longer, so a clip of a mostly still game stays small.
+#[derive(Clone, Debug, Default)]
+pub struct Clip {
+    frames: Vec<ClipFrame>,
+}
+
+impl Clip {
+    /// Adds a frame that was shown for `duration`.
+    pub fn push(&mut self, display: &Display, duration: Duration) {
+        match self.frames.last_mut() {
+            Some(last) if last.display == *display => last.duration += duration,
+            _ => self.frames.push(ClipFrame {
+                display: *display,
+                duration,
+            }),
+        }
+    }
+
+    pub fn frames(&self) -> &[ClipFrame] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+
+    /// How many `unit`s each frame lasts, rounded against the running total so rounding
+    /// errors don't add up over a long clip. Frames that round to nothing are left out.
+    pub fn frame_delays(&self, unit: Duration) -> Vec<(&ClipFrame, u32)> {
+        let mut delays = Vec::new();
+        let mut elapsed = Duration::ZERO;
+        let mut shown = 0;
+        for frame in &self.frames {
+            elapsed += frame.duration;
+            let end = (elapsed.as_secs_f64() / unit.as_secs_f64()).round() as u32;
+            if end > shown {
+                delays.push((frame, end - shown));
+                shown = end;
+            }
+        }
+        delays
+    }
+
+    /// Writes the clip as a GIF, or as an APNG if `path` ends in `.png` or `.apng`.
+    pub fn save(&self, path: impl AsRef<Path>, palette: &Palette, scale: u32) -> io::Result<()> {
+        let path = path.as_ref();
+        match ClipFormat::from_path(path).unwrap_or(ClipFormat::Gif) {
+            ClipFormat::Gif => self.save_gif(path, palette, scale),
+            ClipFormat::Apng => self.save_apng(path, palette, scale),
+        }
+    }
+
+    /// GIF frame delays are in hundredths of a second.
+    pub fn save_gif(
+        &self,
+        path: impl AsRef<Path>,
+        palette: &Palette,
+        scale: u32,
+    ) -> io::Result<()> {
+        let (width, height) = capture_size(scale);
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(io::Error::other)?;
+        for (frame, centis) in self.frame_delays(Duration::from_millis(10)) {
+            let image = render(&frame.display, palette, width, height);
+            let delay = Delay::from_numer_denom_ms(centis * 10, 1);
+            encoder
+                .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                .map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_apng(
+        &self,
+        path: impl AsRef<Path>,
+        palette: &Palette,
+        scale: u32,
+    ) -> io::Result<()> {
+        let (width, height) = capture_size(scale);
+        let delays = self.frame_delays(Duration::from_millis(1));
+        if delays.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Clip has no frames.",
+            ));
+        }
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(delays.len() as u32, 0)?;
+        let mut writer = encoder.write_header()?;
+        for (frame, millis) in delays {
+            writer.set_frame_delay(millis.min(u16::MAX as u32) as u16, 1000)?;
+            writer.write_image_data(&render(&frame.display, palette, width, height))?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Screenshot and recording settings, with the clip being recorded. Driven by the F10/F12
+/// hotkeys and the "Capture" window.
+pub struct Capture {
+    pub scale: u32,
+    pub clip_format: ClipFormat,
+    /// Where captures are saved, named after the ROM and the time they were taken.
+    pub directory: PathBuf,
+    recording: Option<Clip>,
+    /// Outcome of the last capture, shown in the "Capture" window.
+    pub status: String,
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Self {
+            scale: DEFAULT_CAPTURE_SCALE,
+            clip_format: ClipFormat::Gif,
+            directory: PathBuf::from("./captures"),
+            recording: None,
+            status: String::new(),
+        }
+    }
+}
+
+impl Capture {
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// The clip being recorded.
+    pub fn recording(&self) -> Option<&Clip> {
+        self.recording.as_ref()
+    }
+
+    /// Path for a new capture of `rom`, e.g. `captures/snake-1700000000123.gif`.
+    pub fn next_path(&self, rom: Option<&Path>, extension: &str) -> PathBuf {
+        let name = rom
+            .and_then(Path::file_stem)
+            .map_or_else(|| "chip8".into(), |stem| stem.to_string_lossy());
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis());
+        self.directory.join(format!("{name}-{millis}.{extension}"))
+    }
+
+    pub fn screenshot(&mut self, display: &Display, palette: &Palette, rom: Option<&Path>) {
+        let path = self.next_path(rom, "png");
+        let saved = fs::create_dir_all(&self.directory)
+            .and_then(|()| save_screenshot(display, palette, self.scale, &path));
+        self.status = match saved {
+            Ok(()) => format!("Saved {}.", path.display()),
+            Err(err) => format!("Failed to save {}: {err}", path.display()),
+        };
+    }
+
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Clip::default());
+        self.status = String::from("Recording...");
+    }
+
+    /// Adds the frame on screen to the recording, if any. Frames past [`MAX_CLIP_LENGTH`]
+    /// are dropped.
+    pub fn record(&mut self, display: &Display, frame_time: Duration) {
+        if let Some(clip) = &mut self.recording {
+            if clip.duration() < MAX_CLIP_LENGTH {
+                clip.push(display, frame_time);
+            }
+        }
+    }
+
+    /// Saves the recording, named after `rom`.
+    pub fn stop_recording(&mut self, palette: &Palette, rom: Option<&Path>) {
+        let Some(clip) = self.recording.take() else {
+            return;
+        };
+        let path = self.next_path(rom, self.clip_format.extension());
+        let saved = fs::create_dir_all(&self.directory)
+            .and_then(|()| clip.save(&path, palette, self.scale));
+        self.status = match saved {
+            Ok(()) => format!(
+                "Saved {} ({:.1} s, {} frames).",
+                path.display(),
+                clip.duration().as_secs_f64(),
+                clip.len()
+            ),
+            Err(err) => format!("Failed to save {}: {err}", path.display()),
+        };
+    }
+
+    /// Settings part of the "Capture" window, the buttons are drawn by the frontend which
+    /// has the display and palette.
+    pub fn draw_settings(&mut self, ui: &Ui) {
+        let mut scale = self.scale as i32;
+        if ui.input_int("Scale", &mut scale).build() {
+            self.scale =
+                (scale.max(0) as u32).clamp(*CAPTURE_SCALES.start(), *CAPTURE_SCALES.end());
+        }
+        let (width, height) = capture_size(self.scale);
+        ui.same_line();
+        ui.text_disabled(format!("{width}x{height}"));
+        if let Some(_combo) = ui.begin_combo("Clip format", self.clip_format.name()) {
+            for format in ClipFormat::ALL {
+                if ui.selectable(format.name()) {
+                    self.clip_format = format;
+                }
+            }
+        }
+        let mut directory = self.directory.to_string_lossy().into_owned();
+        if ui.input_text("Folder", &mut directory).build() {
+            self.directory = PathBuf::from(directory);
+        }
+    }
+}
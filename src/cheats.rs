@@ -0,0 +1,63 @@
+//! Game Genie style cheats: a byte of memory written over and over to freeze a value (lives,
+//! time left), or once to change it. The emulator applies [`Emulator::cheats`].
+//!
+//! [`Emulator::cheats`]: crate::emulator::Emulator::cheats
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheatMode {
+    /// Written after every instruction, so the game never reads another value.
+    #[default]
+    Instruction,
+    /// Written at the start of every frame, enough for most counters.
+    Frame,
+    /// Written at the start of the next frame, then disabled.
+    Once,
+}
+
+impl CheatMode {
+    pub const ALL: [CheatMode; 3] = [CheatMode::Instruction, CheatMode::Frame, CheatMode::Once];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CheatMode::Instruction => "Every instruction",
+            CheatMode::Frame => "Every frame",
+            CheatMode::Once => "Once",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cheat {
+    pub name: String,
+    pub address: u16,
+    pub value: u8,
+    pub mode: CheatMode,
+    pub enabled: bool,
+}
+
+impl Cheat {
+    /// Parses a code like `3A2 = 09`, `0x3A2:0x09` or `3A2=9`: a hex address and a hex
+    /// value separated by `=` or `:`.
+    pub fn parse_code(code: &str) -> Option<(u16, u8)> {
+        let (address, value) = code.split_once(['=', ':'])?;
+        let hex = |text: &str| {
+            let text = text.trim();
+            let digits = text
+                .strip_prefix("0x")
+                .or_else(|| text.strip_prefix("0X"))
+                .unwrap_or(text);
+            u32::from_str_radix(digits, 16).ok()
+        };
+        Some((
+            u16::try_from(hex(address)?).ok()?,
+            u8::try_from(hex(value)?).ok()?,
+        ))
+    }
+
+    /// The cheat as a code [`Cheat::parse_code`] reads back.
+    pub fn code(&self) -> String {
+        format!("{:03X} = {:02X}", self.address, self.value)
+    }
+}
@@ -0,0 +1,200 @@
+//! Running one ROM on two emulators side by side with different quirks, frame by frame
+//! with the same input, and pausing both where they first disagree. Shows which quirk a
+//! broken game depends on.
+
+use std::{fmt, io, time::Duration};
+
+use crate::{
+    emulator::Emulator,
+    error::EmulatorError,
+    quirks::Quirks,
+    timing::{MAX_ELAPSED, TIMER_HZ},
+};
+
+/// One way the two machines disagree, left value first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Difference {
+    Pc(u16, u16),
+    Index(u16, u16),
+    Register {
+        index: usize,
+        left: u8,
+        right: u8,
+    },
+    Stack(Vec<u16>, Vec<u16>),
+    /// Number of pixels that differ.
+    Display(usize),
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Pc(left, right) => write!(f, "PC 0x{left:03X} / 0x{right:03X}"),
+            Difference::Index(left, right) => write!(f, "I 0x{left:03X} / 0x{right:03X}"),
+            Difference::Register { index, left, right } => {
+                write!(f, "V{index:X} 0x{left:02X} / 0x{right:02X}")
+            }
+            Difference::Stack(left, right) => {
+                write!(f, "stack depth {} / {}", left.len(), right.len())
+            }
+            Difference::Display(pixels) => write!(f, "{pixels} pixels differ"),
+        }
+    }
+}
+
+/// Everything that differs between the registers and displays of two machines, in the
+/// order of [`Difference`]'s variants. Empty when they agree.
+pub fn differences(left: &Emulator, right: &Emulator) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    if left.pc() != right.pc() {
+        differences.push(Difference::Pc(left.pc(), right.pc()));
+    }
+    if left.reg_i() != right.reg_i() {
+        differences.push(Difference::Index(left.reg_i(), right.reg_i()));
+    }
+    for (index, (&l, &r)) in left.regs().iter().zip(right.regs()).enumerate() {
+        if l != r {
+            differences.push(Difference::Register {
+                index,
+                left: l,
+                right: r,
+            });
+        }
+    }
+    if left.stack() != right.stack() {
+        differences.push(Difference::Stack(
+            left.stack().to_vec(),
+            right.stack().to_vec(),
+        ));
+    }
+    let pixels = if left.display.size() == right.display.size() {
+        left.display
+            .iter()
+            .zip(right.display.iter())
+            .map(|(l, r)| l.iter().zip(r).filter(|(l, r)| l != r).count())
+            .sum()
+    } else {
+        // A resolution switch differs everywhere.
+        let (width, height) = left.display.size().max(right.display.size());
+        width * height
+    };
+    if pixels > 0 {
+        differences.push(Difference::Display(pixels));
+    }
+    differences
+}
+
+/// Where the machines first disagreed, see [`Comparison::divergence`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// Frames both had run when it was found.
+    pub frame: u128,
+    pub differences: Vec<Difference>,
+}
+
+/// Two emulators running the same ROM in lockstep, one frame at a time.
+pub struct Comparison {
+    pub left: Emulator,
+    pub right: Emulator,
+    pub paused: bool,
+    divergence: Option<Divergence>,
+    /// Wall time not yet spent on frames.
+    pending: Duration,
+}
+
+impl Comparison {
+    /// Loads the ROM `emulator` has loaded into two new ones with the given quirks, on the
+    /// same machine and running as many instructions per frame as it does in real time.
+    /// They share a random seed, so CXNN doesn't make them diverge by itself.
+    pub fn new(emulator: &Emulator, left: Quirks, right: Quirks) -> Result<Self, EmulatorError> {
+        let rom = emulator
+            .rom
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No ROM loaded."))?;
+        let seed = rand::random();
+        let load = |quirks: Quirks| {
+            let mut copy = Emulator::with_seed(seed);
+            emulator.profile.apply(&mut copy);
+            copy.cpf = (emulator.clock.cpu_hz / TIMER_HZ).round().max(1.0) as i32;
+            copy.load_font();
+            copy.load_rom_data(rom.path.clone(), emulator.rom_data())?;
+            // After loading, as the extension may have picked another machine.
+            copy.quirks = quirks;
+            Ok::<_, EmulatorError>(copy)
+        };
+        Ok(Self {
+            left: load(left)?,
+            right: load(right)?,
+            paused: false,
+            divergence: None,
+            pending: Duration::ZERO,
+        })
+    }
+
+    /// First point where the two disagreed, `None` while they still agree. Once found,
+    /// they aren't compared again until [`Comparison::restart`].
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+
+    /// Holds the keys held in `keypad` on both machines and releases the others.
+    pub fn set_keys(&mut self, keypad: &[bool; 16]) {
+        for emulator in [&mut self.left, &mut self.right] {
+            for (key, &down) in keypad.iter().enumerate() {
+                if emulator.is_key_down(key as u8) != down {
+                    if down {
+                        emulator.press_key(key as u8);
+                    } else {
+                        emulator.release_key(key as u8);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs as many frames as fit in `elapsed` of wall time, unless paused.
+    pub fn run_for(&mut self, elapsed: Duration) {
+        if self.paused {
+            self.pending = Duration::ZERO;
+            return;
+        }
+        let frame = Duration::from_secs_f64(1.0 / TIMER_HZ);
+        self.pending += elapsed.min(MAX_ELAPSED);
+        while self.pending >= frame && !self.paused {
+            self.pending -= frame;
+            self.step_frame();
+        }
+    }
+
+    /// Runs one frame on both, then compares them. Pauses when they diverge for the first
+    /// time or either halts.
+    pub fn step_frame(&mut self) {
+        self.left.step_frame();
+        self.right.step_frame();
+        if self.left.halted().is_some() || self.right.halted().is_some() {
+            self.paused = true;
+        }
+        if self.divergence.is_none() {
+            let differences = differences(&self.left, &self.right);
+            if !differences.is_empty() {
+                self.divergence = Some(Divergence {
+                    frame: self.left.frame_count(),
+                    differences,
+                });
+                self.paused = true;
+            }
+        }
+    }
+
+    /// Starts both over from the beginning of the ROM and looks for a divergence again.
+    pub fn restart(&mut self) -> Result<(), EmulatorError> {
+        for emulator in [&mut self.left, &mut self.right] {
+            let quirks = emulator.quirks;
+            emulator.reload_rom()?;
+            emulator.quirks = quirks;
+        }
+        self.divergence = None;
+        self.pending = Duration::ZERO;
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+//! Checks run when a ROM is opened, before it's loaded: what the compatibility database
+//! knows about it and whether the emulator is set up to run it.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    emulator::{load_offset_for, Emulator},
+    machine::MachineProfile,
+    metadata::{RomDatabase, RomMetadata},
+    octo,
+    quirks::{QuirkPreset, Quirks},
+    rom,
+};
+
+/// Something about a ROM that will keep it from running, or running right.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompatWarning {
+    /// Doesn't fit between the load offset and the profile's reserved memory, loading it
+    /// fails.
+    TooLarge {
+        size: usize,
+        max: usize,
+        profile: MachineProfile,
+    },
+    /// Written for a platform whose quirks differ from the current ones.
+    Quirks {
+        platform: String,
+        /// What the database says the ROM expects.
+        quirks: Quirks,
+    },
+}
+
+impl fmt::Display for CompatWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompatWarning::TooLarge { size, max, profile } => write!(
+                f,
+                "ROM is {size} bytes, only {max} fit in {} memory",
+                profile.name()
+            ),
+            CompatWarning::Quirks { platform, quirks } => {
+                match QuirkPreset::ALL
+                    .into_iter()
+                    .find(|preset| preset.quirks() == *quirks)
+                {
+                    Some(preset) => write!(
+                        f,
+                        "written for {platform}, which expects the {} quirks",
+                        preset.name()
+                    ),
+                    None => write!(f, "written for {platform}, which expects other quirks"),
+                }
+            }
+        }
+    }
+}
+
+/// Result of [`CompatReport::check`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompatReport {
+    pub path: PathBuf,
+    /// [`rom::hash`] of the file.
+    pub hash: String,
+    /// Database entry, `None` for ROMs it doesn't know.
+    pub metadata: Option<RomMetadata>,
+    pub warnings: Vec<CompatWarning>,
+}
+
+impl CompatReport {
+    /// Looks the ROM up and checks it against the emulator's profile and quirks, which
+    /// should already hold the settings the ROM will be opened with.
+    pub fn check(path: &Path, data: &[u8], emulator: &Emulator, database: &RomDatabase) -> Self {
+        let hash = rom::hash(data);
+        let metadata = database.get(&hash).cloned();
+        let mut warnings = Vec::new();
+
+        // Sources are assembled on load, their size says nothing.
+        if !octo::is_source(path) {
+            let profile = emulator.profile.for_rom(path);
+            let offset = load_offset_for(path).unwrap_or(emulator.load_offset);
+            let max = profile.max_rom_size(offset);
+            if data.len() > max {
+                warnings.push(CompatWarning::TooLarge {
+                    size: data.len(),
+                    max,
+                    profile,
+                });
+            }
+        }
+        if let Some(RomMetadata {
+            platform: Some(platform),
+            quirks: Some(quirks),
+            ..
+        }) = &metadata
+        {
+            if *quirks != emulator.quirks {
+                warnings.push(CompatWarning::Quirks {
+                    platform: platform.clone(),
+                    quirks: *quirks,
+                });
+            }
+        }
+        Self {
+            path: path.to_path_buf(),
+            hash,
+            metadata,
+            warnings,
+        }
+    }
+
+    /// `Title by Author` from the database, the file name for unknown ROMs.
+    pub fn title(&self) -> String {
+        let Some((title, authors)) = self
+            .metadata
+            .as_ref()
+            .and_then(|rom| Some((rom.title.as_deref()?, &rom.authors)))
+        else {
+            return self
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+        };
+        if authors.is_empty() {
+            title.to_string()
+        } else {
+            format!("{title} by {}", authors.join(", "))
+        }
+    }
+}
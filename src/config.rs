@@ -0,0 +1,128 @@
+//! Frontend settings that outlive a run: recently opened ROMs, per-ROM overrides, the
+//! window size and sound.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    buzzer::AudioSettings, cheats::Cheat, emulator::Emulator, error::UnknownOpcodePolicy,
+    frame_limiter::PresentMode, palette::Palette, quirks::Quirks,
+};
+
+/// How many ROMs the recent list keeps.
+pub const RECENT_LIMIT: usize = 10;
+
+/// Settings a ROM is launched with instead of the global ones. Fields left `None` keep
+/// whatever is active. Key bindings have their own per-ROM profiles, see
+/// `keymap::KeyProfiles`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RomSettings {
+    pub quirks: Option<Quirks>,
+    /// Instructions per second, see [`crate::timing::Clock::cpu_hz`].
+    pub cpu_hz: Option<f64>,
+    pub palette: Option<Palette>,
+}
+
+impl RomSettings {
+    /// Everything currently in effect.
+    pub fn capture(emulator: &Emulator, palette: &Palette) -> Self {
+        Self {
+            quirks: Some(emulator.quirks),
+            cpu_hz: Some(emulator.clock.cpu_hz),
+            palette: Some(*palette),
+        }
+    }
+
+    pub fn apply(&self, emulator: &mut Emulator, palette: &mut Palette) {
+        if let Some(quirks) = self.quirks {
+            emulator.quirks = quirks;
+        }
+        if let Some(cpu_hz) = self.cpu_hz {
+            emulator.clock.cpu_hz = cpu_hz;
+        }
+        if let Some(overridden) = self.palette {
+            *palette = overridden;
+        }
+    }
+}
+
+/// Contents of the config file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Most recently opened first.
+    pub recent_roms: Vec<PathBuf>,
+    /// Overrides by ROM hash, so they follow the game when the file is moved or renamed.
+    pub rom_settings: BTreeMap<String, RomSettings>,
+    /// Cheats by ROM hash, see [`crate::emulator::Emulator::cheats`].
+    pub cheats: BTreeMap<String, Vec<Cheat>>,
+    /// Present mode asked for, the window falls back to Fifo where it isn't supported.
+    pub present_mode: PresentMode,
+    /// Draw frames as fast as possible instead of at the emulator's max FPS.
+    pub uncapped: bool,
+    /// Window size in logical pixels when it was last closed outside fullscreen.
+    pub window_size: Option<(u32, u32)>,
+    pub audio: AudioSettings,
+    /// Directory the last ROM was browsed from, where the file dialog opens next time.
+    pub rom_dir: Option<PathBuf>,
+    /// See [`crate::emulator::Emulator::unknown_opcodes`].
+    pub unknown_opcodes: UnknownOpcodePolicy,
+}
+
+impl Config {
+    /// Reads the config file, a missing file gives the defaults.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Moves `path` to the top of the recent list, dropping the oldest past [`RECENT_LIMIT`].
+    pub fn add_recent(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        self.recent_roms.retain(|recent| *recent != path);
+        self.recent_roms.insert(0, path);
+        self.recent_roms.truncate(RECENT_LIMIT);
+    }
+
+    pub fn rom_settings(&self, rom_hash: &str) -> Option<&RomSettings> {
+        self.rom_settings.get(rom_hash)
+    }
+
+    /// Stores overrides for a ROM, or forgets them if they don't override anything.
+    pub fn set_rom_settings(&mut self, rom_hash: &str, settings: RomSettings) {
+        if settings == RomSettings::default() {
+            self.rom_settings.remove(rom_hash);
+        } else {
+            self.rom_settings.insert(rom_hash.to_string(), settings);
+        }
+    }
+
+    pub fn cheats(&self, rom_hash: &str) -> &[Cheat] {
+        self.cheats.get(rom_hash).map_or(&[], Vec::as_slice)
+    }
+
+    /// Stores a ROM's cheats, or forgets them if there are none.
+    pub fn set_cheats(&mut self, rom_hash: &str, cheats: Vec<Cheat>) {
+        if cheats.is_empty() {
+            self.cheats.remove(rom_hash);
+        } else {
+            self.cheats.insert(rom_hash.to_string(), cheats);
+        }
+    }
+}
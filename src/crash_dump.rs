@@ -0,0 +1,128 @@
+//! Crash dumps: the CPU state when it halted, with the code around PC, as a text file to
+//! attach to bug reports. Written by the emulator itself when
+//! [`Emulator::crash_dumps`] is set.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    disassembler::{disassemble, Instruction},
+    emulator::{Emulator, Registers},
+};
+
+/// Instructions listed before and after PC.
+pub const DISASSEMBLY_WINDOW: u16 = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrashDump {
+    pub rom: Option<PathBuf>,
+    /// See [`crate::rom::RomInfo::hash`].
+    pub rom_hash: Option<String>,
+    pub machine: &'static str,
+    pub frame: u128,
+    /// Why the CPU halted, see [`crate::error::Halt`].
+    pub reason: String,
+    /// Opcode at PC, the one that failed.
+    pub opcode: u16,
+    pub registers: Registers,
+    /// Return addresses, innermost last.
+    pub stack: Vec<u16>,
+    /// Around PC, in address order.
+    pub disassembly: Vec<Instruction>,
+}
+
+impl CrashDump {
+    /// State of a halted emulator, `None` if it isn't halted. PC is still on the failing
+    /// instruction.
+    pub fn capture(emulator: &Emulator) -> Option<Self> {
+        let halt = emulator.halted()?;
+        let pc = emulator.pc();
+        let start = pc.saturating_sub(DISASSEMBLY_WINDOW * 2);
+        let disassembly = disassemble(
+            emulator.memory(),
+            start,
+            (pc - start) as usize / 2 + DISASSEMBLY_WINDOW as usize + 1,
+        );
+        Some(Self {
+            rom: emulator.rom.as_ref().map(|rom| rom.path.clone()),
+            rom_hash: emulator.rom.as_ref().map(|rom| rom.hash.clone()),
+            machine: emulator.profile.name(),
+            frame: emulator.frame_count(),
+            reason: halt.to_string(),
+            opcode: emulator.curr_inst(),
+            registers: emulator.registers(),
+            stack: emulator.stack().to_vec(),
+            disassembly,
+        })
+    }
+
+    /// Writes the dump into `dir`, named after the ROM and the time, e.g.
+    /// `snake-1700000000123.txt`. Returns the path written.
+    pub fn save(&self, dir: &Path) -> io::Result<PathBuf> {
+        let name = self
+            .rom
+            .as_deref()
+            .and_then(Path::file_stem)
+            .map_or_else(|| "chip8".into(), |stem| stem.to_string_lossy());
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis());
+        let path = dir.join(format!("{name}-{millis}.txt"));
+        fs::create_dir_all(dir)?;
+        fs::write(&path, self.to_string())?;
+        Ok(path)
+    }
+}
+
+impl fmt::Display for CrashDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.reason)?;
+        match (&self.rom, &self.rom_hash) {
+            (Some(rom), Some(hash)) => writeln!(f, "ROM: {} ({hash})", rom.display())?,
+            (Some(rom), None) => writeln!(f, "ROM: {}", rom.display())?,
+            _ => writeln!(f, "ROM: none")?,
+        }
+        writeln!(f, "Machine: {}", self.machine)?;
+        writeln!(f, "Frame: {}", self.frame)?;
+        writeln!(f, "Opcode: {:04X}", self.opcode)?;
+        writeln!(f)?;
+
+        let registers = &self.registers;
+        writeln!(f, "PC: 0x{:03X}  I: 0x{:03X}", registers.pc, registers.i)?;
+        for (row, values) in registers.v.chunks(8).enumerate() {
+            let line: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| format!("V{:X}: {value:02X}", row * 8 + i))
+                .collect();
+            writeln!(f, "{}", line.join("  "))?;
+        }
+        writeln!(
+            f,
+            "DT: {:02X}  ST: {:02X}",
+            registers.delay_timer, registers.sound_timer
+        )?;
+        let stack: Vec<String> = self
+            .stack
+            .iter()
+            .map(|address| format!("0x{address:03X}"))
+            .collect();
+        writeln!(f, "Stack ({}): {}", self.stack.len(), stack.join(" "))?;
+        writeln!(f)?;
+
+        writeln!(f, "Disassembly:")?;
+        for instruction in &self.disassembly {
+            let marker = if instruction.address == registers.pc {
+                '>'
+            } else {
+                ' '
+            };
+            writeln!(f, "{marker} 0x{:03X}  {instruction}", instruction.address)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1516 @@
+//! imgui debug windows. They only read and drive the emulator through its public API,
+//! so the core modules stay free of any GUI dependency.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use imgui::{ImColor32, ListClipper, StyleColor, TableBgTarget, TextureId, Ui};
+
+use crate::{
+    annotations::{AnnotationEditor, Annotations},
+    call_graph::{stack_frames, CallGraph},
+    compare::Comparison,
+    disassembler::{disassemble, disassemble_at},
+    emulator::{
+        big_font_address, font_address, Emulator, MemoryWatch, RegisterBreak, RunState,
+        BIG_FONT_HEIGHT, FONT_HEIGHT, HIRES_DISPLAY_SIZE, MEMORY_SIZE, STACK_SIZE,
+    },
+    flow::{branch_target, BranchKind},
+    frame_limiter::FrameLimiter,
+    heatmap::{Access, HEATMAP_SIZE, PAGE_SIZE},
+    memory_map::{self, Region},
+    palette::Palette,
+    quirks::QuirkPreset,
+    sprites::{sprite_rows, sprite_size},
+    watch::{Expression, Watch},
+};
+
+/// Draws the control flow, register and lint windows for the emulator, with the window's
+/// frame times from `limiter`.
+pub fn draw_info(emulator: &mut Emulator, ui: &Ui, limiter: &FrameLimiter) {
+    ui.window("Control flow").build(|| {
+        let mut paused = false;
+        match emulator.state {
+            RunState::Running => {
+                ui.text("Emulator running...");
+                if ui.button("Pause") {
+                    emulator.pause();
+                }
+            }
+            RunState::Paused => {
+                paused = true;
+                ui.text("Emulator paused.");
+                ui.disabled(emulator.halted().is_some(), || {
+                    if ui.button("Resume") {
+                        emulator.resume();
+                    }
+                });
+            }
+            _ => {}
+        }
+        if let Some(halt) = emulator.halted() {
+            ui.text_colored([1.0, 0.3, 0.3, 1.0], halt.to_string());
+            if let Some(path) = emulator.crash_dump_path() {
+                ui.text_wrapped(format!("Crash dump written to {}", path.display()));
+            }
+            if ui.button("Reset") {
+                if let Err(err) = emulator.reload_rom() {
+                    emulator.break_reason = Some(format!("Failed to reload ROM: {err}"));
+                }
+            }
+        }
+        ui.disabled(!paused, || {
+            ui.disabled(emulator.history.is_empty(), || {
+                if ui.button("Step back") {
+                    emulator.step_back();
+                }
+            });
+            ui.same_line();
+            if ui.button("Step instruction") {
+                emulator.step_instruction();
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("F6");
+            }
+            ui.same_line();
+            if ui.button("Step frame") {
+                emulator.step_frame();
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("F7");
+            }
+            ui.same_line();
+            if ui.button("Run to next draw") {
+                emulator.run_to_draw();
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("F8: run until the next DXYN");
+            }
+            if ui.button("Step over") {
+                emulator.step_over();
+            }
+            ui.same_line();
+            ui.disabled(emulator.stack().is_empty(), || {
+                if ui.button("Step out") {
+                    emulator.step_out();
+                }
+            });
+        });
+        if let Some(reason) = &emulator.break_reason {
+            ui.text_colored([1.0, 0.8, 0.2, 1.0], format!("Break: {reason}"));
+        }
+        ui.separator();
+        let mut depth = emulator.history.depth() as i32;
+        if ui.input_int("History depth", &mut depth).step(1000).build() {
+            emulator.history.set_depth(depth.max(0) as usize);
+        }
+        ui.text_disabled(format!(
+            "{} instructions recorded, hold Backspace to rewind.",
+            emulator.history.len()
+        ));
+        ui.separator();
+        ui.label_text("Frame", emulator.frame_count().to_string());
+        let frame_times = limiter.frame_times();
+        let last = frame_times.last().copied().unwrap_or(0.0);
+        ui.label_text("Frame time (ms)", format!("{last:.2}"));
+        ui.label_text("FPS", format!("{:.1}", limiter.fps()));
+        let highest = frame_times.iter().copied().fold(0.0f32, f32::max);
+        ui.plot_lines("##frame_times", frame_times)
+            .graph_size([0.0, 60.0])
+            .scale_min(0.0)
+            .scale_max(highest.max(1000.0 / emulator.max_fps.max(1) as f32) * 1.2)
+            .overlay_text(format!("max {highest:.2} ms"))
+            .build();
+    });
+
+    if !emulator.lint_warnings.is_empty() {
+        ui.window("ROM Lint").build(|| {
+            ui.text_wrapped("This ROM may not run correctly:");
+            for warning in &emulator.lint_warnings {
+                ui.bullet_text(warning.to_string());
+            }
+        });
+    }
+
+    ui.window("Emulator").build(|| {
+        let editable = matches!(emulator.state, RunState::Paused);
+        if !editable {
+            ui.text_disabled("Pause to edit registers and memory.");
+        }
+        let step = emulator.last_step().cloned().unwrap_or_default();
+        if let Some(last) = emulator.last_step() {
+            ui.text_wrapped(format!("Last step from 0x{:03X}: {last}", last.pc));
+        }
+        let changed = |changed: bool| {
+            changed.then(|| ui.push_style_color(StyleColor::Text, STEP_CHANGE_COLOR))
+        };
+        ui.disabled(!editable, || {
+            if let Some(pc) = edit_hex(ui, "Program counter", emulator.pc(), 4) {
+                if let Err(err) = emulator.set_pc(pc) {
+                    log::warn!("Can't move PC to 0x{pc:04X}: {err}");
+                }
+            }
+            ui.label_text("Instruction", format!("0x{:04X}", emulator.curr_inst()));
+
+            ui.separator();
+
+            let color = changed(step.delay_timer);
+            if let Some(value) = edit_byte(ui, "Delay timer", emulator.delay_timer()) {
+                emulator.set_delay_timer(value);
+            }
+            drop(color);
+            let color = changed(step.sound_timer);
+            if let Some(value) = edit_byte(ui, "Sound timer", emulator.sound_timer()) {
+                emulator.set_sound_timer(value);
+            }
+            drop(color);
+
+            ui.separator();
+
+            let color = changed(step.reg_i);
+            if let Some(value) = edit_hex(ui, "Index Register", emulator.reg_i(), 4) {
+                emulator.set_reg_i(value);
+            }
+            drop(color);
+            let regs = *emulator.regs();
+            for (i, reg) in regs.into_iter().enumerate() {
+                let _color = changed(step.regs[i]);
+                // The value is part of the label, the ID after ### keeps the field stable.
+                let label = format!("Register {i} (0x{reg:02X})###V{i}");
+                if let Some(value) = edit_byte(ui, label, reg) {
+                    emulator.set_reg(i, value);
+                }
+            }
+        });
+
+        if ui.collapsing_header("Break on register change", imgui::TreeNodeFlags::empty()) {
+            const MODES: [&str; 3] = ["Off", "On change", "On value"];
+            for (i, condition) in emulator.register_breaks.iter_mut().enumerate() {
+                let _id = ui.push_id_usize(i);
+                let (mut mode, mut value) = match *condition {
+                    RegisterBreak::Off => (0, 0),
+                    RegisterBreak::OnChange => (1, 0),
+                    RegisterBreak::OnValue(value) => (2, value as i32),
+                };
+                ui.set_next_item_width(100.0);
+                ui.combo_simple_string(format!("V{i:X}"), &mut mode, &MODES);
+                if mode == 2 {
+                    ui.same_line();
+                    ui.set_next_item_width(80.0);
+                    ui.input_int("##value", &mut value).build();
+                }
+                *condition = match mode {
+                    1 => RegisterBreak::OnChange,
+                    2 => RegisterBreak::OnValue(value.clamp(0, 255) as u8),
+                    _ => RegisterBreak::Off,
+                };
+            }
+        }
+        // let mut display_str = String::new();
+        // for y in 0..DISPLAY_SIZE.1 {
+        //     for x in 0..DISPLAY_SIZE.0 {
+        //         display_str += if emulator.display[x][y] == 1 { "X" } else { "_" };
+        //     }
+        //     display_str += "\n";
+        // }
+        // ui.text(display_str);
+    });
+}
+
+/// Hex field for an address or byte, gives the new value once Enter is pressed on a valid
+/// one. Anything else is dropped and the field shows the current value again.
+fn edit_hex(ui: &Ui, label: impl AsRef<str>, value: u16, digits: usize) -> Option<u16> {
+    let mut text = format!("{value:0digits$X}");
+    if !ui
+        .input_text(label, &mut text)
+        .chars_hexadecimal(true)
+        .enter_returns_true(true)
+        .build()
+    {
+        return None;
+    }
+    match u16::from_str_radix(text.trim(), 16) {
+        Ok(parsed) if (parsed as u32) < 1 << (4 * digits) => Some(parsed),
+        _ => {
+            log::warn!("{text:?} isn't a hex value of at most {digits} digits");
+            None
+        }
+    }
+}
+
+/// Decimal field for an 8-bit register, see [`edit_hex`].
+fn edit_byte(ui: &Ui, label: impl AsRef<str>, value: u8) -> Option<u8> {
+    let mut input = value as i32;
+    if !ui
+        .input_int(label, &mut input)
+        .enter_returns_true(true)
+        .build()
+    {
+        return None;
+    }
+    match u8::try_from(input) {
+        Ok(value) => Some(value),
+        Err(_) => {
+            log::warn!("{input} doesn't fit in a byte");
+            None
+        }
+    }
+}
+
+/// Bytes per row of the hex editor in the "Memory" window.
+const HEX_ROW_BYTES: usize = 16;
+
+/// UI state for the "Memory" window, a hex editor over the whole address space.
+#[derive(Default)]
+pub struct MemoryPanel {
+    goto: String,
+    search: String,
+    status: String,
+    /// Address the hex rows should scroll to this frame.
+    scroll_to: Option<u16>,
+}
+
+impl MemoryPanel {
+    /// Lists memory 16 bytes a row with an ASCII column, each byte colored by its
+    /// [`Region`]. Clicking a byte selects it for the annotation editor and, while paused,
+    /// for editing. Double-clicking toggles a breakpoint.
+    pub fn draw(&mut self, ui: &Ui, emulator: &mut Emulator, annotations: &mut AnnotationEditor) {
+        ui.window("Memory").build(|| {
+            annotations.draw(ui);
+            if ui.collapsing_header("Bookmarks", imgui::TreeNodeFlags::empty()) {
+                annotations.draw_bookmarks(ui);
+            }
+            if ui.collapsing_header("Symbols", imgui::TreeNodeFlags::empty()) {
+                annotations.draw_symbols(ui);
+            }
+            if ui.collapsing_header("Breakpoints", imgui::TreeNodeFlags::empty()) {
+                draw_breakpoints(ui, emulator, annotations);
+            }
+            ui.separator();
+            let break_hit = emulator.take_break_hit();
+            if let Some(address) = annotations
+                .take_scroll_to()
+                .or(break_hit.then(|| emulator.pc()))
+            {
+                self.scroll_to = Some(address);
+            }
+            self.draw_toolbar(ui, emulator, annotations);
+            self.draw_rows(ui, emulator, annotations);
+        });
+    }
+
+    fn draw_toolbar(
+        &mut self,
+        ui: &Ui,
+        emulator: &mut Emulator,
+        annotations: &mut AnnotationEditor,
+    ) {
+        ui.set_next_item_width(60.0);
+        if ui
+            .input_text("Go to", &mut self.goto)
+            .chars_hexadecimal(true)
+            .enter_returns_true(true)
+            .build()
+        {
+            match u16::from_str_radix(self.goto.trim(), 16) {
+                Ok(address) => self.jump(address, annotations),
+                Err(_) => self.status = format!("{:?} isn't an address.", self.goto),
+            }
+        }
+        ui.same_line();
+        ui.set_next_item_width(120.0);
+        let entered = ui
+            .input_text("##search", &mut self.search)
+            .enter_returns_true(true)
+            .build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Hex bytes to search for, e.g. A2 1E");
+        }
+        ui.same_line();
+        if ui.button("Find next") || entered {
+            self.find_next(emulator, annotations);
+        }
+
+        if let Some(address) = annotations.selected() {
+            ui.text(format!("0x{address:04X}"));
+            ui.same_line();
+            ui.disabled(!matches!(emulator.state, RunState::Paused), || {
+                ui.set_next_item_width(30.0);
+                let value = emulator.memory()[address as usize];
+                if let Some(value) = edit_hex(ui, "Value", value as u16, 2) {
+                    if let Err(err) = emulator.poke(address, value as u8) {
+                        log::warn!("Can't write 0x{value:02X} to 0x{address:04X}: {err}");
+                    }
+                }
+            });
+        }
+        if !self.status.is_empty() {
+            ui.text_disabled(&self.status);
+        }
+        for (i, region) in Region::ALL.into_iter().enumerate() {
+            if i > 0 {
+                ui.same_line();
+            }
+            ui.text_colored(region_color(region), region.name());
+        }
+    }
+
+    fn jump(&mut self, address: u16, annotations: &mut AnnotationEditor) {
+        annotations.select(address);
+        self.scroll_to = Some(address);
+        self.status.clear();
+    }
+
+    /// Selects the next match of the search after the selected address.
+    fn find_next(&mut self, emulator: &Emulator, annotations: &mut AnnotationEditor) {
+        let Some(pattern) = memory_map::parse_bytes(&self.search) else {
+            self.status = format!("{:?} isn't a list of hex bytes.", self.search);
+            return;
+        };
+        let from = annotations
+            .selected()
+            .map_or(0, |address| address as usize + 1);
+        match memory_map::find(emulator.memory(), &pattern, from) {
+            Some(address) => self.jump(address, annotations),
+            None => self.status = "Not found.".to_string(),
+        }
+    }
+
+    fn draw_rows(&mut self, ui: &Ui, emulator: &mut Emulator, annotations: &mut AnnotationEditor) {
+        let selected = annotations.selected();
+        let rom_end =
+            emulator.load_offset as usize + emulator.rom.as_ref().map_or(0, |rom| rom.size);
+        let mem = emulator.memory();
+        // XO-CHIP has 64K of memory, only list past the classic 4K when the program uses it.
+        let shown = 0x1000usize
+            .max(rom_end)
+            .max(emulator.pc() as usize + 2)
+            .max(selected.map_or(0, |address| address as usize + 1))
+            .min(mem.len());
+        let rows = shown.div_ceil(HEX_ROW_BYTES);
+
+        let char_width = ui.calc_text_size("0")[0];
+        // Address, then the bytes in two groups of 8, then the ASCII column.
+        let byte_x = |col: usize| char_width * (6 + col * 3 + col / 8) as f32;
+        let ascii_x = byte_x(HEX_ROW_BYTES) + char_width;
+        let row_height = ui.text_line_height_with_spacing();
+        let mut clicked = None;
+        let mut toggled = None;
+        ui.child_window("hex_rows").build(|| {
+            if let Some(address) = self.scroll_to.take() {
+                ui.set_scroll_y((address as usize / HEX_ROW_BYTES) as f32 * row_height);
+            }
+            let mut clipper = ListClipper::new(rows as i32)
+                .items_height(row_height)
+                .begin(ui);
+            while clipper.step() {
+                for row in clipper.display_start()..clipper.display_end() {
+                    let start = row as usize * HEX_ROW_BYTES;
+                    let bytes = &mem[start..(start + HEX_ROW_BYTES).min(shown)];
+                    ui.text_disabled(format!("{start:04X}"));
+                    for (col, byte) in bytes.iter().enumerate() {
+                        let address = (start + col) as u16;
+                        ui.same_line_with_pos(byte_x(col));
+                        let color = Region::of(emulator, address).map(|region| {
+                            ui.push_style_color(StyleColor::Text, region_color(region))
+                        });
+                        if ui
+                            .selectable_config(format!("{byte:02X}##{address}"))
+                            .selected(selected == Some(address))
+                            .size([char_width * 2.0, 0.0])
+                            .build()
+                        {
+                            clicked = Some(address);
+                        }
+                        drop(color);
+                        if let Some(heat) = heatmap_color(emulator, address) {
+                            ui.get_window_draw_list()
+                                .add_rect(ui.item_rect_min(), ui.item_rect_max(), heat)
+                                .filled(true)
+                                .build();
+                        }
+                        if emulator.breakpoints.contains(&address) {
+                            ui.get_window_draw_list()
+                                .add_rect(ui.item_rect_min(), ui.item_rect_max(), BREAKPOINT_COLOR)
+                                .filled(true)
+                                .build();
+                        }
+                        if emulator
+                            .last_step()
+                            .is_some_and(|step| step.memory_changed(address))
+                        {
+                            ui.get_window_draw_list()
+                                .add_rect(ui.item_rect_min(), ui.item_rect_max(), STEP_CHANGE_BG)
+                                .filled(true)
+                                .build();
+                        }
+                        if ui.is_item_hovered() {
+                            if ui.is_mouse_double_clicked(imgui::MouseButton::Left) {
+                                toggled = Some(address);
+                            }
+                            ui.tooltip_text(match annotations.summary(address) {
+                                Some(summary) => format!("0x{address:04X}  {summary}"),
+                                None => format!("0x{address:04X}"),
+                            });
+                        }
+                    }
+                    ui.same_line_with_pos(ascii_x);
+                    let ascii: String = bytes
+                        .iter()
+                        .map(|&byte| {
+                            if byte.is_ascii_graphic() || byte == b' ' {
+                                byte as char
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect();
+                    ui.text(ascii);
+                }
+            }
+        });
+        if let Some(address) = clicked {
+            annotations.select(address);
+        }
+        if let Some(address) = toggled {
+            emulator.toggle_breakpoint(address);
+        }
+    }
+}
+
+/// Text color of bytes in a memory region.
+fn region_color(region: Region) -> [f32; 4] {
+    match region {
+        Region::ProgramCounter => [0.3, 1.0, 0.3, 1.0],
+        Region::Index => [1.0, 0.8, 0.2, 1.0],
+        Region::Written => [1.0, 0.45, 0.45, 1.0],
+        Region::Rom => [0.55, 0.75, 1.0, 1.0],
+        Region::Font => [0.75, 0.6, 1.0, 1.0],
+        Region::Reserved => [0.6, 0.6, 0.6, 1.0],
+    }
+}
+
+/// Row background of instructions with a breakpoint.
+const BREAKPOINT_COLOR: [f32; 4] = [1.0, 0.0, 0.0, 0.2];
+
+/// Text of registers the last step changed, see [`Emulator::last_step`].
+const STEP_CHANGE_COLOR: [f32; 4] = [1.0, 0.6, 0.1, 1.0];
+/// Background of bytes and stack entries the last step changed.
+const STEP_CHANGE_BG: [f32; 4] = [1.0, 0.6, 0.1, 0.3];
+
+/// Lists the breakpoints and memory watches. Watches are added on the address selected in
+/// the memory table.
+fn draw_breakpoints(ui: &Ui, emulator: &mut Emulator, annotations: &AnnotationEditor) {
+    ui.text_disabled("Double-click an address to toggle a breakpoint.");
+    let selected = annotations.selected();
+    let mut removed = None;
+    for address in &emulator.breakpoints {
+        let _id = ui.push_id_usize(*address as usize);
+        ui.text(annotations.annotations.address_name(*address));
+        ui.same_line();
+        if ui.small_button("Remove") {
+            removed = Some(*address);
+        }
+    }
+    if let Some(address) = removed {
+        emulator.breakpoints.remove(&address);
+    }
+
+    ui.separator();
+    ui.disabled(selected.is_none(), || {
+        let label = match selected {
+            Some(address) => format!("Watch writes to 0x{address:03X}"),
+            None => String::from("Watch writes to selected address"),
+        };
+        if ui.button(label) {
+            if let Some(address) = selected {
+                emulator
+                    .memory_watches
+                    .entry(address)
+                    .or_insert(MemoryWatch::OnWrite);
+            }
+        }
+    });
+    let mut removed = None;
+    for (address, watch) in emulator.memory_watches.iter_mut() {
+        let _id = ui.push_id_usize(0x10000 + *address as usize);
+        let (mut mode, mut value) = match *watch {
+            MemoryWatch::OnWrite => (0, 0),
+            MemoryWatch::OnChange => (1, 0),
+            MemoryWatch::OnValue(value) => (2, value as i32),
+        };
+        ui.set_next_item_width(100.0);
+        ui.combo_simple_string(
+            format!("mem[0x{address:03X}]"),
+            &mut mode,
+            &MemoryWatch::NAMES,
+        );
+        if mode == 2 {
+            ui.same_line();
+            ui.set_next_item_width(80.0);
+            ui.input_int("##value", &mut value).build();
+        }
+        ui.same_line();
+        if ui.small_button("Remove") {
+            removed = Some(*address);
+        }
+        *watch = match mode {
+            1 => MemoryWatch::OnChange,
+            2 => MemoryWatch::OnValue(value.clamp(0, 255) as u8),
+            _ => MemoryWatch::OnWrite,
+        };
+    }
+    if let Some(address) = removed {
+        emulator.memory_watches.remove(&address);
+    }
+}
+
+/// Screen position of a listed instruction, collected while drawing a listing.
+pub struct RowPosition {
+    pub address: u16,
+    pub y: f32,
+}
+
+/// Draws arrows in a gutter left of a listing, from each branch to its target row.
+/// `gutter` is the screen x range `[left, right]` the arrows may use. Arrows get their own
+/// lane so overlapping spans stay readable, shorter spans are drawn closer to the listing.
+pub fn draw_branch_arrows(
+    ui: &Ui,
+    gutter: [f32; 2],
+    rows: &[RowPosition],
+    branches: &[(u16, BranchKind, u16)],
+) {
+    const LANE_WIDTH: f32 = 5.0;
+    const HEAD: f32 = 3.0;
+
+    // Rows are listed in address order.
+    let row_y = |address: u16| {
+        rows.binary_search_by_key(&address, |row| row.address)
+            .ok()
+            .map(|i| rows[i].y)
+    };
+    let mut arrows: Vec<(f32, f32, BranchKind)> = branches
+        .iter()
+        .filter_map(|(from, kind, to)| Some((row_y(*from)?, row_y(*to)?, *kind)))
+        .filter(|(from, to, _)| from != to)
+        .collect();
+    arrows.sort_by(|a, b| (a.0 - a.1).abs().total_cmp(&(b.0 - b.1).abs()));
+
+    let lanes = (((gutter[1] - gutter[0]) / LANE_WIDTH) as usize).max(1);
+    let draw_list = ui.get_window_draw_list();
+    for (i, (from, to, kind)) in arrows.into_iter().enumerate() {
+        let color = match kind {
+            BranchKind::Jump => ImColor32::from_rgba(90, 160, 255, 200),
+            BranchKind::Call => ImColor32::from_rgba(90, 220, 120, 200),
+            BranchKind::Skip => ImColor32::from_rgba(180, 180, 180, 160),
+        };
+        let x = gutter[1] - LANE_WIDTH * ((i % lanes) as f32 + 1.0);
+        let right = gutter[1];
+        draw_list
+            .add_polyline(vec![[right, from], [x, from], [x, to], [right, to]], color)
+            .build();
+        draw_list
+            .add_triangle(
+                [right, to],
+                [right - HEAD * 1.5, to - HEAD],
+                [right - HEAD * 1.5, to + HEAD],
+                color,
+            )
+            .filled(true)
+            .build();
+    }
+}
+
+/// "Stack" window, the calls in progress from the innermost out, each with its return
+/// address and call site. Double-clicking a row puts a breakpoint on the return address,
+/// the same as stepping out of that call.
+pub fn draw_stack(ui: &Ui, emulator: &mut Emulator, annotations: &Annotations) {
+    ui.window("Stack").build(|| {
+        let frames = stack_frames(emulator.memory(), emulator.stack());
+        ui.text(format!("Depth {} / {STACK_SIZE}", frames.len()));
+        let current = frames.first().and_then(|frame| frame.subroutine);
+        ui.text(match current {
+            Some(subroutine) => format!(
+                "PC 0x{:03X} in {}",
+                emulator.pc(),
+                annotations.address_name(subroutine)
+            ),
+            None => format!("PC 0x{:03X}", emulator.pc()),
+        });
+        // Pushed entries are the innermost calls, popped ones are gone from the table.
+        let pushed = emulator.last_step().map_or(0, |step| step.pushed.len());
+        if let Some(step) = emulator.last_step().filter(|step| !step.popped.is_empty()) {
+            let popped: Vec<String> = step
+                .popped
+                .iter()
+                .map(|&address| annotations.address_name(address))
+                .collect();
+            ui.text_colored(
+                STEP_CHANGE_COLOR,
+                format!("Last step popped {}", popped.join(", ")),
+            );
+        }
+        let table_flags = imgui::TableFlags::BORDERS_H
+            | imgui::TableFlags::BORDERS_V
+            | imgui::TableFlags::RESIZABLE;
+        let Some(_table) = ui.begin_table_with_flags("stack_table", 4, table_flags) else {
+            return;
+        };
+        ui.table_setup_column("#");
+        ui.table_setup_column("Return");
+        ui.table_setup_column("Call site");
+        ui.table_setup_column("Subroutine");
+        ui.table_headers_row();
+        for (depth, frame) in frames.iter().enumerate() {
+            ui.table_next_row();
+            ui.table_set_column_index(0);
+            ui.text((frames.len() - depth).to_string());
+            ui.table_set_column_index(1);
+            let return_address = frame.return_address;
+            if emulator.breakpoints.contains(&return_address) {
+                ui.table_set_bg_color(TableBgTarget::ROW_BG0, BREAKPOINT_COLOR);
+            } else if depth < pushed {
+                ui.table_set_bg_color(TableBgTarget::ROW_BG0, STEP_CHANGE_BG);
+            }
+            let selectable = imgui::SelectableFlags::SPAN_ALL_COLUMNS
+                | imgui::SelectableFlags::ALLOW_DOUBLE_CLICK;
+            if ui
+                .selectable_config(format!(
+                    "{}##{depth}",
+                    annotations.address_name(return_address)
+                ))
+                .flags(selectable)
+                .build()
+                && ui.is_mouse_double_clicked(imgui::MouseButton::Left)
+            {
+                emulator.toggle_breakpoint(return_address);
+            }
+            ui.table_set_column_index(2);
+            ui.text(format!(
+                "{}  {}",
+                annotations.address_name(frame.call_site.address),
+                frame.call_site
+            ));
+            ui.table_set_column_index(3);
+            match frame.subroutine {
+                Some(subroutine) => ui.text(annotations.address_name(subroutine)),
+                None => ui.text_disabled("?"),
+            }
+        }
+    });
+}
+
+/// UI state for the "Call Graph" window.
+pub struct CallGraphPanel {
+    export_path: String,
+    status: String,
+}
+
+impl Default for CallGraphPanel {
+    fn default() -> Self {
+        Self {
+            export_path: String::from("./calls.dot"),
+            status: String::new(),
+        }
+    }
+}
+
+impl CallGraphPanel {
+    pub fn draw(&mut self, ui: &Ui, graph: &mut CallGraph) {
+        ui.window("Call Graph").build(|| {
+            ui.input_text("##export_path", &mut self.export_path)
+                .build();
+            ui.same_line();
+            if ui.button("Export DOT") {
+                self.status = match graph.export_dot(&self.export_path) {
+                    Ok(()) => format!("Exported to {}.", self.export_path),
+                    Err(err) => format!("Export failed: {err}"),
+                };
+            }
+            ui.same_line();
+            if ui.button("Clear") {
+                graph.clear();
+            }
+            if !self.status.is_empty() {
+                ui.text_wrapped(&self.status);
+            }
+            ui.separator();
+
+            let mut callees: BTreeMap<u16, Vec<(u16, u64)>> = BTreeMap::new();
+            for (caller, callee, count) in graph.edges() {
+                callees.entry(caller).or_default().push((callee, count));
+            }
+            let mut visited = Vec::new();
+            draw_node(ui, graph.entry(), &callees, &mut visited, None);
+        });
+    }
+}
+
+/// UI state for the "Trace" window.
+pub struct TracePanel {
+    export_path: String,
+    status: String,
+}
+
+impl Default for TracePanel {
+    fn default() -> Self {
+        Self {
+            export_path: String::from("./trace.txt"),
+            status: String::new(),
+        }
+    }
+}
+
+impl TracePanel {
+    /// Lists the recorded instructions, newest at the bottom, following them while the
+    /// emulator runs. Paths ending in `.csv` export as CSV, anything else as text.
+    pub fn draw(&mut self, ui: &Ui, emulator: &mut Emulator) {
+        ui.window("Trace").build(|| {
+            let trace = &mut emulator.trace;
+            let mut enabled = trace.is_enabled();
+            if ui.checkbox("Record", &mut enabled) {
+                trace.set_enabled(enabled);
+            }
+            ui.same_line();
+            ui.set_next_item_width(120.0);
+            let mut capacity = trace.capacity() as i32;
+            if ui.input_int("Capacity", &mut capacity).step(10_000).build() {
+                trace.set_capacity(capacity.max(0) as usize);
+            }
+            ui.same_line();
+            if ui.button("Clear") {
+                trace.clear();
+            }
+            ui.input_text("##export_path", &mut self.export_path)
+                .build();
+            ui.same_line();
+            if ui.button("Export") {
+                self.status = match trace.export(&self.export_path) {
+                    Ok(()) => format!(
+                        "Exported {} instructions to {}.",
+                        trace.len(),
+                        self.export_path
+                    ),
+                    Err(err) => format!("Export failed: {err}"),
+                };
+            }
+            if !self.status.is_empty() {
+                ui.text_wrapped(&self.status);
+            }
+            ui.separator();
+
+            let table_flags = imgui::TableFlags::BORDERS_H
+                | imgui::TableFlags::BORDERS_V
+                | imgui::TableFlags::RESIZABLE
+                | imgui::TableFlags::SCROLL_Y;
+            let Some(_table) = ui.begin_table_with_flags("trace_table", 4, table_flags) else {
+                return;
+            };
+            ui.table_setup_column("Frame");
+            ui.table_setup_column("PC");
+            ui.table_setup_column("Instruction");
+            ui.table_setup_column("Changes");
+            ui.table_setup_scroll_freeze(4, 1);
+            ui.table_headers_row();
+            let entries = trace.entries();
+            let mut clipper = ListClipper::new(entries.len() as i32).begin(ui);
+            while clipper.step() {
+                for row in clipper.display_start()..clipper.display_end() {
+                    let entry = &entries[row as usize];
+                    ui.table_next_row();
+                    ui.table_set_column_index(0);
+                    ui.text(entry.frame.to_string());
+                    ui.table_set_column_index(1);
+                    ui.text(format!("0x{:03X}", entry.instruction.address));
+                    ui.table_set_column_index(2);
+                    ui.text(entry.instruction.to_string());
+                    ui.table_set_column_index(3);
+                    ui.text(entry.changes_text());
+                }
+            }
+            if matches!(emulator.state, RunState::Running) {
+                ui.set_scroll_here_y_with_ratio(1.0);
+            }
+        });
+    }
+}
+
+const SPRITE_ON: ImColor32 = ImColor32::from_rgb(230, 230, 230);
+const SPRITE_OFF: ImColor32 = ImColor32::from_rgb(40, 40, 40);
+
+/// Draws sprite rows as blocks of `zoom` pixels, see [`sprite_rows`]. Returns whether the
+/// sprite was clicked.
+fn draw_sprite(ui: &Ui, id: &str, rows: &[u16], wide: bool, zoom: f32) -> bool {
+    let width = if wide { 16 } else { 8 };
+    let origin = ui.cursor_screen_pos();
+    let size = [width as f32 * zoom, rows.len() as f32 * zoom];
+    let clicked = ui.invisible_button(id, size);
+    let draw_list = ui.get_window_draw_list();
+    draw_list
+        .add_rect(
+            origin,
+            [origin[0] + size[0], origin[1] + size[1]],
+            SPRITE_OFF,
+        )
+        .filled(true)
+        .build();
+    for (y, row) in rows.iter().enumerate() {
+        for x in 0..width {
+            if row & (1 << (width - 1 - x)) == 0 {
+                continue;
+            }
+            let min = [origin[0] + x as f32 * zoom, origin[1] + y as f32 * zoom];
+            draw_list
+                .add_rect(min, [min[0] + zoom, min[1] + zoom], SPRITE_ON)
+                .filled(true)
+                .build();
+        }
+    }
+    clicked
+}
+
+/// UI state for the "Sprites" window.
+pub struct SpritePanel {
+    /// Show the sprite I points to, as the next DXYN would draw it.
+    follow_i: bool,
+    address: u16,
+    height: i32,
+    /// 16x16 sprites as drawn by SUPER-CHIP's DXY0.
+    wide: bool,
+    zoom: f32,
+}
+
+impl Default for SpritePanel {
+    fn default() -> Self {
+        Self {
+            follow_i: true,
+            address: 0,
+            height: 15,
+            wide: false,
+            zoom: 12.0,
+        }
+    }
+}
+
+impl SpritePanel {
+    /// Shows memory as sprite rows with their bytes, and both fonts as they are in memory.
+    /// Clicking a font glyph shows it as the sprite.
+    pub fn draw(&mut self, ui: &Ui, emulator: &Emulator) {
+        ui.window("Sprites").build(|| {
+            ui.checkbox("Follow I", &mut self.follow_i);
+            if self.follow_i {
+                self.address = emulator.reg_i();
+            }
+            ui.same_line();
+            ui.set_next_item_width(60.0);
+            if let Some(address) = edit_hex(ui, "Address", self.address, 4) {
+                self.address = address;
+                self.follow_i = false;
+            }
+            ui.set_next_item_width(100.0);
+            if ui.input_int("Rows", &mut self.height).build() {
+                self.height = self.height.clamp(1, 16);
+            }
+            ui.same_line();
+            ui.checkbox("16x16", &mut self.wide);
+            ui.slider("Zoom", 2.0, 32.0, &mut self.zoom);
+
+            let height = self.height as usize;
+            let size = sprite_size(height, self.wide) as u16;
+            if ui.button("< Previous") {
+                self.address = self.address.wrapping_sub(size);
+                self.follow_i = false;
+            }
+            ui.same_line();
+            if ui.button("Next >") {
+                self.address = self.address.wrapping_add(size);
+                self.follow_i = false;
+            }
+
+            let rows = sprite_rows(emulator.memory(), self.address, height, self.wide);
+            draw_sprite(ui, "##sprite", &rows, self.wide, self.zoom);
+            ui.same_line();
+            ui.group(|| {
+                for (i, row) in rows.iter().enumerate() {
+                    let address = self.address as usize + sprite_size(i, self.wide);
+                    if self.wide {
+                        ui.text(format!("{address:04X}: {row:04X}  {row:016b}"));
+                    } else {
+                        ui.text(format!("{address:04X}: {row:02X}  {row:08b}"));
+                    }
+                }
+            });
+
+            if ui.collapsing_header("Font", imgui::TreeNodeFlags::DEFAULT_OPEN) {
+                let memory = emulator.memory();
+                for digit in 0..16 {
+                    if digit > 0 {
+                        ui.same_line();
+                    }
+                    let address = font_address(digit);
+                    let rows = sprite_rows(memory, address, FONT_HEIGHT, false);
+                    if draw_sprite(ui, &format!("##font{digit}"), &rows, false, 3.0) {
+                        self.show_glyph(address, FONT_HEIGHT);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(format!("{digit:X} at 0x{address:03X}"));
+                    }
+                }
+                for digit in 0..16 {
+                    if digit > 0 {
+                        ui.same_line();
+                    }
+                    let address = big_font_address(digit);
+                    let rows = sprite_rows(memory, address, BIG_FONT_HEIGHT, false);
+                    if draw_sprite(ui, &format!("##big_font{digit}"), &rows, false, 3.0) {
+                        self.show_glyph(address, BIG_FONT_HEIGHT);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(format!("Big {digit:X} at 0x{address:03X}"));
+                    }
+                }
+            }
+        });
+    }
+
+    fn show_glyph(&mut self, address: u16, height: usize) {
+        self.address = address;
+        self.height = height as i32;
+        self.wide = false;
+        self.follow_i = false;
+    }
+}
+
+/// Hotspots listed in the "Profiler" window.
+const PROFILER_HOTSPOTS: usize = 64;
+
+/// "Profiler" window, execution counts per address and per instruction pattern.
+pub fn draw_profiler(ui: &Ui, emulator: &mut Emulator) {
+    ui.window("Profiler").build(|| {
+        let profiler = &mut emulator.profiler;
+        let mut enabled = profiler.is_enabled();
+        if ui.checkbox("Record", &mut enabled) {
+            profiler.set_enabled(enabled);
+        }
+        ui.same_line();
+        ui.checkbox("Heatmap", &mut profiler.show_heatmap);
+        ui.same_line();
+        if ui.button("Reset") {
+            profiler.reset();
+        }
+        ui.separator();
+
+        let instructions = profiler.instructions();
+        ui.label_text("Instructions", instructions.to_string());
+        ui.label_text("Frames", profiler.frames().to_string());
+        ui.label_text(
+            "Instructions per frame",
+            format!(
+                "{:.1} (cpf {})",
+                profiler.instructions_per_frame(),
+                emulator.cpf
+            ),
+        );
+        ui.label_text(
+            "Host time per frame",
+            format!(
+                "{:.3} ms",
+                profiler.host_time_per_frame().as_secs_f64() * 1000.0
+            ),
+        );
+        let share = |count: u64| 100.0 * count as f64 / instructions.max(1) as f64;
+
+        if ui.collapsing_header("Hotspots", imgui::TreeNodeFlags::DEFAULT_OPEN) {
+            let table_flags = imgui::TableFlags::BORDERS_H | imgui::TableFlags::BORDERS_V;
+            if let Some(_table) = ui.begin_table_with_flags("hotspot_table", 4, table_flags) {
+                ui.table_setup_column("Address");
+                ui.table_setup_column("Count");
+                ui.table_setup_column("%");
+                ui.table_setup_column("Instruction");
+                ui.table_headers_row();
+                for (address, count) in profiler.hotspots(PROFILER_HOTSPOTS) {
+                    ui.table_next_row();
+                    ui.table_set_column_index(0);
+                    ui.text(format!("0x{address:03X}"));
+                    ui.table_set_column_index(1);
+                    ui.text(count.to_string());
+                    ui.table_set_column_index(2);
+                    ui.text(format!("{:.1}", share(count)));
+                    ui.table_set_column_index(3);
+                    ui.text(disassemble_at(emulator.memory(), address).to_string());
+                }
+            }
+        }
+        if ui.collapsing_header("Instructions", imgui::TreeNodeFlags::DEFAULT_OPEN) {
+            let table_flags = imgui::TableFlags::BORDERS_H | imgui::TableFlags::BORDERS_V;
+            if let Some(_table) = ui.begin_table_with_flags("class_table", 3, table_flags) {
+                ui.table_setup_column("Pattern");
+                ui.table_setup_column("Count");
+                ui.table_setup_column("%");
+                ui.table_headers_row();
+                for (class, count) in emulator.profiler.classes() {
+                    ui.table_next_row();
+                    ui.table_set_column_index(0);
+                    ui.text(class);
+                    ui.table_set_column_index(1);
+                    ui.text(count.to_string());
+                    ui.table_set_column_index(2);
+                    ui.text(format!("{:.1}", share(count)));
+                }
+            }
+        }
+    });
+}
+
+/// Background shading an address gets from the profiler heatmap, if it's shown and the
+/// address ran at all.
+fn heatmap_color(emulator: &Emulator, address: u16) -> Option<[f32; 4]> {
+    if !emulator.profiler.show_heatmap {
+        return None;
+    }
+    let heat = emulator.profiler.heat(address);
+    (heat > 0.0).then_some([1.0, 0.6 * (1.0 - heat), 0.0, 0.15 + 0.45 * heat])
+}
+
+/// UI state for the "Memory heatmap" window.
+pub struct HeatmapPanel {
+    /// 4 KiB page of memory shown, see [`PAGE_SIZE`].
+    page: i32,
+    zoom: f32,
+}
+
+impl Default for HeatmapPanel {
+    fn default() -> Self {
+        Self { page: 0, zoom: 4.0 }
+    }
+}
+
+impl HeatmapPanel {
+    /// Page to upload into the texture with [`crate::heatmap::MemoryHeatmap::rgba`].
+    pub fn page(&self) -> usize {
+        self.page as usize
+    }
+
+    /// Shows `texture`, the heatmap of the selected page, with the address under the mouse
+    /// and its access counts.
+    pub fn draw(&mut self, ui: &Ui, emulator: &mut Emulator, texture: TextureId) {
+        ui.window("Memory heatmap").build(|| {
+            let heatmap = &mut emulator.heatmap;
+            let mut enabled = heatmap.is_enabled();
+            if ui.checkbox("Record", &mut enabled) {
+                heatmap.set_enabled(enabled);
+            }
+            ui.same_line();
+            if ui.button("Reset") {
+                heatmap.reset();
+            }
+            ui.slider_config("Decay", 0.5, 1.0)
+                .display_format("%.3f")
+                .build(&mut heatmap.decay);
+            let pages = (MEMORY_SIZE / PAGE_SIZE) as i32;
+            ui.slider_config("Page", 0, pages - 1)
+                .display_format("%X")
+                .build(&mut self.page);
+            ui.slider("Zoom", 1.0, 8.0, &mut self.zoom);
+            ui.text_colored([1.0, 0.3, 0.3, 1.0], "Write");
+            ui.same_line();
+            ui.text_colored([0.3, 1.0, 0.3, 1.0], "Execute");
+            ui.same_line();
+            ui.text_colored([0.3, 0.5, 1.0, 1.0], "Read");
+
+            let side = HEATMAP_SIZE as f32 * self.zoom;
+            let origin = ui.cursor_screen_pos();
+            imgui::Image::new(texture, [side, side]).build(ui);
+            if !ui.is_item_hovered() {
+                return;
+            }
+            let [mouse_x, mouse_y] = ui.io().mouse_pos;
+            let x = ((mouse_x - origin[0]) / self.zoom) as usize;
+            let y = ((mouse_y - origin[1]) / self.zoom) as usize;
+            if x >= HEATMAP_SIZE || y >= HEATMAP_SIZE {
+                return;
+            }
+            let address = (self.page() * PAGE_SIZE + y * HEATMAP_SIZE + x) as u16;
+            ui.tooltip(|| {
+                ui.text(format!("0x{address:04X}"));
+                for access in Access::ALL {
+                    ui.text(format!(
+                        "{}: {:.1}",
+                        access.name(),
+                        emulator.heatmap.count(address, access)
+                    ));
+                }
+            });
+        });
+    }
+}
+
+/// UI state for the "Compare quirks" window: the loaded ROM on two machines with
+/// different quirks, see [`Comparison`].
+#[derive(Default)]
+pub struct ComparePanel {
+    /// Index into the left choices, the first being the current quirks.
+    left: usize,
+    /// Index into [`QuirkPreset::ALL`].
+    right: usize,
+    comparison: Option<Comparison>,
+    status: String,
+}
+
+impl ComparePanel {
+    /// Zoom of the display shown for each side.
+    const SCALE: f32 = 3.0;
+
+    /// Runs the comparison for `elapsed`, with the keys held in `keypad`. Takes a copy of
+    /// the keys rather than the emulator, so the lock isn't held while both sides run.
+    pub fn update(&mut self, keypad: &[bool; 16], elapsed: Duration) {
+        if let Some(comparison) = &mut self.comparison {
+            comparison.set_keys(keypad);
+            comparison.run_for(elapsed);
+        }
+    }
+
+    /// Pixels of the left and right displays to upload, row by row at high resolution.
+    /// `None` before a comparison is started.
+    pub fn display_rgba(&self, palette: &Palette) -> Option<[Vec<u8>; 2]> {
+        let comparison = self.comparison.as_ref()?;
+        Some([&comparison.left, &comparison.right].map(|side| {
+            let display = &side.display;
+            let scale = HIRES_DISPLAY_SIZE.0 / display.size().0;
+            (0..HIRES_DISPLAY_SIZE.1)
+                .flat_map(|y| {
+                    (0..HIRES_DISPLAY_SIZE.0)
+                        .flat_map(move |x| palette.color(display[x / scale][y / scale]))
+                })
+                .collect()
+        }))
+    }
+
+    /// Picks the quirks of both sides, starts the comparison on the ROM loaded in
+    /// `emulator`, and shows `textures`, the two displays, with where they diverged.
+    pub fn draw(&mut self, ui: &Ui, emulator: &Emulator, textures: [TextureId; 2]) {
+        ui.window("Compare quirks").build(|| {
+            let current = QuirkPreset::detect(emulator).map_or("Custom", QuirkPreset::name);
+            let current = format!("Current ({current})");
+            let mut left_names = vec![current.as_str()];
+            left_names.extend(QuirkPreset::ALL.map(QuirkPreset::name));
+            let right_names = QuirkPreset::ALL.map(QuirkPreset::name);
+            ui.set_next_item_width(160.0);
+            ui.combo_simple_string("Left", &mut self.left, &left_names);
+            ui.same_line();
+            ui.set_next_item_width(160.0);
+            ui.combo_simple_string("Right", &mut self.right, &right_names);
+
+            ui.disabled(emulator.rom.is_none(), || {
+                if ui.button("Start") {
+                    let left = match self.left {
+                        0 => emulator.quirks,
+                        i => QuirkPreset::ALL[i - 1].quirks(),
+                    };
+                    let right = QuirkPreset::ALL[self.right].quirks();
+                    match Comparison::new(emulator, left, right) {
+                        Ok(comparison) => {
+                            self.comparison = Some(comparison);
+                            self.status.clear();
+                        }
+                        Err(err) => self.status = format!("Failed to start: {err}"),
+                    }
+                }
+            });
+            let Some(comparison) = &mut self.comparison else {
+                ui.text_disabled("Start runs the loaded ROM with both quirk sets.");
+                ui.text_wrapped(&self.status);
+                return;
+            };
+
+            ui.same_line();
+            if ui.button("Restart") {
+                if let Err(err) = comparison.restart() {
+                    self.status = format!("Failed to restart: {err}");
+                }
+                comparison.paused = false;
+            }
+            ui.same_line();
+            let label = if comparison.paused { "Resume" } else { "Pause" };
+            if ui.button(label) {
+                comparison.paused = !comparison.paused;
+            }
+            ui.same_line();
+            ui.disabled(!comparison.paused, || {
+                if ui.button("Step frame") {
+                    comparison.step_frame();
+                }
+            });
+            if !self.status.is_empty() {
+                ui.text_wrapped(&self.status);
+            }
+
+            let size = [
+                HIRES_DISPLAY_SIZE.0 as f32 * Self::SCALE,
+                HIRES_DISPLAY_SIZE.1 as f32 * Self::SCALE,
+            ];
+            ui.group(|| {
+                ui.text(left_names[self.left]);
+                imgui::Image::new(textures[0], size).build(ui);
+            });
+            ui.same_line();
+            ui.group(|| {
+                ui.text(right_names[self.right]);
+                imgui::Image::new(textures[1], size).build(ui);
+            });
+
+            ui.text(format!("Frame {}", comparison.left.frame_count()));
+            match comparison.divergence() {
+                None => ui.text("No divergence so far."),
+                Some(divergence) => {
+                    ui.text_colored(
+                        [1.0, 0.4, 0.4, 1.0],
+                        format!("Diverged at frame {}:", divergence.frame),
+                    );
+                    for difference in &divergence.differences {
+                        ui.bullet_text(difference.to_string());
+                    }
+                }
+            }
+            for (side, halted) in [
+                ("Left", comparison.left.halted()),
+                ("Right", comparison.right.halted()),
+            ] {
+                if let Some(halt) = halted {
+                    ui.text(format!("{side} halted: {halt}"));
+                }
+            }
+        });
+    }
+}
+
+/// UI state for the "Watches" window.
+#[derive(Default)]
+pub struct WatchPanel {
+    input: String,
+    error: Option<String>,
+}
+
+impl WatchPanel {
+    /// Lists the watch expressions with their current values, and adds new ones.
+    pub fn draw(&mut self, ui: &Ui, emulator: &mut Emulator) {
+        ui.window("Watches").build(|| {
+            let entered = ui
+                .input_text("##expression", &mut self.input)
+                .hint("V1*10+V2, [I+2]")
+                .enter_returns_true(true)
+                .build();
+            ui.same_line();
+            if (ui.button("Add") || entered) && !self.input.trim().is_empty() {
+                match Expression::parse(&self.input) {
+                    Ok(expression) => {
+                        emulator.watches.push(Watch::new(expression));
+                        self.input.clear();
+                        self.error = None;
+                    }
+                    Err(err) => self.error = Some(err.to_string()),
+                }
+            }
+            if let Some(error) = &self.error {
+                ui.text_colored([1.0, 0.3, 0.3, 1.0], error);
+            }
+
+            let values: Vec<Option<i64>> = emulator
+                .watches
+                .iter()
+                .map(|watch| watch.expression.evaluate(emulator))
+                .collect();
+            let mut removed = None;
+            let table_flags = imgui::TableFlags::BORDERS_H
+                | imgui::TableFlags::BORDERS_V
+                | imgui::TableFlags::RESIZABLE;
+            let Some(_table) = ui.begin_table_with_flags("watch_table", 5, table_flags) else {
+                return;
+            };
+            ui.table_setup_column("Expression");
+            ui.table_setup_column("Value");
+            ui.table_setup_column("Hex");
+            ui.table_setup_column("Break");
+            ui.table_setup_column("");
+            ui.table_headers_row();
+            for (index, (watch, value)) in emulator.watches.iter_mut().zip(values).enumerate() {
+                let _id = ui.push_id_usize(index);
+                ui.table_next_row();
+                ui.table_set_column_index(0);
+                ui.text(watch.expression.text());
+                ui.table_set_column_index(1);
+                match value {
+                    Some(value) => {
+                        ui.text(value.to_string());
+                        ui.table_set_column_index(2);
+                        ui.text(format!("0x{value:X}"));
+                    }
+                    None => ui.text_disabled("error"),
+                }
+                ui.table_set_column_index(3);
+                ui.checkbox("##break", &mut watch.break_on_change);
+                ui.table_set_column_index(4);
+                if ui.small_button("Remove") {
+                    removed = Some(index);
+                }
+            }
+            if let Some(index) = removed {
+                emulator.watches.remove(index);
+            }
+        });
+    }
+}
+
+/// Width of the column branch arrows are drawn in, left of the disassembly.
+const GUTTER_WIDTH: f32 = 60.0;
+
+/// Instructions listed before and after PC in the "Disassembly" window.
+const DISASSEMBLY_CONTEXT: u16 = 32;
+
+/// "Disassembly" window, the decoded instructions around PC.
+#[derive(Default)]
+pub struct DisassemblyPanel {
+    /// PC the listing was last scrolled to.
+    followed_pc: Option<u16>,
+}
+
+impl DisassemblyPanel {
+    /// Lists the instructions around PC with the current one highlighted. The listing
+    /// scrolls to PC while running and whenever PC moves, e.g. after a step or a break.
+    /// Clicking an address toggles its breakpoint and right-clicking selects it for
+    /// labeling. Jumps, calls and skips within the ROM get arrows in the gutter.
+    pub fn draw(&mut self, ui: &Ui, emulator: &mut Emulator, annotations: &mut AnnotationEditor) {
+        ui.window("Disassembly").build(|| {
+            let pc = emulator.pc();
+            let follow =
+                matches!(emulator.state, RunState::Running) || self.followed_pc != Some(pc);
+            self.followed_pc = Some(pc);
+            let start = pc.saturating_sub(DISASSEMBLY_CONTEXT * 2);
+            let count = (pc - start) as usize / 2 + DISASSEMBLY_CONTEXT as usize + 1;
+            let listing = disassemble(emulator.memory(), start, count);
+            let rom_start = emulator.load_offset;
+            let rom_end = rom_start as usize + emulator.rom.as_ref().map_or(0, |rom| rom.size);
+
+            ui.checkbox("Branch arrows", &mut emulator.show_branch_arrows);
+            if annotations.selected().is_some() {
+                annotations.draw(ui);
+            }
+            let table_flags = imgui::TableFlags::BORDERS_H
+                | imgui::TableFlags::BORDERS_V
+                | imgui::TableFlags::SCROLL_Y;
+            let Some(_table) = ui.begin_table_with_flags("disassembly_table", 4, table_flags)
+            else {
+                return;
+            };
+            ui.table_setup_column_with(imgui::TableColumnSetup {
+                name: "##flow",
+                flags: imgui::TableColumnFlags::WIDTH_FIXED,
+                init_width_or_weight: GUTTER_WIDTH,
+                user_id: imgui::Id::default(),
+            });
+            ui.table_setup_column("Address");
+            ui.table_setup_column("Opcode");
+            ui.table_setup_column("Instruction");
+            ui.table_setup_scroll_freeze(4, 1);
+            ui.table_headers_row();
+            let mut rows = Vec::new();
+            let mut branches = Vec::new();
+            let mut gutter = [0.0, 0.0];
+            for instruction in &listing {
+                ui.table_next_row();
+                ui.table_set_column_index(0);
+                if emulator.show_branch_arrows {
+                    let [x, y] = ui.cursor_screen_pos();
+                    gutter = [x, x + GUTTER_WIDTH - 4.0];
+                    rows.push(RowPosition {
+                        address: instruction.address,
+                        y: y + ui.text_line_height() * 0.5,
+                    });
+                    if (rom_start as usize..rom_end).contains(&(instruction.address as usize)) {
+                        if let Some((kind, target)) =
+                            branch_target(instruction.address, instruction.opcode)
+                        {
+                            branches.push((instruction.address, kind, target));
+                        }
+                    }
+                }
+                let breakpoint = emulator.breakpoints.contains(&instruction.address);
+                if breakpoint {
+                    ui.table_set_bg_color(TableBgTarget::ROW_BG1, BREAKPOINT_COLOR);
+                }
+                let current = instruction.address == pc;
+                if current {
+                    ui.table_set_bg_color(TableBgTarget::ROW_BG0, [0.0, 1.0, 0.0, 0.1]);
+                    if follow {
+                        ui.set_scroll_here_y();
+                    }
+                }
+                ui.table_set_column_index(1);
+                if let Some(heat) = heatmap_color(emulator, instruction.address) {
+                    ui.table_set_bg_color(TableBgTarget::CELL_BG, heat);
+                }
+                if ui
+                    .selectable_config(annotations.annotations.address_name(instruction.address))
+                    .selected(breakpoint)
+                    .build()
+                {
+                    emulator.toggle_breakpoint(instruction.address);
+                }
+                if ui.is_item_clicked_with_button(imgui::MouseButton::Right) {
+                    annotations.select(instruction.address);
+                }
+                ui.table_set_column_index(2);
+                match instruction.operand {
+                    Some(operand) => ui.text(format!("{:04X} {operand:04X}", instruction.opcode)),
+                    None => ui.text(format!("{:04X}", instruction.opcode)),
+                }
+                ui.table_set_column_index(3);
+                let target = branch_target(instruction.address, instruction.opcode)
+                    .and_then(|(_, target)| annotations.annotations.label(target));
+                match target {
+                    Some(label) => ui.text(format!("{}  ; {label}", instruction.mnemonic)),
+                    None => ui.text(&instruction.mnemonic),
+                }
+            }
+            if emulator.show_branch_arrows {
+                // Back to the gutter column, so the arrows are clipped to it.
+                ui.table_set_column_index(0);
+                draw_branch_arrows(ui, gutter, &rows, &branches);
+            }
+        });
+    }
+}
+
+/// Draws a subroutine and, recursively, everything it calls. Recursive calls are shown
+/// once and marked instead of being expanded again.
+fn draw_node(
+    ui: &Ui,
+    address: u16,
+    callees: &BTreeMap<u16, Vec<(u16, u64)>>,
+    visited: &mut Vec<u16>,
+    count: Option<u64>,
+) {
+    let label = match count {
+        Some(count) => format!("0x{address:03X}  ({count} calls)##{address}"),
+        None => format!("0x{address:03X}##{address}"),
+    };
+    let children = callees.get(&address);
+    if visited.contains(&address) {
+        ui.text_disabled(format!("0x{address:03X}  (recursive)"));
+        return;
+    }
+    if children.is_none() {
+        ui.bullet_text(label.split("##").next().unwrap_or_default());
+        return;
+    }
+    if let Some(_node) = ui
+        .tree_node_config(&label)
+        .default_open(visited.is_empty())
+        .push()
+    {
+        visited.push(address);
+        for (callee, count) in children.into_iter().flatten() {
+            draw_node(ui, *callee, callees, visited, Some(*count));
+        }
+        visited.pop();
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+
+/// One decoded instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u16,
+    pub opcode: u16,
+    /// Second word of the 4 byte XO-CHIP `F000 NNNN` instruction.
+    pub operand: Option<u16>,
+    pub mnemonic: String,
+}
+
+impl Instruction {
+    /// Size in bytes, 4 for `F000 NNNN` and 2 for everything else.
+    pub fn size(&self) -> u16 {
+        if self.operand.is_some() {
+            4
+        } else {
+            2
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04X}", self.opcode)?;
+        match self.operand {
+            Some(operand) => write!(f, " {operand:04X}  {}", self.mnemonic),
+            None => write!(f, "  {}", self.mnemonic),
+        }
+    }
+}
+
+/// Mnemonic for a 2 byte instruction, in the usual Cowgod syntax with the SUPER-CHIP and
+/// XO-CHIP extensions. Words that aren't instructions show up as `DW` data. The low SYS
+/// addresses, inside the interpreter, show as the MegaChip8 instructions there, whose
+/// `01NN` is followed by the low 16 bits of I.
+pub fn mnemonic(inst: u16) -> String {
+    let x = (inst & 0x0F00) >> 8;
+    let y = (inst & 0x00F0) >> 4;
+    let n = inst & 0x000F;
+    let nn = inst & 0x00FF;
+    let nnn = inst & 0x0FFF;
+    match inst & 0xF000 {
+        0x0000 => match inst {
+            0x0010 => String::from("MEGAOFF"),
+            0x0011 => String::from("MEGAON"),
+            0x0100..=0x01FF => format!("LDHI 0x{nn:02X}"),
+            0x0200..=0x02FF => format!("LDPAL {nn}"),
+            0x0300..=0x03FF => format!("SPRW {nn}"),
+            0x0400..=0x04FF => format!("SPRH {nn}"),
+            0x0500..=0x05FF => format!("ALPHA {nn}"),
+            0x0600..=0x060F => format!("DIGISND {n}"),
+            0x0700 => String::from("STOPSND"),
+            0x0800..=0x080F => format!("BMODE {n}"),
+            0x0900..=0x09FF => format!("CCOL {nn}"),
+            0x00E0 => String::from("CLS"),
+            0x00EE => String::from("RET"),
+            0x00FB => String::from("SCR"),
+            0x00FC => String::from("SCL"),
+            0x00FD => String::from("EXIT"),
+            0x00FE => String::from("LOW"),
+            0x00FF => String::from("HIGH"),
+            _ if inst & 0xFFF0 == 0x00C0 => format!("SCD {n}"),
+            _ if inst & 0xFFF0 == 0x00B0 || inst & 0xFFF0 == 0x00D0 => format!("SCU {n}"),
+            _ => format!("SYS 0x{nnn:03X}"),
+        },
+        0x1000 => format!("JP 0x{nnn:03X}"),
+        0x2000 => format!("CALL 0x{nnn:03X}"),
+        0x3000 => format!("SE V{x:X}, 0x{nn:02X}"),
+        0x4000 => format!("SNE V{x:X}, 0x{nn:02X}"),
+        0x5000 => match n {
+            0x0 => format!("SE V{x:X}, V{y:X}"),
+            0x2 => format!("LD [I], V{x:X}-V{y:X}"),
+            0x3 => format!("LD V{x:X}-V{y:X}, [I]"),
+            _ => data(inst),
+        },
+        0x6000 => format!("LD V{x:X}, 0x{nn:02X}"),
+        0x7000 => format!("ADD V{x:X}, 0x{nn:02X}"),
+        0x8000 => {
+            let op = match n {
+                0x0 => "LD",
+                0x1 => "OR",
+                0x2 => "AND",
+                0x3 => "XOR",
+                0x4 => "ADD",
+                0x5 => "SUB",
+                0x6 => "SHR",
+                0x7 => "SUBN",
+                0xE => "SHL",
+                _ => return data(inst),
+            };
+            format!("{op} V{x:X}, V{y:X}")
+        }
+        0x9000 if n == 0 => format!("SNE V{x:X}, V{y:X}"),
+        0xA000 => format!("LD I, 0x{nnn:03X}"),
+        0xB000 => format!("JP V0, 0x{nnn:03X}"),
+        0xC000 => format!("RND V{x:X}, 0x{nn:02X}"),
+        0xD000 => format!("DRW V{x:X}, V{y:X}, {n}"),
+        0xE000 => match nn {
+            0x9E => format!("SKP V{x:X}"),
+            0xA1 => format!("SKNP V{x:X}"),
+            _ => data(inst),
+        },
+        0xF000 => match (inst, nn) {
+            (0xF000, _) => String::from("LD I, long"),
+            (0xF002, _) => String::from("AUDIO"),
+            (_, 0x01) => format!("PLANE {x}"),
+            (_, 0x07) => format!("LD V{x:X}, DT"),
+            (_, 0x0A) => format!("LD V{x:X}, K"),
+            (_, 0x15) => format!("LD DT, V{x:X}"),
+            (_, 0x18) => format!("LD ST, V{x:X}"),
+            (_, 0x1E) => format!("ADD I, V{x:X}"),
+            (_, 0x29) => format!("LD F, V{x:X}"),
+            (_, 0x30) => format!("LD HF, V{x:X}"),
+            (_, 0x33) => format!("LD B, V{x:X}"),
+            (_, 0x3A) => format!("PITCH V{x:X}"),
+            (_, 0x55) => format!("LD [I], V{x:X}"),
+            (_, 0x65) => format!("LD V{x:X}, [I]"),
+            (_, 0x75) => format!("LD R, V{x:X}"),
+            (_, 0x85) => format!("LD V{x:X}, R"),
+            _ => data(inst),
+        },
+        _ => data(inst),
+    }
+}
+
+fn data(inst: u16) -> String {
+    format!("DW 0x{inst:04X}")
+}
+
+fn word_at(memory: &[u8], address: u16) -> u16 {
+    let byte = |address: u16| memory.get(address as usize).copied().unwrap_or(0) as u16;
+    byte(address) << 8 | byte(address.wrapping_add(1))
+}
+
+/// Decodes the instruction at `address`.
+pub fn disassemble_at(memory: &[u8], address: u16) -> Instruction {
+    let opcode = word_at(memory, address);
+    if opcode == 0xF000 {
+        let operand = word_at(memory, address.wrapping_add(2));
+        return Instruction {
+            address,
+            opcode,
+            operand: Some(operand),
+            mnemonic: format!("LD I, 0x{operand:04X}"),
+        };
+    }
+    Instruction {
+        address,
+        opcode,
+        operand: None,
+        mnemonic: mnemonic(opcode),
+    }
+}
+
+/// Decodes `count` consecutive instructions starting at `start`, stopping early at the
+/// end of memory.
+pub fn disassemble(memory: &[u8], start: u16, count: usize) -> Vec<Instruction> {
+    let mut instructions = Vec::with_capacity(count);
+    let mut address = start as usize;
+    while instructions.len() < count && address + 1 < memory.len() {
+        let instruction = disassemble_at(memory, address as u16);
+        address += instruction.size() as usize;
+        instructions.push(instruction);
+    }
+    instructions
+}
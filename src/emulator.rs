@@ -1,6 +1,40 @@
-use std::{fs::File, io::Read};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    ops::{Index, IndexMut, Range},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use imgui::{TableBgTarget, Ui};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+use crate::{
+    buzzer::{BuzzerQueue, BuzzerTick},
+    call_graph::CallGraph,
+    cheats::{Cheat, CheatMode},
+    crash_dump::CrashDump,
+    disassembler::disassemble_at,
+    error::{EmulatorError, Halt, UnknownOpcodePolicy},
+    heatmap::{Access, MemoryHeatmap},
+    history::{History, Undo},
+    hooks::{Hooks, MemoryHook},
+    lint::{lint_rom, LintWarning},
+    machine::MachineProfile,
+    megachip::{self, BlendMode, MegaChip, MegaUndo, Sample},
+    netplay::Netplay,
+    octo,
+    profiler::Profiler,
+    quirks::{IndexIncrement, Quirks, RngMode},
+    replay::{Replay, ReplayFrame, ReplayMode, ReplayStatus},
+    rom::RomInfo,
+    step_diff::{Snapshot, StepDiff},
+    timing::{Clock, Tick},
+    trace::{register_changes, Trace, TraceEntry},
+    watch::Watch,
+};
 
 const FONTSET: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -21,111 +55,1749 @@ const FONTSET: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
-pub const DISPLAY_SIZE: (usize, usize) = (64, 32);
-const MEM_OFFSET: usize = 512;
+/// SUPER-CHIP 8x10 font for the digits, and hex letters as most interpreters add them.
+const BIG_FONTSET: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/// Resolution of the original CHIP-8 display, and of SUPER-CHIP's low resolution mode.
+pub const DISPLAY_SIZE: (usize, usize) = (64, 32);
+/// Resolution of SUPER-CHIP's high resolution mode.
+pub const HIRES_DISPLAY_SIZE: (usize, usize) = (128, 64);
+/// Words [`Display::write_packed`] fills at most, for a high resolution display.
+pub const PACKED_DISPLAY_WORDS: usize = HIRES_DISPLAY_SIZE.0 * HIRES_DISPLAY_SIZE.1 / 16;
+
+/// Addressable memory. 4K on the original machines, XO-CHIP extends it to 64K.
+pub const MEMORY_SIZE: usize = 0x10000;
+/// Nesting depth of subroutine calls, as on SUPER-CHIP and most modern interpreters.
+pub const STACK_SIZE: usize = 16;
+
+/// Display pixels indexed as `display[x][y]`. Each pixel holds one bit per XO-CHIP plane,
+/// so 0 is unlit, 1 lit on the first plane, 2 on the second and 3 on both.
+/// Storage is always sized for high resolution, in low resolution only the top-left
+/// [`DISPLAY_SIZE`] area is used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Display {
+    columns: [[u8; HIRES_DISPLAY_SIZE.1]; HIRES_DISPLAY_SIZE.0],
+    pub hires: bool,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl Display {
+    pub fn new(hires: bool) -> Self {
+        Self {
+            columns: [[0; HIRES_DISPLAY_SIZE.1]; HIRES_DISPLAY_SIZE.0],
+            hires,
+        }
+    }
+
+    /// Width and height of the current resolution.
+    pub fn size(&self) -> (usize, usize) {
+        if self.hires {
+            HIRES_DISPLAY_SIZE
+        } else {
+            DISPLAY_SIZE
+        }
+    }
+
+    /// Columns of the current resolution, left to right.
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        let (width, height) = self.size();
+        self.columns[..width]
+            .iter()
+            .map(move |column| &column[..height])
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        let (width, height) = self.size();
+        self.columns[..width]
+            .iter_mut()
+            .map(move |column| &mut column[..height])
+    }
+
+    pub fn clear(&mut self) {
+        self.columns = [[0; HIRES_DISPLAY_SIZE.1]; HIRES_DISPLAY_SIZE.0];
+    }
+
+    /// Clears the given planes, leaving the others untouched.
+    pub fn clear_planes(&mut self, planes: u8) {
+        for column in self.columns.iter_mut() {
+            for pixel in column.iter_mut() {
+                *pixel &= !planes;
+            }
+        }
+    }
+
+    /// Moves the given planes by `(dx, dy)` pixels, filling the uncovered area with unlit pixels.
+    pub fn scroll(&mut self, planes: u8, dx: isize, dy: isize) {
+        let (width, height) = self.size();
+        let previous = self.columns;
+        for x in 0..width {
+            for y in 0..height {
+                let (src_x, src_y) = (x as isize - dx, y as isize - dy);
+                let moved = if (0..width as isize).contains(&src_x)
+                    && (0..height as isize).contains(&src_y)
+                {
+                    previous[src_x as usize][src_y as usize]
+                } else {
+                    0
+                };
+                let pixel = &mut self.columns[x][y];
+                *pixel = (*pixel & !planes) | (moved & planes);
+            }
+        }
+    }
+
+    /// Pixels of the current resolution row by row, top to bottom, one byte each with the
+    /// plane bits of `display[x][y]`. For embedders drawing into a row-major buffer.
+    pub fn rows(&self) -> Vec<u8> {
+        let mut rows = Vec::new();
+        self.write_rows(&mut rows);
+        rows
+    }
+
+    /// [`Display::rows`] into an existing buffer, reusing its allocation.
+    pub fn write_rows(&self, rows: &mut Vec<u8>) {
+        let (width, height) = self.size();
+        rows.clear();
+        rows.extend(
+            (0..height).flat_map(|y| self.columns[..width].iter().map(move |column| column[y])),
+        );
+    }
+
+    /// Pixels two bits each, 16 to a word starting from the lowest bits, row by row at the
+    /// current resolution. What the renderer uploads for its shader to decode, words past
+    /// the display are zeroed.
+    pub fn write_packed(&self, words: &mut [u32; PACKED_DISPLAY_WORDS]) {
+        let (width, height) = self.size();
+        words.fill(0);
+        for y in 0..height {
+            for (x, column) in self.columns[..width].iter().enumerate() {
+                let index = y * width + x;
+                words[index / 16] |= u32::from(column[y] & 0b11) << (index % 16 * 2);
+            }
+        }
+    }
+
+    /// `(x, y, pixel)` of every lit pixel, column by column.
+    pub fn lit_pixels(&self) -> impl Iterator<Item = (usize, usize, u8)> + '_ {
+        self.iter().enumerate().flat_map(|(x, column)| {
+            column
+                .iter()
+                .enumerate()
+                .filter(|(_, pixel)| **pixel != 0)
+                .map(move |(y, pixel)| (x, y, *pixel))
+        })
+    }
+
+    /// Pixels that differ from `before`, with their value in `before`.
+    fn changes_since(&self, before: &Display) -> Vec<(u8, u8, u8)> {
+        let mut changes = Vec::new();
+        for (x, (column, old_column)) in self.columns.iter().zip(&before.columns).enumerate() {
+            for (y, (pixel, old)) in column.iter().zip(old_column).enumerate() {
+                if pixel != old {
+                    changes.push((x as u8, y as u8, *old));
+                }
+            }
+        }
+        changes
+    }
+}
+
+impl Index<usize> for Display {
+    type Output = [u8];
+
+    fn index(&self, x: usize) -> &[u8] {
+        &self.columns[x][..self.size().1]
+    }
+}
+
+impl IndexMut<usize> for Display {
+    fn index_mut(&mut self, x: usize) -> &mut [u8] {
+        let height = self.size().1;
+        &mut self.columns[x][..height]
+    }
+}
+/// Where programs are loaded and start executing on the COSMAC VIP and most interpreters.
+pub const DEFAULT_LOAD_OFFSET: u16 = 0x200;
+/// Programs written for the ETI-660 are loaded at 0x600 instead.
+pub const ETI660_LOAD_OFFSET: u16 = 0x600;
+const FONT_OFFSET: usize = 0x050;
+/// XO-CHIP pitch register value that plays the audio pattern at 4000 bits per second.
+pub const DEFAULT_PITCH: u8 = 64;
+const BIG_FONT_OFFSET: usize = FONT_OFFSET + FONTSET.len();
+/// Where [`Emulator::load_font`] puts both fonts, the small one followed by the big one.
+pub const FONT_AREA: Range<u16> = FONT_OFFSET as u16..(BIG_FONT_OFFSET + BIG_FONTSET.len()) as u16;
+/// Page the COSMAC VIP's CXNN routine reads its table from, see [`RngMode::Vip`].
+pub const VIP_RNG_PAGE: u16 = 0x100;
+/// Rows of a glyph in the small (FX29) and big (FX30) fonts.
+pub const FONT_HEIGHT: usize = 5;
+pub const BIG_FONT_HEIGHT: usize = 10;
+
+/// Where FX29 points I for hex digit `digit`, only the low nibble counts.
+pub fn font_address(digit: u8) -> u16 {
+    (FONT_OFFSET + (digit & 0x0F) as usize * FONT_HEIGHT) as u16
+}
+
+/// Where FX30 points I for the big hex digit `digit`.
+pub fn big_font_address(digit: u8) -> u16 {
+    (BIG_FONT_OFFSET + (digit & 0x0F) as usize * BIG_FONT_HEIGHT) as u16
+}
+
+fn default_planes() -> u8 {
+    1
+}
+
+fn default_pitch() -> u8 {
+    DEFAULT_PITCH
+}
+
+/// XO-CHIP 1-bit audio: 128 samples played in a loop at a rate set by the pitch register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioPattern {
+    pub bits: [u8; 16],
+    pub pitch: u8,
+}
+
+impl AudioPattern {
+    /// Playback rate in bits per second.
+    pub fn rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+    }
+
+    /// Whether the bit at `index` (0 to 127, most significant bit first) is set.
+    pub fn bit(&self, index: usize) -> bool {
+        self.bits[index / 8] & (0x80 >> (index % 8)) != 0
+    }
+}
+
+/// Load offset implied by a ROM's file extension, if it names a specific machine.
+pub fn load_offset_for(path: &Path) -> Option<u16> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "ch8" | "c8" | "sc8" | "xo8" | megachip::EXTENSION | octo::SOURCE_EXTENSION => {
+            Some(DEFAULT_LOAD_OFFSET)
+        }
+        "eti" | "660" => Some(ETI660_LOAD_OFFSET),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunState {
+    NoROM,
+    Running,
+    Paused,
+}
+
+/// Condition that pauses emulation when a register is modified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterBreak {
+    Off,
+    OnChange,
+    OnValue(u8),
+}
+
+/// Condition that pauses emulation when an instruction writes to a memory address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryWatch {
+    OnWrite,
+    OnChange,
+    OnValue(u8),
+}
+
+impl MemoryWatch {
+    pub const NAMES: [&'static str; 3] = ["On write", "On change", "On value"];
+
+    fn triggers(self, old: u8, new: u8) -> bool {
+        match self {
+            MemoryWatch::OnWrite => true,
+            MemoryWatch::OnChange => old != new,
+            MemoryWatch::OnValue(value) => old != new && new == value,
+        }
+    }
+}
+
+/// Progress of an FX0A instruction. Like on the COSMAC VIP it waits for a key to be
+/// pressed and then released, and stores the released key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyWait {
+    Idle,
+    Waiting,
+    Held(u8),
+}
+
+/// Where a "run until" debugger action should stop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RunTarget {
+    /// Stop once the call at the current PC returns to the next instruction.
+    StepOver { return_pc: u16, depth: usize },
+    /// Stop once the stack is shallower than `depth`.
+    StepOut { depth: usize },
+    /// Stop after the next DXYN.
+    NextDraw,
+}
+
+/// The CPU registers at one point, see [`Emulator::registers`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Registers {
+    pub pc: u16,
+    pub i: u16,
+    pub v: [u8; 16],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    /// Return addresses on the stack.
+    pub stack_depth: usize,
+}
+
+pub struct Emulator {
+    pub max_fps: i32,
+    /// Instructions per frame for [`Emulator::step_frame`], used when stepping frame by frame.
+    pub cpf: i32,
+    /// Drives real-time emulation, see [`Emulator::run_for`].
+    pub clock: Clock,
+    pub quirks: Quirks,
+    pub rng_mode: RngMode,
+    /// Seed every reset starts [`RngMode::Host`] from, a new random one each time if `None`.
+    pub locked_seed: Option<u64>,
+    /// Address ROMs are loaded at and execution starts from. Updated from the file
+    /// extension when a ROM is loaded, see [`load_offset_for`].
+    pub load_offset: u16,
+    /// Machine whose memory layout is emulated, see [`MachineProfile::apply`].
+    pub profile: MachineProfile,
+    pub state: RunState,
+    frame_count: u128,
+    mem: [u8; MEMORY_SIZE],
+    pub display: Display,
+    pc: u16,
+    reg_i: u16,
+    stack: Vec<u16>,
+    delay_timer: u8,
+    sound_timer: u8,
+    regs: [u8; 16],
+    /// SUPER-CHIP RPL user flags, written by FX75 and kept across resets like on the HP-48.
+    /// XO-CHIP extends them from 8 to 16.
+    rpl_flags: [u8; 16],
+    /// XO-CHIP planes drawn and cleared by the display instructions, bit 0 for the first plane.
+    plane_mask: u8,
+    /// XO-CHIP audio pattern loaded by F002, the classic beep is used until one is loaded.
+    audio_bits: Option<[u8; 16]>,
+    pitch: u8,
+    /// R9 of the COSMAC VIP, bumped every frame and by CXNN, see [`RngMode::Vip`].
+    vip_rng: u16,
+    /// Generator behind [`RngMode::Host`]. ChaCha gives the same sequence on every
+    /// platform, so replays recorded on one machine play back on another.
+    rng: ChaCha8Rng,
+    rng_seed: u64,
+    /// Keypad state, true while a key is held.
+    keypad: [bool; 16],
+    /// Keys held by the player, one bit per key. Same as `keypad` except during a replay
+    /// or netplay, where the keypad follows the replay frames or both players' keys.
+    input: u16,
+    replay: ReplayMode,
+    netplay: Option<Netplay>,
+    /// Keys released since FX0A started waiting, one bit per key. Catches taps that are
+    /// pressed and released between two instructions.
+    released_keys: u16,
+    key_wait: KeyWait,
+    pub rom: Option<RomInfo>,
+    /// Contents of the loaded ROM, reloaded when its file can't be read again (a ROM opened
+    /// in the browser has no file), see [`Emulator::reload_rom`].
+    rom_data: Vec<u8>,
+    pub register_breaks: [RegisterBreak; 16],
+    /// Addresses that pause emulation before the instruction there executes.
+    pub breakpoints: BTreeSet<u16>,
+    pub memory_watches: BTreeMap<u16, MemoryWatch>,
+    /// First watched write of the current instruction: address, old and new value.
+    watch_hit: Option<(u16, u8, u8)>,
+    /// Set by 00FD, stops the run loops in [`Emulator::check_breaks`] like a breakpoint.
+    exited: bool,
+    /// Set when the debugger pauses emulation, so listings can scroll to PC once.
+    break_hit: bool,
+    /// Why emulation was last paused by the debugger, shown in the control flow window.
+    pub break_reason: Option<String>,
+    /// Error that stopped the CPU, cleared by a reset or a restored state.
+    halted: Option<Halt>,
+    /// What an opcode the interpreter doesn't implement does, kept across resets.
+    pub unknown_opcodes: UnknownOpcodePolicy,
+    /// Directory a [`CrashDump`] is written to whenever the CPU halts, none by default.
+    /// Kept across resets.
+    pub crash_dumps: Option<PathBuf>,
+    /// Where the dump of the current halt was written.
+    crash_dump_path: Option<PathBuf>,
+    run_target: Option<RunTarget>,
+    pub call_graph: CallGraph,
+    pub show_branch_arrows: bool,
+    /// Problems found scanning the loaded ROM, see [`lint_rom`].
+    pub lint_warnings: Vec<LintWarning>,
+    /// Executed instructions that can be stepped back, see [`Emulator::step_back`].
+    pub history: History,
+    /// Instruction log for the "Trace" window, off until enabled.
+    pub trace: Trace,
+    /// Execution counts for the "Profiler" window, off until enabled.
+    pub profiler: Profiler,
+    /// Memory access counts for the "Memory heatmap" window, off until enabled.
+    pub heatmap: MemoryHeatmap,
+    /// Undo record of the instruction being executed, collects its memory writes.
+    recording: Option<Undo>,
+    /// Set by DXYN with [`Quirks::display_wait`], ends the current frame.
+    waiting_vblank: bool,
+    /// Set by DXYN, for [`Emulator::run_to_draw`].
+    drew: bool,
+    /// Addresses written by instructions since the last reset, see [`Emulator::was_written`].
+    written: BTreeSet<u16>,
+    /// Memory the game can't change, applied while the ROM runs and kept across resets.
+    pub cheats: Vec<Cheat>,
+    /// Expressions shown in the "Watches" window, kept across resets. The ones breaking on
+    /// change are checked after every instruction.
+    pub watches: Vec<Watch>,
+    /// Where every timer tick reports the buzzer state for playback, kept across resets.
+    pub buzzer: Option<BuzzerQueue>,
+    /// Callbacks installed with [`Emulator::set_hooks`], kept across resets.
+    hooks: Option<Box<dyn Hooks>>,
+    /// Peripherals added with [`Emulator::map_memory`], in mapping order, kept across
+    /// resets.
+    memory_hooks: Vec<(Range<u16>, Box<dyn MemoryHook>)>,
+    /// Memory writes of the instruction being executed, reported to the hooks after it.
+    hook_writes: Vec<(u16, u8)>,
+    /// What the last debugger step changed, cleared once emulation runs on.
+    last_step: Option<StepDiff>,
+    /// [`Emulator::framebuffer`], rebuilt when `framebuffer_stale`.
+    framebuffer: Vec<u8>,
+    framebuffer_stale: bool,
+    /// Set by instructions that change what's shown, see
+    /// [`Emulator::display_changed_since_last_read`].
+    display_changed: bool,
+    /// MegaChip8 mode state, present from 0011 until 0010, see [`Emulator::megachip`].
+    megachip: Option<Box<MegaChip>>,
+    /// ROM bytes past the 64 KiB of memory, only reachable by MegaChip8's 24-bit I.
+    high_mem: Vec<u8>,
+}
+
+/// Complete machine state, enough to resume execution exactly where it was taken.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveState {
+    pub frame_count: u64,
+    pub mem: Vec<u8>,
+    /// Column-major over the current resolution, same layout as [`Display::iter`].
+    pub display: Vec<u8>,
+    #[serde(default)]
+    pub hires: bool,
+    pub pc: u16,
+    pub reg_i: u16,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub regs: [u8; 16],
+    /// R9 of the COSMAC VIP, see [`RngMode::Vip`].
+    #[serde(default, alias = "lfsr")]
+    pub vip_rng: u16,
+    #[serde(default)]
+    pub rng_seed: u64,
+    /// Position in the [`RngMode::Host`] sequence.
+    #[serde(default)]
+    pub rng_word_pos: u128,
+    #[serde(default = "default_planes")]
+    pub plane_mask: u8,
+    #[serde(default)]
+    pub audio_bits: Option<[u8; 16]>,
+    #[serde(default = "default_pitch")]
+    pub pitch: u8,
+    /// A DXYN with [`Quirks::display_wait`] is waiting for the next frame.
+    #[serde(default)]
+    pub waiting_vblank: bool,
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Emulator {
+    pub fn new() -> Self {
+        let mut emulator = Self {
+            max_fps: 60,
+            cpf: 10,
+            clock: Clock::default(),
+            quirks: Quirks::default(),
+            rng_mode: RngMode::Host,
+            locked_seed: None,
+            load_offset: DEFAULT_LOAD_OFFSET,
+            profile: MachineProfile::Generic,
+            state: RunState::NoROM,
+            frame_count: 0,
+            mem: [0; MEMORY_SIZE],
+            regs: [0; 16],
+            rpl_flags: [0; 16],
+            plane_mask: 1,
+            audio_bits: None,
+            pitch: DEFAULT_PITCH,
+            vip_rng: 0,
+            rng: ChaCha8Rng::seed_from_u64(0),
+            rng_seed: 0,
+            display: Display::default(),
+            pc: DEFAULT_LOAD_OFFSET,
+            reg_i: 0,
+            stack: Vec::new(),
+            delay_timer: 0,
+            sound_timer: 0,
+            keypad: [false; 16],
+            input: 0,
+            replay: ReplayMode::Off,
+            netplay: None,
+            released_keys: 0,
+            key_wait: KeyWait::Idle,
+            rom: None,
+            rom_data: Vec::new(),
+            register_breaks: [RegisterBreak::Off; 16],
+            breakpoints: BTreeSet::new(),
+            memory_watches: BTreeMap::new(),
+            watch_hit: None,
+            exited: false,
+            break_hit: false,
+            break_reason: None,
+            halted: None,
+            unknown_opcodes: UnknownOpcodePolicy::default(),
+            crash_dumps: None,
+            crash_dump_path: None,
+            run_target: None,
+            call_graph: CallGraph::new(DEFAULT_LOAD_OFFSET),
+            show_branch_arrows: true,
+            lint_warnings: Vec::new(),
+            history: History::default(),
+            trace: Trace::default(),
+            profiler: Profiler::default(),
+            heatmap: MemoryHeatmap::default(),
+            recording: None,
+            waiting_vblank: false,
+            drew: false,
+            written: BTreeSet::new(),
+            cheats: Vec::new(),
+            watches: Vec::new(),
+            buzzer: None,
+            hooks: None,
+            memory_hooks: Vec::new(),
+            hook_writes: Vec::new(),
+            last_step: None,
+            framebuffer: Vec::new(),
+            framebuffer_stale: true,
+            display_changed: true,
+            megachip: None,
+            high_mem: Vec::new(),
+        };
+        emulator.reseed();
+        emulator
+    }
+
+    /// Emulator whose [`RngMode::Host`] numbers always start from `seed`, see
+    /// [`Emulator::locked_seed`].
+    pub fn with_seed(seed: u64) -> Self {
+        let mut emulator = Self::new();
+        emulator.locked_seed = Some(seed);
+        emulator.set_seed(seed);
+        emulator
+    }
+
+    pub fn reset(&mut self) {
+        self.mem = [0; MEMORY_SIZE];
+        self.high_mem.clear();
+        self.megachip = None;
+        self.state = RunState::NoROM;
+        self.frame_count = 0;
+        self.regs = [0; 16];
+        self.vip_rng = 0;
+        self.reseed();
+        self.plane_mask = 1;
+        self.audio_bits = None;
+        self.pitch = DEFAULT_PITCH;
+        self.waiting_vblank = false;
+        self.display = Display::default();
+        self.mark_display_changed();
+        self.pc = self.load_offset;
+        self.reg_i = 0;
+        self.stack = Vec::new();
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.keypad = [false; 16];
+        self.input = 0;
+        self.replay = ReplayMode::Off;
+        self.stop_netplay();
+        self.released_keys = 0;
+        self.key_wait = KeyWait::Idle;
+        self.clock.reset();
+        self.rom = None;
+        self.break_reason = None;
+        self.halted = None;
+        self.crash_dump_path = None;
+        self.watch_hit = None;
+        self.exited = false;
+        self.run_target = None;
+        self.last_step = None;
+        self.call_graph = CallGraph::new(self.load_offset);
+        self.lint_warnings.clear();
+        self.history.clear();
+        self.trace.clear();
+        self.profiler.reset();
+        self.heatmap.reset();
+        self.written.clear();
+        for watch in &mut self.watches {
+            watch.reset();
+        }
+    }
+
+    /// Removes every breakpoint, register break and memory watch.
+    pub fn clear_breakpoints(&mut self) {
+        self.register_breaks = [RegisterBreak::Off; 16];
+        self.breakpoints.clear();
+        self.memory_watches.clear();
+    }
+
+    pub fn toggle_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.remove(&address) {
+            self.breakpoints.insert(address);
+        }
+    }
+
+    /// Whether the debugger paused emulation since the last call.
+    pub fn take_break_hit(&mut self) -> bool {
+        std::mem::take(&mut self.break_hit)
+    }
+
+    pub fn pause(&mut self) {
+        self.state = RunState::Paused;
+    }
+
+    /// Resumes emulation, unless the CPU is halted by an error.
+    pub fn resume(&mut self) {
+        if self.halted.is_some() {
+            return;
+        }
+        self.state = RunState::Running;
+        self.break_reason = None;
+    }
+
+    fn break_execution(&mut self, reason: String) {
+        self.state = RunState::Paused;
+        self.break_reason = Some(reason);
+        self.break_hit = true;
+        self.run_target = None;
+    }
+
+    /// Error that stopped the CPU, if any. The emulator stays paused until it's reset.
+    pub fn halted(&self) -> Option<&Halt> {
+        self.halted.as_ref()
+    }
+
+    /// Where the [`CrashDump`] of the current halt was written, see
+    /// [`Emulator::crash_dumps`].
+    pub fn crash_dump_path(&self) -> Option<&Path> {
+        self.crash_dump_path.as_deref()
+    }
+
+    fn halt(&mut self, address: u16, error: EmulatorError) {
+        let halt = Halt { address, error };
+        log::error!("{halt}");
+        self.state = RunState::Paused;
+        self.break_hit = true;
+        self.run_target = None;
+        self.halted = Some(halt);
+        let dump = CrashDump::capture(self);
+        if let (Some(dir), Some(dump)) = (&self.crash_dumps, dump) {
+            match dump.save(dir) {
+                Ok(path) => {
+                    log::info!("Crash dump written to {}", path.display());
+                    self.crash_dump_path = Some(path);
+                }
+                Err(err) => log::error!("Failed to write crash dump: {err}"),
+            }
+        }
+    }
+
+    /// Executes a single instruction without ticking the timers.
+    pub fn step_instruction(&mut self) {
+        self.diff_step(|emulator| {
+            let regs = emulator.regs;
+            if emulator.internal_step() {
+                emulator.check_breaks(&regs);
+            }
+        });
+    }
+
+    /// Runs a debugger step, keeping what it changed for [`Emulator::last_step`].
+    fn diff_step<R>(&mut self, step: impl FnOnce(&mut Self) -> R) -> R {
+        let before = Snapshot::of(self);
+        let result = step(self);
+        self.last_step = Some(StepDiff::between(&before, self));
+        result
+    }
+
+    /// What the last step, frame step or step back changed. `None` once emulation ran
+    /// on or after a reset.
+    pub fn last_step(&self) -> Option<&StepDiff> {
+        self.last_step.as_ref()
+    }
+
+    /// Runs a 2NNN call at the current PC until it returns, or a single instruction otherwise.
+    pub fn step_over(&mut self) {
+        if self.curr_inst() & 0xF000 == 0x2000 {
+            self.run_target = Some(RunTarget::StepOver {
+                return_pc: self.pc.wrapping_add(2),
+                depth: self.stack.len(),
+            });
+            self.state = RunState::Running;
+            self.break_reason = None;
+        } else {
+            self.step_instruction();
+        }
+    }
+
+    /// Runs until the current subroutine returns to its caller.
+    pub fn step_out(&mut self) {
+        if self.stack.is_empty() {
+            return;
+        }
+        self.run_target = Some(RunTarget::StepOut {
+            depth: self.stack.len(),
+        });
+        self.state = RunState::Running;
+        self.break_reason = None;
+    }
+
+    /// Runs until the next DXYN has drawn, to see how a frame is built sprite by sprite.
+    pub fn run_to_draw(&mut self) {
+        self.drew = false;
+        self.run_target = Some(RunTarget::NextDraw);
+        self.state = RunState::Running;
+        self.break_reason = None;
+    }
+
+    fn check_run_target(&mut self) -> bool {
+        let reached = match self.run_target {
+            Some(RunTarget::StepOver { return_pc, depth }) => {
+                self.pc == return_pc && self.stack.len() == depth
+            }
+            Some(RunTarget::StepOut { depth }) => self.stack.len() < depth,
+            Some(RunTarget::NextDraw) => std::mem::take(&mut self.drew),
+            None => false,
+        };
+        if reached {
+            self.state = RunState::Paused;
+            self.run_target = None;
+        }
+        reached
+    }
+
+    pub fn load_rom(&mut self, path: String) -> Result<(), EmulatorError> {
+        let data = fs::read(&path)?;
+        self.load_rom_data(path, &data)
+    }
+
+    /// Loads a ROM that was already read, `path` names it and picks the load offset from its
+    /// extension like [`Emulator::load_rom`]. Used where there is no file system, e.g. for a
+    /// ROM picked in the browser. Octo sources (`.8o`) are assembled first, see [`octo`].
+    pub fn load_rom_data(
+        &mut self,
+        path: impl Into<PathBuf>,
+        data: &[u8],
+    ) -> Result<(), EmulatorError> {
+        let path = path.into();
+        // Reloading assembles the source again, so that is what is kept.
+        let source = data;
+        let assembled;
+        let data = if octo::is_source(&path) {
+            let text = std::str::from_utf8(data)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            assembled = octo::assemble(text)?;
+            &assembled[..]
+        } else {
+            data
+        };
+        let profile = self.profile.for_rom(&path);
+        if profile != self.profile {
+            profile.apply(self);
+        }
+        let offset = load_offset_for(&path).unwrap_or(self.load_offset);
+        let max = self.profile.max_rom_size(offset);
+        if data.len() > max {
+            return Err(EmulatorError::RomTooLarge {
+                size: data.len(),
+                max,
+            });
+        }
+        self.load_offset = offset;
+        let offset = offset as usize;
+        // Only MegaChip8 ROMs go past the end of memory, the rest is read through its I.
+        let len = data.len().min(MEMORY_SIZE - offset);
+        self.mem[offset..offset + len].copy_from_slice(&data[..len]);
+        self.high_mem = data[len..].to_vec();
+        self.pc = self.load_offset;
+        self.call_graph = CallGraph::new(self.load_offset);
+        self.rom = Some(RomInfo::new(path, source));
+        self.rom_data = source.to_vec();
+        self.lint_warnings = lint_rom(&data[..len], self.load_offset);
+        if !self.lint_warnings.is_empty() {
+            log::warn!("ROM has {} lint warnings", self.lint_warnings.len());
+        }
+        self.resume();
+        Ok(())
+    }
+
+    /// Resets the machine and loads the current ROM again from disk, or from the bytes it
+    /// was loaded from if the file can't be found.
+    pub fn reload_rom(&mut self) -> Result<(), EmulatorError> {
+        let path = self
+            .rom
+            .as_ref()
+            .map(|rom| rom.path.clone())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No ROM loaded."))?;
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => std::mem::take(&mut self.rom_data),
+            Err(err) => return Err(err.into()),
+        };
+        self.reset();
+        self.load_font();
+        self.load_rom_data(path, &data)
+    }
+
+    /// Holds a keypad key down, only the low nibble of `key` is used like on EX9E.
+    pub fn press_key(&mut self, key: u8) {
+        let key = key & 0xF;
+        self.input |= 1 << key;
+        if self.keypad_follows_input() {
+            self.set_key(key, true);
+        }
+    }
+
+    pub fn release_key(&mut self, key: u8) {
+        let key = key & 0xF;
+        self.input &= !(1 << key);
+        if self.keypad_follows_input() {
+            self.set_key(key, false);
+        }
+    }
+
+    fn keypad_follows_input(&self) -> bool {
+        matches!(self.replay, ReplayMode::Off) && self.netplay.is_none()
+    }
+
+    fn set_key(&mut self, key: u8, down: bool) {
+        if !down && self.keypad[key as usize] {
+            self.released_keys |= 1 << key;
+        }
+        self.keypad[key as usize] = down;
+    }
+
+    /// Sets the whole keypad, bit N for key N.
+    fn set_keys(&mut self, keys: u16) {
+        for key in 0..16 {
+            self.set_key(key, keys & (1 << key) != 0);
+        }
+    }
+
+    /// Whether a keypad key is held, only the low nibble of `key` is used like on EX9E.
+    pub fn is_key_down(&self, key: u8) -> bool {
+        self.keypad[(key & 0xF) as usize]
+    }
+
+    pub fn keypad(&self) -> &[bool; 16] {
+        &self.keypad
+    }
+
+    /// Whether the buzzer should be sounding, i.e. the sound timer is running.
+    pub fn sound_active(&self) -> bool {
+        self.sound_timer > 0
+    }
+
+    /// The XO-CHIP audio pattern to play instead of the beep, once a ROM has loaded one.
+    pub fn audio_pattern(&self) -> Option<AudioPattern> {
+        self.audio_bits.map(|bits| AudioPattern {
+            bits,
+            pitch: self.pitch,
+        })
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn reg_i(&self) -> u16 {
+        self.reg_i
+    }
+
+    pub fn regs(&self) -> &[u8; 16] {
+        &self.regs
+    }
+
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn frame_count(&self) -> u128 {
+        self.frame_count
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.mem
+    }
+
+    /// All the CPU registers in one go, for embedders that show or log them.
+    pub fn registers(&self) -> Registers {
+        Registers {
+            pc: self.pc,
+            i: self.reg_i,
+            v: self.regs,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            stack_depth: self.stack.len(),
+        }
+    }
+
+    /// The display row by row, see [`Display::rows`]. `display.size()` gives its width.
+    /// The buffer is kept between calls and only rebuilt after the display changed.
+    pub fn framebuffer(&mut self) -> &[u8] {
+        if self.framebuffer_stale {
+            self.display.write_rows(&mut self.framebuffer);
+            self.framebuffer_stale = false;
+        }
+        &self.framebuffer
+    }
+
+    /// Whether what's shown changed since the last call, so embedders only redraw when
+    /// needed. True the first time. Set by the draw, clear and scroll instructions, and by
+    /// MegaChip8's 00E0 showing a new frame. Edits made through [`Emulator::display`]
+    /// directly need [`Emulator::mark_display_changed`].
+    pub fn display_changed_since_last_read(&mut self) -> bool {
+        std::mem::take(&mut self.display_changed)
+    }
+
+    /// Flags the display as changed, for [`Emulator::framebuffer`] and
+    /// [`Emulator::display_changed_since_last_read`].
+    pub fn mark_display_changed(&mut self) {
+        self.display_changed = true;
+        self.framebuffer_stale = true;
+    }
+
+    /// MegaChip8 mode state while the ROM has it on, its screen replacing
+    /// [`Emulator::display`]. `None` otherwise.
+    pub fn megachip(&self) -> Option<&MegaChip> {
+        self.megachip.as_deref()
+    }
+
+    /// Bytes the loaded ROM was read from, the source text for Octo files.
+    pub fn rom_data(&self) -> &[u8] {
+        &self.rom_data
+    }
+
+    /// Whether an instruction (FX33, FX55) stored to `address` since the ROM was loaded or a
+    /// state restored. Debugger pokes don't count.
+    pub fn was_written(&self, address: u16) -> bool {
+        self.written.contains(&address)
+    }
+
+    /// Moves PC for the debugger, it has to leave room for a whole instruction.
+    pub fn set_pc(&mut self, pc: u16) -> Result<(), EmulatorError> {
+        self.check_range(pc, 2)?;
+        self.pc = pc;
+        self.break_hit = true;
+        Ok(())
+    }
+
+    pub fn set_reg_i(&mut self, value: u16) {
+        self.reg_i = value;
+    }
+
+    /// Writes V`reg`, for the debugger. Panics if `reg` isn't 0 to 15.
+    pub fn set_reg(&mut self, reg: usize, value: u8) {
+        self.regs[reg] = value;
+    }
+
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.delay_timer = value;
+    }
+
+    pub fn set_sound_timer(&mut self, value: u8) {
+        self.sound_timer = value;
+    }
+
+    /// Patches a byte of memory for the debugger. Unlike writes done by instructions it
+    /// doesn't trigger memory watches or go into the history.
+    pub fn poke(&mut self, address: u16, value: u8) -> Result<(), EmulatorError> {
+        self.check_range(address, 1)?;
+        self.mem[address as usize] = value;
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> SaveState {
+        SaveState {
+            frame_count: self.frame_count as u64,
+            mem: self.mem.to_vec(),
+            display: self.display.iter().flatten().copied().collect(),
+            hires: self.display.hires,
+            pc: self.pc,
+            reg_i: self.reg_i,
+            stack: self.stack.clone(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            regs: self.regs,
+            vip_rng: self.vip_rng,
+            rng_seed: self.rng_seed,
+            rng_word_pos: self.rng.get_word_pos(),
+            plane_mask: self.plane_mask,
+            audio_bits: self.audio_bits,
+            pitch: self.pitch,
+            waiting_vblank: self.waiting_vblank,
+        }
+    }
+
+    /// Restores a snapshot and leaves the emulator paused on it. Ends any replay.
+    pub fn restore(&mut self, state: &SaveState) {
+        self.frame_count = state.frame_count as u128;
+        let len = state.mem.len().min(self.mem.len());
+        self.mem[..len].copy_from_slice(&state.mem[..len]);
+        self.display = Display::new(state.hires);
+        let (width, height) = self.display.size();
+        for (i, pixel) in state.display.iter().enumerate().take(width * height) {
+            self.display[i / height][i % height] = *pixel;
+        }
+        self.mark_display_changed();
+        self.pc = state.pc;
+        self.reg_i = state.reg_i;
+        self.stack = state.stack.clone();
+        self.delay_timer = state.delay_timer;
+        self.sound_timer = state.sound_timer;
+        self.regs = state.regs;
+        self.vip_rng = state.vip_rng;
+        self.set_seed(state.rng_seed);
+        self.rng.set_word_pos(state.rng_word_pos);
+        self.plane_mask = state.plane_mask;
+        self.audio_bits = state.audio_bits;
+        self.pitch = state.pitch;
+        self.waiting_vblank = state.waiting_vblank;
+        // Save states don't carry the MegaChip8 screen, so they restore to the CHIP-8 one.
+        self.megachip = None;
+        self.history.clear();
+        self.last_step = None;
+        self.written.clear();
+        self.halted = None;
+        self.crash_dump_path = None;
+        self.stop_replay();
+        self.stop_netplay();
+        self.pause();
+    }
+
+    /// Undoes the last executed instruction, returns false once the history is exhausted.
+    /// Does nothing during a replay or netplay, going back would make it diverge.
+    pub fn step_back(&mut self) -> bool {
+        self.diff_step(Self::undo_step)
+    }
+
+    fn undo_step(&mut self) -> bool {
+        if !self.keypad_follows_input() {
+            return false;
+        }
+        let Some(undo) = self.history.pop() else {
+            return false;
+        };
+        // FX0A starts over if execution goes back into it.
+        self.key_wait = KeyWait::Idle;
+        // Going back before a failed instruction clears the halt.
+        self.halted = None;
+        self.crash_dump_path = None;
+        self.pc = undo.pc;
+        self.reg_i = undo.reg_i;
+        self.regs = undo.regs;
+        self.rpl_flags = undo.rpl_flags;
+        self.stack = undo.stack;
+        self.delay_timer = undo.delay_timer;
+        self.sound_timer = undo.sound_timer;
+        self.vip_rng = undo.vip_rng;
+        self.rng.set_word_pos(undo.rng_word_pos);
+        self.plane_mask = undo.plane_mask;
+        self.audio_bits = undo.audio_bits;
+        self.pitch = undo.pitch;
+        self.waiting_vblank = undo.waiting_vblank;
+        if let Some(megachip) = undo.megachip {
+            self.megachip = megachip;
+        }
+        if let (Some(saved), Some(megachip)) = (undo.mega_state, self.megachip.as_deref_mut()) {
+            megachip.restore(saved);
+        }
+        for (address, value) in undo.mem.into_iter().rev() {
+            self.mem[address as usize] = value;
+        }
+        self.display.hires = undo.hires;
+        for (x, y, pixel) in undo.pixels {
+            self.display.columns[x as usize][y as usize] = pixel;
+        }
+        self.mark_display_changed();
+        true
+    }
+
+    /// Steps back a frame worth of instructions, used while the rewind key is held.
+    pub fn rewind_frame(&mut self) {
+        for _ in 0..self.cpf.max(1) {
+            if !self.undo_step() {
+                break;
+            }
+        }
+    }
+
+    fn record_undo(&self, inst: u16) -> Undo {
+        Undo {
+            pc: self.pc,
+            reg_i: self.reg_i,
+            regs: self.regs,
+            rpl_flags: self.rpl_flags,
+            stack: self.stack.clone(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            vip_rng: self.vip_rng,
+            rng_word_pos: self.rng.get_word_pos(),
+            plane_mask: self.plane_mask,
+            audio_bits: self.audio_bits,
+            pitch: self.pitch,
+            waiting_vblank: self.waiting_vblank,
+            hires: self.display.hires,
+            megachip: None,
+            mega_state: self.record_megachip(inst),
+            mem: Vec::new(),
+            pixels: Vec::new(),
+        }
+    }
+
+    /// What `inst` is about to change of the MegaChip8 state, `None` outside the mode and
+    /// for instructions that leave it alone.
+    fn record_megachip(&self, inst: u16) -> Option<MegaUndo> {
+        let megachip = self.megachip.as_deref()?;
+        match inst & 0xF000 {
+            0x0000 => {
+                let buffers = matches!(inst, 0x00E0 | 0x00FB | 0x00FC | 0x00B0..=0x00CF);
+                Some(megachip.save(buffers))
+            }
+            0xD000 => {
+                let x = self.regs[(inst as usize & 0x0F00) >> 8];
+                let y = self.regs[(inst as usize & 0x00F0) >> 4];
+                Some(megachip.save_sprite_area(x as usize, y as usize))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn load_font(&mut self) {
+        self.mem[FONT_OFFSET..FONT_OFFSET + FONTSET.len()].clone_from_slice(&FONTSET);
+        self.mem[BIG_FONT_OFFSET..BIG_FONT_OFFSET + BIG_FONTSET.len()]
+            .clone_from_slice(&BIG_FONTSET);
+    }
+
+    /// The instruction at the program counter.
+    pub fn curr_inst(&self) -> u16 {
+        self.word_at(self.pc)
+    }
+
+    fn word_at(&self, address: u16) -> u16 {
+        (self.mem[address as usize] as u16) << 8 | self.mem[address.wrapping_add(1) as usize] as u16
+    }
+
+    /// Skips the next instruction, which is 4 bytes long if it's an XO-CHIP F000 NNNN or a
+    /// MegaChip8 01NN NNNN.
+    fn skip(&mut self) {
+        let inst = self.word_at(self.pc);
+        let len = if inst == 0xF000 || (self.megachip.is_some() && inst & 0xFF00 == 0x0100) {
+            4
+        } else {
+            2
+        };
+        self.pc = self.pc.wrapping_add(len);
+    }
+
+    fn tick_timers(&mut self) {
+        let sample = self
+            .megachip
+            .as_mut()
+            .and_then(|megachip| megachip.next_sample_tick());
+        if let Some(buzzer) = &self.buzzer {
+            buzzer.push(BuzzerTick {
+                active: self.sound_timer > 0,
+                pattern: self.audio_pattern(),
+                speed: self.clock.effective_speed(),
+                sample,
+            });
+        }
+
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        // The VIP's display interrupt also increments R9, mixing frame timing into CXNN.
+        self.vip_rng = self.vip_rng.wrapping_add(1);
+
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+
+        self.heatmap.tick();
+        self.apply_cheats(false);
+        self.call_hooks(|hooks, emulator| hooks.on_frame(emulator));
+
+        // While recording, key changes only reach the game at the start of a frame.
+        if let ReplayMode::Recording(replay) = &mut self.replay {
+            let keys = self.input;
+            replay.frames.push(ReplayFrame {
+                keys,
+                instructions: 0,
+            });
+            self.set_keys(keys);
+        }
+    }
+
+    pub fn step_frame(&mut self) {
+        self.diff_step(Self::run_frame);
+    }
+
+    fn run_frame(&mut self) {
+        if let ReplayMode::Playing { .. } = self.replay {
+            self.play_frame();
+            return;
+        }
+        if self.netplay.is_some() {
+            self.netplay_frame();
+            return;
+        }
+        self.tick_timers();
+        self.waiting_vblank = false;
+
+        for _ in 0..self.cpf {
+            let regs = self.regs;
+            if !self.internal_step()
+                || self.check_breaks(&regs)
+                || self.check_run_target()
+                || self.waiting_vblank
+            {
+                break;
+            }
+        }
+
+        self.frame_count += 1;
+    }
+
+    /// Runs for `elapsed` wall time: instructions at [`Clock::cpu_hz`] and timers at 60 Hz,
+    /// both scaled by [`Clock::speed`]. Used for real-time emulation instead of
+    /// [`Emulator::step_frame`], so the speed doesn't depend on how often it's called.
+    pub fn run_for(&mut self, elapsed: Duration) {
+        self.clock.advance(elapsed);
+        while let Some(tick) = self.clock.next_tick() {
+            let playing = matches!(self.replay, ReplayMode::Playing { .. });
+            let netplay = self.netplay.is_some();
+            match tick {
+                // Replays run each frame's recorded instructions on its timer tick.
+                Tick::Timer if playing => {
+                    if !self.play_frame() {
+                        self.clock.reset();
+                        break;
+                    }
+                }
+                Tick::Instruction if playing => {}
+                // Netplay runs whole frames once both players' keys for them are known.
+                Tick::Timer if netplay => {
+                    if !self.netplay_frame() {
+                        self.clock.reset();
+                        break;
+                    }
+                }
+                Tick::Instruction if netplay => {}
+                Tick::Timer => {
+                    self.tick_timers();
+                    self.waiting_vblank = false;
+                    self.frame_count += 1;
+                }
+                // A draw waiting for the display idles the CPU until the next frame.
+                Tick::Instruction if self.waiting_vblank => {}
+                Tick::Instruction => {
+                    let regs = self.regs;
+                    if !self.internal_step() || self.check_breaks(&regs) || self.check_run_target()
+                    {
+                        self.clock.reset();
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Seed [`RngMode::Host`] was started from, a new random one on every reset.
+    pub fn rng_seed(&self) -> u64 {
+        self.rng_seed
+    }
+
+    /// Restarts the [`RngMode::Host`] sequence from `seed`, e.g. to reproduce a run. Only
+    /// until the next reset unless the seed is also [`Emulator::locked_seed`].
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng_seed = seed;
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    fn reseed(&mut self) {
+        self.set_seed(self.locked_seed.unwrap_or_else(rand::random));
+    }
+
+    /// Restarts the ROM and records the keypad of every frame from there, until
+    /// [`Emulator::stop_input_recording`]. Key presses only reach the game at the start of
+    /// the next frame while recording, so the replay sees them at the same point.
+    pub fn start_input_recording(&mut self) -> Result<(), EmulatorError> {
+        self.reload_rom()?;
+        let rom_hash = self
+            .rom
+            .as_ref()
+            .map(|rom| rom.hash.clone())
+            .unwrap_or_default();
+        self.replay = ReplayMode::Recording(Replay {
+            rom_hash,
+            seed: self.rng_seed,
+            quirks: self.quirks,
+            rng_mode: self.rng_mode,
+            // Instructions run before the first timer tick.
+            frames: vec![ReplayFrame {
+                keys: self.input,
+                instructions: 0,
+            }],
+        });
+        self.set_keys(self.input);
+        Ok(())
+    }
+
+    /// Ends the recording and returns it, `None` if nothing was being recorded.
+    pub fn stop_input_recording(&mut self) -> Option<Replay> {
+        match std::mem::take(&mut self.replay) {
+            ReplayMode::Recording(replay) => Some(replay),
+            other => {
+                self.replay = other;
+                None
+            }
+        }
+    }
 
-pub enum RunState {
-    NoROM,
-    Running,
-    Paused,
-}
+    /// Restarts the ROM with the replay's quirks and seed and feeds it the recorded keys
+    /// frame by frame, ignoring the player's. Emulation pauses when the replay ends.
+    pub fn play_replay(&mut self, replay: Replay) -> Result<(), EmulatorError> {
+        let rom_hash = self.rom.as_ref().map(|rom| rom.hash.as_str());
+        if rom_hash != Some(replay.rom_hash.as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Replay was recorded on a different ROM.",
+            )
+            .into());
+        }
+        self.reload_rom()?;
+        self.quirks = replay.quirks;
+        self.rng_mode = replay.rng_mode;
+        self.set_seed(replay.seed);
+        self.replay = ReplayMode::Playing {
+            replay,
+            next: 0,
+            remaining: 0,
+        };
+        Ok(())
+    }
 
-pub struct Emulator {
-    pub max_fps: i32,
-    pub cpf: i32,
-    pub shift_swap: bool,
-    pub complex_jump: bool,
-    pub state: RunState,
-    frame_count: u128,
-    mem: [u8; 4096],
-    pub display: [[u8; DISPLAY_SIZE.1]; DISPLAY_SIZE.0],
-    pc: u16,
-    reg_i: u16,
-    stack: Vec<u16>,
-    delay_timer: u8,
-    sound_timer: u8,
-    regs: [u8; 16],
-    pub key: Option<u8>,
-}
+    /// Stops recording or playing, dropping the recording. The keypad goes back to the
+    /// keys the player holds.
+    pub fn stop_replay(&mut self) {
+        self.replay = ReplayMode::Off;
+        self.set_keys(self.input);
+    }
 
-impl Emulator {
-    pub fn new() -> Self {
-        Self {
-            max_fps: 60,
-            cpf: 10,
-            shift_swap: false,
-            complex_jump: false,
-            state: RunState::NoROM,
-            frame_count: 0,
-            mem: [0; 4096],
-            regs: [0; 16],
-            display: [[0; 32]; 64],
-            pc: MEM_OFFSET as u16,
-            reg_i: 0,
-            stack: Vec::new(),
-            delay_timer: 0,
-            sound_timer: 0,
-            key: None,
+    pub fn replay_status(&self) -> ReplayStatus {
+        match &self.replay {
+            ReplayMode::Off => ReplayStatus::Off,
+            ReplayMode::Recording(replay) => ReplayStatus::Recording {
+                frames: replay.frames.len(),
+            },
+            ReplayMode::Playing { replay, next, .. } => ReplayStatus::Playing {
+                frame: *next,
+                frames: replay.frames.len(),
+            },
         }
     }
 
-    pub fn reset(&mut self) {
-        self.mem = [0; 4096];
-        self.state = RunState::NoROM;
-        self.frame_count = 0;
-        self.mem = [0; 4096];
-        self.regs = [0; 16];
-        self.display = [[0; 32]; 64];
-        self.pc = MEM_OFFSET as u16;
-        self.reg_i = 0;
-        self.stack = Vec::new();
-        self.delay_timer = 0;
-        self.sound_timer = 0;
-        self.key = None;
+    /// Runs the rest of the current replay frame, or the next one if it's done. Returns
+    /// false if a break stopped it or the replay is over.
+    fn play_frame(&mut self) -> bool {
+        let ReplayMode::Playing {
+            replay,
+            next,
+            remaining,
+        } = &mut self.replay
+        else {
+            return false;
+        };
+        if *remaining == 0 {
+            let Some(frame) = replay.frames.get(*next).copied() else {
+                self.stop_replay();
+                self.pause();
+                self.break_reason = Some(String::from("Replay finished."));
+                return false;
+            };
+            let first = *next == 0;
+            *next += 1;
+            *remaining = frame.instructions;
+            // Frame 0 is what ran before the first timer tick.
+            if !first {
+                self.tick_timers();
+                self.frame_count += 1;
+            }
+            self.waiting_vblank = false;
+            self.set_keys(frame.keys);
+        }
+
+        loop {
+            let ReplayMode::Playing { remaining, .. } = &mut self.replay else {
+                return false;
+            };
+            if *remaining == 0 {
+                return true;
+            }
+            *remaining -= 1;
+            let regs = self.regs;
+            if !self.internal_step() || self.check_breaks(&regs) || self.check_run_target() {
+                return false;
+            }
+        }
     }
 
-    pub fn pause(&mut self) {
-        self.state = RunState::Paused;
+    /// Restarts the ROM with the settings of the session's host and runs it in lockstep
+    /// with the other player from there, see [`crate::netplay`].
+    pub fn start_netplay(&mut self, netplay: Netplay) -> Result<(), EmulatorError> {
+        self.reload_rom()?;
+        let handshake = netplay.handshake();
+        self.quirks = handshake.quirks;
+        self.rng_mode = handshake.rng_mode;
+        self.cpf = handshake.cpf;
+        self.set_seed(handshake.seed);
+        self.netplay = Some(netplay);
+        Ok(())
     }
 
-    pub fn resume(&mut self) {
-        self.state = RunState::Running;
+    /// Leaves the netplay session, the keypad goes back to the player's keys.
+    pub fn stop_netplay(&mut self) {
+        if let Some(netplay) = self.netplay.take() {
+            netplay.close();
+            self.set_keys(self.input);
+        }
     }
 
-    pub fn load_rom(&mut self, path: String) {
-        let mut file = File::open(path).expect("Not able to open ROM file.");
-        file.read(&mut self.mem[MEM_OFFSET..])
-            .expect("Memory overflow while reading ROM.");
-        self.resume();
+    pub fn netplay(&self) -> Option<&Netplay> {
+        self.netplay.as_ref()
     }
 
-    pub fn load_font(&mut self) {
-        self.mem[0x050..0x0A0].clone_from_slice(&FONTSET);
+    /// Runs the next netplay frame with both players' keys. Returns false if the other
+    /// player's keys haven't arrived yet or the session ended.
+    fn netplay_frame(&mut self) -> bool {
+        let Some(netplay) = &mut self.netplay else {
+            return false;
+        };
+        let keys = match netplay.next_frame(self.input) {
+            Ok(Some(keys)) => keys,
+            Ok(None) => return false,
+            Err(err) => {
+                self.stop_netplay();
+                self.pause();
+                self.break_reason = Some(format!("Netplay ended: {err}"));
+                return false;
+            }
+        };
+        self.set_keys(keys);
+        self.tick_timers();
+        self.waiting_vblank = false;
+        // Breakpoints are ignored, pausing one side would only stall the other. An exited
+        // program just runs 00FD once a frame.
+        for _ in 0..self.cpf {
+            if !self.internal_step() || self.waiting_vblank || std::mem::take(&mut self.exited) {
+                break;
+            }
+        }
+        self.frame_count += 1;
+        true
     }
 
-    fn curr_inst(&self) -> u16 {
-        (self.mem[self.pc as usize] as u16) << 8 | self.mem[(self.pc + 1) as usize] as u16
+    /// Writes the enabled cheats due after an instruction, or at the start of a frame.
+    fn apply_cheats(&mut self, after_instruction: bool) {
+        for cheat in self.cheats.iter_mut().filter(|cheat| cheat.enabled) {
+            if after_instruction && cheat.mode != CheatMode::Instruction {
+                continue;
+            }
+            if let Some(byte) = self.mem.get_mut(cheat.address as usize) {
+                *byte = cheat.value;
+            }
+            if cheat.mode == CheatMode::Once {
+                cheat.enabled = false;
+            }
+        }
     }
 
-    pub fn step(&mut self) {
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
+    /// Installs callbacks run as the emulator executes, replacing the previous ones. `None`
+    /// removes them.
+    pub fn set_hooks(&mut self, hooks: Option<Box<dyn Hooks>>) {
+        self.hooks = hooks;
+    }
+
+    pub fn has_hooks(&self) -> bool {
+        self.hooks.is_some()
+    }
+
+    /// Maps a peripheral over `range`: data instructions read and write there go through
+    /// `hook`, see [`MemoryHook`]. Ranges may overlap, the hooks then run in the order they
+    /// were mapped.
+    pub fn map_memory(&mut self, range: Range<u16>, hook: Box<dyn MemoryHook>) {
+        self.memory_hooks.push((range, hook));
+    }
+
+    /// Removes the peripherals mapped over `address`.
+    pub fn unmap_memory(&mut self, address: u16) {
+        self.memory_hooks
+            .retain(|(range, _)| !range.contains(&address));
+    }
+
+    /// Address ranges with a peripheral mapped, in mapping order.
+    pub fn mapped_memory(&self) -> impl Iterator<Item = Range<u16>> + '_ {
+        self.memory_hooks.iter().map(|(range, _)| range.clone())
+    }
+
+    /// Calls the hooks with the emulator, which they can't reach while it holds them. Hooks
+    /// installed by the call replace the ones it was made on.
+    fn call_hooks(&mut self, call: impl FnOnce(&mut dyn Hooks, &mut Emulator)) {
+        let Some(mut hooks) = self.hooks.take() else {
+            return;
+        };
+        call(hooks.as_mut(), self);
+        if self.hooks.is_none() {
+            self.hooks = Some(hooks);
         }
+    }
 
-        if self.sound_timer > 0 {
-            self.sound_timer -= 1;
-            //TODO: Do sound
+    /// Executes instructions until `pred` holds or `max_cycles` instructions have run.
+    /// Timers tick every `cpf` instructions, so timing matches running frame by frame with
+    /// [`Emulator::step_frame`], and a draw waiting for the display (see [`Quirks::display_wait`])
+    /// uses up the rest of its frame's cycles. Stops early if a breakpoint or watch triggers.
+    /// Returns whether the predicate was met.
+    pub fn run_until(&mut self, mut pred: impl FnMut(&Emulator) -> bool, max_cycles: u64) -> bool {
+        let cpf = self.cpf.max(1) as u64;
+        let mut cycle = 0;
+        while cycle < max_cycles {
+            if pred(self) {
+                return true;
+            }
+            if cycle % cpf == 0 {
+                self.tick_timers();
+            }
+            let regs = self.regs;
+            if !self.internal_step() {
+                break;
+            }
+            cycle += 1;
+            if std::mem::take(&mut self.waiting_vblank) {
+                cycle = cycle.div_ceil(cpf) * cpf;
+            }
+            if cycle % cpf == 0 {
+                self.frame_count += 1;
+            }
+            if self.check_breaks(&regs) {
+                break;
+            }
+        }
+        pred(self)
+    }
+
+    /// Pauses if the last instruction triggered a register break, memory watch or watch
+    /// expression, or if PC reached a breakpoint. `before` holds the registers before the instruction.
+    fn check_breaks(&mut self, before: &[u8; 16]) -> bool {
+        if std::mem::take(&mut self.exited) {
+            self.break_execution(String::from("Program exited (00FD)"));
+            return true;
+        }
+        if let Some((address, old, new)) = self.watch_hit.take() {
+            let pc = self.pc.wrapping_sub(2);
+            self.break_execution(format!(
+                "mem[0x{address:03X}] written 0x{old:02X} -> 0x{new:02X} near 0x{pc:03X}"
+            ));
+            return true;
+        }
+        for (i, condition) in self.register_breaks.iter().enumerate() {
+            let (old, new) = (before[i], self.regs[i]);
+            let triggered = match condition {
+                RegisterBreak::Off => false,
+                RegisterBreak::OnChange => old != new,
+                RegisterBreak::OnValue(value) => old != new && new == *value,
+            };
+            if triggered {
+                let address = self.pc.wrapping_sub(2);
+                self.break_execution(format!(
+                    "V{i:X} changed 0x{old:02X} -> 0x{new:02X} near 0x{address:03X}"
+                ));
+                return true;
+            }
+        }
+        // Taken out so they can be evaluated against the rest of the machine.
+        let mut watches = std::mem::take(&mut self.watches);
+        let mut changed = None;
+        for watch in &mut watches {
+            if let Some((old, new)) = watch.check(self) {
+                changed.get_or_insert_with(|| (watch.expression.text().to_string(), old, new));
+            }
+        }
+        self.watches = watches;
+        if let Some((text, old, new)) = changed {
+            let address = self.pc.wrapping_sub(2);
+            self.break_execution(format!(
+                "{text} changed {old} -> {new} near 0x{address:03X}"
+            ));
+            return true;
+        }
+        if self.breakpoints.contains(&self.pc) {
+            self.break_execution(format!("Breakpoint at 0x{:03X}", self.pc));
+            return true;
+        }
+        false
+    }
+
+    /// Executes the instruction at PC, recording it in the history if enabled. If it fails,
+    /// PC is left on it and the CPU is halted. Returns whether it executed.
+    fn internal_step(&mut self) -> bool {
+        self.last_step = None;
+        self.call_hooks(|hooks, emulator| {
+            let pc = emulator.pc;
+            hooks.on_instruction(emulator, pc)
+        });
+        let pc = self.pc;
+        let traced = self
+            .trace
+            .is_enabled()
+            .then(|| (disassemble_at(&self.mem, pc), self.regs, self.reg_i));
+        let profiled = self
+            .profiler
+            .is_enabled()
+            .then(|| (self.curr_inst(), Instant::now()));
+        let len = if self.word_at(pc) == 0xF000 { 4 } else { 2 };
+        self.heatmap.record(pc, len, Access::Execute);
+        match self.try_step() {
+            Ok(()) => {
+                if let ReplayMode::Recording(replay) = &mut self.replay {
+                    if let Some(frame) = replay.frames.last_mut() {
+                        frame.instructions += 1;
+                    }
+                }
+                if let Some((inst, started)) = profiled {
+                    let frame = self.frame_count;
+                    self.profiler.record(pc, inst, frame, started.elapsed());
+                }
+                if let Some((instruction, regs, reg_i)) = traced {
+                    self.trace.push(TraceEntry {
+                        frame: self.frame_count as u64,
+                        instruction,
+                        changes: register_changes((&regs, reg_i), (&self.regs, self.reg_i)),
+                    });
+                }
+                self.apply_cheats(true);
+                for (address, value) in std::mem::take(&mut self.hook_writes) {
+                    self.call_hooks(|hooks, emulator| {
+                        hooks.on_memory_write(emulator, address, value)
+                    });
+                }
+                true
+            }
+            Err(error) => {
+                self.pc = pc;
+                self.recording = None;
+                self.hook_writes.clear();
+                match error {
+                    EmulatorError::UnknownOpcode(inst)
+                        if self.unknown_opcodes == UnknownOpcodePolicy::Break =>
+                    {
+                        self.break_execution(format!("Unknown opcode {inst:04X} at 0x{pc:03X}"));
+                    }
+                    error => self.halt(pc, error),
+                }
+                false
+            }
         }
+    }
 
-        for n in 0..self.cpf {
-            self.internal_step();
+    fn try_step(&mut self) -> Result<(), EmulatorError> {
+        if self.history.depth() == 0 {
+            return self.execute_or_skip();
+        }
+        // Only the 0NNN group and DXYN touch the display, skip comparing it otherwise.
+        let inst = self.curr_inst();
+        let display = matches!(inst & 0xF000, 0x0000 | 0xD000).then_some(self.display);
+        self.recording = Some(self.record_undo(inst));
+        self.execute_or_skip()?;
+        if let Some(mut undo) = self.recording.take() {
+            if let Some(before) = display {
+                undo.pixels = self.display.changes_since(&before);
+            }
+            self.history.push(undo);
         }
+        Ok(())
+    }
 
-        self.frame_count += 1;
+    /// Fails unless `len` bytes starting at `address` are all in the profile's memory, see
+    /// [`MachineProfile::memory_size`]. Instructions check this before touching anything,
+    /// so a failed one leaves the machine as it was.
+    fn check_range(&self, address: u16, len: usize) -> Result<(), EmulatorError> {
+        if address as usize + len > self.profile.memory_size() {
+            return Err(EmulatorError::MemoryOutOfBounds(address));
+        }
+        Ok(())
     }
-    fn internal_step(&mut self) {
+
+    /// [`Emulator::execute`], going on past unknown opcodes with
+    /// [`UnknownOpcodePolicy::Skip`].
+    fn execute_or_skip(&mut self) -> Result<(), EmulatorError> {
+        match self.execute() {
+            Err(EmulatorError::UnknownOpcode(inst))
+                if self.unknown_opcodes == UnknownOpcodePolicy::Skip =>
+            {
+                // Fetching already moved PC past it.
+                let address = self.pc.wrapping_sub(2);
+                log::warn!("Skipped unknown opcode {inst:04X} at 0x{address:03X}");
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn execute(&mut self) -> Result<(), EmulatorError> {
+        self.check_range(self.pc, 2)?;
         let inst: u16 = self.curr_inst();
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
 
         let x: u8 = ((inst & 0x0F00) >> 8) as u8;
         let y: u8 = ((inst & 0x00F0) >> 4) as u8;
@@ -134,17 +1806,31 @@ impl Emulator {
         let nnn: u16 = inst & 0x0FFF;
 
         match inst & 0xF000 {
+            0x0000 if self.megachip.is_some() => self.execute_megachip(inst)?,
             //CLEAR SCREEN
             0x0000 => match inst {
+                0x0011 if self.profile == MachineProfile::MegaChip => self.op_set_megachip(true),
                 0x00E0 => self.op_clear_screen(),
-                0x00EE => self.op_ret(),
-                _ => {}
+                0x00EE => self.op_ret()?,
+                0x00FB => self.op_scroll_right(),
+                0x00FC => self.op_scroll_left(),
+                0x00FD => self.op_exit(),
+                0x00FE => self.op_set_hires(false),
+                0x00FF => self.op_set_hires(true),
+                _ if inst & 0xFFF0 == 0x00C0 => self.op_scroll_down(n),
+                _ if inst & 0xFFF0 == 0x00D0 => self.op_scroll_up(n),
+                _ => return Err(EmulatorError::UnknownOpcode(inst)),
             },
             0x1000 => self.op_jump(nnn),
-            0x2000 => self.op_subroutine(nnn),
+            0x2000 => self.op_subroutine(nnn)?,
             0x3000 => self.op_eq_skip(x, nn),
             0x4000 => self.op_neq_skip(x, nn),
-            0x5000 => self.op_req_skip(x, y),
+            0x5000 => match n {
+                0x0 => self.op_req_skip(x, y),
+                0x2 => self.op_store_range(x, y)?,
+                0x3 => self.op_load_range(x, y)?,
+                _ => return Err(EmulatorError::UnknownOpcode(inst)),
+            },
             0x6000 => self.op_set_reg(x, nn),
             0x7000 => self.op_add_reg(x, nn),
             0x8000 => match n {
@@ -154,89 +1840,228 @@ impl Emulator {
                 0x3 => self.op_xor(x, y),
                 0x4 => self.op_add(x, y),
                 0x5 => self.op_sub(x, y),
-                0x6 => self.op_shift_r(x, y, self.shift_swap),
+                0x6 => self.op_shift_r(x, y, self.quirks.shift_swap),
                 0x7 => self.op_rsub(x, y),
-                0xE => self.op_shift_l(x, y, self.shift_swap),
-                _ => {
-                    eprintln!("Instruction {:X} not yet implemented.", inst);
-                }
+                0xE => self.op_shift_l(x, y, self.quirks.shift_swap),
+                _ => return Err(EmulatorError::UnknownOpcode(inst)),
             },
             0x9000 => self.op_rneq_skip(x, y),
             0xA000 => self.op_set_ireg(nnn),
             0xB000 => {
-                if !self.complex_jump {
+                if !self.quirks.complex_jump {
                     self.op_jump_off(nnn)
                 } else {
                     self.op_jump_coff(nnn, x);
                 }
             }
             0xC000 => self.op_rng(x, nn),
-            0xD000 => self.op_display(x, y, n),
+            0xD000 if self.megachip.is_some() => self.op_mega_display(x, y),
+            0xD000 => self.op_display(x, y, n)?,
             0xE000 => match nn {
                 0x9E => self.op_key_skip(x),
                 0xA1 => self.op_nkey_skip(x),
-                _ => {
-                    eprintln!("Instruction {:X} not yet implemented.", inst);
-                }
+                _ => return Err(EmulatorError::UnknownOpcode(inst)),
             },
             0xF000 => match nn {
+                0x00 if x == 0 => self.op_long_ireg()?,
+                0x01 => self.op_select_planes(x),
+                0x02 if x == 0 => self.op_load_audio()?,
+                0x3A => self.op_set_pitch(x),
                 0x07 => self.op_check_timer(x),
                 0x15 => self.op_set_dtimer(x),
                 0x18 => self.op_set_stimer(x),
                 0x1E => self.op_add_ireg(x),
                 0x0A => self.op_get_key(x),
                 0x29 => self.op_font_char(x),
-                0x33 => self.op_decimals(x),
-                0x55 => self.op_store(x),
-                0x65 => self.op_load(x),
-                _ => {
-                    eprintln!("Instruction {:X} not yet implemented.", inst);
-                }
+                0x30 => self.op_big_font_char(x),
+                0x33 => self.op_decimals(x)?,
+                0x55 => self.op_store(x)?,
+                0x65 => self.op_load(x)?,
+                0x75 => self.op_store_flags(x),
+                0x85 => self.op_load_flags(x),
+                _ => return Err(EmulatorError::UnknownOpcode(inst)),
             },
-            _ => {
-                eprintln!("Instruction {:X} not yet implemented.", inst);
+            _ => return Err(EmulatorError::UnknownOpcode(inst)),
+        }
+        Ok(())
+    }
+
+    /// The 0 group in MegaChip8 mode, where the screen instructions act on its buffers
+    /// instead of [`Emulator::display`].
+    fn execute_megachip(&mut self, inst: u16) -> Result<(), EmulatorError> {
+        let n = (inst & 0x000F) as u8;
+        let nn = (inst & 0x00FF) as u8;
+        match inst {
+            0x0010 => self.op_set_megachip(false),
+            0x0011 => {}
+            0x00E0 => {
+                self.mega().present();
+                self.mark_display_changed();
+            }
+            0x00EE => self.op_ret()?,
+            0x00FB => self.mega().scroll(4, 0),
+            0x00FC => self.mega().scroll(-4, 0),
+            0x00FD => self.op_exit(),
+            0x00B0..=0x00BF => self.mega().scroll(0, -(n as isize)),
+            0x00C0..=0x00CF => self.mega().scroll(0, n as isize),
+            0x0100..=0x01FF => self.op_mega_long_ireg(nn)?,
+            0x0200..=0x02FF => self.op_load_palette(nn),
+            0x0300..=0x03FF => self.mega().sprite_width = nn as usize,
+            0x0400..=0x04FF => self.mega().sprite_height = nn as usize,
+            0x0500..=0x05FF => self.mega().alpha = nn,
+            0x0600..=0x060F => self.op_play_sample(n == 0),
+            0x0700 => self.mega().sample = None,
+            0x0800..=0x080F => {
+                self.mega().blend =
+                    BlendMode::from_operand(n).ok_or(EmulatorError::UnknownOpcode(inst))?
             }
+            0x0900..=0x09FF => self.mega().collision_color = nn,
+            _ => return Err(EmulatorError::UnknownOpcode(inst)),
+        }
+        Ok(())
+    }
+
+    /// Only called in MegaChip8 mode.
+    fn mega(&mut self) -> &mut MegaChip {
+        self.megachip
+            .as_deref_mut()
+            .expect("MegaChip8 mode is off.")
+    }
+
+    /// 0011 and 0010. The CHIP-8 display stays as it was underneath. Only the MegaChip8
+    /// profile knows 0011, elsewhere it's an unknown opcode.
+    fn op_set_megachip(&mut self, on: bool) {
+        let previous = std::mem::replace(&mut self.megachip, on.then(Box::default));
+        self.mark_display_changed();
+        if let Some(undo) = &mut self.recording {
+            undo.megachip = Some(previous);
+        }
+    }
+
+    /// I with the bits MegaChip8's 01NN NNNN sets above 16.
+    fn long_i(&self) -> u32 {
+        let high = self.megachip.as_ref().map_or(0, |megachip| megachip.i_high);
+        (high as u32) << 16 | self.reg_i as u32
+    }
+
+    /// Byte at a 24-bit address, from the ROM past the end of memory above 64 KiB and 0
+    /// past its end.
+    fn read_long(&mut self, address: u32) -> u8 {
+        match address.checked_sub(MEMORY_SIZE as u32) {
+            None => self.read_mem(address as u16),
+            Some(offset) => self.high_mem.get(offset as usize).copied().unwrap_or(0),
         }
     }
 
+    fn read_long_range(&mut self, address: u32, len: usize) -> Vec<u8> {
+        (0..len as u32)
+            .map(|i| self.read_long(address + i))
+            .collect()
+    }
+
+    fn op_mega_long_ireg(&mut self, high: u8) -> Result<(), EmulatorError> {
+        self.op_long_ireg()?;
+        self.mega().i_high = high;
+        Ok(())
+    }
+
+    fn op_load_palette(&mut self, colors: u8) {
+        let colors = self.read_long_range(self.long_i(), colors as usize * 4);
+        self.mega().load_palette(&colors);
+    }
+
+    fn op_play_sample(&mut self, looping: bool) {
+        let sample = Sample::read(self.long_i(), looping, |address| self.read_long(address));
+        self.mega().sample = Some(sample);
+    }
+
+    fn op_mega_display(&mut self, reg_x: u8, reg_y: u8) {
+        let (width, height) = self.mega().sprite_size();
+        let sprite = self.read_long_range(self.long_i(), width * height);
+        let (x, y) = (self.regs[reg_x as usize], self.regs[reg_y as usize]);
+        let collision = self.mega().draw_sprite(x as usize, y as usize, &sprite);
+        self.regs[15] = collision as u8;
+        self.drew = true;
+    }
+
     fn op_clear_screen(&mut self) {
-        self.display = [[0; 32]; 64];
+        self.display.clear_planes(self.plane_mask);
+        self.mark_display_changed();
+    }
+
+    fn op_set_hires(&mut self, hires: bool) {
+        self.display = Display::new(hires);
+        self.mark_display_changed();
+    }
+
+    fn op_scroll_down(&mut self, rows: u8) {
+        self.scroll_display(0, rows as isize);
+    }
+
+    fn op_scroll_up(&mut self, rows: u8) {
+        self.scroll_display(0, -(rows as isize));
+    }
+
+    fn op_scroll_right(&mut self) {
+        self.scroll_display(4, 0);
+    }
+
+    fn op_scroll_left(&mut self) {
+        self.scroll_display(-4, 0);
+    }
+
+    fn scroll_display(&mut self, dx: isize, dy: isize) {
+        self.display.scroll(self.plane_mask, dx, dy);
+        self.mark_display_changed();
+    }
+
+    /// 00FD, the SUPER-CHIP way to quit. There's nothing to return to, so just stop.
+    fn op_exit(&mut self) {
+        self.pc = self.pc.wrapping_sub(2);
+        self.exited = true;
     }
 
     fn op_jump(&mut self, address: u16) {
         self.pc = address;
     }
 
-    fn op_subroutine(&mut self, address: u16) {
+    fn op_subroutine(&mut self, address: u16) -> Result<(), EmulatorError> {
+        if self.stack.len() >= STACK_SIZE {
+            return Err(EmulatorError::StackOverflow);
+        }
         self.stack.push(self.pc);
         self.pc = address;
+        self.call_graph.on_call(address);
+        Ok(())
     }
 
-    fn op_ret(&mut self) {
-        self.pc = self.stack.pop().unwrap();
+    fn op_ret(&mut self) -> Result<(), EmulatorError> {
+        self.pc = self.stack.pop().ok_or(EmulatorError::StackUnderflow)?;
+        self.call_graph.on_return();
+        Ok(())
     }
 
     fn op_eq_skip(&mut self, reg: u8, val: u8) {
         if self.regs[reg as usize] == val {
-            self.pc += 2;
+            self.skip();
         }
     }
 
     fn op_neq_skip(&mut self, reg: u8, val: u8) {
         if self.regs[reg as usize] != val {
-            self.pc += 2;
+            self.skip();
         }
     }
 
     fn op_req_skip(&mut self, reg_x: u8, reg_y: u8) {
         if self.regs[reg_x as usize] == self.regs[reg_y as usize] {
-            self.pc += 2;
+            self.skip();
         }
     }
 
     fn op_rneq_skip(&mut self, reg_x: u8, reg_y: u8) {
         if self.regs[reg_x as usize] != self.regs[reg_y as usize] {
-            self.pc += 2;
+            self.skip();
         }
     }
 
@@ -254,14 +2079,24 @@ impl Emulator {
 
     fn op_or(&mut self, reg_x: u8, reg_y: u8) {
         self.regs[reg_x as usize] |= self.regs[reg_y as usize];
+        self.reset_vf();
     }
 
     fn op_and(&mut self, reg_x: u8, reg_y: u8) {
         self.regs[reg_x as usize] &= self.regs[reg_y as usize];
+        self.reset_vf();
     }
 
     fn op_xor(&mut self, reg_x: u8, reg_y: u8) {
         self.regs[reg_x as usize] ^= self.regs[reg_y as usize];
+        self.reset_vf();
+    }
+
+    /// The COSMAC VIP clobbers VF in the logic instructions.
+    fn reset_vf(&mut self) {
+        if self.quirks.vf_reset {
+            self.regs[15] = 0;
+        }
     }
 
     fn op_add(&mut self, reg_x: u8, reg_y: u8) {
@@ -277,34 +2112,29 @@ impl Emulator {
     }
 
     fn op_rsub(&mut self, reg_x: u8, reg_y: u8) {
-        let sub = self.regs[reg_y as usize].checked_sub(self.regs[reg_x as usize]);
-        match sub {
-            Some(x) => {
-                self.regs[reg_x as usize] = x;
-                self.regs[15] = 0;
-            }
-            None => self.regs[15] = 1,
-        }
+        let (val, overflow) = self.regs[reg_y as usize].overflowing_sub(self.regs[reg_x as usize]);
+        self.regs[reg_x as usize] = val;
+        self.regs[15] = if overflow { 0 } else { 1 };
     }
 
+    /// Like the other 8XYN instructions, VF is written last so the flag wins over VF as X.
     fn op_shift_r(&mut self, reg_x: u8, reg_y: u8, swap: bool) {
-        if swap {
-            self.regs[reg_x as usize] = self.regs[reg_y as usize];
-        }
-        self.regs[15] = self.regs[reg_x as usize] & 0b0000_0001;
-        self.regs[reg_x as usize] >>= 1;
+        let val = self.regs[if swap { reg_y } else { reg_x } as usize];
+        self.regs[reg_x as usize] = val >> 1;
+        self.regs[15] = val & 0b0000_0001;
     }
 
     fn op_shift_l(&mut self, reg_x: u8, reg_y: u8, swap: bool) {
-        if swap {
-            self.regs[reg_x as usize] = self.regs[reg_y as usize];
-        }
-        self.regs[15] = (self.regs[reg_x as usize] & 0b1000_0000) >> 7;
-        self.regs[reg_x as usize] <<= 1;
+        let val = self.regs[if swap { reg_y } else { reg_x } as usize];
+        self.regs[reg_x as usize] = val << 1;
+        self.regs[15] = (val & 0b1000_0000) >> 7;
     }
 
     fn op_set_ireg(&mut self, val: u16) {
         self.reg_i = val;
+        if let Some(megachip) = &mut self.megachip {
+            megachip.i_high = 0;
+        }
     }
 
     fn op_jump_off(&mut self, address: u16) {
@@ -316,58 +2146,89 @@ impl Emulator {
     }
 
     fn op_rng(&mut self, reg: u8, val: u8) {
-        let rng = rand::random::<u8>();
+        let rng = match self.rng_mode {
+            RngMode::Host => self.rng.gen::<u8>(),
+            RngMode::Vip => self.next_vip_byte(),
+        };
         self.regs[reg as usize] = rng & val;
     }
 
-    fn op_display(&mut self, reg_x: u8, reg_y: u8, val: u8) {
-        let pos_x = self.regs[reg_x as usize] % DISPLAY_SIZE.0 as u8;
-        let pos_y = self.regs[reg_y as usize] % DISPLAY_SIZE.1 as u8;
-        self.regs[15] = 0;
+    /// The VIP interpreter's CXNN routine: R9 is incremented and its low byte picks a byte
+    /// of the page at [`VIP_RNG_PAGE`]. That byte plus R9's high byte, rotated right through
+    /// the carry and added to itself, is the random number and the new high byte.
+    fn next_vip_byte(&mut self) -> u8 {
+        self.vip_rng = self.vip_rng.wrapping_add(1);
+        let [high, low] = self.vip_rng.to_be_bytes();
+        let table = self.mem[(VIP_RNG_PAGE + low as u16) as usize];
+        let (sum, carry) = high.overflowing_add(table);
+        let rotated = sum >> 1 | (carry as u8) << 7;
+        let byte = rotated.wrapping_add(sum);
+        self.vip_rng = u16::from_be_bytes([byte, low]);
+        byte
+    }
 
-        for y in 0..val {
-            if pos_y + y >= DISPLAY_SIZE.1 as u8 {
-                break;
-            }
+    /// DXYN draws an 8xN sprite, DXY0 a 16x16 one as on SUPER-CHIP. With both XO-CHIP
+    /// planes selected, the second plane's sprite data follows the first one's.
+    fn op_display(&mut self, reg_x: u8, reg_y: u8, val: u8) -> Result<(), EmulatorError> {
+        let (width, height) = self.display.size();
+        let pos_x = self.regs[reg_x as usize] as usize % width;
+        let pos_y = self.regs[reg_y as usize] as usize % height;
+        let (sprite_width, rows) = if val == 0 {
+            (16, 16)
+        } else {
+            (8, val as usize)
+        };
+        let bytes_per_row = sprite_width / 8;
+        let planes = self.plane_mask.count_ones() as usize;
+        self.check_range(self.reg_i, planes * rows * bytes_per_row)?;
+        self.heatmap
+            .record(self.reg_i, planes * rows * bytes_per_row, Access::Read);
+        let clipping = self.quirks.clipping;
+        self.regs[15] = 0;
 
-            let sprite: u8 = self.mem[(self.reg_i + y as u16) as usize];
-            for x in 0..8 {
-                if pos_x + x >= DISPLAY_SIZE.0 as u8 {
+        let mut address = self.reg_i as usize;
+        let plane_mask = self.plane_mask;
+        for plane in [1u8, 2].into_iter().filter(|plane| plane_mask & plane != 0) {
+            for y in 0..rows {
+                if clipping && pos_y + y >= height {
                     break;
                 }
 
-                // let pixel = sprite & (1 << 7 - x);
-                let display = self.display[(pos_x + x) as usize][(pos_y + y) as usize];
-
-                if sprite & (0b1000_0000 >> x) != 0 {
-                    if display == 1 {
-                        self.regs[15] = 1;
+                let row_address = address + y * bytes_per_row;
+                let mut sprite = 0u16;
+                for byte in 0..bytes_per_row {
+                    sprite = sprite << 8 | self.read_mem((row_address + byte) as u16) as u16;
+                }
+                for x in 0..sprite_width {
+                    if clipping && pos_x + x >= width {
+                        break;
                     }
 
-                    self.display[(pos_x + x) as usize][(pos_y + y) as usize] ^= 1;
+                    if sprite & (1 << (sprite_width - 1 - x)) != 0 {
+                        let pixel = &mut self.display[(pos_x + x) % width][(pos_y + y) % height];
+                        if *pixel & plane != 0 {
+                            self.regs[15] = 1;
+                        }
+                        *pixel ^= plane;
+                    }
                 }
             }
+            address += rows * bytes_per_row;
         }
+        self.waiting_vblank = self.quirks.display_wait;
+        self.drew = true;
+        self.mark_display_changed();
+        Ok(())
     }
 
     fn op_key_skip(&mut self, reg: u8) {
-        match self.key {
-            Some(key) => {
-                if key == self.regs[reg as usize] {
-                    self.pc += 2;
-                }
-            }
-            None => {}
+        if self.is_key_down(self.regs[reg as usize]) {
+            self.skip();
         }
     }
     fn op_nkey_skip(&mut self, reg: u8) {
-        match self.key {
-            Some(key) => {
-                if key != self.regs[reg as usize] {
-                    self.pc += 2;
-                }
-            }
-            None => {}
+        if !self.is_key_down(self.regs[reg as usize]) {
+            self.skip();
         }
     }
 
@@ -382,142 +2243,187 @@ impl Emulator {
     }
 
     fn op_add_ireg(&mut self, reg: u8) {
-        self.reg_i += self.regs[reg as usize] as u16;
+        self.reg_i = self.reg_i.wrapping_add(self.regs[reg as usize] as u16);
         if self.reg_i > 0x1000 {
             self.regs[15] = 1;
         }
     }
 
+    /// FX0A, repeats until a key has been pressed and released.
     fn op_get_key(&mut self, reg: u8) {
-        match self.key {
-            Some(key) => self.regs[reg as usize] = key,
-            None => self.pc -= 2,
+        if self.key_wait == KeyWait::Idle {
+            self.released_keys = 0;
+            self.key_wait = KeyWait::Waiting;
+        }
+        let released = match self.key_wait {
+            KeyWait::Held(key) if !self.keypad[key as usize] => Some(key),
+            KeyWait::Waiting if self.released_keys != 0 => {
+                Some(self.released_keys.trailing_zeros() as u8)
+            }
+            _ => None,
+        };
+        match released {
+            Some(key) => {
+                self.regs[reg as usize] = key;
+                self.key_wait = KeyWait::Idle;
+            }
+            None => {
+                if self.key_wait == KeyWait::Waiting {
+                    if let Some(key) = self.keypad.iter().position(|down| *down) {
+                        self.key_wait = KeyWait::Held(key as u8);
+                    }
+                }
+                self.pc = self.pc.wrapping_sub(2);
+            }
         }
     }
 
     fn op_font_char(&mut self, reg: u8) {
-        self.reg_i = MEM_OFFSET as u16 + ((self.regs[reg as usize] & 0x0F) * 5) as u16;
+        self.reg_i = font_address(self.regs[reg as usize]);
     }
 
-    fn op_decimals(&mut self, reg: u8) {
-        let n = self.regs[reg as usize];
-        self.mem[self.reg_i as usize] = n / 100;
-        self.mem[(self.reg_i + 1) as usize] = (n % 100) / 10;
-        self.mem[(self.reg_i + 2) as usize] = n % 10;
+    fn op_big_font_char(&mut self, reg: u8) {
+        self.reg_i = big_font_address(self.regs[reg as usize]);
     }
 
-    fn op_store(&mut self, reg: u8) {
-        for n in 0..=reg {
-            self.mem[(self.reg_i + n as u16) as usize] = self.regs[n as usize];
+    fn op_store_flags(&mut self, reg: u8) {
+        let count = reg as usize + 1;
+        self.rpl_flags[..count].copy_from_slice(&self.regs[..count]);
+    }
+
+    fn op_load_flags(&mut self, reg: u8) {
+        let count = reg as usize + 1;
+        self.regs[..count].copy_from_slice(&self.rpl_flags[..count]);
+    }
+
+    /// F000 NNNN, loads I with the 16-bit address in the next word.
+    fn op_long_ireg(&mut self) -> Result<(), EmulatorError> {
+        self.check_range(self.pc, 2)?;
+        self.reg_i = self.word_at(self.pc);
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
+    }
+
+    fn op_select_planes(&mut self, planes: u8) {
+        self.plane_mask = planes & 0b11;
+    }
+
+    fn op_load_audio(&mut self) -> Result<(), EmulatorError> {
+        self.check_range(self.reg_i, 16)?;
+        self.heatmap.record(self.reg_i, 16, Access::Read);
+        let mut bits = [0; 16];
+        for (i, bit) in bits.iter_mut().enumerate() {
+            *bit = self.read_mem(self.reg_i + i as u16);
         }
+        self.audio_bits = Some(bits);
+        Ok(())
     }
 
-    fn op_load(&mut self, reg: u8) {
-        for n in 0..=reg {
-            self.regs[n as usize] = self.mem[(self.reg_i + n as u16) as usize];
+    fn op_set_pitch(&mut self, reg: u8) {
+        self.pitch = self.regs[reg as usize];
+    }
+
+    /// 5XY2, stores VX..VY at I, in reverse order if X > Y. I is left unchanged.
+    fn op_store_range(&mut self, reg_x: u8, reg_y: u8) -> Result<(), EmulatorError> {
+        self.check_range(self.reg_i, reg_x.abs_diff(reg_y) as usize + 1)?;
+        for (i, reg) in register_range(reg_x, reg_y).enumerate() {
+            self.write_mem(self.reg_i + i as u16, self.regs[reg]);
         }
+        Ok(())
     }
 
-    pub fn draw_info(&mut self, ui: &Ui, ms_dt: u128) {
-        ui.window("Control flow").build(|| {
-            let mut paused = false;
-            match self.state {
-                RunState::Running => {
-                    ui.text("Emulator running...");
-                    if ui.button("Pause") {
-                        self.pause();
-                    }
-                }
-                RunState::Paused => {
-                    paused = true;
-                    ui.text("Emulator paused.");
-                    if ui.button("Resume") {
-                        self.resume();
-                    }
-                }
-                _ => {}
+    /// 5XY3, loads VX..VY from I, in reverse order if X > Y. I is left unchanged.
+    fn op_load_range(&mut self, reg_x: u8, reg_y: u8) -> Result<(), EmulatorError> {
+        let len = reg_x.abs_diff(reg_y) as usize + 1;
+        self.check_range(self.reg_i, len)?;
+        self.heatmap.record(self.reg_i, len, Access::Read);
+        for (i, reg) in register_range(reg_x, reg_y).enumerate() {
+            self.regs[reg] = self.read_mem(self.reg_i + i as u16);
+        }
+        Ok(())
+    }
+
+    /// Memory read done by an instruction, passed through the peripherals mapped there.
+    fn read_mem(&mut self, address: u16) -> u8 {
+        let value = self.mem[address as usize];
+        if self.memory_hooks.is_empty() {
+            return value;
+        }
+        self.memory_hooks
+            .iter_mut()
+            .filter(|(range, _)| range.contains(&address))
+            .fold(value, |value, (_, hook)| hook.on_read(address, value))
+    }
+
+    /// Memory write done by an instruction, checked against the memory watches and passed
+    /// on to the peripherals mapped there.
+    fn write_mem(&mut self, address: u16, value: u8) {
+        let old = self.mem[address as usize];
+        self.mem[address as usize] = value;
+        for (range, hook) in &mut self.memory_hooks {
+            if range.contains(&address) {
+                hook.on_write(address, value);
             }
-            ui.disabled(!paused, || {
-                if ui.button("Step") {
-                    self.step();
-                }
-            });
-            ui.separator();
-            ui.label_text("Frame", self.frame_count.to_string());
-            ui.label_text("Delta time (ms)", ms_dt.to_string());
-        });
+        }
+        self.written.insert(address);
+        self.heatmap.record(address, 1, Access::Write);
+        if self.hooks.is_some() {
+            self.hook_writes.push((address, value));
+        }
+        if let Some(undo) = &mut self.recording {
+            undo.mem.push((address, old));
+        }
+        if let Some(watch) = self.memory_watches.get(&address) {
+            if self.watch_hit.is_none() && watch.triggers(old, value) {
+                self.watch_hit = Some((address, old, value));
+            }
+        }
+    }
 
-        ui.window("Emulator").build(|| {
-            ui.disabled(true, || {
-                ui.input_text(
-                    "Program counter",
-                    &mut format!("{:?} (0x{:04X})", self.pc, self.curr_inst()),
-                )
-                .build();
-            });
+    fn op_decimals(&mut self, reg: u8) -> Result<(), EmulatorError> {
+        self.check_range(self.reg_i, 3)?;
+        let n = self.regs[reg as usize];
+        self.write_mem(self.reg_i, n / 100);
+        self.write_mem(self.reg_i + 1, (n % 100) / 10);
+        self.write_mem(self.reg_i + 2, n % 10);
+        Ok(())
+    }
 
-            ui.separator();
+    fn op_store(&mut self, reg: u8) -> Result<(), EmulatorError> {
+        self.check_range(self.reg_i, reg as usize + 1)?;
+        for n in 0..=reg {
+            self.write_mem(self.reg_i + n as u16, self.regs[n as usize]);
+        }
+        self.increment_index(reg);
+        Ok(())
+    }
 
-            ui.disabled(true, || {
-                ui.input_int("Delay timer", &mut (self.delay_timer as i32))
-                    .build();
-                ui.input_int("Sound timer", &mut (self.sound_timer as i32))
-                    .build();
-            });
+    fn op_load(&mut self, reg: u8) -> Result<(), EmulatorError> {
+        self.check_range(self.reg_i, reg as usize + 1)?;
+        self.heatmap
+            .record(self.reg_i, reg as usize + 1, Access::Read);
+        for n in 0..=reg {
+            self.regs[n as usize] = self.read_mem(self.reg_i + n as u16);
+        }
+        self.increment_index(reg);
+        Ok(())
+    }
 
-            ui.separator();
-
-            ui.disabled(true, || {
-                ui.input_text(
-                    "Index Register",
-                    &mut format!("{:?} (0x{:04X})", self.reg_i, self.reg_i),
-                )
-                .build();
-                for (i, reg) in self.regs.iter().enumerate() {
-                    ui.input_text(
-                        format!("Register {i}"),
-                        &mut format!("{:?} (0x{:04X})", reg, reg),
-                    )
-                    .build();
-                }
-            });
-            // let mut display_str = String::new();
-            // for y in 0..DISPLAY_SIZE.1 {
-            //     for x in 0..DISPLAY_SIZE.0 {
-            //         display_str += if self.display[x][y] == 1 { "X" } else { "_" };
-            //     }
-            //     display_str += "\n";
-            // }
-            // ui.text(display_str);
-        });
-        ui.window("Memory").build(|| {
-            let table_flags = imgui::TableFlags::RESIZABLE
-                | imgui::TableFlags::BORDERS_H
-                | imgui::TableFlags::BORDERS_V;
-            if let Some(_) =
-                ui.begin_table_with_sizing("mem_table", 2, table_flags, [300.0, 100.0], 0.0)
-            {
-                ui.table_setup_column("Index");
-                ui.table_setup_column("Value");
-                ui.table_setup_scroll_freeze(2, 1);
-                ui.table_headers_row();
-                for (i, byte) in self.mem.iter().enumerate() {
-                    if i % 2 != 0 {
-                        continue;
-                    }
-                    if i as u16 == self.pc {
-                        ui.table_set_bg_color(TableBgTarget::ROW_BG0, [0.0, 1.0, 0.0, 0.1]);
-                        if let RunState::Running = self.state {
-                            ui.set_scroll_here_y();
-                        }
-                    }
-                    ui.table_next_row();
-                    ui.table_set_column_index(0);
-                    ui.text(format!("{:} ", i).as_str());
-                    ui.table_set_column_index(1);
-                    ui.text(format!("0x{:02X}{:02X}", byte, self.mem[i + 1]).as_str());
-                }
-            }
-        });
+    fn increment_index(&mut self, reg: u8) {
+        self.reg_i = match self.quirks.index_increment {
+            IndexIncrement::None => self.reg_i,
+            IndexIncrement::X => self.reg_i.wrapping_add(reg as u16),
+            IndexIncrement::XPlusOne => self.reg_i.wrapping_add(reg as u16 + 1),
+        };
+    }
+}
+
+/// Register indices from X to Y inclusive, counting down when X > Y.
+fn register_range(reg_x: u8, reg_y: u8) -> Box<dyn Iterator<Item = usize>> {
+    let (x, y) = (reg_x as usize, reg_y as usize);
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
     }
 }
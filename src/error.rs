@@ -0,0 +1,131 @@
+use std::{fmt, io, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::octo::OctoError;
+
+/// Why the CPU couldn't go on, or why a ROM couldn't be loaded.
+#[derive(Debug)]
+pub enum EmulatorError {
+    /// 00EE with nothing on the stack.
+    StackUnderflow,
+    /// 2NNN with the stack already [`STACK_SIZE`](crate::emulator::STACK_SIZE) deep.
+    StackOverflow,
+    /// An instruction fetch or memory access runs past the last address.
+    MemoryOutOfBounds(u16),
+    /// Opcode the interpreter doesn't implement.
+    UnknownOpcode(u16),
+    /// ROM doesn't fit in memory after its load address.
+    RomTooLarge {
+        size: usize,
+        max: usize,
+    },
+    IoError(io::Error),
+    /// A `.8o` source that didn't assemble.
+    Assembly(OctoError),
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmulatorError::StackUnderflow => write!(f, "return with an empty stack"),
+            EmulatorError::StackOverflow => write!(f, "stack overflow"),
+            EmulatorError::MemoryOutOfBounds(address) => {
+                write!(
+                    f,
+                    "memory access past the end of memory from 0x{address:04X}"
+                )
+            }
+            EmulatorError::UnknownOpcode(inst) => write!(f, "unknown opcode {inst:04X}"),
+            EmulatorError::RomTooLarge { size, max } => {
+                write!(f, "ROM is {size} bytes, only {max} fit in memory")
+            }
+            EmulatorError::IoError(err) => write!(f, "{err}"),
+            EmulatorError::Assembly(err) => write!(f, "Octo source doesn't assemble, {err}"),
+        }
+    }
+}
+
+impl std::error::Error for EmulatorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EmulatorError::IoError(err) => Some(err),
+            EmulatorError::Assembly(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for EmulatorError {
+    fn from(err: io::Error) -> Self {
+        EmulatorError::IoError(err)
+    }
+}
+
+impl From<OctoError> for EmulatorError {
+    fn from(err: OctoError) -> Self {
+        EmulatorError::Assembly(err)
+    }
+}
+
+/// Error that stopped the CPU, and the address of the instruction that caused it.
+#[derive(Debug)]
+pub struct Halt {
+    pub address: u16,
+    pub error: EmulatorError,
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CPU halted at 0x{:03X}: {}", self.address, self.error)
+    }
+}
+
+/// What the CPU does on an opcode it doesn't implement.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnknownOpcodePolicy {
+    /// Stops with [`EmulatorError::UnknownOpcode`] until the emulator is reset.
+    #[default]
+    Halt,
+    /// Logs a warning and goes on with the next instruction.
+    Skip,
+    /// Pauses on the opcode like a breakpoint, so it can be looked at in the debugger.
+    Break,
+}
+
+impl UnknownOpcodePolicy {
+    pub const ALL: [UnknownOpcodePolicy; 3] = [
+        UnknownOpcodePolicy::Halt,
+        UnknownOpcodePolicy::Skip,
+        UnknownOpcodePolicy::Break,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            UnknownOpcodePolicy::Halt => "Halt",
+            UnknownOpcodePolicy::Skip => "Skip and continue",
+            UnknownOpcodePolicy::Break => "Break into debugger",
+        }
+    }
+
+    /// Short name for the command line, see the [`FromStr`] impl.
+    pub fn id(self) -> &'static str {
+        match self {
+            UnknownOpcodePolicy::Halt => "halt",
+            UnknownOpcodePolicy::Skip => "skip",
+            UnknownOpcodePolicy::Break => "break",
+        }
+    }
+}
+
+/// Parses [`UnknownOpcodePolicy::id`], ignoring case.
+impl FromStr for UnknownOpcodePolicy {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        UnknownOpcodePolicy::ALL
+            .into_iter()
+            .find(|policy| policy.id().eq_ignore_ascii_case(text))
+            .ok_or_else(|| format!("unknown policy `{text}`, expected halt, skip or break"))
+    }
+}
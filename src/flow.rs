@@ -0,0 +1,22 @@
+/// Control flow transfer encoded by an instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BranchKind {
+    Jump,
+    Call,
+    Skip,
+}
+
+/// Statically known target of the instruction at `address`, if it transfers control.
+/// BNNN is left out since its target depends on a register.
+pub fn branch_target(address: u16, inst: u16) -> Option<(BranchKind, u16)> {
+    match inst & 0xF000 {
+        0x1000 => Some((BranchKind::Jump, inst & 0x0FFF)),
+        0x2000 => Some((BranchKind::Call, inst & 0x0FFF)),
+        0x3000 | 0x4000 => Some((BranchKind::Skip, address.wrapping_add(4))),
+        0x5000 | 0x9000 if inst & 0x000F == 0 => Some((BranchKind::Skip, address.wrapping_add(4))),
+        0xE000 if matches!(inst & 0x00FF, 0x9E | 0xA1) => {
+            Some((BranchKind::Skip, address.wrapping_add(4)))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,126 @@
+//! Window frame pacing: how frames are presented and an accurate limiter between them.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+/// Frame times kept for the graph in the "Control flow" window.
+pub const FRAME_TIME_HISTORY: usize = 120;
+
+/// How presented frames line up with the monitor refresh. Fifo is supported everywhere, the
+/// others depend on the GPU and platform.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentMode {
+    /// Waits for vertical blank, no tearing.
+    Fifo,
+    /// Replaces the queued frame, no tearing and less latency than Fifo.
+    Mailbox,
+    /// Presents right away, may tear.
+    #[default]
+    Immediate,
+}
+
+impl PresentMode {
+    pub const ALL: [PresentMode; 3] = [
+        PresentMode::Fifo,
+        PresentMode::Mailbox,
+        PresentMode::Immediate,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PresentMode::Fifo => "VSync (Fifo)",
+            PresentMode::Mailbox => "Mailbox",
+            PresentMode::Immediate => "Immediate",
+        }
+    }
+}
+
+/// Holds each frame until its deadline, sleeping most of the wait and spinning the rest, as
+/// OS sleeps overshoot by up to a couple of milliseconds. Also records the frame times.
+#[derive(Clone, Debug)]
+pub struct FrameLimiter {
+    /// Part of each wait spent spinning instead of sleeping.
+    pub spin: Duration,
+    /// When the next frame is due.
+    deadline: Option<Instant>,
+    last_frame: Option<Instant>,
+    /// Milliseconds between the last frames, oldest first.
+    frame_times: Vec<f32>,
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self {
+            spin: Duration::from_micros(1500),
+            deadline: None,
+            last_frame: None,
+            frame_times: Vec::with_capacity(FRAME_TIME_HISTORY),
+        }
+    }
+}
+
+impl FrameLimiter {
+    /// Call once per presented frame. Blocks until the next frame is due at `fps` frames per
+    /// second, `None` runs uncapped. Deadlines advance by whole frames so the rate doesn't
+    /// drift, unless the loop fell more than a frame behind.
+    ///
+    /// In the browser, which paces frames itself, it only records the frame time.
+    pub fn wait(&mut self, fps: Option<f64>) {
+        let period = fps
+            .filter(|fps| *fps > 0.0)
+            .map(|fps| Duration::from_secs_f64(1.0 / fps));
+        match period {
+            Some(period) if !cfg!(target_arch = "wasm32") => {
+                let now = Instant::now();
+                let deadline = match self.deadline {
+                    Some(deadline) if deadline + period > now => deadline,
+                    _ => now,
+                };
+                self.sleep_until(deadline);
+                self.deadline = Some(deadline + period);
+            }
+            _ => self.deadline = None,
+        }
+
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame {
+            if self.frame_times.len() == FRAME_TIME_HISTORY {
+                self.frame_times.remove(0);
+            }
+            self.frame_times
+                .push((now - last_frame).as_secs_f32() * 1000.0);
+        }
+        self.last_frame = Some(now);
+    }
+
+    fn sleep_until(&self, deadline: Instant) {
+        let now = Instant::now();
+        if deadline <= now {
+            return;
+        }
+        let wait = deadline - now;
+        if wait > self.spin {
+            #[cfg(not(target_arch = "wasm32"))]
+            std::thread::sleep(wait - self.spin);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Milliseconds between the last [`FRAME_TIME_HISTORY`] frames, oldest first.
+    pub fn frame_times(&self) -> &[f32] {
+        &self.frame_times
+    }
+
+    /// Average frames per second over the recorded frame times.
+    pub fn fps(&self) -> f32 {
+        let total: f32 = self.frame_times.iter().sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        self.frame_times.len() as f32 * 1000.0 / total
+    }
+}
@@ -0,0 +1,1377 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use imgui::Ui;
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+use crate::{
+    capture::Capture,
+    cheats::{Cheat, CheatMode},
+    compat::{CompatReport, CompatWarning},
+    config::{Config, RomSettings},
+    emulator::{Emulator, RunState, HIRES_DISPLAY_SIZE},
+    error::UnknownOpcodePolicy,
+    frame_limiter::FrameLimiter,
+    keymap::{self, BindTarget, KeyBindings, KeyProfiles},
+    megachip::MEGA_DISPLAY_SIZE,
+    metadata::RomDatabase,
+    netplay::{Netplay, NetplayHost, NetplayRole, DEFAULT_INPUT_DELAY, DEFAULT_PORT},
+    renderer::{Palette, PostEffects, Rotation, Scaling, Theme, Viewport},
+    replay::{Replay, ReplayStatus},
+    rom::{self, RomInfo},
+    savestate,
+    script::Script,
+    timing::{SLOW_MOTION_SPEED, TURBO_SPEED},
+    worker::{EmulationThread, EmulatorCommand},
+};
+
+/// Window title without a ROM, see [`Frontend::window_title`].
+pub const WINDOW_TITLE: &str = "CHIP-8 Emulator";
+
+/// Anything the frontend can present into and that needs to follow the window size.
+pub trait SurfaceTarget {
+    fn resize(&mut self, width: u32, height: u32);
+    fn size(&self) -> (u32, u32);
+}
+
+/// What the event loop should do after an event has been handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventResponse {
+    Continue,
+    Redraw,
+    Exit,
+}
+
+/// Windows that can be shown and hidden from the "View" and "Debug" menus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Panel {
+    Setup,
+    Library,
+    Project,
+    Keypad,
+    KeyMapping,
+    Display,
+    Audio,
+    Capture,
+    Replay,
+    Netplay,
+    Script,
+    Cheats,
+    Controls,
+    Memory,
+    RamSearch,
+    CallGraph,
+    Stack,
+    Disassembly,
+    Trace,
+    Sprites,
+    Profiler,
+    Heatmap,
+    Watches,
+    Compare,
+}
+
+impl Panel {
+    /// Listed in the "View" menu.
+    pub const VIEW: [Panel; 12] = [
+        Panel::Setup,
+        Panel::Library,
+        Panel::Project,
+        Panel::Keypad,
+        Panel::KeyMapping,
+        Panel::Display,
+        Panel::Audio,
+        Panel::Capture,
+        Panel::Replay,
+        Panel::Netplay,
+        Panel::Script,
+        Panel::Cheats,
+    ];
+    /// Listed in the "Debug" menu.
+    pub const DEBUG: [Panel; 12] = [
+        Panel::Controls,
+        Panel::Memory,
+        Panel::RamSearch,
+        Panel::CallGraph,
+        Panel::Stack,
+        Panel::Disassembly,
+        Panel::Trace,
+        Panel::Sprites,
+        Panel::Profiler,
+        Panel::Heatmap,
+        Panel::Watches,
+        Panel::Compare,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Panel::Setup => "Emulator setup",
+            Panel::Library => "Library",
+            Panel::Project => "Project",
+            Panel::Keypad => "Keypad",
+            Panel::KeyMapping => "Key mapping",
+            Panel::Display => "Display",
+            Panel::Audio => "Audio",
+            Panel::Capture => "Capture",
+            Panel::Replay => "Replay",
+            Panel::Netplay => "Netplay",
+            Panel::Script => "Script",
+            Panel::Cheats => "Cheats",
+            Panel::Controls => "Controls and registers",
+            Panel::Memory => "Memory",
+            Panel::RamSearch => "RAM search",
+            Panel::CallGraph => "Call graph",
+            Panel::Stack => "Stack",
+            Panel::Disassembly => "Disassembly",
+            Panel::Trace => "Trace",
+            Panel::Sprites => "Sprites",
+            Panel::Profiler => "Profiler",
+            Panel::Heatmap => "Memory heatmap",
+            Panel::Watches => "Watches",
+            Panel::Compare => "Compare quirks",
+        }
+    }
+}
+
+/// Window-independent frontend state. Translates winit events into emulator input so the
+/// same logic runs in the real event loop and in tests fed with synthetic events.
+pub struct Frontend {
+    pub emulator: EmulationThread,
+    /// Accessibility mode where tapping a key toggles it held instead of holding it down.
+    sticky_keys: bool,
+    /// Key currently held by sticky keys.
+    latched: Option<u8>,
+    /// Physical state of each keypad key, so key repeat doesn't count as new taps.
+    down: [bool; 16],
+    /// Keypad key held with the mouse on the on-screen keypad.
+    mouse_key: Option<u8>,
+    /// Rotation the display is drawn with.
+    pub rotation: Rotation,
+    /// How the display is fitted into the window.
+    pub scaling: Scaling,
+    /// Colors the display is drawn with, loaded and saved by the caller.
+    pub palette: Palette,
+    /// CRT post-processing, loaded and saved by the caller.
+    pub effects: PostEffects,
+    /// Turns the arrow keys (2/4/6/8) along with the display, so "up" stays up on screen.
+    pub rotate_input: bool,
+    /// Global keyboard bindings, loaded and saved by the caller.
+    pub key_bindings: KeyBindings,
+    /// Per-ROM overrides of the global bindings, see [`KeyProfiles::map`].
+    pub key_profiles: KeyProfiles,
+    /// Keypad key waiting for a keyboard key to be bound to it.
+    binding: Option<(BindTarget, u8)>,
+    /// Pause while the window is in the background and resume once it's focused again.
+    pub pause_on_focus_loss: bool,
+    /// Set when losing focus paused the emulator, so a pause done by the user is kept.
+    paused_by_focus: bool,
+    /// Slot used by the F5 (save) and F9 (load) hotkeys.
+    pub save_slot: u8,
+    /// Directory of the save slots, set by the caller.
+    pub savestates: PathBuf,
+    /// Outcome of the last save or load, shown in the setup window.
+    pub save_status: String,
+    /// Recent ROMs and per-ROM settings, loaded and saved by the caller.
+    pub config: Config,
+    /// Settings that were active before a ROM's overrides were applied, put back when a
+    /// ROM without overrides is opened.
+    global_settings: Option<RomSettings>,
+    /// Screenshots (F12) and clip recording (F10).
+    pub capture: Capture,
+    /// File input recordings are saved to and played from, set by the caller.
+    pub replay_path: String,
+    /// Outcome of the last replay action, shown in the "Replay" window.
+    pub replay_status: String,
+    /// Port to wait for the other player on when hosting netplay.
+    pub netplay_port: u16,
+    /// Host to connect to when joining netplay, `host:port`.
+    pub netplay_address: String,
+    /// Frames of input delay a hosted session runs with.
+    pub netplay_delay: u32,
+    /// Listener while waiting for a guest to join.
+    netplay_host: Option<NetplayHost>,
+    /// Outcome of the last netplay action, shown in the "Netplay" window.
+    pub netplay_status: String,
+    /// Rhai script loaded from the "Script" window, set by the caller.
+    pub script_path: String,
+    /// Outcome of the last script action, shown in the "Script" window.
+    pub script_status: String,
+    /// Cheat being added in the "Cheats" window.
+    new_cheat: Cheat,
+    /// `address = value` code of the cheat being added.
+    cheat_code: String,
+    /// Windows closed from the menus.
+    hidden_panels: BTreeSet<Panel>,
+    /// Paces the window's frames, see [`Config::uncapped`].
+    pub frame_limiter: FrameLimiter,
+    /// Hides every window and the menu bar, toggled with F11. The caller also makes the
+    /// window fullscreen.
+    pub game_only: bool,
+    /// What opened ROMs are looked up in, the bundled entries unless the caller loads more.
+    pub database: RomDatabase,
+    /// Checks of the last ROM opened, shown until dismissed if anything came up.
+    pub rom_report: Option<CompatReport>,
+    /// Fast-forward, while Tab is held.
+    turbo: bool,
+    /// Slow motion, toggled with F4.
+    slow_motion: bool,
+}
+
+impl Default for Frontend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Frontend {
+    pub fn new() -> Self {
+        Self {
+            emulator: EmulationThread::start(Emulator::new()),
+            sticky_keys: false,
+            latched: None,
+            down: [false; 16],
+            mouse_key: None,
+            rotation: Rotation::None,
+            scaling: Scaling::default(),
+            palette: Palette::default(),
+            effects: PostEffects::default(),
+            rotate_input: false,
+            key_bindings: KeyBindings::default(),
+            key_profiles: KeyProfiles::default(),
+            binding: None,
+            pause_on_focus_loss: false,
+            paused_by_focus: false,
+            save_slot: 0,
+            savestates: PathBuf::new(),
+            save_status: String::new(),
+            config: Config::default(),
+            global_settings: None,
+            capture: Capture::default(),
+            replay_path: String::new(),
+            replay_status: String::new(),
+            netplay_port: DEFAULT_PORT,
+            netplay_address: format!("127.0.0.1:{DEFAULT_PORT}"),
+            netplay_delay: DEFAULT_INPUT_DELAY,
+            netplay_host: None,
+            netplay_status: String::new(),
+            script_path: String::new(),
+            script_status: String::new(),
+            new_cheat: Cheat {
+                enabled: true,
+                ..Cheat::default()
+            },
+            cheat_code: String::new(),
+            hidden_panels: BTreeSet::new(),
+            frame_limiter: FrameLimiter::default(),
+            game_only: false,
+            database: RomDatabase::bundled(),
+            rom_report: None,
+            turbo: false,
+            slow_motion: false,
+        }
+    }
+
+    pub fn sticky_keys(&self) -> bool {
+        self.sticky_keys
+    }
+
+    /// Switches sticky keys on or off, releasing any key it was holding.
+    pub fn set_sticky_keys(&mut self, enabled: bool) {
+        if !enabled {
+            if let Some(key) = self.latched.take() {
+                self.emulator.send(EmulatorCommand::KeyUp(key));
+            }
+        }
+        self.sticky_keys = enabled;
+    }
+
+    /// Key currently held by sticky keys.
+    pub fn latched_key(&self) -> Option<u8> {
+        self.latched
+    }
+
+    /// Handles a keypad key going down, from the keyboard or the on-screen keypad.
+    pub fn press_key(&mut self, key: u8) {
+        let was_down = std::mem::replace(&mut self.down[key as usize], true);
+        if !self.sticky_keys {
+            self.emulator.send(EmulatorCommand::KeyDown(key));
+        } else if !was_down {
+            if self.latched == Some(key) {
+                self.latched = None;
+                self.emulator.send(EmulatorCommand::KeyUp(key));
+            } else {
+                self.latched = Some(key);
+                self.emulator.send(EmulatorCommand::KeyDown(key));
+            }
+        }
+    }
+
+    /// Handles a keypad key going up. With sticky keys on the key stays held until tapped again.
+    pub fn release_key(&mut self, key: u8) {
+        self.down[key as usize] = false;
+        if !self.sticky_keys {
+            self.emulator.send(EmulatorCommand::KeyUp(key));
+        }
+    }
+
+    /// Binds the next keyboard key pressed to `key`, globally or in the loaded ROM's profile.
+    pub fn start_binding(&mut self, target: BindTarget, key: u8) {
+        self.binding = Some((target, key));
+    }
+
+    /// Keypad key the next keyboard key pressed will be bound to.
+    pub fn binding(&self, target: BindTarget) -> Option<u8> {
+        self.binding
+            .and_then(|(bind_target, key)| (bind_target == target).then_some(key))
+    }
+
+    /// Fast-forwards at [`TURBO_SPEED`] while `held`, over slow motion if that's on too.
+    pub fn set_turbo(&mut self, held: bool) {
+        self.turbo = held;
+        self.send_speed_override();
+    }
+
+    pub fn slow_motion(&self) -> bool {
+        self.slow_motion
+    }
+
+    /// Switches running at [`SLOW_MOTION_SPEED`] on or off.
+    pub fn set_slow_motion(&mut self, enabled: bool) {
+        self.slow_motion = enabled;
+        self.send_speed_override();
+    }
+
+    fn send_speed_override(&self) {
+        let speed = if self.turbo {
+            Some(TURBO_SPEED)
+        } else if self.slow_motion {
+            Some(SLOW_MOTION_SPEED)
+        } else {
+            None
+        };
+        self.emulator.send(EmulatorCommand::OverrideSpeed(speed));
+    }
+
+    /// Shows the speed in the top right corner of the window while it isn't 1x, also in
+    /// game only mode.
+    pub fn draw_speed_overlay(&self, ui: &Ui) {
+        let speed = self.emulator.status().speed;
+        if speed == 1.0 {
+            return;
+        }
+        let text = if speed > 1.0 {
+            format!("{speed}x >>")
+        } else {
+            format!("{speed}x")
+        };
+        let [width, _] = ui.io().display_size;
+        let [text_width, _] = ui.calc_text_size(&text);
+        let top = if self.game_only {
+            8.0
+        } else {
+            8.0 + ui.frame_height()
+        };
+        ui.get_foreground_draw_list().add_text(
+            [width - text_width - 8.0, top],
+            [1.0, 1.0, 0.4, 1.0],
+            text,
+        );
+    }
+
+    fn focus_changed(&mut self, focused: bool) {
+        // The window doesn't see Tab going up while unfocused.
+        if !focused && self.turbo {
+            self.set_turbo(false);
+        }
+        let mut emulator = self.emulator.lock();
+        if !focused && self.pause_on_focus_loss {
+            if let RunState::Running = emulator.state {
+                emulator.pause();
+                self.paused_by_focus = true;
+            }
+        } else if focused && self.paused_by_focus {
+            self.paused_by_focus = false;
+            if let RunState::Paused = emulator.state {
+                emulator.resume();
+            }
+        }
+    }
+
+    /// Saves the machine state into the current slot.
+    pub fn save_state(&mut self) {
+        let slot = self.save_slot;
+        let saved = savestate::save_slot(&self.emulator.lock(), &self.savestates, slot);
+        self.save_status = match saved {
+            Ok(()) => format!("Saved to slot {slot}."),
+            Err(err) => format!("Failed to save slot {slot}: {err}"),
+        };
+    }
+
+    /// Runs a debugger stepping action, ignored while the game is running.
+    fn while_paused(&self, action: fn(&mut Emulator)) {
+        let mut emulator = self.emulator.lock();
+        if matches!(emulator.state, RunState::Paused) {
+            action(&mut emulator);
+        }
+    }
+
+    /// Saves the display as a PNG in the capture folder.
+    pub fn take_screenshot(&mut self) {
+        let (display, rom) = {
+            let emulator = self.emulator.lock();
+            (
+                emulator.display,
+                emulator.rom.as_ref().map(|rom| rom.path.clone()),
+            )
+        };
+        self.capture
+            .screenshot(&display, &self.palette, rom.as_deref());
+    }
+
+    /// Starts recording a clip, or stops and saves the one being recorded.
+    pub fn toggle_recording(&mut self) {
+        if self.capture.is_recording() {
+            let rom = self
+                .emulator
+                .lock()
+                .rom
+                .as_ref()
+                .map(|rom| rom.path.clone());
+            self.capture.stop_recording(&self.palette, rom.as_deref());
+        } else {
+            self.capture.start_recording();
+        }
+    }
+
+    /// Restarts the ROM and records input, or stops and saves the recording to
+    /// [`Frontend::replay_path`].
+    pub fn toggle_input_recording(&mut self) {
+        let mut emulator = self.emulator.lock();
+        if let Some(replay) = emulator.stop_input_recording() {
+            self.replay_status = match replay.save(&self.replay_path) {
+                Ok(()) => format!(
+                    "Saved {} frames to {}.",
+                    replay.frames.len(),
+                    self.replay_path
+                ),
+                Err(err) => format!("Failed to save {}: {err}", self.replay_path),
+            };
+            return;
+        }
+        self.replay_status = match emulator.start_input_recording() {
+            Ok(()) => {
+                emulator.resume();
+                String::from("Recording input...")
+            }
+            Err(err) => format!("Failed to start recording: {err}"),
+        };
+    }
+
+    /// Plays the replay at [`Frontend::replay_path`] on the loaded ROM.
+    pub fn play_replay(&mut self) {
+        let mut emulator = self.emulator.lock();
+        let played = Replay::load(&self.replay_path)
+            .map_err(Into::into)
+            .and_then(|replay| emulator.play_replay(replay));
+        self.replay_status = match played {
+            Ok(()) => {
+                emulator.resume();
+                format!("Playing {}.", self.replay_path)
+            }
+            Err(err) => format!("Failed to play {}: {err}", self.replay_path),
+        };
+    }
+
+    /// Waits for another player to join on [`Frontend::netplay_port`].
+    pub fn host_netplay(&mut self) {
+        match NetplayHost::listen(self.netplay_port, self.netplay_delay) {
+            Ok(host) => {
+                self.netplay_status =
+                    format!("Waiting for a player on port {}...", self.netplay_port);
+                self.netplay_host = Some(host);
+            }
+            Err(err) => self.netplay_status = format!("Failed to host: {err}"),
+        }
+    }
+
+    /// Starts the session once a guest connected to the hosted game, call every frame.
+    fn poll_netplay_host(&mut self) {
+        let Some(host) = &self.netplay_host else {
+            return;
+        };
+        let mut emulator = self.emulator.lock();
+        let accepted = host
+            .accept(&emulator)
+            .map_err(Into::into)
+            .and_then(|netplay| {
+                let Some(netplay) = netplay else {
+                    return Ok(false);
+                };
+                emulator.start_netplay(netplay).map(|()| true)
+            });
+        match accepted {
+            Ok(false) => {}
+            Ok(true) => {
+                emulator.resume();
+                self.netplay_host = None;
+                self.netplay_status = String::from("Player joined.");
+            }
+            Err(err) => {
+                self.netplay_host = None;
+                self.netplay_status = format!("Failed to start netplay: {err}");
+            }
+        }
+    }
+
+    /// Joins the game hosted at [`Frontend::netplay_address`], which must run the same ROM.
+    pub fn join_netplay(&mut self) {
+        let mut emulator = self.emulator.lock();
+        let joined = Netplay::join(self.netplay_address.as_str(), &emulator)
+            .map_err(Into::into)
+            .and_then(|netplay| emulator.start_netplay(netplay));
+        self.netplay_status = match joined {
+            Ok(()) => {
+                emulator.resume();
+                format!("Joined {}.", self.netplay_address)
+            }
+            Err(err) => format!("Failed to join {}: {err}", self.netplay_address),
+        };
+    }
+
+    /// Runs the script at [`Frontend::script_path`] along with the emulator, replacing the
+    /// one running.
+    pub fn load_script(&mut self) {
+        self.script_status = match Script::load(&self.script_path) {
+            Ok(script) => {
+                self.emulator.lock().set_hooks(Some(Box::new(script)));
+                format!("Running {}.", self.script_path)
+            }
+            Err(err) => format!("Failed to load {}: {err}", self.script_path),
+        };
+    }
+
+    /// Restores the current slot, resuming afterwards if the game was running.
+    pub fn load_state(&mut self) {
+        let slot = self.save_slot;
+        let mut emulator = self.emulator.lock();
+        let running = matches!(emulator.state, RunState::Running);
+        self.save_status = match savestate::load_slot(&mut emulator, &self.savestates, slot) {
+            Ok(()) => {
+                if running {
+                    emulator.resume();
+                }
+                format!("Loaded slot {slot}.")
+            }
+            Err(err) => format!("Failed to load slot {slot}: {err}"),
+        };
+    }
+
+    /// Opens a ROM with its saved settings, and adds it to the recent list.
+    pub fn open_rom(&mut self, path: impl AsRef<Path>) {
+        self.open_rom_with_defaults(path, None);
+    }
+
+    /// Opens a ROM like [`Frontend::open_rom`], with `defaults` (e.g. from the ROM's
+    /// metadata) in place of saved settings if it has none.
+    pub fn open_rom_with_defaults(
+        &mut self,
+        path: impl AsRef<Path>,
+        defaults: Option<RomSettings>,
+    ) {
+        let path = path.as_ref();
+        self.config.add_recent(path);
+        let data = fs::read(path).ok();
+        let rom_hash = data.as_deref().map(rom::hash);
+        self.apply_rom_settings(rom_hash.as_deref(), defaults);
+        if let Some(data) = &data {
+            self.check_rom(path, data);
+        }
+        self.emulator
+            .send(EmulatorCommand::OpenRom(path.to_path_buf()));
+    }
+
+    /// Opens a ROM that was already read with its saved settings, e.g. one picked in the
+    /// browser. `name` stands in for its path and isn't added to the recent list.
+    pub fn load_rom_data(&mut self, name: impl Into<PathBuf>, data: Vec<u8>) {
+        let name = name.into();
+        self.apply_rom_settings(Some(&rom::hash(&data)), None);
+        self.check_rom(&name, &data);
+        self.emulator.send(EmulatorCommand::LoadRom { name, data });
+    }
+
+    /// Looks the ROM about to be opened up in the database and checks it against the
+    /// settings it will run with, logging anything that came up.
+    fn check_rom(&mut self, path: &Path, data: &[u8]) {
+        let report = CompatReport::check(path, data, &self.emulator.lock(), &self.database);
+        for warning in &report.warnings {
+            log::warn!("{}: {warning}", path.display());
+        }
+        self.rom_report = Some(report);
+    }
+
+    /// Title for the window: the loaded ROM's title and authors when the database knows
+    /// it, its file name otherwise.
+    pub fn window_title(&self, rom: Option<&RomInfo>) -> String {
+        let Some(rom) = rom else {
+            return WINDOW_TITLE.to_string();
+        };
+        let title = match &self.rom_report {
+            Some(report) if report.hash == rom.hash => report.title(),
+            _ => rom
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        };
+        format!("{title} - {WINDOW_TITLE}")
+    }
+
+    /// Window listing the warnings about the last ROM opened, if there were any, with a
+    /// button to switch to the quirks it expects.
+    pub fn draw_rom_report(&mut self, ui: &Ui) {
+        let Some(report) = &mut self.rom_report else {
+            return;
+        };
+        if report.warnings.is_empty() {
+            return;
+        }
+        let mut dismissed = false;
+        ui.window("ROM compatibility").build(|| {
+            ui.text(report.title());
+            let mut fixed = None;
+            for (index, warning) in report.warnings.iter().enumerate() {
+                ui.bullet_text(warning.to_string());
+                if let CompatWarning::Quirks { quirks, .. } = warning {
+                    let _id = ui.push_id_usize(index);
+                    ui.same_line();
+                    if ui.small_button("Use them") {
+                        self.emulator.lock().quirks = *quirks;
+                        fixed = Some(index);
+                    }
+                }
+            }
+            if let Some(index) = fixed {
+                report.warnings.remove(index);
+            }
+            dismissed = ui.button("Dismiss");
+        });
+        if dismissed {
+            report.warnings.clear();
+        }
+    }
+
+    /// Applies the overrides saved for the ROM about to be opened, or `defaults`, or puts the
+    /// global settings back if it has neither.
+    fn apply_rom_settings(&mut self, rom_hash: Option<&str>, defaults: Option<RomSettings>) {
+        let overrides = rom_hash
+            .and_then(|hash| self.config.rom_settings(hash).cloned())
+            .or(defaults);
+        let mut emulator = self.emulator.lock();
+        emulator.cheats = rom_hash.map_or(Vec::new(), |hash| self.config.cheats(hash).to_vec());
+        match overrides {
+            Some(settings) => {
+                if self.global_settings.is_none() {
+                    self.global_settings = Some(RomSettings::capture(&emulator, &self.palette));
+                }
+                settings.apply(&mut emulator, &mut self.palette);
+            }
+            None => {
+                if let Some(settings) = self.global_settings.take() {
+                    settings.apply(&mut emulator, &mut self.palette);
+                }
+            }
+        }
+    }
+
+    /// Palette to save as the global one, leaving out the loaded ROM's override.
+    pub fn global_palette(&self) -> Palette {
+        self.global_settings
+            .as_ref()
+            .and_then(|settings| settings.palette)
+            .unwrap_or(self.palette)
+    }
+
+    /// Saves the current quirks, CPU speed and palette as the loaded ROM's settings.
+    pub fn save_rom_settings(&mut self) {
+        let emulator = self.emulator.lock();
+        let Some(rom) = &emulator.rom else {
+            return;
+        };
+        let settings = RomSettings::capture(&emulator, &self.palette);
+        self.config.set_rom_settings(&rom.hash, settings);
+    }
+
+    /// Forgets the loaded ROM's settings. The current ones stay until another ROM is opened.
+    pub fn clear_rom_settings(&mut self) {
+        let emulator = self.emulator.lock();
+        if let Some(rom) = &emulator.rom {
+            self.config
+                .set_rom_settings(&rom.hash, RomSettings::default());
+        }
+    }
+
+    /// Whether `panel` should be drawn this frame.
+    pub fn shows(&self, panel: Panel) -> bool {
+        !self.game_only && !self.hidden_panels.contains(&panel)
+    }
+
+    pub fn set_shown(&mut self, panel: Panel, shown: bool) {
+        if shown {
+            self.hidden_panels.remove(&panel);
+        } else {
+            self.hidden_panels.insert(panel);
+        }
+    }
+
+    /// Checkable menu items showing and hiding `panels`.
+    fn panel_menu_items(&mut self, ui: &Ui, panels: &[Panel]) {
+        for &panel in panels {
+            let shown = !self.hidden_panels.contains(&panel);
+            if ui.menu_item_config(panel.name()).selected(shown).build() {
+                self.set_shown(panel, !shown);
+            }
+        }
+    }
+
+    /// Main menu bar: opening ROMs, emulation control, the per-ROM settings and which
+    /// windows are shown. Nothing is drawn in game only mode. Returns
+    /// [`EventResponse::Exit`] when "Quit" is picked.
+    pub fn draw_menu_bar(&mut self, ui: &Ui) -> EventResponse {
+        let mut response = EventResponse::Continue;
+        if self.game_only {
+            return response;
+        }
+        ui.main_menu_bar(|| {
+            ui.menu("File", || {
+                ui.menu_with_enabled("Open recent", !self.config.recent_roms.is_empty(), || {
+                    let mut opened = None;
+                    for path in &self.config.recent_roms {
+                        if ui.menu_item(path.to_string_lossy()) {
+                            opened = Some(path.clone());
+                        }
+                    }
+                    if let Some(path) = opened {
+                        self.open_rom(path);
+                    }
+                    ui.separator();
+                    if ui.menu_item("Clear list") {
+                        self.config.recent_roms.clear();
+                    }
+                });
+                ui.separator();
+                if ui.menu_item_config("Save state").shortcut("F5").build() {
+                    self.save_state();
+                }
+                if ui.menu_item_config("Load state").shortcut("F9").build() {
+                    self.load_state();
+                }
+                if ui.menu_item_config("Screenshot").shortcut("F12").build() {
+                    self.take_screenshot();
+                }
+                ui.separator();
+                if ui.menu_item("Quit") {
+                    response = EventResponse::Exit;
+                }
+            });
+            self.draw_emulation_menu(ui);
+            let rom_hash = self
+                .emulator
+                .lock()
+                .rom
+                .as_ref()
+                .map(|rom| rom.hash.clone());
+            ui.menu_with_enabled("ROM", rom_hash.is_some(), || {
+                let saved = rom_hash
+                    .as_deref()
+                    .and_then(|hash| self.config.rom_settings(hash))
+                    .is_some();
+                if ui.menu_item("Save settings for this ROM") {
+                    self.save_rom_settings();
+                }
+                if ui
+                    .menu_item_config("Forget settings for this ROM")
+                    .enabled(saved)
+                    .build()
+                {
+                    self.clear_rom_settings();
+                }
+            });
+            ui.menu("Debug", || self.panel_menu_items(ui, &Panel::DEBUG));
+            ui.menu("View", || {
+                self.panel_menu_items(ui, &Panel::VIEW);
+                ui.separator();
+                if ui.menu_item("Show all") {
+                    self.hidden_panels.clear();
+                }
+                if ui.menu_item("Hide all") {
+                    self.hidden_panels
+                        .extend(Panel::VIEW.into_iter().chain(Panel::DEBUG));
+                }
+                ui.separator();
+                if ui.menu_item_config("Game only").shortcut("F11").build() {
+                    self.game_only = true;
+                }
+            });
+        });
+        response
+    }
+
+    fn draw_emulation_menu(&mut self, ui: &Ui) {
+        let mut toggle_slow_motion = false;
+        ui.menu("Emulation", || {
+            let mut emulator = self.emulator.lock();
+            let loaded = emulator.rom.is_some();
+            match emulator.state {
+                RunState::Running => {
+                    if ui.menu_item("Pause") {
+                        emulator.pause();
+                    }
+                }
+                _ => {
+                    let resumable = loaded && emulator.halted().is_none();
+                    if ui.menu_item_config("Resume").enabled(resumable).build() {
+                        emulator.resume();
+                    }
+                }
+            }
+            if ui.menu_item_config("Reset").enabled(loaded).build() {
+                if let Err(err) = emulator.reload_rom() {
+                    emulator.break_reason = Some(format!("Failed to reload ROM: {err}"));
+                }
+            }
+            ui.separator();
+            let paused = matches!(emulator.state, RunState::Paused);
+            for (label, shortcut, action) in [
+                (
+                    "Step instruction",
+                    "F6",
+                    Emulator::step_instruction as fn(&mut Emulator),
+                ),
+                ("Step frame", "F7", Emulator::step_frame),
+                ("Run to next draw", "F8", Emulator::run_to_draw),
+            ] {
+                if ui
+                    .menu_item_config(label)
+                    .shortcut(shortcut)
+                    .enabled(paused)
+                    .build()
+                {
+                    action(&mut emulator);
+                }
+            }
+            ui.separator();
+            ui.menu_item_config("Fast-forward")
+                .shortcut("Hold Tab")
+                .enabled(false)
+                .build();
+            toggle_slow_motion = ui
+                .menu_item_config("Slow motion")
+                .shortcut("F4")
+                .selected(self.slow_motion)
+                .build();
+            ui.separator();
+            ui.menu("On unknown opcode", || {
+                for policy in UnknownOpcodePolicy::ALL {
+                    let selected = emulator.unknown_opcodes == policy;
+                    if ui
+                        .menu_item_config(policy.name())
+                        .selected(selected)
+                        .build()
+                    {
+                        emulator.unknown_opcodes = policy;
+                        self.config.unknown_opcodes = policy;
+                    }
+                }
+            });
+        });
+        // After the menu let go of the emulator, which the inline worker locks.
+        if toggle_slow_motion {
+            self.set_slow_motion(!self.slow_motion);
+        }
+    }
+
+    pub fn handle_window_event(
+        &mut self,
+        event: &WindowEvent,
+        surface: &mut dyn SurfaceTarget,
+    ) -> EventResponse {
+        match event {
+            WindowEvent::CloseRequested => EventResponse::Exit,
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: pressed,
+                        scancode: key,
+                        virtual_keycode,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some((target, keypad_key)) = self.binding {
+                    if *pressed == ElementState::Pressed {
+                        self.binding = None;
+                        match target {
+                            BindTarget::Global => self.key_bindings.bind(*key, keypad_key),
+                            BindTarget::Rom => {
+                                self.key_profiles.bind(*key, keypad_key);
+                                if let Err(err) = self.key_profiles.save() {
+                                    log::error!("Failed to save key mappings: {err}");
+                                }
+                            }
+                        }
+                    }
+                    return EventResponse::Continue;
+                }
+                if *virtual_keycode == Some(VirtualKeyCode::Back) {
+                    let held = *pressed == ElementState::Pressed;
+                    self.emulator.send(EmulatorCommand::Rewind(held));
+                    return EventResponse::Continue;
+                }
+                if *virtual_keycode == Some(VirtualKeyCode::Tab) {
+                    let held = *pressed == ElementState::Pressed;
+                    if held != self.turbo {
+                        self.set_turbo(held);
+                    }
+                    return EventResponse::Continue;
+                }
+                if *pressed == ElementState::Pressed {
+                    match virtual_keycode {
+                        Some(VirtualKeyCode::F4) => {
+                            self.set_slow_motion(!self.slow_motion);
+                            return EventResponse::Continue;
+                        }
+                        Some(VirtualKeyCode::F5) => {
+                            self.save_state();
+                            return EventResponse::Continue;
+                        }
+                        Some(VirtualKeyCode::F6) => {
+                            self.while_paused(Emulator::step_instruction);
+                            return EventResponse::Continue;
+                        }
+                        Some(VirtualKeyCode::F7) => {
+                            self.while_paused(Emulator::step_frame);
+                            return EventResponse::Continue;
+                        }
+                        Some(VirtualKeyCode::F8) => {
+                            self.while_paused(Emulator::run_to_draw);
+                            return EventResponse::Continue;
+                        }
+                        Some(VirtualKeyCode::F9) => {
+                            self.load_state();
+                            return EventResponse::Continue;
+                        }
+                        Some(VirtualKeyCode::F10) => {
+                            self.toggle_recording();
+                            return EventResponse::Continue;
+                        }
+                        Some(VirtualKeyCode::F11) => {
+                            self.game_only = !self.game_only;
+                            return EventResponse::Redraw;
+                        }
+                        Some(VirtualKeyCode::F12) => {
+                            self.take_screenshot();
+                            return EventResponse::Continue;
+                        }
+                        _ => {}
+                    }
+                }
+                if let Some(mut mapped) = self.key_profiles.map(*key, &self.key_bindings) {
+                    if self.rotate_input {
+                        mapped = self.rotation.unrotate_key(mapped);
+                    }
+                    match pressed {
+                        ElementState::Pressed => self.press_key(mapped),
+                        ElementState::Released => self.release_key(mapped),
+                    }
+                }
+                EventResponse::Continue
+            }
+            WindowEvent::Resized(size) => {
+                surface.resize(size.width.max(1), size.height.max(1));
+                EventResponse::Redraw
+            }
+            WindowEvent::Focused(focused) => {
+                self.focus_changed(*focused);
+                EventResponse::Continue
+            }
+            WindowEvent::DroppedFile(path) => {
+                self.open_rom(path);
+                EventResponse::Redraw
+            }
+            _ => EventResponse::Continue,
+        }
+    }
+
+    /// The "Capture" window: screenshot and recording buttons with their settings.
+    pub fn draw_capture(&mut self, ui: &Ui) {
+        ui.window("Capture").build(|| {
+            if ui.button("Screenshot (F12)") {
+                self.take_screenshot();
+            }
+            ui.same_line();
+            let recording = self.capture.is_recording();
+            let label = if recording {
+                "Stop recording (F10)"
+            } else {
+                "Record clip (F10)"
+            };
+            if ui.button(label) {
+                self.toggle_recording();
+            }
+            if let Some(clip) = self.capture.recording() {
+                ui.text(format!(
+                    "Recording: {:.1} s, {} frames",
+                    clip.duration().as_secs_f64(),
+                    clip.len()
+                ));
+            }
+            self.capture.draw_settings(ui);
+            if !self.capture.status.is_empty() {
+                ui.text_wrapped(&self.capture.status);
+            }
+        });
+    }
+
+    pub fn draw_replay(&mut self, ui: &Ui) {
+        ui.window("Replay").build(|| {
+            ui.input_text("Replay file", &mut self.replay_path).build();
+            let status = self.emulator.lock().replay_status();
+            match status {
+                ReplayStatus::Off => {
+                    if ui.button("Record input") {
+                        self.toggle_input_recording();
+                    }
+                    ui.same_line();
+                    if ui.button("Play") {
+                        self.play_replay();
+                    }
+                }
+                ReplayStatus::Recording { frames } => {
+                    if ui.button("Stop and save") {
+                        self.toggle_input_recording();
+                    }
+                    ui.same_line();
+                    ui.text(format!("{frames} frames"));
+                }
+                ReplayStatus::Playing { frame, frames } => {
+                    if ui.button("Stop playback") {
+                        self.emulator.lock().stop_replay();
+                    }
+                    ui.same_line();
+                    ui.text(format!("Frame {frame} / {frames}"));
+                }
+            }
+            if !self.replay_status.is_empty() {
+                ui.text_wrapped(&self.replay_status);
+            }
+        });
+    }
+
+    pub fn draw_netplay(&mut self, ui: &Ui) {
+        self.poll_netplay_host();
+        ui.window("Netplay").build(|| {
+            let session = self.emulator.lock().netplay().map(|netplay| {
+                let peer = netplay
+                    .peer()
+                    .map_or_else(|_| String::from("?"), |peer| peer.to_string());
+                (netplay.role(), peer, netplay.frame(), netplay.waiting())
+            });
+            if let Some((role, peer, frame, waiting)) = session {
+                match role {
+                    NetplayRole::Host => ui.text(format!("Hosting {peer}")),
+                    NetplayRole::Guest => ui.text(format!("Joined {peer}")),
+                }
+                ui.text(format!("Frame {frame}"));
+                if waiting {
+                    ui.same_line();
+                    ui.text_disabled("waiting for the other player");
+                }
+                if ui.button("Leave") {
+                    self.emulator.lock().stop_netplay();
+                    self.netplay_status.clear();
+                }
+            } else if self.netplay_host.is_some() {
+                if ui.button("Stop hosting") {
+                    self.netplay_host = None;
+                    self.netplay_status.clear();
+                }
+            } else {
+                ui.input_scalar("Port", &mut self.netplay_port).build();
+                ui.input_scalar("Input delay", &mut self.netplay_delay)
+                    .build();
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Frames before a key press reaches the game, hides latency.");
+                }
+                if ui.button("Host") {
+                    self.host_netplay();
+                }
+                ui.separator();
+                ui.input_text("Address", &mut self.netplay_address).build();
+                if ui.button("Join") {
+                    self.join_netplay();
+                }
+            }
+            if !self.netplay_status.is_empty() {
+                ui.text_wrapped(&self.netplay_status);
+            }
+            ui.text_disabled("Both players need the same ROM.");
+        });
+    }
+
+    pub fn draw_script(&mut self, ui: &Ui) {
+        ui.window("Script").build(|| {
+            ui.input_text("Script file", &mut self.script_path).build();
+            if ui.button("Load") {
+                self.load_script();
+            }
+            if self.emulator.lock().has_hooks() {
+                ui.same_line();
+                if ui.button("Stop") {
+                    self.emulator.lock().set_hooks(None);
+                    self.script_status = String::from("Script stopped.");
+                }
+            }
+            if !self.script_status.is_empty() {
+                ui.text_wrapped(&self.script_status);
+            }
+            ui.text_disabled("Define on_frame(), on_instruction(pc) or");
+            ui.text_disabled("on_memory_write(address, value).");
+        });
+    }
+
+    /// "Cheats" window listing the loaded ROM's cheats, saved with its settings.
+    pub fn draw_cheats(&mut self, ui: &Ui) {
+        ui.window("Cheats").build(|| {
+            let mut emulator = self.emulator.lock();
+            let Some(rom_hash) = emulator.rom.as_ref().map(|rom| rom.hash.clone()) else {
+                ui.text_disabled("No ROM loaded.");
+                return;
+            };
+            let mut changed = false;
+            let mut removed = None;
+            let mut poked = None;
+            let table_flags = imgui::TableFlags::BORDERS_H
+                | imgui::TableFlags::BORDERS_V
+                | imgui::TableFlags::RESIZABLE;
+            if let Some(_table) = ui.begin_table_with_flags("cheat_table", 5, table_flags) {
+                ui.table_setup_column("On");
+                ui.table_setup_column("Name");
+                ui.table_setup_column("Code");
+                ui.table_setup_column("Mode");
+                ui.table_setup_column("");
+                ui.table_headers_row();
+                for (index, cheat) in emulator.cheats.iter_mut().enumerate() {
+                    let _id = ui.push_id_usize(index);
+                    ui.table_next_row();
+                    ui.table_set_column_index(0);
+                    changed |= ui.checkbox("##enabled", &mut cheat.enabled);
+                    ui.table_set_column_index(1);
+                    ui.text(&cheat.name);
+                    ui.table_set_column_index(2);
+                    ui.text(cheat.code());
+                    ui.table_set_column_index(3);
+                    ui.text(cheat.mode.name());
+                    ui.table_set_column_index(4);
+                    if ui.small_button("Poke") {
+                        poked = Some((cheat.address, cheat.value));
+                    }
+                    ui.same_line();
+                    if ui.small_button("Remove") {
+                        removed = Some(index);
+                    }
+                }
+            }
+            if let Some((address, value)) = poked {
+                if let Err(err) = emulator.poke(address, value) {
+                    log::error!("Failed to poke 0x{address:03X}: {err}");
+                }
+            }
+            if let Some(index) = removed {
+                emulator.cheats.remove(index);
+                changed = true;
+            }
+
+            ui.separator();
+            ui.input_text("Name", &mut self.new_cheat.name).build();
+            ui.input_text("Code", &mut self.cheat_code)
+                .hint("address = value")
+                .build();
+            let mut mode = CheatMode::ALL
+                .iter()
+                .position(|&mode| mode == self.new_cheat.mode)
+                .unwrap_or(0);
+            let names = CheatMode::ALL.map(CheatMode::name);
+            if ui.combo_simple_string("Mode", &mut mode, &names) {
+                self.new_cheat.mode = CheatMode::ALL[mode];
+            }
+            let code = Cheat::parse_code(&self.cheat_code);
+            ui.disabled(code.is_none(), || {
+                if ui.button("Add") {
+                    if let Some((address, value)) = code {
+                        let mut cheat = self.new_cheat.clone();
+                        cheat.address = address;
+                        cheat.value = value;
+                        if cheat.name.is_empty() {
+                            cheat.name = cheat.code();
+                        }
+                        emulator.cheats.push(cheat);
+                        self.new_cheat.name.clear();
+                        self.cheat_code.clear();
+                        changed = true;
+                    }
+                }
+            });
+            if changed {
+                self.config.set_cheats(&rom_hash, emulator.cheats.clone());
+            }
+        });
+    }
+
+    /// Windows for editing the global bindings and the loaded ROM's key mapping.
+    pub fn draw_key_mapping(&mut self, ui: &Ui) {
+        let binding = self.binding(BindTarget::Global);
+        if let Some(key) = keymap::draw_key_bindings(ui, &mut self.key_bindings, binding) {
+            self.start_binding(BindTarget::Global, key);
+        }
+        let binding = self.binding(BindTarget::Rom);
+        if let Some(key) = keymap::draw_key_profile(ui, &mut self.key_profiles, binding) {
+            self.start_binding(BindTarget::Rom, key);
+        }
+    }
+
+    /// Where the display goes on a surface of the given size, following the rotation and
+    /// scaling settings and the MegaChip8 screen's size while it's on. Call again after the
+    /// window is resized.
+    pub fn viewport(&self, surface_size: (u32, u32)) -> Viewport {
+        let display = if self.emulator.status().megachip {
+            MEGA_DISPLAY_SIZE
+        } else {
+            HIRES_DISPLAY_SIZE
+        };
+        Viewport::for_display(surface_size, display, self.rotation, self.scaling)
+    }
+
+    /// "Display" window with the scaling mode, the color themes, a color picker for each
+    /// pixel value and the CRT effects.
+    pub fn draw_display_settings(&mut self, ui: &Ui) {
+        ui.window("Display").build(|| {
+            if let Some(_combo) = ui.begin_combo("Scaling", self.scaling.name()) {
+                for scaling in Scaling::ALL {
+                    if ui.selectable(scaling.name()) {
+                        self.scaling = scaling;
+                    }
+                }
+            }
+            let current = Theme::detect(&self.palette).map_or("Custom", Theme::name);
+            if let Some(_combo) = ui.begin_combo("Theme", current) {
+                for theme in Theme::ALL {
+                    if ui.selectable(theme.name()) {
+                        self.palette = theme.palette();
+                    }
+                }
+            }
+            let palette = &mut self.palette;
+            edit_color(ui, "Background", &mut palette.background);
+            edit_color(ui, "Foreground", &mut palette.foreground);
+            edit_color(ui, "Plane 2 (XO-CHIP)", &mut palette.plane2);
+            edit_color(ui, "Both planes (XO-CHIP)", &mut palette.both);
+            ui.separator();
+            let effects = &mut self.effects;
+            ui.checkbox("CRT effects", &mut effects.enabled);
+            ui.disabled(!effects.enabled, || {
+                ui.slider("Scanlines", 0.0, 1.0, &mut effects.scanlines);
+                ui.slider("Curvature", 0.0, 0.5, &mut effects.curvature);
+                ui.slider("Vignette", 0.0, 1.0, &mut effects.vignette);
+                ui.slider("Glow", 0.0, 1.0, &mut effects.glow);
+            });
+            ui.slider("Fade frames", 0, 30, &mut effects.fade_frames);
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Fades pixels out after they turn off, to reduce flicker");
+            }
+        });
+    }
+
+    /// On-screen keypad that shows which keys are held and can be clicked, with a toggle
+    /// for sticky keys.
+    pub fn draw_keypad(&mut self, ui: &Ui) {
+        const LAYOUT: [[u8; 4]; 4] = [
+            [0x1, 0x2, 0x3, 0xC],
+            [0x4, 0x5, 0x6, 0xD],
+            [0x7, 0x8, 0x9, 0xE],
+            [0xA, 0x0, 0xB, 0xF],
+        ];
+        ui.window("Keypad").build(|| {
+            let mut sticky_keys = self.sticky_keys;
+            if ui.checkbox("Sticky keys", &mut sticky_keys) {
+                self.set_sticky_keys(sticky_keys);
+            }
+            let mut mouse_key = None;
+            for row in LAYOUT {
+                for (i, key) in row.into_iter().enumerate() {
+                    if i > 0 {
+                        ui.same_line();
+                    }
+                    let held = self.latched == Some(key) || self.down[key as usize];
+                    let _color = held.then(|| {
+                        ui.push_style_color(imgui::StyleColor::Button, [0.2, 0.6, 0.2, 1.0])
+                    });
+                    ui.button_with_size(format!("{key:X}"), [32.0, 32.0]);
+                    if ui.is_item_activated() && self.sticky_keys {
+                        self.press_key(key);
+                        self.release_key(key);
+                    }
+                    if ui.is_item_active() && !self.sticky_keys {
+                        mouse_key = Some(key);
+                    }
+                }
+            }
+            if mouse_key != self.mouse_key {
+                if let Some(key) = self.mouse_key {
+                    self.release_key(key);
+                }
+                if let Some(key) = mouse_key {
+                    self.press_key(key);
+                }
+                self.mouse_key = mouse_key;
+            }
+        });
+    }
+}
+
+/// RGB color picker for an RGBA color, alpha is left untouched.
+fn edit_color(ui: &Ui, label: &str, color: &mut [u8; 4]) {
+    let mut rgb = [0, 1, 2].map(|i| color[i] as f32 / 255.0);
+    if ui.color_edit3(label, &mut rgb) {
+        for (channel, value) in color.iter_mut().zip(rgb) {
+            *channel = (value * 255.0).round() as u8;
+        }
+    }
+}
+
+/// Default keypad mapping, by scancode so it follows the physical layout of a QWERTY
+/// keyboard whatever the active layout is.
+pub fn map_key(scancode: u32) -> Option<u8> {
+    match scancode {
+        0x2 => Some(0x1),
+        0x3 => Some(0x2),
+        0x4 => Some(0x3),
+        0x5 => Some(0xC),
+        0x10 => Some(0x4),
+        0x11 => Some(0x5),
+        0x12 => Some(0x6),
+        0x13 => Some(0xD),
+        0x1E => Some(0x7),
+        0x1F => Some(0x8),
+        0x20 => Some(0x9),
+        0x21 => Some(0xE),
+        0x2C => Some(0xA),
+        0x2D => Some(0x0),
+        0x2E => Some(0xB),
+        0x2F => Some(0xF),
+        _ => None,
+    }
+}
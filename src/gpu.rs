@@ -0,0 +1,139 @@
+use std::fmt::{self, Display};
+
+use wgpu::{Adapter, Backends, Device, Instance, Queue, Surface};
+
+/// Error returned when no usable adapter or device could be created.
+/// Carries a human readable report of what was tried and what the system exposes.
+#[derive(Debug)]
+pub struct GpuInitError {
+    pub reason: String,
+    pub diagnostics: String,
+}
+
+impl Display for GpuInitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n\n{}", self.reason, self.diagnostics)
+    }
+}
+
+impl std::error::Error for GpuInitError {}
+
+/// Requests a hardware adapter first and falls back to a software one if none is available.
+pub async fn request_adapter(
+    instance: &Instance,
+    surface: Option<&Surface>,
+) -> Result<Adapter, GpuInitError> {
+    for force_fallback_adapter in [false, true] {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                force_fallback_adapter,
+                compatible_surface: surface,
+            })
+            .await;
+        if let Some(adapter) = adapter {
+            if force_fallback_adapter {
+                log::warn!(
+                    "No hardware adapter found, using fallback adapter {:?}.",
+                    adapter.get_info().name
+                );
+            }
+            return Ok(adapter);
+        }
+    }
+
+    Err(GpuInitError {
+        reason: String::from("Failed to find a suitable graphics adapter."),
+        diagnostics: diagnostics(instance),
+    })
+}
+
+/// Requests a device with the limits the renderer needs, retrying with the
+/// lowest common limits if the adapter refuses the first request.
+pub async fn request_device(
+    instance: &Instance,
+    adapter: &Adapter,
+) -> Result<(Device, Queue), GpuInitError> {
+    let limits = [
+        // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
+        wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+        wgpu::Limits::downlevel_webgl2_defaults(),
+    ];
+
+    let mut last_error = None;
+    for limits in limits {
+        let device = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits,
+                },
+                None,
+            )
+            .await;
+        match device {
+            Ok(device) => return Ok(device),
+            Err(err) => last_error = Some(err),
+        }
+    }
+
+    Err(GpuInitError {
+        reason: format!(
+            "Failed to create a device on {:?}: {}",
+            adapter.get_info().name,
+            last_error.map(|e| e.to_string()).unwrap_or_default()
+        ),
+        diagnostics: diagnostics(instance),
+    })
+}
+
+/// Lists every adapter wgpu can see, used to make initialization failures actionable.
+pub fn diagnostics(instance: &Instance) -> String {
+    let mut report = String::from("Available adapters:\n");
+    let mut found = false;
+    for adapter in instance.enumerate_adapters(Backends::all()) {
+        let info = adapter.get_info();
+        report += &format!(
+            "  - {} ({:?}, {:?}, driver: {} {})\n",
+            info.name, info.backend, info.device_type, info.driver, info.driver_info
+        );
+        found = true;
+    }
+    if !found {
+        report += "  (none)\n";
+    }
+    if let Ok(backend) = std::env::var("WGPU_BACKEND") {
+        report += &format!("WGPU_BACKEND={backend}\n");
+    }
+    report += &format!(
+        "OS: {} ({})\n",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    report
+}
+
+/// Reports a fatal initialization error to the user through a native dialog,
+/// falling back to stderr if no dialog can be shown.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn show_error_dialog(error: &GpuInitError) {
+    eprintln!("{error}");
+    let shown = native_dialog::MessageDialog::new()
+        .set_type(native_dialog::MessageType::Error)
+        .set_title("CHIP-8 Emulator - Graphics initialization failed")
+        .set_text(&error.to_string())
+        .show_alert();
+    if let Err(err) = shown {
+        eprintln!("Unable to show error dialog: {err}");
+    }
+}
+
+/// Reports a fatal initialization error in the browser console and as an alert.
+#[cfg(target_arch = "wasm32")]
+pub fn show_error_dialog(error: &GpuInitError) {
+    log::error!("{error}");
+    if let Some(window) = web_sys::window() {
+        let _ = window.alert_with_message(&error.to_string());
+    }
+}
@@ -0,0 +1,126 @@
+//! Running ROMs without a window, for scripts and ROM regression tests on machines
+//! without a GPU.
+
+use std::time::Duration;
+
+use serde::Serialize;
+use web_time::Instant;
+
+use crate::{
+    emulator::{Display, Emulator, RunState},
+    timing::TIMER_HZ,
+};
+
+/// How long a headless run lasts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunLength {
+    /// Number of instructions, with the timers ticking every [`Emulator::cpf`] of them.
+    Cycles(u64),
+    /// Emulated seconds, at the speed set by [`Emulator::clock`].
+    Seconds(f64),
+}
+
+/// Runs the loaded ROM for `length`, stopping early if the program exits, hits a breakpoint
+/// or halts with an error.
+pub fn run(emulator: &mut Emulator, length: RunLength) {
+    match length {
+        RunLength::Cycles(cycles) => {
+            emulator.run_until(|emulator| !is_running(emulator), cycles);
+        }
+        RunLength::Seconds(seconds) => {
+            let frame = Duration::from_secs_f64(1.0 / TIMER_HZ);
+            let frames = (seconds * TIMER_HZ).round() as u64;
+            for _ in 0..frames {
+                if !is_running(emulator) {
+                    break;
+                }
+                emulator.run_for(frame);
+            }
+        }
+    }
+}
+
+/// Outcome of [`bench`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BenchResult {
+    pub instructions: u64,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    /// Millions of instructions per second.
+    pub fn mips(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON) / 1e6
+    }
+}
+
+/// Runs up to `cycles` instructions of the loaded ROM as fast as possible, timers included,
+/// and times them. Stops early like [`run`] if the CPU halts or hits a breakpoint.
+pub fn bench(emulator: &mut Emulator, cycles: u64) -> BenchResult {
+    // The predicate runs before every instruction and once more at the end.
+    let mut checks = 0u64;
+    let started = Instant::now();
+    emulator.run_until(
+        |_| {
+            checks += 1;
+            false
+        },
+        cycles,
+    );
+    BenchResult {
+        instructions: checks.saturating_sub(1),
+        elapsed: started.elapsed(),
+    }
+}
+
+fn is_running(emulator: &Emulator) -> bool {
+    matches!(emulator.state, RunState::Running)
+}
+
+/// Machine state at the end of a headless run, dumped as JSON.
+#[derive(Clone, Debug, Serialize)]
+pub struct RegisterDump {
+    pub pc: u16,
+    pub i: u16,
+    pub v: [u8; 16],
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub frames: u64,
+    /// Error that stopped the CPU, if it halted.
+    pub halted: Option<String>,
+}
+
+impl RegisterDump {
+    pub fn new(emulator: &Emulator) -> Self {
+        Self {
+            pc: emulator.pc(),
+            i: emulator.reg_i(),
+            v: *emulator.regs(),
+            stack: emulator.stack().to_vec(),
+            delay_timer: emulator.delay_timer(),
+            sound_timer: emulator.sound_timer(),
+            frames: emulator.frame_count() as u64,
+            halted: emulator.halted().map(ToString::to_string),
+        }
+    }
+}
+
+/// The display as text, one line per row. Unlit pixels are `.`, lit ones `#`, and XO-CHIP
+/// pixels lit on the second plane only or on both planes are `+` and `@`.
+pub fn ascii(display: &Display) -> String {
+    let (width, height) = display.size();
+    let mut text = String::with_capacity((width + 1) * height);
+    for y in 0..height {
+        for x in 0..width {
+            text.push(match display[x][y] & 0b11 {
+                0 => '.',
+                1 => '#',
+                2 => '+',
+                _ => '@',
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
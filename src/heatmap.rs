@@ -0,0 +1,140 @@
+//! Memory access heatmap: how often each address is read, written and executed, fading
+//! over time, for telling code from data and spotting runaway writes.
+
+use crate::emulator::MEMORY_SIZE;
+
+/// Side of the square image a page of memory is shown as, one pixel per address.
+pub const HEATMAP_SIZE: usize = 64;
+/// Addresses shown at once, 4 KiB: all of CHIP-8 memory, a 16th of XO-CHIP's.
+pub const PAGE_SIZE: usize = HEATMAP_SIZE * HEATMAP_SIZE;
+
+/// How an instruction used an address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+impl Access {
+    pub const ALL: [Access; 3] = [Access::Read, Access::Write, Access::Execute];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Access::Read => "Read",
+            Access::Write => "Write",
+            Access::Execute => "Execute",
+        }
+    }
+
+    /// Color channel the access is drawn in: reads blue, writes red, execution green.
+    fn channel(self) -> usize {
+        match self {
+            Access::Read => 2,
+            Access::Write => 0,
+            Access::Execute => 1,
+        }
+    }
+}
+
+/// Access counts per address and kind, multiplied by [`MemoryHeatmap::decay`] every frame.
+/// Off by default, see [`MemoryHeatmap::set_enabled`].
+#[derive(Clone, Debug)]
+pub struct MemoryHeatmap {
+    /// Share of the counts kept from one frame to the next, 1 keeps them forever.
+    pub decay: f32,
+    enabled: bool,
+    /// Indexed by [`Access`] then address, allocated on the first recorded access.
+    counts: [Vec<f32>; 3],
+}
+
+impl Default for MemoryHeatmap {
+    fn default() -> Self {
+        Self {
+            decay: 0.95,
+            enabled: false,
+            counts: Default::default(),
+        }
+    }
+}
+
+impl MemoryHeatmap {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Forgets everything counted so far.
+    pub fn reset(&mut self) {
+        self.counts = Default::default();
+    }
+
+    /// Counts an access to the `len` bytes from `address`.
+    pub(crate) fn record(&mut self, address: u16, len: usize, access: Access) {
+        if !self.enabled {
+            return;
+        }
+        let counts = &mut self.counts[access as usize];
+        if counts.is_empty() {
+            *counts = vec![0.0; MEMORY_SIZE];
+        }
+        let start = address as usize;
+        let end = (start + len).min(MEMORY_SIZE);
+        for count in &mut counts[start..end] {
+            *count += 1.0;
+        }
+    }
+
+    /// Fades the counts, called once per frame.
+    pub(crate) fn tick(&mut self) {
+        if !self.enabled || self.decay >= 1.0 {
+            return;
+        }
+        for counts in &mut self.counts {
+            for count in counts.iter_mut() {
+                // Flushed to zero so untouched addresses go back to black.
+                *count = if *count < 0.01 {
+                    0.0
+                } else {
+                    *count * self.decay
+                };
+            }
+        }
+    }
+
+    /// Decayed count of `access`es to `address`.
+    pub fn count(&self, address: u16, access: Access) -> f32 {
+        self.counts[access as usize]
+            .get(address as usize)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Page `page` as [`HEATMAP_SIZE`] rows of [`HEATMAP_SIZE`] RGBA pixels, address order.
+    /// Each access kind lights its own channel, on a log scale up to the page's busiest
+    /// address so a tight loop doesn't wash out everything else.
+    pub fn rgba(&self, page: usize) -> Vec<u8> {
+        let start = page * PAGE_SIZE;
+        let mut pixels = vec![0; PAGE_SIZE * 4];
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+        for access in Access::ALL {
+            let Some(counts) = self.counts[access as usize].get(start..start + PAGE_SIZE) else {
+                continue;
+            };
+            let max = counts.iter().copied().fold(0.0f32, f32::max);
+            if max <= 0.0 {
+                continue;
+            }
+            for (pixel, count) in pixels.chunks_exact_mut(4).zip(counts) {
+                let heat = count.ln_1p() / max.ln_1p();
+                pixel[access.channel()] = (heat * 255.0) as u8;
+            }
+        }
+        pixels
+    }
+}
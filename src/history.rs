@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+
+use crate::megachip::{MegaChip, MegaUndo};
+
+/// Instructions kept by default, a few seconds of gameplay at the usual speeds.
+pub const DEFAULT_HISTORY_DEPTH: usize = 10_000;
+
+/// Machine state an instruction can change, recorded before it runs so it can be undone.
+/// Memory and display are stored as the bytes and pixels the instruction overwrote.
+#[derive(Clone, Debug)]
+pub(crate) struct Undo {
+    pub pc: u16,
+    pub reg_i: u16,
+    pub regs: [u8; 16],
+    pub rpl_flags: [u8; 16],
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub vip_rng: u16,
+    pub rng_word_pos: u128,
+    pub plane_mask: u8,
+    pub audio_bits: Option<[u8; 16]>,
+    pub pitch: u8,
+    pub waiting_vblank: bool,
+    pub hires: bool,
+    /// MegaChip8 state before a 0011 or 0010 switched the mode, `None` for every other
+    /// instruction.
+    pub megachip: Option<Option<Box<MegaChip>>>,
+    /// What a 0NNN or DXYN instruction changed of the MegaChip8 state while the mode was on.
+    pub mega_state: Option<MegaUndo>,
+    /// Address and previous value of every byte written, in write order.
+    pub mem: Vec<(u16, u8)>,
+    /// Position and previous value of every pixel changed.
+    pub pixels: Vec<(u8, u8, u8)>,
+}
+
+/// Ring buffer of the last executed instructions, used to step backwards. Once full the
+/// oldest instruction is dropped.
+#[derive(Clone, Debug)]
+pub struct History {
+    steps: VecDeque<Undo>,
+    depth: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_DEPTH)
+    }
+}
+
+impl History {
+    /// History keeping up to `depth` instructions, 0 disables recording.
+    pub fn new(depth: usize) -> Self {
+        Self {
+            steps: VecDeque::new(),
+            depth,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.steps.len() > depth {
+            self.steps.pop_front();
+        }
+    }
+
+    /// Number of instructions that can be stepped back.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+    }
+
+    pub(crate) fn push(&mut self, undo: Undo) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.steps.len() == self.depth {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(undo);
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Undo> {
+        self.steps.pop_back()
+    }
+}
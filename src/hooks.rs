@@ -0,0 +1,34 @@
+//! Code called back by the emulator as it runs, e.g. a cheat or a script (see the `script`
+//! module). Installed with [`Emulator::set_hooks`], or for memory mapped peripherals with
+//! [`Emulator::map_memory`].
+
+use crate::emulator::Emulator;
+
+/// Callbacks with full access to the machine. They run on the emulation thread in the
+/// middle of emulation, so they should be quick. Every method does nothing by default.
+pub trait Hooks: Send {
+    /// At the start of every frame, right after the timers ticked.
+    fn on_frame(&mut self, _emulator: &mut Emulator) {}
+
+    /// Before the instruction at `pc` executes.
+    fn on_instruction(&mut self, _emulator: &mut Emulator, _pc: u16) {}
+
+    /// After an instruction (FX33, FX55, 5XY2) stored `value` at `address`. Debugger pokes
+    /// aren't reported.
+    fn on_memory_write(&mut self, _emulator: &mut Emulator, _address: u16, _value: u8) {}
+}
+
+/// Virtual peripheral or logger mapped over an address range, e.g. a serial port at 0xFF0.
+/// Sees the bytes data instructions (FX55/FX65, 5XY2/5XY3, FX33, DXYN, F002) read and write
+/// in its range; instruction fetches and debugger pokes bypass it. Stepping back doesn't
+/// undo what it did. Every method does nothing by default.
+pub trait MemoryHook: Send {
+    /// An instruction reads `address`, which holds `value`. Returns the value it gets
+    /// instead, memory is left as it is.
+    fn on_read(&mut self, _address: u16, value: u8) -> u8 {
+        value
+    }
+
+    /// An instruction stored `value` at `address`. Memory already holds it.
+    fn on_write(&mut self, _address: u16, _value: u8) {}
+}
@@ -0,0 +1,217 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use imgui::Ui;
+use serde::{Deserialize, Serialize};
+
+use crate::frontend::map_key;
+
+/// Keyboard bindings for one ROM, scancode to keypad key. Only the overridden keys are
+/// stored, everything else falls through to the global [`KeyBindings`].
+pub type KeyProfile = BTreeMap<u32, u8>;
+
+/// Which mapping the next key press gets bound into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindTarget {
+    /// The global bindings, used by every ROM.
+    Global,
+    /// The loaded ROM's profile.
+    Rom,
+}
+
+/// Global keyboard bindings, scancode to keypad key. Defaults to the QWERTY layout of
+/// [`map_key`] and is kept in the config directory, so other layouts can be remapped once.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub keys: BTreeMap<u32, u8>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: (0..0x100)
+                .filter_map(|scancode| Some((scancode, map_key(scancode)?)))
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Reads the bindings, falling back to the defaults if the file doesn't exist yet.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn map(&self, scancode: u32) -> Option<u8> {
+        self.keys.get(&scancode).copied()
+    }
+
+    /// Binds a scancode to a keypad key, replacing the key's previous scancode.
+    pub fn bind(&mut self, scancode: u32, key: u8) {
+        self.keys.retain(|_, bound| *bound != key);
+        self.keys.insert(scancode, key);
+    }
+}
+
+/// Per-ROM keypad mappings stored by ROM hash, applied automatically when the ROM loads.
+/// Kept in [`crate::resources::ResourceLocator::keymaps`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct KeyProfiles {
+    pub profiles: BTreeMap<String, KeyProfile>,
+    /// File loaded from, saved back to on every change.
+    #[serde(skip)]
+    path: PathBuf,
+    /// Hash of the loaded ROM, whose profile is applied.
+    #[serde(skip)]
+    rom_hash: Option<String>,
+}
+
+impl KeyProfiles {
+    /// Reads the profiles, starting empty if the file doesn't exist yet. [`KeyProfiles::save`]
+    /// writes back to the same file.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let mut profiles: Self = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => return Err(err),
+        };
+        profiles.path = path;
+        Ok(profiles)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Switches to the profile of the given ROM, if it has one.
+    pub fn sync_rom(&mut self, rom_hash: Option<&str>) {
+        if self.rom_hash.as_deref() != rom_hash {
+            self.rom_hash = rom_hash.map(str::to_string);
+        }
+    }
+
+    pub fn rom_hash(&self) -> Option<&str> {
+        self.rom_hash.as_deref()
+    }
+
+    /// Profile of the loaded ROM.
+    pub fn active(&self) -> Option<&KeyProfile> {
+        self.profiles.get(self.rom_hash.as_ref()?)
+    }
+
+    /// Keypad key for a scancode, checking the loaded ROM's profile before the global bindings.
+    pub fn map(&self, scancode: u32, bindings: &KeyBindings) -> Option<u8> {
+        match self.active().and_then(|profile| profile.get(&scancode)) {
+            Some(key) => Some(*key),
+            None => bindings.map(scancode),
+        }
+    }
+
+    /// Binds a scancode to a keypad key in the loaded ROM's profile.
+    /// Any other scancode bound to the same key in the profile is removed.
+    pub fn bind(&mut self, scancode: u32, key: u8) {
+        let Some(rom_hash) = &self.rom_hash else {
+            return;
+        };
+        let profile = self.profiles.entry(rom_hash.clone()).or_default();
+        profile.retain(|_, bound| *bound != key);
+        profile.insert(scancode, key);
+    }
+
+    /// Removes the loaded ROM's profile, going back to the global mapping.
+    pub fn clear(&mut self) {
+        if let Some(rom_hash) = &self.rom_hash {
+            self.profiles.remove(rom_hash);
+        }
+    }
+}
+
+/// Window listing the loaded ROM's bindings. Returns the keypad key to rebind when its
+/// button is clicked, the next key pressed is then bound to it.
+pub fn draw_key_profile(ui: &Ui, profiles: &mut KeyProfiles, binding: Option<u8>) -> Option<u8> {
+    let mut rebind = None;
+    ui.window("Key Mapping").build(|| {
+        if profiles.rom_hash().is_none() {
+            ui.text_disabled("Load a ROM to edit its key mapping.");
+            return;
+        }
+        ui.text_wrapped("Bindings saved for this ROM, on top of the global bindings.");
+        rebind = draw_key_rows(ui, profiles.active(), binding, "global");
+        ui.separator();
+        let has_profile = profiles.active().is_some();
+        ui.disabled(!has_profile, || {
+            if ui.button("Reset to default") {
+                profiles.clear();
+                if let Err(err) = profiles.save() {
+                    log::error!("Failed to save key mappings: {err}");
+                }
+            }
+        });
+    });
+    rebind
+}
+
+/// "Input" window editing the global bindings. Returns the keypad key to rebind when its
+/// button is clicked, like [`draw_key_profile`].
+pub fn draw_key_bindings(ui: &Ui, bindings: &mut KeyBindings, binding: Option<u8>) -> Option<u8> {
+    let mut rebind = None;
+    ui.window("Input").build(|| {
+        ui.text_wrapped("Click a key, then press the keyboard key to bind to it.");
+        rebind = draw_key_rows(ui, Some(&bindings.keys), binding, "unbound");
+        ui.separator();
+        if ui.button("Reset to default") {
+            *bindings = KeyBindings::default();
+        }
+    });
+    rebind
+}
+
+/// One row per keypad key with its bound scancodes, returns the key whose button was clicked.
+fn draw_key_rows(
+    ui: &Ui,
+    keys: Option<&BTreeMap<u32, u8>>,
+    binding: Option<u8>,
+    unbound: &str,
+) -> Option<u8> {
+    let mut rebind = None;
+    for key in 0..16u8 {
+        let _id = ui.push_id_usize(key as usize);
+        let bound: Vec<String> = keys
+            .into_iter()
+            .flatten()
+            .filter(|(_, bound)| **bound == key)
+            .map(|(scancode, _)| format!("0x{scancode:02X}"))
+            .collect();
+        let label = if binding == Some(key) {
+            String::from("Press a key...")
+        } else if bound.is_empty() {
+            String::from(unbound)
+        } else {
+            bound.join(", ")
+        };
+        if ui.button_with_size(format!("{key:X}"), [32.0, 0.0]) {
+            rebind = Some(key);
+        }
+        ui.same_line();
+        ui.text(label);
+    }
+    rebind
+}
@@ -0,0 +1,76 @@
+//! CHIP-8 emulator. The emulation core (CPU, ROM analysis, sessions, the emulation thread)
+//! has no GUI dependencies; the window, renderer and imgui debug windows are behind the
+//! default `frontend` feature.
+
+pub mod buzzer;
+pub mod call_graph;
+pub mod cheats;
+pub mod compare;
+pub mod compat;
+pub mod config;
+pub mod crash_dump;
+pub mod disassembler;
+pub mod emulator;
+pub mod error;
+pub mod flow;
+pub mod frame_limiter;
+pub mod headless;
+pub mod heatmap;
+pub mod history;
+pub mod hooks;
+pub mod lint;
+pub mod machine;
+pub mod megachip;
+pub mod memory_map;
+pub mod metadata;
+pub mod netplay;
+pub mod octo;
+pub mod palette;
+pub mod playtime;
+pub mod profiler;
+pub mod quirks;
+pub mod replay;
+pub mod resources;
+pub mod rom;
+pub mod savestate;
+pub mod session;
+pub mod sprites;
+pub mod step_diff;
+pub mod symbols;
+pub mod terminal;
+pub mod timing;
+pub mod trace;
+pub mod triple_buffer;
+pub mod watch;
+pub mod worker;
+
+#[cfg(feature = "scripting")]
+pub mod script;
+
+#[cfg(feature = "frontend")]
+pub mod annotations;
+#[cfg(feature = "frontend")]
+pub mod audio;
+#[cfg(feature = "frontend")]
+pub mod capture;
+#[cfg(feature = "frontend")]
+pub mod debug_ui;
+#[cfg(feature = "frontend")]
+pub mod frontend;
+#[cfg(feature = "frontend")]
+pub mod gpu;
+#[cfg(feature = "frontend")]
+pub mod keymap;
+#[cfg(feature = "frontend")]
+pub mod library;
+// Blocks on the GPU, which the browser doesn't allow.
+#[cfg(all(feature = "frontend", not(target_arch = "wasm32")))]
+pub mod offscreen;
+#[cfg(feature = "frontend")]
+pub mod ram_search;
+#[cfg(feature = "frontend")]
+pub mod renderer;
+#[cfg(all(feature = "frontend", target_arch = "wasm32"))]
+pub mod web;
+#[cfg(feature = "frontend")]
+pub mod workspace;
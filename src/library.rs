@@ -0,0 +1,281 @@
+use std::{fs, io, path::PathBuf};
+
+use imgui::{TableFlags, TableSortDirection, Ui};
+
+use crate::{
+    metadata::{self, RomDatabase, RomMetadata},
+    playtime::{format_ago, format_duration, Playtime},
+    rom,
+};
+
+#[derive(Clone, Debug)]
+pub struct LibraryEntry {
+    /// Path relative to the library folder.
+    pub name: String,
+    pub path: PathBuf,
+    pub hash: String,
+    pub size: usize,
+    /// From the ROM's Octo options, the database and its extension, in that order.
+    pub metadata: RomMetadata,
+}
+
+impl LibraryEntry {
+    /// Title from the metadata, the file name if there is none.
+    pub fn title(&self) -> &str {
+        self.metadata.title.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Whether the name, title or an author contains `search`, which must be lowercase.
+    fn matches(&self, search: &str) -> bool {
+        self.name.to_lowercase().contains(search)
+            || self.title().to_lowercase().contains(search)
+            || self
+                .metadata
+                .authors
+                .iter()
+                .any(|author| author.to_lowercase().contains(search))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SortColumn {
+    Name,
+    Platform,
+    Size,
+    Playtime,
+    LastPlayed,
+}
+
+/// ROMs found in a folder and its subfolders, listed with their metadata and play statistics.
+pub struct Library {
+    pub entries: Vec<LibraryEntry>,
+    /// Folder scanned for ROMs, edited in the "Library" window.
+    pub directory: PathBuf,
+    /// Where titles, authors and platforms come from when the ROM has no Octo options.
+    pub database: RomDatabase,
+    search: String,
+    status: String,
+    sort: (SortColumn, TableSortDirection),
+}
+
+impl Default for Library {
+    fn default() -> Self {
+        Self::new(PathBuf::new(), RomDatabase::default())
+    }
+}
+
+impl Library {
+    pub fn new(directory: impl Into<PathBuf>, database: RomDatabase) -> Self {
+        Self {
+            entries: Vec::new(),
+            directory: directory.into(),
+            database,
+            search: String::new(),
+            status: String::new(),
+            sort: (SortColumn::Name, TableSortDirection::Ascending),
+        }
+    }
+
+    /// Lists the ROMs in [`Library::directory`] again, see [`metadata::ROM_EXTENSIONS`].
+    /// Unreadable files and subfolders are skipped.
+    pub fn scan(&mut self) -> io::Result<()> {
+        let mut entries = Vec::new();
+        let mut dirs = vec![self.directory.clone()];
+        while let Some(dir) = dirs.pop() {
+            let files = match fs::read_dir(&dir) {
+                Ok(files) => files,
+                // Only the library folder itself has to be there.
+                Err(err) if dir != self.directory => {
+                    log::warn!("Skipping unreadable folder {dir:?}: {err}");
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            for file in files {
+                let file = file?;
+                let path = file.path();
+                if file.file_type()?.is_dir() {
+                    dirs.push(path);
+                } else if metadata::is_rom_file(&path) {
+                    if let Some(entry) = self.read_entry(path) {
+                        entries.push(entry);
+                    }
+                }
+            }
+        }
+        self.status = format!("{} ROMs in {}.", entries.len(), self.directory.display());
+        self.entries = entries;
+        self.sort_entries(&Playtime::default());
+        Ok(())
+    }
+
+    fn read_entry(&self, path: PathBuf) -> Option<LibraryEntry> {
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) => {
+                log::warn!("Skipping unreadable ROM {path:?}: {err}");
+                return None;
+            }
+        };
+        let hash = rom::hash(&data);
+        let octo = match metadata::load_sidecar(&path) {
+            Some(Ok(options)) => options.metadata(),
+            Some(Err(err)) => {
+                log::warn!("Ignoring the Octo options of {path:?}: {err}");
+                RomMetadata::default()
+            }
+            None => RomMetadata::default(),
+        };
+        let metadata = octo
+            .or(self.database.get(&hash).cloned().unwrap_or_default())
+            .or(metadata::extension_metadata(&path));
+        let name = path
+            .strip_prefix(&self.directory)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .into_owned();
+        Some(LibraryEntry {
+            name,
+            path,
+            hash,
+            size: data.len(),
+            metadata,
+        })
+    }
+
+    fn sort_entries(&mut self, playtime: &Playtime) {
+        let (column, direction) = self.sort;
+        let key = |entry: &LibraryEntry| {
+            let record = playtime.get(&entry.hash).cloned().unwrap_or_default();
+            (record.total_secs, record.last_played)
+        };
+        self.entries.sort_by(|a, b| {
+            let ordering = match column {
+                SortColumn::Name => a.title().to_lowercase().cmp(&b.title().to_lowercase()),
+                SortColumn::Platform => a.metadata.platform.cmp(&b.metadata.platform),
+                SortColumn::Size => a.size.cmp(&b.size),
+                SortColumn::Playtime => key(a).0.cmp(&key(b).0),
+                SortColumn::LastPlayed => key(a).1.cmp(&key(b).1),
+            };
+            match direction {
+                TableSortDirection::Ascending => ordering,
+                TableSortDirection::Descending => ordering.reverse(),
+            }
+            .then_with(|| a.name.cmp(&b.name))
+        });
+    }
+
+    /// Draws the "Library" window, returns the ROM to open when one is double-clicked.
+    pub fn draw(&mut self, ui: &Ui, playtime: &Playtime) -> Option<LibraryEntry> {
+        let mut open = None;
+        ui.window("Library").build(|| {
+            let mut directory = self.directory.to_string_lossy().into_owned();
+            if ui.input_text("Folder", &mut directory).build() {
+                self.directory = PathBuf::from(directory);
+            }
+            ui.same_line();
+            if ui.button("Scan") {
+                if let Err(err) = self.scan() {
+                    self.status = format!("Failed to read {}: {err}", self.directory.display());
+                }
+            }
+            ui.input_text("Search", &mut self.search)
+                .hint("name, title or author")
+                .build();
+            if !self.status.is_empty() {
+                ui.text_disabled(&self.status);
+            }
+
+            let flags = TableFlags::SORTABLE
+                | TableFlags::BORDERS_H
+                | TableFlags::SCROLL_Y
+                | TableFlags::RESIZABLE;
+            let Some(_table) = ui.begin_table_with_flags("library_table", 5, flags) else {
+                return;
+            };
+            ui.table_setup_column("Name");
+            ui.table_setup_column("Platform");
+            ui.table_setup_column("Size");
+            ui.table_setup_column("Play time");
+            ui.table_setup_column("Last played");
+            ui.table_setup_scroll_freeze(5, 1);
+            ui.table_headers_row();
+
+            let mut resort = false;
+            if let Some(specs) = ui.table_sort_specs_mut() {
+                specs.conditional_sort(|specs| {
+                    if let Some(spec) = specs.iter().next() {
+                        let column = match spec.column_idx() {
+                            1 => SortColumn::Platform,
+                            2 => SortColumn::Size,
+                            3 => SortColumn::Playtime,
+                            4 => SortColumn::LastPlayed,
+                            _ => SortColumn::Name,
+                        };
+                        let direction = spec
+                            .sort_direction()
+                            .unwrap_or(TableSortDirection::Ascending);
+                        self.sort = (column, direction);
+                        resort = true;
+                    }
+                });
+            }
+            if resort {
+                self.sort_entries(playtime);
+            }
+
+            let search = self.search.trim().to_lowercase();
+            for entry in self.entries.iter().filter(|entry| entry.matches(&search)) {
+                let record = playtime.get(&entry.hash);
+                ui.table_next_row();
+                ui.table_set_column_index(0);
+                ui.selectable_config(format!("{}##{}", entry.title(), entry.name))
+                    .flags(imgui::SelectableFlags::SPAN_ALL_COLUMNS)
+                    .build();
+                if ui.is_item_hovered() {
+                    if ui.is_mouse_double_clicked(imgui::MouseButton::Left) {
+                        open = Some(entry.clone());
+                    }
+                    ui.tooltip(|| draw_details(ui, entry));
+                }
+                ui.table_set_column_index(1);
+                match &entry.metadata.platform {
+                    Some(platform) => ui.text(platform),
+                    None => ui.text_disabled("-"),
+                }
+                ui.table_set_column_index(2);
+                ui.text(format!("{} B", entry.size));
+                ui.table_set_column_index(3);
+                match record {
+                    Some(record) => ui.text(format_duration(record.total_secs)),
+                    None => ui.text_disabled("-"),
+                }
+                ui.table_set_column_index(4);
+                match record {
+                    Some(record) => ui.text(format_ago(record.last_played)),
+                    None => ui.text_disabled("never"),
+                }
+            }
+        });
+        open
+    }
+}
+
+fn draw_details(ui: &Ui, entry: &LibraryEntry) {
+    let metadata = &entry.metadata;
+    ui.text(entry.title());
+    if !metadata.authors.is_empty() {
+        ui.text(format!("By {}", metadata.authors.join(", ")));
+    }
+    if let Some(release) = &metadata.release {
+        ui.text(format!("Released {release}"));
+    }
+    if let Some(description) = &metadata.description {
+        ui.text_wrapped(description);
+    }
+    if let Some(tickrate) = metadata.tickrate {
+        ui.text(format!("{tickrate} instructions per frame"));
+    }
+    ui.text_disabled(&entry.name);
+    ui.text_disabled(format!("SHA-1 {}", entry.hash));
+}
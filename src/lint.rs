@@ -0,0 +1,131 @@
+use std::{collections::BTreeSet, fmt};
+
+use crate::flow::{branch_target, BranchKind};
+
+/// Something suspicious found while statically scanning a ROM before running it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintWarning {
+    pub address: u16,
+    pub kind: LintKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LintKind {
+    /// Reachable opcode the interpreter doesn't implement, it will be skipped.
+    UnknownOpcode(u16),
+    /// Jump or call to an address outside the loaded ROM.
+    TargetOutOfRange(u16),
+    /// Execution can run past the last byte of the ROM.
+    RunsOffEnd,
+    /// Code is reachable at both this address and the next byte, so instructions overlap.
+    OverlappingCode,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:03X}: ", self.address)?;
+        match self.kind {
+            LintKind::UnknownOpcode(inst) => write!(f, "unknown opcode {inst:04X}"),
+            LintKind::TargetOutOfRange(target) => {
+                write!(f, "branch target 0x{target:03X} is outside the ROM")
+            }
+            LintKind::RunsOffEnd => write!(f, "execution can run past the end of the ROM"),
+            LintKind::OverlappingCode => {
+                write!(f, "code overlaps the instruction one byte later")
+            }
+        }
+    }
+}
+
+/// Whether the interpreter has a handler for `inst`.
+pub fn is_implemented(inst: u16) -> bool {
+    let n = inst & 0x000F;
+    let nn = inst & 0x00FF;
+    match inst & 0xF000 {
+        0x0000 => {
+            matches!(inst, 0x00E0 | 0x00EE | 0x00FB..=0x00FF)
+                || matches!(inst & 0xFFF0, 0x00C0 | 0x00D0)
+        }
+        0x5000 => matches!(n, 0x0 | 0x2 | 0x3),
+        0x9000 => n == 0,
+        0x8000 => matches!(n, 0x0..=0x7 | 0xE),
+        0xE000 => matches!(nn, 0x9E | 0xA1),
+        0xF000 => {
+            matches!(inst, 0xF000 | 0xF002)
+                || matches!(nn, 0x01 | 0x07 | 0x0A | 0x15 | 0x18 | 0x1E | 0x29 | 0x30)
+                || matches!(nn, 0x33 | 0x3A | 0x55 | 0x65 | 0x75 | 0x85)
+        }
+        _ => true,
+    }
+}
+
+/// Traces every statically reachable instruction of `rom`, loaded at `offset`, and reports
+/// anything that would make it misbehave. Only reachable code is checked so sprite data
+/// mixed into the ROM doesn't show up as unknown opcodes.
+pub fn lint_rom(rom: &[u8], offset: u16) -> Vec<LintWarning> {
+    let end = offset as usize + rom.len();
+    let in_rom = |address: u16| (offset as usize..end).contains(&(address as usize));
+    let mut warnings = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut pending = vec![offset];
+
+    while let Some(address) = pending.pop() {
+        if !visited.insert(address) {
+            continue;
+        }
+        if !in_rom(address) || !in_rom(address + 1) {
+            warnings.push(LintWarning {
+                address,
+                kind: LintKind::RunsOffEnd,
+            });
+            continue;
+        }
+        let index = (address - offset) as usize;
+        let inst = (rom[index] as u16) << 8 | rom[index + 1] as u16;
+        if !is_implemented(inst) {
+            warnings.push(LintWarning {
+                address,
+                kind: LintKind::UnknownOpcode(inst),
+            });
+        }
+
+        // XO-CHIP's F000 NNNN is the only 4-byte instruction.
+        let next = address + if inst == 0xF000 { 4 } else { 2 };
+        match branch_target(address, inst) {
+            Some((BranchKind::Jump, target)) | Some((BranchKind::Call, target))
+                if !in_rom(target) =>
+            {
+                warnings.push(LintWarning {
+                    address,
+                    kind: LintKind::TargetOutOfRange(target),
+                });
+            }
+            Some((BranchKind::Jump, target)) => {
+                // A jump to itself is the usual way to halt, nothing follows it.
+                pending.push(target);
+            }
+            Some((BranchKind::Call, target)) => {
+                pending.push(target);
+                pending.push(next);
+            }
+            Some((BranchKind::Skip, target)) => {
+                pending.push(target);
+                pending.push(next);
+            }
+            // Returns and BNNN leave to somewhere we can't follow statically, 00FD exits.
+            None if matches!(inst, 0x00EE | 0x00FD) || inst & 0xF000 == 0xB000 => {}
+            None => pending.push(next),
+        }
+    }
+
+    for address in &visited {
+        if visited.contains(&(address + 1)) && in_rom(*address) && in_rom(address + 1) {
+            warnings.push(LintWarning {
+                address: *address,
+                kind: LintKind::OverlappingCode,
+            });
+        }
+    }
+    warnings.sort_by_key(|warning| warning.address);
+    warnings
+}
@@ -0,0 +1,195 @@
+//! Machine profiles: the memory layout of the computer a ROM was written for, where it's
+//! loaded, how much memory there is and which areas belong to the interpreter, along with
+//! that machine's quirks.
+
+use std::{ops::Range, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    emulator::{load_offset_for, Emulator, DEFAULT_LOAD_OFFSET, ETI660_LOAD_OFFSET, MEMORY_SIZE},
+    megachip::{self, MEGA_MEMORY_SIZE},
+    quirks::{QuirkPreset, Quirks},
+};
+
+/// Memory of the 4 KiB machines.
+pub const SMALL_MEMORY_SIZE: usize = 0x1000;
+/// Stack and interpreter variables at the top of VIP and ETI-660 memory.
+const VIP_WORK_AREA: Range<u16> = 0xEA0..0xF00;
+/// The 64x32 display buffer after them, up to the end of memory.
+const VIP_DISPLAY_AREA: Range<u16> = 0xF00..0x1000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MachineProfile {
+    /// No particular machine: ROMs at 0x200, all 64 KiB usable and modern quirks.
+    #[default]
+    Generic,
+    /// 4 KiB COSMAC VIP, with the interpreter below 0x200 and its stack, variables and
+    /// display buffer from 0xEA0.
+    CosmacVip,
+    /// ETI-660, laid out like the VIP but with programs loaded at 0x600.
+    Eti660,
+    /// 4 KiB HP-48 calculators running SUPER-CHIP.
+    SuperChip,
+    /// XO-CHIP as implemented by Octo, with 64 KiB of memory.
+    XoChip,
+    /// MegaChip8: SUPER-CHIP with a 256x192 color mode and ROMs of up to 16 MiB, the part
+    /// past 64 KiB only reachable through its 24-bit I, see [`crate::megachip`].
+    MegaChip,
+}
+
+impl MachineProfile {
+    pub const ALL: [MachineProfile; 6] = [
+        MachineProfile::Generic,
+        MachineProfile::CosmacVip,
+        MachineProfile::Eti660,
+        MachineProfile::SuperChip,
+        MachineProfile::XoChip,
+        MachineProfile::MegaChip,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MachineProfile::Generic => "Generic CHIP-8",
+            MachineProfile::CosmacVip => "COSMAC VIP",
+            MachineProfile::Eti660 => "ETI-660",
+            MachineProfile::SuperChip => "SUPER-CHIP (HP-48)",
+            MachineProfile::XoChip => "XO-CHIP",
+            MachineProfile::MegaChip => "MegaChip8",
+        }
+    }
+
+    /// Short name for the command line, e.g. `schip`, see the [`FromStr`] impl.
+    pub fn id(self) -> &'static str {
+        match self {
+            MachineProfile::Generic => "generic",
+            MachineProfile::CosmacVip => "vip",
+            MachineProfile::Eti660 => "eti660",
+            MachineProfile::SuperChip => "schip",
+            MachineProfile::XoChip => "xochip",
+            MachineProfile::MegaChip => "megachip",
+        }
+    }
+
+    /// Profile whose programs start at `offset`, used when a ROM's extension names a load
+    /// address, see [`load_offset_for`].
+    pub fn for_load_offset(offset: u16) -> Self {
+        if offset == ETI660_LOAD_OFFSET {
+            MachineProfile::Eti660
+        } else {
+            MachineProfile::Generic
+        }
+    }
+
+    /// Profile a ROM at `path` is loaded with when this one is selected: this one, unless
+    /// the extension names another machine's layout, like an ETI-660 ROM on a VIP, or
+    /// MegaChip8.
+    pub fn for_rom(self, path: &Path) -> Self {
+        let megachip = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(megachip::EXTENSION));
+        if megachip {
+            return MachineProfile::MegaChip;
+        }
+        match load_offset_for(path) {
+            Some(offset) if offset != self.load_offset() => Self::for_load_offset(offset),
+            _ => self,
+        }
+    }
+
+    /// Address programs are loaded at and start executing from.
+    pub fn load_offset(self) -> u16 {
+        match self {
+            MachineProfile::Eti660 => ETI660_LOAD_OFFSET,
+            _ => DEFAULT_LOAD_OFFSET,
+        }
+    }
+
+    /// Bytes of addressable memory. Instructions touching anything above fail with
+    /// [`crate::error::EmulatorError::MemoryOutOfBounds`].
+    pub fn memory_size(self) -> usize {
+        match self {
+            MachineProfile::Generic | MachineProfile::XoChip | MachineProfile::MegaChip => {
+                MEMORY_SIZE
+            }
+            MachineProfile::CosmacVip | MachineProfile::Eti660 | MachineProfile::SuperChip => {
+                SMALL_MEMORY_SIZE
+            }
+        }
+    }
+
+    pub fn quirk_preset(self) -> QuirkPreset {
+        match self {
+            MachineProfile::Generic => QuirkPreset::Modern,
+            MachineProfile::CosmacVip | MachineProfile::Eti660 => QuirkPreset::CosmacVip,
+            MachineProfile::SuperChip | MachineProfile::MegaChip => QuirkPreset::SuperChip,
+            MachineProfile::XoChip => QuirkPreset::XoChip,
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        self.quirk_preset().quirks()
+    }
+
+    /// Areas the interpreter uses for itself, in address order: everything below the load
+    /// offset, which includes the fonts, and on the VIP style machines the top of memory.
+    pub fn reserved(self) -> Vec<Range<u16>> {
+        let interpreter = 0..self.load_offset();
+        match self {
+            MachineProfile::CosmacVip | MachineProfile::Eti660 => {
+                vec![interpreter, VIP_WORK_AREA, VIP_DISPLAY_AREA]
+            }
+            _ => Vec::from([interpreter]),
+        }
+    }
+
+    /// Whether the interpreter keeps `address` for itself, see [`MachineProfile::reserved`].
+    pub fn is_reserved(self, address: u16) -> bool {
+        self.reserved().iter().any(|range| range.contains(&address))
+    }
+
+    /// Largest ROM that fits between `offset` and the next reserved area or the end of
+    /// memory, or [`MEGA_MEMORY_SIZE`] for MegaChip8.
+    pub fn max_rom_size(self, offset: u16) -> usize {
+        if self == MachineProfile::MegaChip {
+            return MEGA_MEMORY_SIZE - offset as usize;
+        }
+        let end = self
+            .reserved()
+            .iter()
+            .map(|range| range.start as usize)
+            .filter(|&start| start > offset as usize)
+            .min()
+            .unwrap_or(self.memory_size());
+        end.saturating_sub(offset as usize)
+    }
+
+    /// Switches the emulator to this machine: its memory layout, load offset and quirks.
+    /// The new load offset is used from the next ROM loaded.
+    pub fn apply(self, emulator: &mut Emulator) {
+        emulator.profile = self;
+        emulator.load_offset = self.load_offset();
+        emulator.quirks = self.quirks();
+    }
+}
+
+/// Parses [`MachineProfile::id`], ignoring case.
+impl FromStr for MachineProfile {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        MachineProfile::ALL
+            .into_iter()
+            .find(|profile| profile.id().eq_ignore_ascii_case(text))
+            .ok_or_else(|| {
+                let ids: Vec<&str> = MachineProfile::ALL
+                    .iter()
+                    .map(|profile| profile.id())
+                    .collect();
+                format!(
+                    "unknown machine `{text}`, expected one of {}",
+                    ids.join(", ")
+                )
+            })
+    }
+}
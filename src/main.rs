@@ -1,245 +1,355 @@
-use std::{
-    borrow::Cow,
-    fs::{self, File},
-    io::Read,
-    mem,
-    path::Path,
-    str::FromStr,
-    time::{Duration, Instant},
-};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::{fs, io, path::PathBuf, sync::Arc};
 
-use emulator::{Emulator, RunState, DISPLAY_SIZE};
-use image::GenericImageView;
-use imgui::{FontSource, Ui};
-use imgui_wgpu::{Renderer, RendererConfig};
-use wgpu::{
-    util::{BufferInitDescriptor, DeviceExt},
-    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
-    BufferAddress, BufferUsages, ImageDataLayout, Origin3d, SamplerDescriptor, ShaderStages,
-    TextureUsages, TextureViewDescriptor,
+use chip_8_emulator::{
+    annotations::AnnotationEditor,
+    audio::Beeper,
+    config::Config,
+    debug_ui::{
+        self, CallGraphPanel, ComparePanel, DisassemblyPanel, HeatmapPanel, MemoryPanel,
+        SpritePanel, TracePanel, WatchPanel,
+    },
+    emulator::{RunState, HIRES_DISPLAY_SIZE},
+    error::UnknownOpcodePolicy,
+    frame_limiter::PresentMode,
+    frontend::{EventResponse, Frontend, Panel, SurfaceTarget, WINDOW_TITLE},
+    gpu,
+    heatmap::HEATMAP_SIZE,
+    keymap::{KeyBindings, KeyProfiles},
+    library::Library,
+    machine::MachineProfile,
+    metadata::RomDatabase,
+    playtime::PlaytimeTracker,
+    quirks::{IndexIncrement, QuirkPreset, Quirks, RngMode},
+    ram_search::RamSearch,
+    renderer::{DisplayRenderer, Palette, PostEffects, Rotation},
+    resources::ResourceLocator,
+    savestate::SLOT_COUNT,
+    session::Session,
+    timing::{SPEED_PRESETS, TIMER_HZ},
+    worker::EmulatorCommand,
+    workspace::{ProjectAction, ProjectPanel},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use chip_8_emulator::{
+    emulator::{Display, Emulator, DISPLAY_SIZE},
+    headless::{self, BenchResult, RegisterDump, RunLength},
+    metadata::ROM_EXTENSIONS,
+    offscreen::OffscreenRenderer,
+    script::Script,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use clap::Parser;
+#[cfg(not(target_arch = "wasm32"))]
+use image::{Rgba, RgbaImage};
+use imgui::{FontSource, Ui};
+use imgui_wgpu::{Renderer, RendererConfig, Texture, TextureConfig};
+use web_time::Instant;
 use winit::{
     dpi::LogicalSize,
-    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event::{ElementState, Event, KeyboardInput, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::Window,
+    window::{Fullscreen, Window},
 };
 
-mod emulator;
+#[cfg(target_arch = "wasm32")]
+use chip_8_emulator::web;
+
+/// Instructions run by `--headless` when neither `--cycles` nor `--seconds` is given,
+/// about ten seconds at the default speed.
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_HEADLESS_CYCLES: u64 = 6000;
+
+/// The window swapchain, reconfigured whenever the frontend reports a resize.
+struct WindowSurface {
+    surface: wgpu::Surface,
+    config: wgpu::SurfaceConfiguration,
+    device: Arc<wgpu::Device>,
+}
+
+impl SurfaceTarget for WindowSurface {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.config.width = width;
+        self.config.height = height;
+        self.surface.configure(&self.device, &self.config);
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+}
+
+impl WindowSurface {
+    /// Reconfigures the swapchain if `mode` isn't the current present mode.
+    fn set_present_mode(&mut self, mode: wgpu::PresentMode) {
+        if self.config.present_mode != mode {
+            self.config.present_mode = mode;
+            self.surface.configure(&self.device, &self.config);
+        }
+    }
+}
+
+fn wgpu_present_mode(mode: PresentMode) -> wgpu::PresentMode {
+    match mode {
+        PresentMode::Fifo => wgpu::PresentMode::Fifo,
+        PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+        PresentMode::Immediate => wgpu::PresentMode::Immediate,
+    }
+}
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct Vertex {
-    pub pos: [f32; 3],
-    pub uvs: [f32; 2],
+/// What the window starts with, taken from the command line on native builds.
+#[derive(Default)]
+struct LaunchOptions {
+    rom: Option<PathBuf>,
+    profile: Option<MachineProfile>,
+    cpf: Option<i32>,
+    unknown_opcodes: Option<UnknownOpcodePolicy>,
+    paused: bool,
+    fullscreen: bool,
+    script: Option<PathBuf>,
 }
 
-const RGBA_BLACK: [u8; 4] = [0, 0, 0, 255];
-const RGBA_WHITE: [u8; 4] = [255, 255, 255, 255];
+impl LaunchOptions {
+    /// Opens the ROM with these settings. The machine is switched first so the ROM loads
+    /// with its layout, the rest is set after opening so it wins over the ROM's saved
+    /// settings.
+    fn apply(&self, frontend: &mut Frontend) {
+        if let Some(profile) = self.profile {
+            profile.apply(&mut frontend.emulator.lock());
+        }
+        if let Some(path) = &self.rom {
+            frontend.open_rom(path);
+        }
+        let mut emulator = frontend.emulator.lock();
+        if let Some(profile) = self.profile {
+            emulator.quirks = profile.quirks();
+        }
+        if let Some(cpf) = self.cpf {
+            emulator.cpf = cpf;
+            emulator.clock.cpu_hz = cpf as f64 * TIMER_HZ;
+        }
+        if let Some(policy) = self.unknown_opcodes {
+            emulator.unknown_opcodes = policy;
+        }
+        drop(emulator);
+        if self.paused {
+            frontend.emulator.send(EmulatorCommand::Pause);
+        }
+        frontend.game_only = self.fullscreen;
+    }
+}
+
+/// CHIP-8 emulator and debugger. Opens a window unless `--headless` or `--capture-out` is
+/// given.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// ROM to open, or to run in headless mode.
+    rom: Option<PathBuf>,
+    /// Machine to emulate: its memory layout and quirks.
+    #[arg(long, value_name = "MACHINE")]
+    profile: Option<MachineProfile>,
+    /// Instructions per frame, 60 frames a second.
+    #[arg(long, value_parser = clap::value_parser!(i32).range(1..))]
+    cpf: Option<i32>,
+    /// What the CPU does on an opcode it doesn't know: halt, skip or break.
+    #[arg(long, value_name = "POLICY")]
+    unknown_opcodes: Option<UnknownOpcodePolicy>,
+    /// Starts paused, e.g. to set breakpoints before the ROM runs.
+    #[arg(long, requires = "rom", conflicts_with = "headless")]
+    paused: bool,
+    /// Starts fullscreen with only the game showing, like F11.
+    #[arg(long, conflicts_with = "headless")]
+    fullscreen: bool,
+    /// Runs the ROM without a window or GPU, then dumps the display and registers.
+    #[arg(long, requires = "rom")]
+    headless: bool,
+    /// Instructions to run in headless mode.
+    #[arg(long, requires = "headless", conflicts_with = "seconds")]
+    cycles: Option<u64>,
+    /// Emulated seconds to run in headless mode, at the default CPU speed.
+    #[arg(long, requires = "headless")]
+    seconds: Option<f64>,
+    /// Writes the final display as ASCII art, `-` for stdout.
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    ascii: Option<PathBuf>,
+    /// Writes the final display as a PNG.
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    png: Option<PathBuf>,
+    /// Size of a CHIP-8 pixel: in the PNG in headless mode or with `--capture-out`, otherwise
+    /// in the window, which is sized to fit the display.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    scale: Option<u32>,
+    /// Writes the final registers as JSON, `-` for stdout.
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    json: Option<PathBuf>,
+    /// Writes a crash dump into this directory if the CPU halts in headless mode.
+    #[arg(long, value_name = "DIR", requires = "headless")]
+    crash_dumps: Option<PathBuf>,
+    /// Seed for CXNN in headless mode, instead of the VIP routine.
+    #[arg(long, requires = "headless")]
+    seed: Option<u64>,
+    /// Runs the ROM headless for this many million instructions as fast as possible and
+    /// prints the speed in MIPS.
+    #[arg(
+        long,
+        value_name = "MILLIONS",
+        requires = "rom",
+        conflicts_with = "headless"
+    )]
+    bench: Option<f64>,
+    /// Rhai script run along with the emulator, see the `script` module.
+    #[arg(long, value_name = "FILE")]
+    script: Option<PathBuf>,
+    /// Renders the ROM offscreen with the GPU renderer and saves the display to a PNG.
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "rom",
+        conflicts_with = "headless"
+    )]
+    capture_out: Option<PathBuf>,
+    /// Frames to run before `--capture-out` saves the display.
+    #[arg(long, requires = "capture_out", default_value_t = 60)]
+    frames: u32,
+}
 
+#[cfg(not(target_arch = "wasm32"))]
 fn main() {
     env_logger::init();
-    fs::create_dir_all("./resources/roms").expect("Error creating ROM path");
-    fs::create_dir_all("./resources/font").expect("Error creating fonts path");
+    let cli = Cli::parse();
+    if let Some(output) = &cli.capture_out {
+        if let Err(err) = capture(&cli, output) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(millions) = cli.bench {
+        match run_bench(&cli, millions) {
+            Ok(result) => println!(
+                "{} instructions in {:.3} s: {:.2} MIPS",
+                result.instructions,
+                result.elapsed.as_secs_f64(),
+                result.mips()
+            ),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if cli.headless {
+        match run_headless(&cli) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(2),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let resources = ResourceLocator::detect();
+    fs::create_dir_all(resources.roms()).expect("Error creating ROM path");
+    fs::create_dir_all(resources.fonts()).expect("Error creating fonts path");
+    let eloop = EventLoop::new();
+    let wnd = winit::window::Window::new(&eloop).expect("Error creating window.");
+    // Read before the window is shown, the rest of the config is loaded by `run`.
+    let saved_size = Config::load(resources.config_file())
+        .ok()
+        .and_then(|config| config.window_size);
+    wnd.set_inner_size(match (cli.scale, saved_size) {
+        (Some(scale), _) => LogicalSize {
+            width: (DISPLAY_SIZE.0 as u32 * scale) as f64,
+            height: (DISPLAY_SIZE.1 as u32 * scale) as f64,
+        },
+        (None, Some((width, height))) => LogicalSize {
+            width: width as f64,
+            height: height as f64,
+        },
+        (None, None) => LogicalSize {
+            width: 1280.0,
+            height: 720.0,
+        },
+    });
+    let launch = LaunchOptions {
+        rom: cli.rom,
+        profile: cli.profile,
+        cpf: cli.cpf,
+        unknown_opcodes: cli.unknown_opcodes,
+        paused: cli.paused,
+        fullscreen: cli.fullscreen,
+        script: cli.script,
+    };
+    pollster::block_on(run(eloop, wnd, resources, launch));
+}
+
+/// Browser entry point. The canvas is added to the page and ROMs are picked with the
+/// browser's file input or dropped on the page, see [`web`].
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    console_log::init_with_level(log::Level::Info).expect("Error initializing logging.");
+    let resources = ResourceLocator::detect();
     let eloop = EventLoop::new();
     let wnd = winit::window::Window::new(&eloop).expect("Error creating window.");
     wnd.set_inner_size(LogicalSize {
         width: 1280.0,
         height: 720.0,
     });
-    pollster::block_on(run(eloop, wnd));
+    web::attach_canvas(&wnd);
+    wasm_bindgen_futures::spawn_local(run(eloop, wnd, resources, LaunchOptions::default()));
 }
 
-async fn run(event_loop: EventLoop<()>, wnd: Window) {
+async fn run(
+    event_loop: EventLoop<()>,
+    wnd: Window,
+    resources: ResourceLocator,
+    launch: LaunchOptions,
+) {
     let size = wnd.inner_size();
     let wgpu = wgpu::Instance::new(wgpu::Backends::all());
     let surface = unsafe { wgpu.create_surface(&wnd) };
-    let adapter = wgpu
-        .request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::default(),
-            force_fallback_adapter: false,
-            compatible_surface: Some(&surface),
-        })
-        .await
-        .expect("Failed to find an adapter.");
-
-    let (device, queue) = adapter
-        .request_device(
-            &wgpu::DeviceDescriptor {
-                label: None,
-                features: wgpu::Features::empty(),
-                // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
-                limits: wgpu::Limits::downlevel_webgl2_defaults()
-                    .using_resolution(adapter.limits()),
-            },
-            None,
-        )
-        .await
-        .expect("Failed to create device");
-
-    let mut shader_source = String::new();
-    File::open(Path::new("./resources/shader.wgsl"))
-        .expect("Issue finding shader file.")
-        .read_to_string(&mut shader_source)
-        .expect("Issue reading source file.");
-    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: None,
-        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(shader_source.as_str())),
-    });
-
-    let mut texture_data: [[u8; 4]; DISPLAY_SIZE.1 * DISPLAY_SIZE.0] =
-        [RGBA_BLACK; DISPLAY_SIZE.1 * DISPLAY_SIZE.0];
-    let texture_size = wgpu::Extent3d {
-        width: DISPLAY_SIZE.0 as u32,
-        height: DISPLAY_SIZE.1 as u32,
-        depth_or_array_layers: 1,
+    let adapter = match gpu::request_adapter(&wgpu, Some(&surface)).await {
+        Ok(adapter) => adapter,
+        Err(err) => return gpu::show_error_dialog(&err),
     };
-    let texture = device.create_texture(&wgpu::TextureDescriptor {
-        size: texture_size,
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Rgba8Unorm,
-        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
-        label: Some("CHIP-8 Display diffuse"),
-    });
-    let texture_view = texture.create_view(&TextureViewDescriptor::default());
-    let texture_sampler = device.create_sampler(&SamplerDescriptor {
-        address_mode_u: wgpu::AddressMode::ClampToEdge,
-        address_mode_v: wgpu::AddressMode::ClampToEdge,
-        address_mode_w: wgpu::AddressMode::ClampToEdge,
-        mag_filter: wgpu::FilterMode::Nearest,
-        min_filter: wgpu::FilterMode::Nearest,
-        mipmap_filter: wgpu::FilterMode::Nearest,
-        ..Default::default()
-    });
-
-    let texture_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-        entries: &[
-            BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Texture {
-                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
-                    view_dimension: wgpu::TextureViewDimension::D2,
-                    multisampled: false,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                binding: 1,
-                visibility: ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
-                count: None,
-            },
-        ],
-        label: None,
-    });
-    let texture_bind_group = device.create_bind_group(&BindGroupDescriptor {
-        label: None,
-        layout: &texture_bind_group_layout,
-        entries: &[
-            BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(&texture_view),
-            },
-            BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::Sampler(&texture_sampler),
-            },
-        ],
-    });
-
-    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("CHIP-8 Vertex buffer"),
-        contents: bytemuck::cast_slice(&[
-            Vertex {
-                pos: [1.0, 1.0, 0.0],
-                uvs: [1.0, 0.0],
-            },
-            Vertex {
-                pos: [-1.0, -1.0, 0.0],
-                uvs: [0.0, 1.0],
-            },
-            Vertex {
-                pos: [1.0, -1.0, 0.0],
-                uvs: [1.0, 1.0],
-            },
-            Vertex {
-                pos: [-1.0, 1.0, 0.0],
-                uvs: [0.0, 0.0],
-            },
-        ]),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("CHIP-8 Index buffer"),
-        contents: bytemuck::cast_slice(&[0u16, 1, 2, 1, 0, 3]),
-        usage: BufferUsages::INDEX,
-    });
-
-    let vertex_layout = wgpu::VertexBufferLayout {
-        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &[
-            wgpu::VertexAttribute {
-                offset: 0,
-                shader_location: 0,
-                format: wgpu::VertexFormat::Float32x3,
-            },
-            wgpu::VertexAttribute {
-                offset: mem::size_of::<[f32; 3]>() as BufferAddress,
-                shader_location: 1,
-                format: wgpu::VertexFormat::Float32x2,
-            },
-        ],
+    let (device, queue) = match gpu::request_device(&wgpu, &adapter).await {
+        Ok((device, queue)) => (Arc::new(device), queue),
+        Err(err) => return gpu::show_error_dialog(&err),
     };
 
-    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-        label: None,
-        bind_group_layouts: &[&texture_bind_group_layout],
-        push_constant_ranges: &[],
-    });
-
     let swapchain_format = surface.get_supported_formats(&adapter)[0];
+    let supported_present_modes = surface.get_supported_present_modes(&adapter);
+    // Fifo is always supported.
+    let present_modes: Vec<PresentMode> = PresentMode::ALL
+        .into_iter()
+        .filter(|&mode| {
+            mode == PresentMode::Fifo || supported_present_modes.contains(&wgpu_present_mode(mode))
+        })
+        .collect();
+    let mut display_renderer = DisplayRenderer::new(&device, swapchain_format);
 
-    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: None,
-        layout: Some(&pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: "vs_main",
-            buffers: &[vertex_layout],
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
-            targets: &[Some(swapchain_format.into())],
-        }),
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            polygon_mode: wgpu::PolygonMode::Fill,
-            unclipped_depth: false,
-            conservative: false,
-        },
-        depth_stencil: None,
-        multisample: wgpu::MultisampleState::default(),
-        multiview: None,
-    });
-
-    let mut config = wgpu::SurfaceConfiguration {
+    let config = wgpu::SurfaceConfiguration {
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-        format: surface.get_supported_formats(&adapter)[0],
+        format: swapchain_format,
         width: size.width,
         height: size.height,
-        present_mode: wgpu::PresentMode::AutoNoVsync,
+        present_mode: wgpu::PresentMode::Fifo,
         alpha_mode: wgpu::CompositeAlphaMode::Auto,
     };
 
     surface.configure(&device, &config);
+    let mut surface = WindowSurface {
+        surface,
+        config,
+        device: device.clone(),
+    };
 
     let hidpi_factor = wnd.scale_factor();
 
@@ -250,7 +360,18 @@ async fn run(event_loop: EventLoop<()>, wnd: Window) {
         &wnd,
         imgui_winit_support::HiDpiMode::Default,
     );
-    imgui.set_ini_filename(Some(Path::new("./imgui.ini").to_path_buf()));
+    if let Err(err) = fs::create_dir_all(resources.config()) {
+        log::error!(
+            "Failed to create config directory {:?}: {err}",
+            resources.config()
+        );
+    }
+    // Dear ImGui saves its layout with fopen, which the browser doesn't have.
+    if cfg!(target_arch = "wasm32") {
+        imgui.set_ini_filename(None);
+    } else {
+        imgui.set_ini_filename(Some(resources.imgui_ini()));
+    }
     imgui.io_mut().font_global_scale = (1.0 / hidpi_factor) as f32;
     imgui.fonts().add_font(&[FontSource::DefaultFontData {
         config: Some(imgui::FontConfig {
@@ -261,66 +382,218 @@ async fn run(event_loop: EventLoop<()>, wnd: Window) {
         }),
     }]);
     let renderer_config = RendererConfig {
-        texture_format: config.format,
+        texture_format: surface.config.format,
         ..Default::default()
     };
+    imgui.io_mut().config_flags |= imgui::ConfigFlags::DOCKING_ENABLE;
     let mut renderer = Renderer::new(&mut imgui, &device, &queue, renderer_config);
+    let heatmap_texture = Texture::new(
+        &device,
+        &renderer,
+        TextureConfig {
+            size: wgpu::Extent3d {
+                width: HEATMAP_SIZE as u32,
+                height: HEATMAP_SIZE as u32,
+                depth_or_array_layers: 1,
+            },
+            label: Some("Memory heatmap"),
+            format: Some(wgpu::TextureFormat::Rgba8Unorm),
+            sampler_desc: wgpu::SamplerDescriptor {
+                mag_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+    let heatmap_texture = renderer.textures.insert(heatmap_texture);
+    let compare_textures = ["Compare left", "Compare right"].map(|label| {
+        let texture = Texture::new(
+            &device,
+            &renderer,
+            TextureConfig {
+                size: wgpu::Extent3d {
+                    width: HIRES_DISPLAY_SIZE.0 as u32,
+                    height: HIRES_DISPLAY_SIZE.1 as u32,
+                    depth_or_array_layers: 1,
+                },
+                label: Some(label),
+                format: Some(wgpu::TextureFormat::Rgba8Unorm),
+                sampler_desc: wgpu::SamplerDescriptor {
+                    mag_filter: wgpu::FilterMode::Nearest,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        renderer.textures.insert(texture)
+    });
 
-    let mut emulator = Emulator::new();
+    let mut frontend = Frontend::new();
+    frontend.key_bindings = KeyBindings::load(resources.key_bindings()).unwrap_or_else(|err| {
+        log::error!("Failed to load {:?}: {err}", resources.key_bindings());
+        KeyBindings::default()
+    });
+    frontend.savestates = resources.savestates();
+    frontend.replay_path = resources.replay().to_string_lossy().into_owned();
+    frontend.script_path = resources.script().to_string_lossy().into_owned();
+    frontend.key_profiles = KeyProfiles::load(resources.keymaps()).unwrap_or_else(|err| {
+        log::error!("Failed to load {:?}: {err}", resources.keymaps());
+        KeyProfiles::default()
+    });
+    frontend.config = Config::load(resources.config_file()).unwrap_or_else(|err| {
+        log::error!("Failed to load {:?}: {err}", resources.config_file());
+        Config::default()
+    });
+    match Palette::load(resources.palette()) {
+        Ok(palette) => frontend.palette = palette,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => log::error!("Failed to load {:?}: {err}", resources.palette()),
+    }
+    match PostEffects::load(resources.effects()) {
+        Ok(effects) => frontend.effects = effects,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => log::error!("Failed to load {:?}: {err}", resources.effects()),
+    }
+    match Quirks::load(resources.quirks()) {
+        Ok(quirks) => frontend.emulator.lock().quirks = quirks,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => log::error!("Failed to load {:?}: {err}", resources.quirks()),
+    }
+    {
+        let mut emulator = frontend.emulator.lock();
+        emulator.unknown_opcodes = frontend.config.unknown_opcodes;
+        if !cfg!(target_arch = "wasm32") {
+            emulator.crash_dumps = Some(resources.crash_dumps());
+        }
+    }
+    if let Some(path) = &launch.script {
+        frontend.script_path = path.to_string_lossy().into_owned();
+        frontend.load_script();
+        if !frontend.script_status.is_empty() {
+            log::info!("{}", frontend.script_status);
+        }
+    }
     let mut rom: usize = 0;
-    let mut roms = Vec::new();
-    for file in fs::read_dir("./resources/roms").unwrap() {
-        roms.push(String::from_str(file.unwrap().file_name().to_str().unwrap()).unwrap());
+    let roms = list_roms(&resources);
+    #[cfg(target_arch = "wasm32")]
+    let picked_roms = web::listen_for_roms();
+
+    let mut session_ui = SessionUi {
+        path: resources.session().to_string_lossy().into_owned(),
+        status: String::new(),
+    };
+
+    let mut project_panel = ProjectPanel::default();
+    let mut ram_search = RamSearch::default();
+    let mut call_graph_panel = CallGraphPanel::default();
+    let mut disassembly_panel = DisassemblyPanel::default();
+    let mut memory_panel = MemoryPanel::default();
+    let mut trace_panel = TracePanel::default();
+    let mut sprite_panel = SpritePanel::default();
+    let mut heatmap_panel = HeatmapPanel::default();
+    let mut watch_panel = WatchPanel::default();
+    let mut compare_panel = ComparePanel::default();
+    let mut annotation_editor = AnnotationEditor::new(resources.annotations());
+    let mut database = RomDatabase::bundled();
+    match RomDatabase::load(resources.rom_database()) {
+        Ok(loaded) => database.extend(loaded),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => log::error!("Failed to load {:?}: {err}", resources.rom_database()),
     }
+    frontend.database = database.clone();
+    let mut library = Library::new(resources.roms(), database);
+    if !cfg!(target_arch = "wasm32") {
+        if let Err(err) = library.scan() {
+            log::error!("Failed to read {:?}: {err}", resources.roms());
+        }
+    }
+    let mut playtime = PlaytimeTracker::new(resources.playtime());
+    let mut beeper = Beeper::new()
+        .map_err(|err| log::error!("Audio disabled, failed to open output device: {err}"))
+        .ok();
+    if let Some(beeper) = &mut beeper {
+        beeper.set_settings(frontend.config.audio);
+        frontend.emulator.lock().buzzer = Some(beeper.queue());
+    }
+
+    launch.apply(&mut frontend);
 
     let mut last_frame = Instant::now();
     let mut last_cursor = None;
+    let mut window_title = String::from(WINDOW_TITLE);
+    wnd.set_title(WINDOW_TITLE);
 
     event_loop.run(move |event, _, flow| {
-        let _ = (&wgpu, &adapter, &shader, &pipeline_layout);
+        let _ = (&wgpu, &adapter);
         wnd.request_redraw();
         match event {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == wnd.id() => match event {
-                WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            state: pressed,
-                            scancode: key,
+            } if window_id == wnd.id() => {
+                // Typing into a debugger field is for imgui alone, below. Releases still reach
+                // the frontend so no keypad key or hotkey stays held.
+                let typing = imgui.io().want_capture_keyboard
+                    && matches!(
+                        event,
+                        WindowEvent::KeyboardInput {
+                            input: KeyboardInput {
+                                state: ElementState::Pressed,
+                                ..
+                            },
                             ..
-                        },
-                    ..
-                } => {
-                    let key_mapped = map_key(*key);
-                    if let ElementState::Released = pressed {
-                        if emulator.key == key_mapped {
-                            emulator.key = None;
-                        }
-                    } else {
-                        if let Some(mapped) = key_mapped {
-                            emulator.key = Some(mapped);
                         }
+                    );
+                if !typing {
+                    match frontend.handle_window_event(event, &mut surface) {
+                        EventResponse::Exit => *flow = ControlFlow::Exit,
+                        EventResponse::Redraw => wnd.request_redraw(),
+                        EventResponse::Continue => {}
                     }
                 }
-                WindowEvent::Resized(size) => {
-                    config.width = size.width.max(1);
-                    config.height = size.height.max(1);
-                    surface.configure(&device, &config);
-                    wnd.request_redraw();
-                }
-                _ => {}
-            },
+            }
             Event::RedrawEventsCleared => wnd.request_redraw(),
+            Event::LoopDestroyed => {
+                playtime.flush();
+                if wnd.fullscreen().is_none() {
+                    let size = wnd.inner_size().to_logical(wnd.scale_factor());
+                    frontend.config.window_size = Some((size.width, size.height));
+                }
+                if let Some(beeper) = &beeper {
+                    frontend.config.audio = beeper.settings;
+                }
+                if let Err(err) = frontend.key_bindings.save(resources.key_bindings()) {
+                    log::error!("Failed to save {:?}: {err}", resources.key_bindings());
+                }
+                if let Err(err) = frontend.config.save(resources.config_file()) {
+                    log::error!("Failed to save {:?}: {err}", resources.config_file());
+                }
+                if let Err(err) = frontend.global_palette().save(resources.palette()) {
+                    log::error!("Failed to save {:?}: {err}", resources.palette());
+                }
+                if let Err(err) = frontend.effects.save(resources.effects()) {
+                    log::error!("Failed to save {:?}: {err}", resources.effects());
+                }
+            }
             Event::RedrawRequested(_) => {
                 let start_time = Instant::now();
                 let dt = start_time - last_frame;
                 imgui.io_mut().update_delta_time(dt);
                 last_frame = start_time;
 
+                #[cfg(target_arch = "wasm32")]
+                for picked in picked_roms.take() {
+                    frontend.load_rom_data(picked.name, picked.data);
+                }
+
+                let present_mode = if present_modes.contains(&frontend.config.present_mode) {
+                    frontend.config.present_mode
+                } else {
+                    PresentMode::Fifo
+                };
+                surface.set_present_mode(wgpu_present_mode(present_mode));
                 let frame = surface
+                    .surface
                     .get_current_texture()
                     .expect("Failed to acquire next swap chain texture");
                 let view = frame
@@ -334,40 +607,193 @@ async fn run(event_loop: EventLoop<()>, wnd: Window) {
                     .expect("Failed to prepare frame.");
                 let ui = imgui.frame();
 
-                draw_emulator_setup(ui, &mut emulator, &mut rom, &mut roms);
-                if let RunState::Running = emulator.state {
-                    emulator.step();
+                if frontend.draw_menu_bar(ui) == EventResponse::Exit {
+                    *flow = ControlFlow::Exit;
                 }
-                emulator.draw_info(ui, dt.as_millis());
-
-                for x in 0..DISPLAY_SIZE.0 {
-                    for y in 0..DISPLAY_SIZE.1 {
-                        texture_data[(y * DISPLAY_SIZE.0) + x] = if emulator.display[x][y] == 1 {
-                            RGBA_WHITE
-                        } else {
-                            RGBA_BLACK
-                        };
+                frontend.draw_speed_overlay(ui);
+                if !frontend.game_only {
+                    ui.dockspace_over_main_viewport();
+                }
+                if wnd.fullscreen().is_some() != frontend.game_only {
+                    wnd.set_fullscreen(frontend.game_only.then_some(Fullscreen::Borderless(None)));
+                }
+                let session_action = if frontend.shows(Panel::Setup) {
+                    draw_emulator_setup(
+                        ui,
+                        &mut frontend,
+                        &resources,
+                        &mut rom,
+                        &roms,
+                        &mut session_ui,
+                        &present_modes,
+                    )
+                } else {
+                    None
+                };
+                if !frontend.game_only {
+                    frontend.draw_rom_report(ui);
+                }
+                if frontend.shows(Panel::Keypad) {
+                    frontend.draw_keypad(ui);
+                }
+                if frontend.shows(Panel::KeyMapping) {
+                    frontend.draw_key_mapping(ui);
+                }
+                if frontend.shows(Panel::Display) {
+                    frontend.draw_display_settings(ui);
+                }
+                if frontend.shows(Panel::Capture) {
+                    frontend.draw_capture(ui);
+                }
+                if frontend.shows(Panel::Replay) {
+                    frontend.draw_replay(ui);
+                }
+                if frontend.shows(Panel::Netplay) {
+                    frontend.draw_netplay(ui);
+                }
+                if frontend.shows(Panel::Script) {
+                    frontend.draw_script(ui);
+                }
+                if frontend.shows(Panel::Cheats) {
+                    frontend.draw_cheats(ui);
+                }
+                if frontend.shows(Panel::Library) {
+                    if let Some(entry) = library.draw(ui, &playtime.playtime) {
+                        frontend.open_rom_with_defaults(entry.path, entry.metadata.settings());
+                    }
+                }
+                let project_action = if frontend.shows(Panel::Project) {
+                    project_panel.draw(ui)
+                } else {
+                    None
+                };
+                match project_action {
+                    Some(ProjectAction::Run(rom_path)) => frontend.open_rom(rom_path),
+                    Some(ProjectAction::HotRestart {
+                        rom,
+                        keep_breakpoints,
+                    }) => {
+                        if !keep_breakpoints {
+                            frontend.emulator.lock().clear_breakpoints();
+                        }
+                        frontend.open_rom(rom);
+                    }
+                    None => {}
+                }
+                let shown: Vec<Panel> = Panel::DEBUG
+                    .into_iter()
+                    .chain([Panel::Audio])
+                    .filter(|&panel| frontend.shows(panel))
+                    .collect();
+                let shows = |panel| shown.contains(&panel);
+                // Every panel locks the emulator only while it draws, so the emulation thread
+                // keeps stepping in between instead of waiting out the whole UI frame.
+                let (rom, max_fps) = {
+                    let emulator = frontend.emulator.lock();
+                    (emulator.rom.clone(), emulator.max_fps)
+                };
+                let rom_hash = rom.as_ref().map(|rom| rom.hash.as_str());
+                frontend.key_profiles.sync_rom(rom_hash);
+                annotation_editor.sync_rom(rom.as_ref());
+                let title = frontend.window_title(rom.as_ref());
+                if title != window_title {
+                    wnd.set_title(&title);
+                    window_title = title;
+                }
+                let running = matches!(frontend.emulator.status().state, RunState::Running);
+                playtime.tick(rom_hash, running, dt);
+                if shows(Panel::Controls) {
+                    let mut emulator = frontend.emulator.lock();
+                    debug_ui::draw_info(&mut emulator, ui, &frontend.frame_limiter);
+                }
+                if shows(Panel::Memory) {
+                    let mut emulator = frontend.emulator.lock();
+                    memory_panel.draw(ui, &mut emulator, &mut annotation_editor);
+                }
+                if shows(Panel::RamSearch) {
+                    ram_search.draw(ui, &frontend.emulator.lock());
+                }
+                if shows(Panel::CallGraph) {
+                    call_graph_panel.draw(ui, &mut frontend.emulator.lock().call_graph);
+                }
+                if shows(Panel::Stack) {
+                    let annotations = &annotation_editor.annotations;
+                    debug_ui::draw_stack(ui, &mut frontend.emulator.lock(), annotations);
+                }
+                if shows(Panel::Disassembly) {
+                    let mut emulator = frontend.emulator.lock();
+                    disassembly_panel.draw(ui, &mut emulator, &mut annotation_editor);
+                }
+                if shows(Panel::Trace) {
+                    trace_panel.draw(ui, &mut frontend.emulator.lock());
+                }
+                if shows(Panel::Sprites) {
+                    sprite_panel.draw(ui, &frontend.emulator.lock());
+                }
+                if shows(Panel::Profiler) {
+                    debug_ui::draw_profiler(ui, &mut frontend.emulator.lock());
+                }
+                if shows(Panel::Heatmap) {
+                    let mut emulator = frontend.emulator.lock();
+                    if let Some(texture) = renderer.textures.get(heatmap_texture) {
+                        let pixels = emulator.heatmap.rgba(heatmap_panel.page());
+                        let size = HEATMAP_SIZE as u32;
+                        texture.write(&queue, &pixels, size, size);
                     }
+                    heatmap_panel.draw(ui, &mut emulator, heatmap_texture);
+                }
+                if shows(Panel::Watches) {
+                    watch_panel.draw(ui, &mut frontend.emulator.lock());
                 }
-                queue.write_texture(
-                    wgpu::ImageCopyTexture {
-                        texture: &texture,
-                        mip_level: 0,
-                        origin: Origin3d::ZERO,
-                        aspect: wgpu::TextureAspect::All,
-                    },
-                    bytemuck::cast_slice(&texture_data),
-                    ImageDataLayout {
-                        offset: 0,
-                        bytes_per_row: std::num::NonZeroU32::new(4 * DISPLAY_SIZE.0 as u32),
-                        rows_per_image: std::num::NonZeroU32::new(DISPLAY_SIZE.1 as u32),
-                    },
-                    texture_size,
+                if shows(Panel::Compare) {
+                    // The two compared emulators run without the lock, only the keys are read.
+                    let keypad = *frontend.emulator.lock().keypad();
+                    compare_panel.update(&keypad, dt);
+                    if let Some(sides) = compare_panel.display_rgba(&frontend.palette) {
+                        for (texture, pixels) in compare_textures.iter().zip(sides) {
+                            if let Some(texture) = renderer.textures.get(*texture) {
+                                let (width, height) = HIRES_DISPLAY_SIZE;
+                                texture.write(&queue, &pixels, width as u32, height as u32);
+                            }
+                        }
+                    }
+                    compare_panel.draw(ui, &frontend.emulator.lock(), compare_textures);
+                }
+                if let Some(beeper) = &mut beeper {
+                    if shows(Panel::Audio) {
+                        beeper.draw_settings(ui);
+                    }
+                }
+
+                display_renderer.set_rotation(&queue, frontend.rotation);
+                let surface_size = surface.size();
+                display_renderer.set_viewport(
+                    &queue,
+                    surface_size,
+                    frontend.viewport(surface_size),
                 );
+                display_renderer.palette = frontend.palette;
+                display_renderer.set_effects(&queue, &frontend.effects);
+                let megachip_frame = if frontend.emulator.status().megachip {
+                    let emulator = frontend.emulator.lock();
+                    emulator
+                        .megachip()
+                        .map(|megachip| megachip.frame().to_vec())
+                } else {
+                    None
+                };
+                match megachip_frame {
+                    Some(frame) => display_renderer.update_megachip(&queue, &frame),
+                    None => {
+                        let display = *frontend.emulator.latest_frame();
+                        frontend.capture.record(&display, dt);
+                        display_renderer.update(&queue, &mut encoder, &display);
+                    }
+                }
 
                 if last_cursor != Some(ui.mouse_cursor()) {
                     last_cursor = Some(ui.mouse_cursor());
-                    platform.prepare_render(&ui, &wnd);
+                    platform.prepare_render(ui, &wnd);
                 }
 
                 {
@@ -383,11 +809,7 @@ async fn run(event_loop: EventLoop<()>, wnd: Window) {
                         })],
                         depth_stencil_attachment: None,
                     });
-                    rpass.set_pipeline(&render_pipeline);
-                    rpass.set_bind_group(0, &texture_bind_group, &[]);
-                    rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                    rpass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                    rpass.draw_indexed(0..6, 0, 0..1);
+                    display_renderer.draw(&mut rpass);
 
                     renderer
                         .render(imgui.render(), &queue, &device, &mut rpass)
@@ -397,16 +819,13 @@ async fn run(event_loop: EventLoop<()>, wnd: Window) {
                 queue.submit(Some(encoder.finish()));
                 frame.present();
 
-                let max_fps = emulator.max_fps;
-                let elapsed_time = Instant::now().duration_since(start_time).as_millis() as u64;
-                let wait_time = if 1000 / max_fps as u64 >= elapsed_time {
-                    (1000 / max_fps as u64) - elapsed_time
-                } else {
-                    0
-                };
-                println!("{:}", wait_time);
-                let wait_instant = start_time + Duration::from_millis(wait_time);
-                *flow = ControlFlow::WaitUntil(wait_instant);
+                if let Some(action) = session_action {
+                    session_ui.status = handle_session_action(action, &mut imgui, &mut frontend);
+                }
+
+                let fps = (!frontend.config.uncapped).then_some(max_fps as f64);
+                frontend.frame_limiter.wait(fps);
+                *flow = ControlFlow::Poll;
             }
             _ => {}
         }
@@ -415,41 +834,372 @@ async fn run(event_loop: EventLoop<()>, wnd: Window) {
     });
 }
 
-fn draw_emulator_setup(ui: &Ui, emulator: &mut Emulator, mut rom: &mut usize, roms: &Vec<String>) {
+/// Runs `--headless`: the ROM for the requested cycles or seconds, then writes the outputs.
+/// Without any output option, the display and registers are printed to stdout.
+/// Returns false if the CPU halted with an error.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_headless(cli: &Cli) -> Result<bool, Box<dyn std::error::Error>> {
+    let rom = cli.rom.as_ref().ok_or("No ROM given.")?;
+    let length = match (cli.cycles, cli.seconds) {
+        (_, Some(seconds)) => RunLength::Seconds(seconds),
+        (cycles, None) => RunLength::Cycles(cycles.unwrap_or(DEFAULT_HEADLESS_CYCLES)),
+    };
+
+    // Seeded generators make runs reproducible, so their output can be compared.
+    let mut emulator = match cli.seed {
+        Some(seed) => Emulator::with_seed(seed),
+        None => {
+            let mut emulator = Emulator::new();
+            emulator.rng_mode = RngMode::Vip;
+            emulator
+        }
+    };
+    emulator.unknown_opcodes = cli.unknown_opcodes.unwrap_or_default();
+    emulator.crash_dumps = cli.crash_dumps.clone();
+    emulator.load_font();
+    emulator.load_rom(rom.to_string_lossy().into_owned())?;
+    if let Some(path) = &cli.script {
+        emulator.set_hooks(Some(Box::new(Script::load(path)?)));
+    }
+    headless::run(&mut emulator, length);
+
+    let dump = serde_json::to_string_pretty(&RegisterDump::new(&emulator))?;
+    let ascii = headless::ascii(&emulator.display);
+    if cli.ascii.is_none() && cli.png.is_none() && cli.json.is_none() {
+        print!("{ascii}");
+        println!("{dump}");
+    }
+    if let Some(path) = &cli.ascii {
+        write_output(path, &ascii)?;
+    }
+    if let Some(path) = &cli.json {
+        write_output(path, &format!("{dump}\n"))?;
+    }
+    if let Some(path) = &cli.png {
+        write_png(&emulator.display, cli.scale.unwrap_or(10), path)?;
+    }
+    if let Some(halt) = emulator.halted() {
+        eprintln!("{halt}");
+    }
+    if let Some(path) = emulator.crash_dump_path() {
+        eprintln!("Crash dump written to {}", path.display());
+    }
+    Ok(emulator.halted().is_none())
+}
+
+/// Runs `--bench`, seeded so runs of the same ROM execute the same instructions.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_bench(cli: &Cli, millions: f64) -> Result<BenchResult, Box<dyn std::error::Error>> {
+    let rom = cli.rom.as_ref().ok_or("No ROM given.")?;
+    let mut emulator = Emulator::with_seed(0);
+    emulator.load_font();
+    emulator.load_rom(rom.to_string_lossy().into_owned())?;
+    let result = headless::bench(&mut emulator, (millions * 1e6) as u64);
+    if let Some(halt) = emulator.halted() {
+        eprintln!("Stopped early: {halt}");
+    }
+    Ok(result)
+}
+
+/// Writes to a file, or to stdout if the path is `-`.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_output(path: &Path, contents: &str) -> io::Result<()> {
+    if path == Path::new("-") {
+        print!("{contents}");
+        Ok(())
+    } else {
+        fs::write(path, contents)
+    }
+}
+
+/// Saves the display with the default palette, without going through the GPU.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_png(display: &Display, scale: u32, path: &Path) -> image::ImageResult<()> {
+    let scale = scale.max(1);
+    let palette = Palette::default();
+    let (width, height) = display.size();
+    let image = RgbaImage::from_fn(width as u32 * scale, height as u32 * scale, |x, y| {
+        Rgba(palette.color(display[(x / scale) as usize][(y / scale) as usize]))
+    });
+    image.save(path)
+}
+
+/// Runs `--capture-out`: the ROM for `--frames` frames without opening a window, then
+/// renders the display to `output`.
+#[cfg(not(target_arch = "wasm32"))]
+fn capture(cli: &Cli, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let rom = cli.rom.as_ref().ok_or("No ROM given.")?;
+    let scale = cli.scale.unwrap_or(10);
+
+    let mut emulator = Emulator::new();
+    emulator.load_font();
+    emulator.load_rom(rom.to_string_lossy().into_owned())?;
+    for _ in 0..cli.frames {
+        emulator.step_frame();
+    }
+    if let Some(halt) = emulator.halted() {
+        return Err(halt.to_string().into());
+    }
+
+    let mut renderer =
+        OffscreenRenderer::new(DISPLAY_SIZE.0 as u32 * scale, DISPLAY_SIZE.1 as u32 * scale)?;
+    renderer.render_to_png(&emulator.display, output)?;
+    Ok(())
+}
+
+enum SessionAction {
+    Save(String),
+    Load(String),
+}
+
+fn draw_emulator_setup(
+    ui: &Ui,
+    frontend: &mut Frontend,
+    resources: &ResourceLocator,
+    rom: &mut usize,
+    roms: &[String],
+    session: &mut SessionUi,
+    present_modes: &[PresentMode],
+) -> Option<SessionAction> {
+    let mut action = None;
     ui.window("Emulator Setup").build(|| {
-        ui.input_int("Max FPS", &mut emulator.max_fps).build();
-        ui.input_int("Cycles per frame", &mut emulator.cpf).build();
-        ui.checkbox("Shift Swap", &mut emulator.shift_swap);
-        ui.checkbox("Complex Jump", &mut emulator.complex_jump);
+        {
+            let mut emulator = frontend.emulator.lock();
+            ui.input_int("Max FPS", &mut emulator.max_fps).build();
+            ui.checkbox("Uncapped frame rate", &mut frontend.config.uncapped);
+            let current = frontend.config.present_mode;
+            if let Some(_combo) = ui.begin_combo("Present mode", current.name()) {
+                for &mode in present_modes {
+                    if ui
+                        .selectable_config(mode.name())
+                        .selected(mode == current)
+                        .build()
+                    {
+                        frontend.config.present_mode = mode;
+                    }
+                }
+            }
+            ui.input_int("Cycles per frame", &mut emulator.cpf).build();
+            ui.slider("CPU speed (Hz)", 60.0, 5000.0, &mut emulator.clock.cpu_hz);
+            ui.text(format!("Speed: {}x", emulator.clock.speed));
+            if let Some(speed) = emulator.clock.speed_override {
+                ui.same_line();
+                ui.text_disabled(format!("({speed}x from the hotkeys)"));
+            }
+            for speed in SPEED_PRESETS {
+                ui.same_line();
+                if ui.small_button(format!("{speed}x")) {
+                    emulator.clock.speed = speed;
+                }
+            }
+            if let Some(_combo) = ui.begin_combo("Machine", emulator.profile.name()) {
+                for profile in MachineProfile::ALL {
+                    if ui.selectable(profile.name()) {
+                        profile.apply(&mut emulator);
+                    }
+                }
+            }
+            ui.text(format!(
+                "Load at {:#05X}, {} KiB of memory",
+                emulator.load_offset,
+                emulator.profile.memory_size() / 1024
+            ));
+            let current = QuirkPreset::detect(&emulator).map_or("Custom", QuirkPreset::name);
+            if let Some(_combo) = ui.begin_combo("Quirk preset", current) {
+                for preset in QuirkPreset::ALL {
+                    if ui.selectable(preset.name()) {
+                        preset.apply(&mut emulator);
+                    }
+                }
+            }
+            let quirks = &mut emulator.quirks;
+            ui.checkbox("Shift Swap", &mut quirks.shift_swap);
+            ui.checkbox("Complex Jump", &mut quirks.complex_jump);
+            ui.checkbox("VF reset (8XY1/2/3)", &mut quirks.vf_reset);
+            ui.checkbox("Clip sprites", &mut quirks.clipping);
+            ui.checkbox("Display wait", &mut quirks.display_wait);
+            if let Some(_combo) = ui.begin_combo("FX55/FX65 index", quirks.index_increment.name()) {
+                for increment in IndexIncrement::ALL {
+                    if ui.selectable(increment.name()) {
+                        quirks.index_increment = increment;
+                    }
+                }
+            }
+            if ui.button("Save quirks as default") {
+                if let Err(err) = quirks.save(resources.quirks()) {
+                    log::error!("Failed to save {:?}: {err}", resources.quirks());
+                }
+            }
+            if let Some(_combo) = ui.begin_combo("CXNN random", emulator.rng_mode.name()) {
+                for mode in RngMode::ALL {
+                    if ui.selectable(mode.name()) {
+                        emulator.rng_mode = mode;
+                    }
+                }
+            }
+            if let RngMode::Host = emulator.rng_mode {
+                let mut seed = emulator.rng_seed();
+                if ui.input_scalar("Seed", &mut seed).build() {
+                    emulator.set_seed(seed);
+                    if emulator.locked_seed.is_some() {
+                        emulator.locked_seed = Some(seed);
+                    }
+                }
+                ui.same_line();
+                let mut locked = emulator.locked_seed.is_some();
+                if ui.checkbox("Lock", &mut locked) {
+                    emulator.locked_seed = locked.then_some(seed);
+                }
+            }
+        }
+        ui.separator();
+        if let Some(_combo) = ui.begin_combo("Rotation (clockwise)", frontend.rotation.name()) {
+            for rotation in Rotation::ALL {
+                if ui.selectable(rotation.name()) {
+                    frontend.rotation = rotation;
+                }
+            }
+        }
+        ui.checkbox("Rotate arrow keys", &mut frontend.rotate_input);
+        ui.checkbox("Pause when unfocused", &mut frontend.pause_on_focus_loss);
         ui.separator();
-        ui.combo_simple_string("ROM", &mut rom, roms);
+        ui.combo_simple_string("ROM", rom, roms);
         if ui.button("Open ROM") {
-            let rom_path = format!("./resources/roms/{:}", roms[*rom]);
-            emulator.reset();
-            emulator.load_font();
-            emulator.load_rom(String::from_str(rom_path.as_str()).unwrap());
+            if let Some(name) = roms.get(*rom) {
+                open_listed_rom(frontend, resources, name);
+            }
+        }
+        ui.same_line();
+        if ui.button("Browse...") {
+            browse_rom(frontend, resources);
+        }
+        ui.separator();
+        let mut slot = frontend.save_slot as i32;
+        ui.set_next_item_width(100.0);
+        if ui.input_int("Save slot", &mut slot).build() {
+            frontend.save_slot = slot.clamp(0, SLOT_COUNT as i32 - 1) as u8;
+        }
+        ui.same_line();
+        if ui.button("Save (F5)") {
+            frontend.save_state();
+        }
+        ui.same_line();
+        if ui.button("Load (F9)") {
+            frontend.load_state();
+        }
+        if !frontend.save_status.is_empty() {
+            ui.text_wrapped(&frontend.save_status);
+        }
+        ui.separator();
+        ui.input_text("Session file", &mut session.path).build();
+        if ui.button("Save session") {
+            action = Some(SessionAction::Save(session.path.clone()));
+        }
+        ui.same_line();
+        if ui.button("Load session") {
+            action = Some(SessionAction::Load(session.path.clone()));
+        }
+        if !session.status.is_empty() {
+            ui.text_wrapped(&session.status);
         }
     });
+    action
 }
 
-fn map_key(scancode: u32) -> Option<u8> {
-    match scancode {
-        0x2 => Some(0x1),
-        0x3 => Some(0x2),
-        0x4 => Some(0x3),
-        0x5 => Some(0xC),
-        0x10 => Some(0x4),
-        0x11 => Some(0x5),
-        0x12 => Some(0x6),
-        0x13 => Some(0xD),
-        0x1E => Some(0x7),
-        0x1F => Some(0x8),
-        0x20 => Some(0x9),
-        0x21 => Some(0xE),
-        0x2C => Some(0xA),
-        0x2D => Some(0x0),
-        0x2E => Some(0xB),
-        0x3F => Some(0xF),
-        _ => None,
+/// Asks for a ROM with the native file dialog, `None` if the user cancelled.
+#[cfg(not(target_arch = "wasm32"))]
+fn pick_rom(location: &Path) -> Result<Option<PathBuf>, native_dialog::Error> {
+    native_dialog::FileDialog::new()
+        .set_location(location)
+        .add_filter("CHIP-8 ROM", &ROM_EXTENSIONS)
+        .show_open_single_file()
+}
+
+/// Names of the ROMs offered by the "ROM" combo.
+#[cfg(not(target_arch = "wasm32"))]
+fn list_roms(resources: &ResourceLocator) -> Vec<String> {
+    match fs::read_dir(resources.roms()) {
+        Ok(files) => files
+            .filter_map(|file| Some(file.ok()?.file_name().to_string_lossy().into_owned()))
+            .collect(),
+        Err(err) => {
+            log::error!("Failed to read {:?}: {err}", resources.roms());
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn list_roms(_resources: &ResourceLocator) -> Vec<String> {
+    web::BUNDLED_ROMS
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn open_listed_rom(frontend: &mut Frontend, resources: &ResourceLocator, name: &str) {
+    frontend.open_rom(resources.roms().join(name));
+}
+
+#[cfg(target_arch = "wasm32")]
+fn open_listed_rom(frontend: &mut Frontend, _resources: &ResourceLocator, name: &str) {
+    if let Some(data) = web::bundled_rom(name) {
+        frontend.load_rom_data(name, data.to_vec());
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn browse_rom(frontend: &mut Frontend, resources: &ResourceLocator) {
+    let location = frontend
+        .config
+        .rom_dir
+        .clone()
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(|| resources.roms());
+    match pick_rom(&location) {
+        Ok(Some(rom_path)) => {
+            frontend.config.rom_dir = rom_path.parent().map(Path::to_path_buf);
+            frontend.open_rom(rom_path);
+        }
+        Ok(None) => {}
+        Err(err) => log::error!("Failed to show file dialog: {err}"),
+    }
+}
+
+/// The picked ROM is opened by the event loop once the browser has read it.
+#[cfg(target_arch = "wasm32")]
+fn browse_rom(_frontend: &mut Frontend, _resources: &ResourceLocator) {
+    web::pick_rom();
+}
+
+struct SessionUi {
+    path: String,
+    status: String,
+}
+
+fn handle_session_action(
+    action: SessionAction,
+    imgui: &mut imgui::Context,
+    frontend: &mut Frontend,
+) -> String {
+    match action {
+        SessionAction::Save(path) => {
+            let mut layout = String::new();
+            imgui.save_ini_settings(&mut layout);
+            let session = Session::capture(&frontend.emulator.lock(), layout);
+            match session.save(&path) {
+                Ok(()) => format!("Session saved to {path}."),
+                Err(err) => format!("Failed to save session: {err}"),
+            }
+        }
+        SessionAction::Load(path) => match Session::load(&path) {
+            Ok(session) => {
+                session.apply(&mut frontend.emulator.lock());
+                imgui.load_ini_settings(&session.ui_layout);
+                format!("Session restored from {path}.")
+            }
+            Err(err) => format!("Failed to load session: {err}"),
+        },
     }
 }
@@ -0,0 +1,359 @@
+//! MegaChip8: a 256x192 mode switched on by 0011, with sprites of palette indices drawn
+//! with a blend mode into a back buffer that 00E0 shows, a 256 color palette loaded from
+//! memory, a 24-bit I register and digitized sound. See
+//! [`crate::emulator::Emulator::megachip`].
+
+use std::sync::Arc;
+
+use crate::timing::TIMER_HZ;
+
+/// Extension of MegaChip8 ROMs, loaded with [`crate::machine::MachineProfile::MegaChip`].
+pub const EXTENSION: &str = "mc8";
+/// Bytes a 24-bit I reaches, and so the largest MegaChip8 ROM with what's below it.
+pub const MEGA_MEMORY_SIZE: usize = 0x100_0000;
+
+/// Width and height of the MegaChip display.
+pub const MEGA_DISPLAY_SIZE: (usize, usize) = (256, 192);
+
+const PIXELS: usize = MEGA_DISPLAY_SIZE.0 * MEGA_DISPLAY_SIZE.1;
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+/// How a sprite pixel is combined with the pixel under it, set by 080N.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The sprite color replaces the pixel.
+    #[default]
+    Normal,
+    /// A quarter of the sprite color over the pixel.
+    Alpha25,
+    Alpha50,
+    Alpha75,
+    /// Channels added, saturating at white.
+    Add,
+    /// Channels multiplied, darkening the pixel.
+    Multiply,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 6] = [
+        BlendMode::Normal,
+        BlendMode::Alpha25,
+        BlendMode::Alpha50,
+        BlendMode::Alpha75,
+        BlendMode::Add,
+        BlendMode::Multiply,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BlendMode::Normal => "Normal",
+            BlendMode::Alpha25 => "25% opacity",
+            BlendMode::Alpha50 => "50% opacity",
+            BlendMode::Alpha75 => "75% opacity",
+            BlendMode::Add => "Add",
+            BlendMode::Multiply => "Multiply",
+        }
+    }
+
+    /// Mode selected by the N of 080N, `None` for an unknown one.
+    pub fn from_operand(n: u8) -> Option<Self> {
+        Self::ALL.get(n as usize).copied()
+    }
+
+    /// `source` drawn over `target`, both RGBA. The result is opaque.
+    pub fn blend(self, source: [u8; 4], target: [u8; 4]) -> [u8; 4] {
+        let channel = |source: u8, target: u8| {
+            let (source, target) = (source as u16, target as u16);
+            let mix = |opacity: u16| (source * opacity + target * (4 - opacity)) / 4;
+            let value = match self {
+                BlendMode::Normal => source,
+                BlendMode::Alpha25 => mix(1),
+                BlendMode::Alpha50 => mix(2),
+                BlendMode::Alpha75 => mix(3),
+                BlendMode::Add => (source + target).min(255),
+                BlendMode::Multiply => source * target / 255,
+            };
+            value as u8
+        };
+        [
+            channel(source[0], target[0]),
+            channel(source[1], target[1]),
+            channel(source[2], target[2]),
+            255,
+        ]
+    }
+}
+
+/// Digitized sound started by 060N, 8-bit unsigned samples read from memory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    /// Samples per second.
+    pub rate: u16,
+    /// Samples from -1.0 to 1.0.
+    pub data: Arc<[f32]>,
+    pub looping: bool,
+    /// Samples played so far, fractional as ticks rarely end on a sample.
+    position: f64,
+}
+
+impl Sample {
+    /// Reads a sample from its header in memory: the rate in two bytes, then the length in
+    /// three, then the samples. `read` gives the byte at a 24-bit address.
+    pub fn read(address: u32, looping: bool, mut read: impl FnMut(u32) -> u8) -> Self {
+        let rate = u16::from_be_bytes([read(address), read(address + 1)]);
+        let len = (read(address + 2) as usize) << 16
+            | (read(address + 3) as usize) << 8
+            | read(address + 4) as usize;
+        let data = (0..len as u32)
+            .map(|i| (read(address + 5 + i) as f32 - 128.0) / 128.0)
+            .collect();
+        Self {
+            rate,
+            data,
+            looping,
+            position: 0.0,
+        }
+    }
+
+    /// Samples played during one 60 Hz timer tick, empty once a sample that doesn't loop
+    /// is over.
+    pub fn next_tick(&mut self) -> Vec<f32> {
+        let end = self.position + self.rate as f64 / TIMER_HZ;
+        let mut samples = Vec::new();
+        if self.data.is_empty() {
+            return samples;
+        }
+        for i in self.position as usize..end as usize {
+            if i < self.data.len() {
+                samples.push(self.data[i]);
+            } else if self.looping {
+                samples.push(self.data[i % self.data.len()]);
+            } else {
+                break;
+            }
+        }
+        self.position = if self.looping {
+            end % self.data.len() as f64
+        } else {
+            end.min(self.data.len() as f64)
+        };
+        samples
+    }
+
+    pub fn is_over(&self) -> bool {
+        !self.looping && self.position >= self.data.len() as f64
+    }
+}
+
+/// State of the MegaChip mode, present while it's on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MegaChip {
+    /// Colors by index, RGBA. Index 0 is transparent in sprites.
+    pub palette: [[u8; 4]; 256],
+    /// Set by 03NN and 04NN, 0 standing for 256.
+    pub sprite_width: usize,
+    pub sprite_height: usize,
+    pub blend: BlendMode,
+    /// Palette index whose pixels set VF when a sprite draws over them, set by 09NN.
+    pub collision_color: u8,
+    /// Opacity of the whole screen, set by 05NN.
+    pub alpha: u8,
+    /// Bits 16 to 23 of I, set by 01NN together with the rest. Plain I writes clear them.
+    pub i_high: u8,
+    pub sample: Option<Sample>,
+    /// Being drawn into, row by row.
+    back: Vec<[u8; 4]>,
+    /// Palette index drawn last at each pixel of `back`, for collisions.
+    indices: Vec<u8>,
+    /// Shown, as of the last 00E0.
+    front: Vec<[u8; 4]>,
+}
+
+impl Default for MegaChip {
+    fn default() -> Self {
+        let mut palette = [BLACK; 256];
+        palette[255] = [255, 255, 255, 255];
+        Self {
+            palette,
+            sprite_width: 0,
+            sprite_height: 0,
+            blend: BlendMode::Normal,
+            collision_color: 0,
+            alpha: 255,
+            i_high: 0,
+            sample: None,
+            back: vec![BLACK; PIXELS],
+            indices: vec![0; PIXELS],
+            front: vec![BLACK; PIXELS],
+        }
+    }
+}
+
+impl MegaChip {
+    /// Picture shown, row by row at [`MEGA_DISPLAY_SIZE`].
+    pub fn frame(&self) -> &[[u8; 4]] {
+        &self.front
+    }
+
+    /// Picture being drawn, not shown until the next 00E0.
+    pub fn back_buffer(&self) -> &[[u8; 4]] {
+        &self.back
+    }
+
+    /// Width and height sprites are drawn with.
+    pub fn sprite_size(&self) -> (usize, usize) {
+        let size = |value: usize| if value == 0 { 256 } else { value };
+        (size(self.sprite_width), size(self.sprite_height))
+    }
+
+    /// 02NN: sets the colors from index 1 on from `colors`, 4 bytes each in ARGB order.
+    pub fn load_palette(&mut self, colors: &[u8]) {
+        for (entry, argb) in self.palette[1..].iter_mut().zip(colors.chunks_exact(4)) {
+            *entry = [argb[1], argb[2], argb[3], argb[0]];
+        }
+    }
+
+    /// DXYN: draws a sprite of [`MegaChip::sprite_size`] palette indices, row by row, with
+    /// its top-left corner at `(x, y)`. Index 0 is transparent and pixels off the screen are
+    /// clipped. Returns whether it drew over the collision color.
+    pub fn draw_sprite(&mut self, x: usize, y: usize, indices: &[u8]) -> bool {
+        let (width, height) = self.sprite_size();
+        let mut collision = false;
+        for (row, line) in indices.chunks(width).take(height).enumerate() {
+            let pos_y = y + row;
+            if pos_y >= MEGA_DISPLAY_SIZE.1 {
+                break;
+            }
+            for (column, &index) in line.iter().enumerate() {
+                let pos_x = x + column;
+                if pos_x >= MEGA_DISPLAY_SIZE.0 {
+                    break;
+                }
+                if index == 0 {
+                    continue;
+                }
+                let pixel = pos_y * MEGA_DISPLAY_SIZE.0 + pos_x;
+                if self.indices[pixel] == self.collision_color && self.collision_color != 0 {
+                    collision = true;
+                }
+                self.indices[pixel] = index;
+                self.back[pixel] = self
+                    .blend
+                    .blend(self.palette[index as usize], self.back[pixel]);
+            }
+        }
+        collision
+    }
+
+    /// 00E0: shows the back buffer, faded by the screen alpha, and clears it.
+    pub fn present(&mut self) {
+        let alpha = self.alpha as u16;
+        for (shown, drawn) in self.front.iter_mut().zip(&self.back) {
+            for channel in 0..3 {
+                shown[channel] = (drawn[channel] as u16 * alpha / 255) as u8;
+            }
+            shown[3] = 255;
+        }
+        self.back.fill(BLACK);
+        self.indices.fill(0);
+    }
+
+    /// Moves the back buffer by `(dx, dy)` pixels, filling the uncovered area with black.
+    pub fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = MEGA_DISPLAY_SIZE;
+        let back = self.back.clone();
+        let indices = self.indices.clone();
+        for y in 0..height {
+            for x in 0..width {
+                let (src_x, src_y) = (x as isize - dx, y as isize - dy);
+                let pixel = y * width + x;
+                if (0..width as isize).contains(&src_x) && (0..height as isize).contains(&src_y) {
+                    let source = src_y as usize * width + src_x as usize;
+                    self.back[pixel] = back[source];
+                    self.indices[pixel] = indices[source];
+                } else {
+                    self.back[pixel] = BLACK;
+                    self.indices[pixel] = 0;
+                }
+            }
+        }
+    }
+
+    /// Saves what DXYN drawing at `(x, y)` is about to change: the back buffer under the
+    /// sprite, clipped like the sprite is.
+    pub(crate) fn save_sprite_area(&self, x: usize, y: usize) -> MegaUndo {
+        let (width, height) = self.sprite_size();
+        let columns = x.min(MEGA_DISPLAY_SIZE.0)..(x + width).min(MEGA_DISPLAY_SIZE.0);
+        let rows = y.min(MEGA_DISPLAY_SIZE.1)..(y + height).min(MEGA_DISPLAY_SIZE.1);
+        let pixels = rows
+            .flat_map(|row| {
+                columns
+                    .clone()
+                    .map(move |column| row * MEGA_DISPLAY_SIZE.0 + column)
+            })
+            .map(|pixel| (pixel as u32, self.back[pixel], self.indices[pixel]))
+            .collect();
+        MegaUndo::Pixels(pixels)
+    }
+
+    /// Saves what a 0NNN instruction is about to change. Only presenting and scrolling
+    /// touch the buffers, the rest keeps them out of the copy.
+    pub(crate) fn save(&self, buffers: bool) -> MegaUndo {
+        if buffers {
+            return MegaUndo::Whole(Box::new(self.clone()));
+        }
+        MegaUndo::Settings(Box::new(Self {
+            sample: self.sample.clone(),
+            back: Vec::new(),
+            indices: Vec::new(),
+            front: Vec::new(),
+            ..*self
+        }))
+    }
+
+    /// Puts back what [`MegaChip::save`] or [`MegaChip::save_sprite_area`] saved.
+    pub(crate) fn restore(&mut self, undo: MegaUndo) {
+        match undo {
+            MegaUndo::Pixels(pixels) => {
+                for (pixel, color, index) in pixels {
+                    self.back[pixel as usize] = color;
+                    self.indices[pixel as usize] = index;
+                }
+            }
+            MegaUndo::Settings(settings) => {
+                let back = std::mem::take(&mut self.back);
+                let indices = std::mem::take(&mut self.indices);
+                let front = std::mem::take(&mut self.front);
+                *self = Self {
+                    back,
+                    indices,
+                    front,
+                    ..*settings
+                };
+            }
+            MegaUndo::Whole(megachip) => *self = *megachip,
+        }
+    }
+
+    /// Samples of the digitized sound for one timer tick, `None` when none is playing.
+    /// Forgets a sample once it's over.
+    pub fn next_sample_tick(&mut self) -> Option<Arc<[f32]>> {
+        let sample = self.sample.as_mut()?;
+        let samples = sample.next_tick();
+        if sample.is_over() {
+            self.sample = None;
+        }
+        Some(samples.into())
+    }
+}
+
+/// MegaChip8 state an instruction is about to change, kept in the history to step back
+/// over it.
+#[derive(Clone, Debug)]
+pub(crate) enum MegaUndo {
+    /// Position, color and palette index of the back buffer pixels under a sprite.
+    Pixels(Vec<(u32, [u8; 4], u8)>),
+    /// Everything but the buffers, which are left empty.
+    Settings(Box<MegaChip>),
+    Whole(Box<MegaChip>),
+}
@@ -0,0 +1,96 @@
+//! Classifying and searching memory, for the hex editor.
+
+use crate::emulator::{Emulator, FONT_AREA};
+
+/// What a byte of memory is used for. Where they overlap the earlier variant wins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region {
+    /// The instruction at PC.
+    ProgramCounter,
+    /// The byte I points to.
+    Index,
+    /// Stored to by the program, see [`Emulator::was_written`]. The call stack lives outside
+    /// of memory here, so these are the program's own variables and buffers.
+    Written,
+    Rom,
+    Font,
+    /// Kept by the interpreter of the emulated machine, see
+    /// [`crate::machine::MachineProfile::reserved`].
+    Reserved,
+}
+
+impl Region {
+    pub const ALL: [Region; 6] = [
+        Region::ProgramCounter,
+        Region::Index,
+        Region::Written,
+        Region::Rom,
+        Region::Font,
+        Region::Reserved,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Region::ProgramCounter => "PC",
+            Region::Index => "I",
+            Region::Written => "Written",
+            Region::Rom => "ROM",
+            Region::Font => "Font",
+            Region::Reserved => "Reserved",
+        }
+    }
+
+    /// Region `address` belongs to, `None` for unused memory.
+    pub fn of(emulator: &Emulator, address: u16) -> Option<Self> {
+        let pc = emulator.pc();
+        let rom_start = emulator.load_offset as usize;
+        let rom_end = rom_start + emulator.rom.as_ref().map_or(0, |rom| rom.size);
+        if address == pc || address == pc.wrapping_add(1) {
+            Some(Region::ProgramCounter)
+        } else if address == emulator.reg_i() {
+            Some(Region::Index)
+        } else if emulator.was_written(address) {
+            Some(Region::Written)
+        } else if (rom_start..rom_end).contains(&(address as usize)) {
+            Some(Region::Rom)
+        } else if FONT_AREA.contains(&address) {
+            Some(Region::Font)
+        } else if emulator.profile.is_reserved(address) {
+            Some(Region::Reserved)
+        } else {
+            None
+        }
+    }
+}
+
+/// Parses a byte search like `A2 1E` or `a21e`, `None` unless it's whole hex bytes.
+pub fn parse_bytes(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    if digits.is_empty()
+        || !digits.len().is_multiple_of(2)
+        || !digits.iter().all(u8::is_ascii_hexdigit)
+    {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+/// First address at or after `from` where `pattern` starts, wrapping around to the start
+/// of memory.
+pub fn find(memory: &[u8], pattern: &[u8], from: usize) -> Option<u16> {
+    if pattern.is_empty() || pattern.len() > memory.len() {
+        return None;
+    }
+    let starts = memory.len() - pattern.len() + 1;
+    let from = from.min(starts);
+    (from..starts)
+        .chain(0..from)
+        .find(|&start| memory[start..start + pattern.len()] == *pattern)
+        .map(|start| start as u16)
+}
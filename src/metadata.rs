@@ -0,0 +1,256 @@
+//! What is known about a ROM besides its bytes: entries of the community CHIP-8 database
+//! (<https://github.com/chip-8/chip-8-database>) and Octo's cartridge options, used by the
+//! library to describe ROMs and to open them with the right quirks.
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    config::RomSettings,
+    quirks::{IndexIncrement, QuirkPreset, Quirks},
+    timing::TIMER_HZ,
+};
+
+/// Entries for the ROMs shipped in `resources/roms`, always available even without the full
+/// database, see [`RomDatabase::bundled`].
+const BUNDLED_DATABASE: &str = include_str!("../resources/compatibility.json");
+
+/// File extensions the library lists as ROMs, Octo sources included.
+pub const ROM_EXTENSIONS: [&str; 8] = ["ch8", "c8", "sc8", "xo8", "mc8", "eti", "660", "8o"];
+
+pub fn is_rom_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ROM_EXTENSIONS
+                .iter()
+                .any(|rom| extension.eq_ignore_ascii_case(rom))
+        })
+}
+
+/// Description of a ROM, every field optional since the sources rarely have them all.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RomMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    pub release: Option<String>,
+    /// Platform the ROM was written for, in the database's naming (e.g. `superchip`).
+    pub platform: Option<String>,
+    pub quirks: Option<Quirks>,
+    /// Instructions per frame.
+    pub tickrate: Option<u32>,
+}
+
+impl RomMetadata {
+    /// Settings to open the ROM with when the user saved none, see [`RomSettings`].
+    pub fn settings(&self) -> Option<RomSettings> {
+        if self.quirks.is_none() && self.tickrate.is_none() {
+            return None;
+        }
+        Some(RomSettings {
+            quirks: self.quirks,
+            cpu_hz: self.tickrate.map(|tickrate| tickrate as f64 * TIMER_HZ),
+            palette: None,
+        })
+    }
+
+    /// Fills in what `self` is missing from `other`.
+    pub fn or(self, other: RomMetadata) -> RomMetadata {
+        RomMetadata {
+            title: self.title.or(other.title),
+            description: self.description.or(other.description),
+            authors: if self.authors.is_empty() {
+                other.authors
+            } else {
+                self.authors
+            },
+            release: self.release.or(other.release),
+            platform: self.platform.or(other.platform),
+            quirks: self.quirks.or(other.quirks),
+            tickrate: self.tickrate.or(other.tickrate),
+        }
+    }
+}
+
+/// Quirk preset for a platform id of the CHIP-8 database.
+pub fn platform_preset(platform: &str) -> Option<QuirkPreset> {
+    match platform {
+        "originalChip8" | "hybridVIP" | "chip8x" => Some(QuirkPreset::CosmacVip),
+        "modernChip8" => Some(QuirkPreset::Modern),
+        "chip48" => Some(QuirkPreset::Chip48),
+        "superchip1" | "superchip" => Some(QuirkPreset::SuperChip),
+        "xochip" => Some(QuirkPreset::XoChip),
+        _ => None,
+    }
+}
+
+/// Platform implied by the usual extensions, `.sc8` for SUPER-CHIP and `.xo8` for XO-CHIP.
+pub fn extension_metadata(path: &Path) -> RomMetadata {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let platform = match extension.as_deref() {
+        Some("sc8") => "superchip",
+        Some("xo8") => "xochip",
+        _ => return RomMetadata::default(),
+    };
+    RomMetadata {
+        platform: Some(platform.to_string()),
+        quirks: platform_preset(platform).map(QuirkPreset::quirks),
+        ..RomMetadata::default()
+    }
+}
+
+#[derive(Deserialize)]
+struct DatabaseProgram {
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    release: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    roms: HashMap<String, DatabaseRom>,
+}
+
+#[derive(Deserialize)]
+struct DatabaseRom {
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(default)]
+    tickrate: Option<u32>,
+}
+
+/// The CHIP-8 database's `programs.json`, indexed by ROM hash.
+#[derive(Clone, Debug, Default)]
+pub struct RomDatabase {
+    roms: HashMap<String, RomMetadata>,
+}
+
+impl RomDatabase {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let programs: Vec<DatabaseProgram> = serde_json::from_str(json)?;
+        let mut roms = HashMap::new();
+        for program in programs {
+            for (hash, rom) in program.roms {
+                // The first platform listed is the one the ROM was written for.
+                let platform = rom.platforms.first().cloned();
+                let metadata = RomMetadata {
+                    title: Some(program.title.clone()),
+                    description: program.description.clone(),
+                    authors: program.authors.clone(),
+                    release: program.release.clone(),
+                    quirks: platform
+                        .as_deref()
+                        .and_then(platform_preset)
+                        .map(QuirkPreset::quirks),
+                    platform,
+                    tickrate: rom.tickrate,
+                };
+                roms.insert(hash.to_ascii_lowercase(), metadata);
+            }
+        }
+        Ok(Self { roms })
+    }
+
+    /// The entries compiled into the emulator, for the ROMs it ships with.
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_DATABASE).expect("Bundled compatibility database is invalid.")
+    }
+
+    /// Adds the entries of `other`, which win over entries for the same ROM.
+    pub fn extend(&mut self, other: RomDatabase) {
+        self.roms.extend(other.roms);
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+
+    /// Entry for a ROM by its [`crate::rom::hash`].
+    pub fn get(&self, hash: &str) -> Option<&RomMetadata> {
+        self.roms.get(hash)
+    }
+}
+
+/// The `options` of an Octo cartridge or project file. Each quirk flag is named after the
+/// non-VIP behaviour it turns on.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OctoOptions {
+    pub tickrate: Option<u32>,
+    /// 8XY6/8XYE shift VX in place.
+    pub shift_quirks: Option<bool>,
+    /// FX55/FX65 leave I alone.
+    pub load_store_quirks: Option<bool>,
+    /// Sprites are clipped at the edges instead of wrapping.
+    pub clip_quirks: Option<bool>,
+    /// BNNN jumps to XNN + VX.
+    pub jump_quirks: Option<bool>,
+    /// DXYN waits for the next frame.
+    pub v_blank_quirks: Option<bool>,
+    /// 8XY1/8XY2/8XY3 reset VF.
+    pub logic_quirks: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct OctoFile {
+    options: OctoOptions,
+}
+
+impl OctoOptions {
+    /// Reads the options from an Octo project (`{"options": {...}}`) or a bare options object.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        match serde_json::from_str::<OctoFile>(json) {
+            Ok(file) => Ok(file.options),
+            Err(_) => Ok(serde_json::from_str(json)?),
+        }
+    }
+
+    /// Quirks with the flags Octo set, Octo's defaults (XO-CHIP) for the rest.
+    pub fn quirks(&self) -> Quirks {
+        let defaults = QuirkPreset::XoChip.quirks();
+        let index_increment = match self.load_store_quirks {
+            Some(true) => IndexIncrement::None,
+            Some(false) => IndexIncrement::XPlusOne,
+            None => defaults.index_increment,
+        };
+        Quirks {
+            shift_swap: self
+                .shift_quirks
+                .map_or(defaults.shift_swap, |quirk| !quirk),
+            complex_jump: self.jump_quirks.unwrap_or(defaults.complex_jump),
+            index_increment,
+            vf_reset: self.logic_quirks.unwrap_or(defaults.vf_reset),
+            clipping: self.clip_quirks.unwrap_or(defaults.clipping),
+            display_wait: self.v_blank_quirks.unwrap_or(defaults.display_wait),
+        }
+    }
+
+    pub fn metadata(&self) -> RomMetadata {
+        RomMetadata {
+            quirks: Some(self.quirks()),
+            tickrate: self.tickrate,
+            ..RomMetadata::default()
+        }
+    }
+}
+
+/// Octo options saved next to a ROM as `<name>.json`, `None` if there are none.
+pub fn load_sidecar(rom: &Path) -> Option<io::Result<OctoOptions>> {
+    let path = rom.with_extension("json");
+    path.is_file()
+        .then(|| fs::read_to_string(&path).and_then(|json| OctoOptions::from_json(&json)))
+}
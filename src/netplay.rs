@@ -0,0 +1,309 @@
+//! Experimental two-player netplay over TCP. Both sides run the same ROM in lockstep: every
+//! frame each one sends its keypad a few frames ahead (the input delay) and a frame only
+//! runs once the other side's keys for it have arrived. The game sees both keypads OR-ed
+//! together, which suits two-player games where each player has their own keys.
+//!
+//! Lockstep only works if both machines compute exactly the same thing, so the host sends
+//! its seed, quirks and speed when the guest connects (see [`Handshake`]) and both restart
+//! the ROM from there. Frames always run [`Emulator::cpf`] instructions.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    emulator::Emulator,
+    quirks::{Quirks, RngMode},
+};
+
+pub const DEFAULT_PORT: u16 = 6464;
+/// Frames between reading a key and the game seeing it. More hides more latency but makes
+/// the controls feel slower, 3 frames cover a 50 ms round trip.
+pub const DEFAULT_INPUT_DELAY: u32 = 3;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Everything the two machines must agree on to stay in sync, sent by the host.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Handshake {
+    /// See [`crate::rom::hash`], the guest refuses to play another ROM.
+    pub rom_hash: String,
+    pub seed: u64,
+    pub quirks: Quirks,
+    pub rng_mode: RngMode,
+    /// Instructions per frame.
+    pub cpf: i32,
+    pub input_delay: u32,
+}
+
+impl Handshake {
+    /// Settings the emulator currently runs the loaded ROM with.
+    pub fn from_emulator(emulator: &Emulator, input_delay: u32) -> io::Result<Self> {
+        let rom = emulator
+            .rom
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No ROM is loaded."))?;
+        Ok(Self {
+            rom_hash: rom.hash.clone(),
+            seed: emulator.rng_seed(),
+            quirks: emulator.quirks,
+            rng_mode: emulator.rng_mode,
+            cpf: emulator.cpf,
+            input_delay,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Message {
+    Hello(Handshake),
+    /// Keypad of one player for `frame`, bit N for key N.
+    Input {
+        frame: u64,
+        keys: u16,
+    },
+    Bye,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetplayRole {
+    Host,
+    Guest,
+}
+
+/// Waits for a guest to connect, without blocking the caller.
+pub struct NetplayHost {
+    listener: TcpListener,
+    input_delay: u32,
+}
+
+impl NetplayHost {
+    pub fn listen(port: u16, input_delay: u32) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            input_delay,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Returns the session once a guest connected and was sent the emulator's settings,
+    /// pass it to [`Emulator::start_netplay`].
+    pub fn accept(&self, emulator: &Emulator) -> io::Result<Option<Netplay>> {
+        let stream = match self.listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let handshake = Handshake::from_emulator(emulator, self.input_delay)?;
+        let mut netplay = Netplay::new(stream, NetplayRole::Host, handshake.clone())?;
+        netplay.send(&Message::Hello(handshake))?;
+        Ok(Some(netplay))
+    }
+}
+
+/// A connected netplay session, see the [module docs](self).
+pub struct Netplay {
+    stream: TcpStream,
+    role: NetplayRole,
+    handshake: Handshake,
+    /// Bytes received that don't form a whole message yet.
+    received: Vec<u8>,
+    /// Bytes of sent messages the socket didn't take yet, written on the next frames.
+    unsent: VecDeque<u8>,
+    /// Next frame to run.
+    frame: u64,
+    /// Next frame to send the local keys for.
+    sent: u64,
+    local: BTreeMap<u64, u16>,
+    remote: BTreeMap<u64, u16>,
+}
+
+impl Netplay {
+    /// Connects to a host, blocking until it answered with its settings. Fails if the host
+    /// plays a ROM other than the one `emulator` has loaded.
+    pub fn join(address: impl ToSocketAddrs, emulator: &Emulator) -> io::Result<Self> {
+        let address = address.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "The address doesn't resolve.")
+        })?;
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line)?;
+        // The host may already have sent its first keys.
+        let received = reader.buffer().to_vec();
+        let handshake = match serde_json::from_slice(&line)? {
+            Message::Hello(handshake) => handshake,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "The host didn't send its settings.",
+                ))
+            }
+        };
+        let rom_hash = emulator.rom.as_ref().map(|rom| rom.hash.as_str());
+        if rom_hash != Some(handshake.rom_hash.as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The host is playing a different ROM.",
+            ));
+        }
+        stream.set_read_timeout(None)?;
+        let mut netplay = Self::new(stream, NetplayRole::Guest, handshake)?;
+        netplay.received = received;
+        Ok(netplay)
+    }
+
+    fn new(stream: TcpStream, role: NetplayRole, handshake: Handshake) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+        // Nobody presses anything during the first frames, before any input could arrive.
+        let delay = handshake.input_delay as u64;
+        let idle: BTreeMap<u64, u16> = (0..delay).map(|frame| (frame, 0)).collect();
+        Ok(Self {
+            stream,
+            role,
+            handshake,
+            received: Vec::new(),
+            unsent: VecDeque::new(),
+            frame: 0,
+            sent: delay,
+            local: idle.clone(),
+            remote: idle,
+        })
+    }
+
+    pub fn role(&self) -> NetplayRole {
+        self.role
+    }
+
+    pub fn handshake(&self) -> &Handshake {
+        &self.handshake
+    }
+
+    pub fn peer(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Frames run so far.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Whether the next frame waits on the other player's keys.
+    pub fn waiting(&self) -> bool {
+        !self.remote.contains_key(&self.frame)
+    }
+
+    /// Sends the player's keys for a later frame and returns the keypad for the next frame,
+    /// or `None` if the other player's keys for it haven't arrived yet.
+    pub fn next_frame(&mut self, player_keys: u16) -> io::Result<Option<u16>> {
+        self.flush()?;
+        self.receive()?;
+        let ahead = self.frame + self.handshake.input_delay as u64;
+        if self.sent <= ahead {
+            let frame = self.sent;
+            self.send(&Message::Input {
+                frame,
+                keys: player_keys,
+            })?;
+            self.local.insert(frame, player_keys);
+            self.sent += 1;
+        }
+        let Some(remote) = self.remote.remove(&self.frame) else {
+            return Ok(None);
+        };
+        let local = self.local.remove(&self.frame).unwrap_or_default();
+        self.frame += 1;
+        Ok(Some(local | remote))
+    }
+
+    /// Tells the other player the session is over, waiting for what's still queued to
+    /// be sent.
+    pub fn close(mut self) {
+        let _ = self.stream.set_nonblocking(false);
+        let _ = self.send(&Message::Bye);
+    }
+
+    /// Queues `message` and writes as much of the queue as the socket takes without
+    /// blocking, the rest goes out on the next frames. Writing a message straight away
+    /// could leave half of it out and break the framing.
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        serde_json::to_writer(&mut self.unsent, message)?;
+        self.unsent.push_back(b'\n');
+        self.flush()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        loop {
+            let (queued, _) = self.unsent.as_slices();
+            if queued.is_empty() {
+                return Ok(());
+            }
+            match self.stream.write(queued) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.unsent.drain(..written);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn receive(&mut self) -> io::Result<()> {
+        let mut buffer = [0; 1024];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::ConnectionAborted,
+                        "The other player disconnected.",
+                    ))
+                }
+                Ok(read) => self.received.extend_from_slice(&buffer[..read]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        // Whole messages are dropped from the buffer at once, it can hold many of them.
+        let mut parsed = 0;
+        while let Some(end) = self.received[parsed..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+        {
+            let line = &self.received[parsed..=parsed + end];
+            parsed += end + 1;
+            match serde_json::from_slice(line)? {
+                Message::Input { frame, keys } => {
+                    self.remote.insert(frame, keys);
+                }
+                Message::Bye => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::ConnectionAborted,
+                        "The other player left.",
+                    ))
+                }
+                Message::Hello(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Unexpected handshake.",
+                    ))
+                }
+            }
+        }
+        self.received.drain(..parsed);
+        Ok(())
+    }
+}
@@ -0,0 +1,725 @@
+//! Assembler for Octo (<https://github.com/JohnEarnest/Octo>) sources, so `.8o` files can be
+//! opened like ROMs. Covers labels, `:const`, `:alias`, `:byte`, `:org`, `:call`, `:unpack`,
+//! every instruction statement and the `if`/`else`/`loop`/`while` control flow. Macros,
+//! `:calc`, `:stringmode` and the `<`/`>` comparisons are not supported.
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use crate::emulator::DEFAULT_LOAD_OFFSET;
+
+pub const SOURCE_EXTENSION: &str = "8o";
+
+pub fn is_source(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(SOURCE_EXTENSION))
+}
+
+/// Why a source didn't assemble, and the line it happened on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OctoError {
+    /// 1-based, 0 for errors about the whole program.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for OctoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for OctoError {}
+
+/// Assembles an Octo program into a ROM loaded at 0x200. Like Octo, execution starts at the
+/// `main` label: the ROM begins with a jump to it unless `main` comes first.
+pub fn assemble(source: &str) -> Result<Vec<u8>, OctoError> {
+    let with_jump = Assembler::new(source, DEFAULT_LOAD_OFFSET + 2).run()?;
+    if with_jump.main != DEFAULT_LOAD_OFFSET + 2 {
+        return Ok(with_jump.rom);
+    }
+    // Same program without the jump, `:org` can still put `main` elsewhere.
+    let without = Assembler::new(source, DEFAULT_LOAD_OFFSET).run()?;
+    Ok(if without.main == DEFAULT_LOAD_OFFSET {
+        without.rom
+    } else {
+        with_jump.rom
+    })
+}
+
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    source
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            let code = line.split('#').next().unwrap_or_default();
+            code.split_whitespace().map(move |text| Token {
+                text,
+                line: index + 1,
+            })
+        })
+        .collect()
+}
+
+fn parse_number(text: &str) -> Option<i32> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i32::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i32::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn parse_register(text: &str) -> Option<u8> {
+    let digit = text.strip_prefix(['v', 'V'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(digit, 16).ok()
+}
+
+fn is_name(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Address operand, labels defined further down are patched in at the end.
+#[derive(Clone, Copy)]
+enum Target<'a> {
+    Known(u16),
+    Label(Token<'a>),
+}
+
+#[derive(Clone, Copy)]
+enum Patch {
+    /// Low 12 bits of an instruction.
+    Nnn,
+    /// The whole word after `i := long`.
+    Long,
+    /// `v0 :=` half of `:unpack`, the nibble goes above the top of the address.
+    UnpackHigh(u8),
+    /// `v1 :=` half of `:unpack`.
+    UnpackLow,
+}
+
+struct Fixup<'a> {
+    /// Offset of the word in the ROM.
+    offset: usize,
+    patch: Patch,
+    label: Token<'a>,
+}
+
+enum Block {
+    /// `if ... begin`, with the jump taken when the condition is false, or past the else
+    /// branch once `else` was seen.
+    If {
+        jump: usize,
+        has_else: bool,
+        line: usize,
+    },
+    Loop {
+        start: u16,
+        /// Jumps out of the loop from `while`.
+        breaks: Vec<usize>,
+        line: usize,
+    },
+}
+
+struct Assembled {
+    rom: Vec<u8>,
+    main: u16,
+}
+
+struct Assembler<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    /// Where the first statement goes, past the jump to `main` if there is one.
+    start: u16,
+    here: u16,
+    rom: Vec<u8>,
+    labels: HashMap<&'a str, u16>,
+    constants: HashMap<&'a str, i32>,
+    aliases: HashMap<&'a str, u8>,
+    fixups: Vec<Fixup<'a>>,
+    blocks: Vec<Block>,
+}
+
+impl<'a> Assembler<'a> {
+    fn new(source: &'a str, start: u16) -> Self {
+        Self {
+            tokens: tokenize(source),
+            pos: 0,
+            start,
+            here: start,
+            rom: Vec::new(),
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> Result<Assembled, OctoError> {
+        while self.pos < self.tokens.len() {
+            self.statement()?;
+        }
+        if let Some(block) = self.blocks.last() {
+            let (line, message) = match block {
+                Block::If { line, .. } => (*line, "`if ... begin` without `end`"),
+                Block::Loop { line, .. } => (*line, "`loop` without `again`"),
+            };
+            return Err(error(line, message));
+        }
+        for fixup in std::mem::take(&mut self.fixups) {
+            let Some(&address) = self.labels.get(fixup.label.text) else {
+                return Err(error(
+                    fixup.label.line,
+                    format!("undefined label `{}`", fixup.label.text),
+                ));
+            };
+            self.patch(fixup.offset, fixup.patch, address, fixup.label.line)?;
+        }
+        let main = *self
+            .labels
+            .get("main")
+            .ok_or_else(|| error(0, "the program has no `main` label"))?;
+        if self.start != DEFAULT_LOAD_OFFSET {
+            self.here = DEFAULT_LOAD_OFFSET;
+            self.emit_word(0x1000, 0)?;
+            self.patch(0, Patch::Nnn, main, 0)?;
+        }
+        Ok(Assembled {
+            rom: self.rom,
+            main,
+        })
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, OctoError> {
+        let token = self.tokens.get(self.pos).copied().ok_or_else(|| {
+            let line = self.tokens.last().map_or(0, |token| token.line);
+            error(line, "unexpected end of file")
+        })?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|token| token.text)
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), OctoError> {
+        let token = self.next()?;
+        if token.text == text {
+            Ok(())
+        } else {
+            Err(error(
+                token.line,
+                format!("expected `{text}`, found `{}`", token.text),
+            ))
+        }
+    }
+
+    fn name(&mut self) -> Result<Token<'a>, OctoError> {
+        let token = self.next()?;
+        if !is_name(token.text) || self.register_of(token.text).is_some() {
+            return Err(error(
+                token.line,
+                format!("`{}` is not a valid name", token.text),
+            ));
+        }
+        Ok(token)
+    }
+
+    fn register_of(&self, text: &str) -> Option<u8> {
+        parse_register(text).or_else(|| self.aliases.get(text).copied())
+    }
+
+    fn register(&mut self) -> Result<u8, OctoError> {
+        let token = self.next()?;
+        self.register_of(token.text).ok_or_else(|| {
+            error(
+                token.line,
+                format!("expected a register, found `{}`", token.text),
+            )
+        })
+    }
+
+    fn number_of(&self, text: &str) -> Option<i32> {
+        parse_number(text).or_else(|| self.constants.get(text).copied())
+    }
+
+    fn number(&mut self, min: i32, max: i32) -> Result<i32, OctoError> {
+        let token = self.next()?;
+        let value = self.number_of(token.text).ok_or_else(|| {
+            error(
+                token.line,
+                format!("expected a number, found `{}`", token.text),
+            )
+        })?;
+        if !(min..=max).contains(&value) {
+            return Err(error(
+                token.line,
+                format!("{value} is out of range ({min} to {max})"),
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Byte operand, negative numbers are two's complement.
+    fn byte(&mut self) -> Result<u8, OctoError> {
+        Ok(self.number(-128, 255)? as u8)
+    }
+
+    fn nibble(&mut self) -> Result<u16, OctoError> {
+        Ok(self.number(0, 15)? as u16)
+    }
+
+    fn target(&mut self) -> Result<Target<'a>, OctoError> {
+        let token = self.next()?;
+        if let Some(&address) = self.labels.get(token.text) {
+            return Ok(Target::Known(address));
+        }
+        if let Some(value) = self.number_of(token.text) {
+            return u16::try_from(value)
+                .map(Target::Known)
+                .map_err(|_| error(token.line, format!("{value} is not an address")));
+        }
+        if is_name(token.text) && self.register_of(token.text).is_none() {
+            Ok(Target::Label(token))
+        } else {
+            Err(error(
+                token.line,
+                format!("expected an address, found `{}`", token.text),
+            ))
+        }
+    }
+
+    fn emit_byte(&mut self, byte: u8, line: usize) -> Result<(), OctoError> {
+        let offset = self
+            .here
+            .checked_sub(DEFAULT_LOAD_OFFSET)
+            .ok_or_else(|| error(line, format!("0x{:X} is below 0x200", self.here)))?
+            as usize;
+        if self.rom.len() <= offset {
+            self.rom.resize(offset + 1, 0);
+        }
+        self.rom[offset] = byte;
+        self.here = self
+            .here
+            .checked_add(1)
+            .ok_or_else(|| error(line, "the program doesn't fit in 64 KiB"))?;
+        Ok(())
+    }
+
+    fn emit_word(&mut self, word: u16, line: usize) -> Result<usize, OctoError> {
+        let offset = self.here.wrapping_sub(DEFAULT_LOAD_OFFSET) as usize;
+        let [high, low] = word.to_be_bytes();
+        self.emit_byte(high, line)?;
+        self.emit_byte(low, line)?;
+        Ok(offset)
+    }
+
+    /// Emits `word` with an address patched in now or once the label is defined.
+    fn emit_target(
+        &mut self,
+        word: u16,
+        patch: Patch,
+        target: Target<'a>,
+        line: usize,
+    ) -> Result<(), OctoError> {
+        let offset = self.emit_word(word, line)?;
+        match target {
+            Target::Known(address) => self.patch(offset, patch, address, line),
+            Target::Label(label) => {
+                self.fixups.push(Fixup {
+                    offset,
+                    patch,
+                    label,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn patch(
+        &mut self,
+        offset: usize,
+        patch: Patch,
+        address: u16,
+        line: usize,
+    ) -> Result<(), OctoError> {
+        let word = u16::from_be_bytes([self.rom[offset], self.rom[offset + 1]]);
+        let word = match patch {
+            Patch::Nnn => {
+                if address > 0xFFF {
+                    return Err(error(
+                        line,
+                        format!("0x{address:X} is out of reach, use `i := long`"),
+                    ));
+                }
+                word & 0xF000 | address
+            }
+            Patch::Long => address,
+            Patch::UnpackHigh(nibble) => word & 0xFF00 | (nibble as u16) << 4 | address >> 8 & 0xF,
+            Patch::UnpackLow => word & 0xFF00 | address & 0xFF,
+        };
+        self.rom[offset..offset + 2].copy_from_slice(&word.to_be_bytes());
+        Ok(())
+    }
+
+    /// Points the placeholder jump at `offset` to the current address.
+    fn land(&mut self, offset: usize, line: usize) -> Result<(), OctoError> {
+        self.patch(offset, Patch::Nnn, self.here, line)
+    }
+
+    fn statement(&mut self) -> Result<(), OctoError> {
+        let token = self.next()?;
+        let line = token.line;
+        let word = match token.text {
+            ":" => {
+                let name = self.name()?;
+                if self.labels.contains_key(name.text) || self.constants.contains_key(name.text) {
+                    return Err(error(line, format!("`{}` is already defined", name.text)));
+                }
+                self.labels.insert(name.text, self.here);
+                return Ok(());
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.number(i32::MIN, i32::MAX)?;
+                self.constants.insert(name.text, value);
+                return Ok(());
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name.text, register);
+                return Ok(());
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                return self.emit_byte(byte, line);
+            }
+            ":org" => {
+                self.here = self.number(DEFAULT_LOAD_OFFSET as i32, 0xFFFF)? as u16;
+                return Ok(());
+            }
+            ":call" => {
+                let target = self.target()?;
+                return self.emit_target(0x2000, Patch::Nnn, target, line);
+            }
+            ":unpack" => {
+                let nibble = self.nibble()? as u8;
+                let target = self.target()?;
+                self.emit_target(0x6000, Patch::UnpackHigh(nibble), target, line)?;
+                return self.emit_target(0x6100, Patch::UnpackLow, target, line);
+            }
+            // Debugger hints, nothing to assemble.
+            ":breakpoint" => {
+                self.next()?;
+                return Ok(());
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+                return Ok(());
+            }
+            ";" | "return" => 0x00EE,
+            "clear" => 0x00E0,
+            "exit" => 0x00FD,
+            "lores" => 0x00FE,
+            "hires" => 0x00FF,
+            "scroll-down" => 0x00C0 | self.nibble()?,
+            "scroll-up" => 0x00D0 | self.nibble()?,
+            "scroll-right" => 0x00FB,
+            "scroll-left" => 0x00FC,
+            "audio" => 0xF002,
+            "plane" => 0xF001 | self.nibble()? << 8,
+            "bcd" => 0xF033 | x(self.register()?),
+            "saveflags" => 0xF075 | x(self.register()?),
+            "loadflags" => 0xF085 | x(self.register()?),
+            "save" | "load" => {
+                let first = self.register()?;
+                if self.peek() == Some("-") {
+                    self.next()?;
+                    let last = self.register()?;
+                    let op = if token.text == "save" { 0x5002 } else { 0x5003 };
+                    op | x(first) | y(last)
+                } else {
+                    let op = if token.text == "save" { 0xF055 } else { 0xF065 };
+                    op | x(first)
+                }
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let op = match token.text {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A,
+                };
+                op | x(self.register()?)
+            }
+            "sprite" => {
+                let vx = self.register()?;
+                let vy = self.register()?;
+                0xD000 | x(vx) | y(vy) | self.nibble()?
+            }
+            "jump" | "jump0" => {
+                let op = if token.text == "jump" { 0x1000 } else { 0xB000 };
+                let target = self.target()?;
+                return self.emit_target(op, Patch::Nnn, target, line);
+            }
+            "i" => return self.index(line),
+            "if" => return self.conditional(line),
+            "else" => {
+                let jump = self.emit_word(0x1000, line)?;
+                match self.blocks.last_mut() {
+                    Some(Block::If {
+                        jump: pending,
+                        has_else,
+                        ..
+                    }) if !*has_else => {
+                        let skipped = std::mem::replace(pending, jump);
+                        *has_else = true;
+                        return self.land(skipped, line);
+                    }
+                    _ => return Err(error(line, "`else` without `if ... begin`")),
+                }
+            }
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. }) => return self.land(jump, line),
+                _ => return Err(error(line, "`end` without `if ... begin`")),
+            },
+            "loop" => {
+                self.blocks.push(Block::Loop {
+                    start: self.here,
+                    breaks: Vec::new(),
+                    line,
+                });
+                return Ok(());
+            }
+            "while" => {
+                let (_, inverse) = self.condition()?;
+                self.emit_word(inverse, line)?;
+                let jump = self.emit_word(0x1000, line)?;
+                let innermost = self.blocks.iter_mut().rev().find_map(|block| match block {
+                    Block::Loop { breaks, .. } => Some(breaks),
+                    Block::If { .. } => None,
+                });
+                match innermost {
+                    Some(breaks) => {
+                        breaks.push(jump);
+                        return Ok(());
+                    }
+                    None => return Err(error(line, "`while` outside of a loop")),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, breaks, .. }) => {
+                    let jump = self.emit_word(0x1000, line)?;
+                    self.patch(jump, Patch::Nnn, start, line)?;
+                    for jump in breaks {
+                        self.land(jump, line)?;
+                    }
+                    return Ok(());
+                }
+                _ => return Err(error(line, "`again` without `loop`")),
+            },
+            text => {
+                if let Some(register) = self.register_of(text) {
+                    self.assignment(register)?
+                } else if let Some(value) = self.number_of(text) {
+                    if !(-128..=255).contains(&value) {
+                        return Err(error(line, format!("{value} doesn't fit in a byte")));
+                    }
+                    return self.emit_byte(value as u8, line);
+                } else if text.starts_with(':') {
+                    return Err(error(line, format!("`{text}` is not supported")));
+                } else if is_name(text) {
+                    // A bare label calls the subroutine.
+                    self.pos -= 1;
+                    let target = self.target()?;
+                    return self.emit_target(0x2000, Patch::Nnn, target, line);
+                } else {
+                    return Err(error(line, format!("unexpected `{text}`")));
+                }
+            }
+        };
+        self.emit_word(word, line)?;
+        Ok(())
+    }
+
+    /// `vx := ...` and the other register operators, after `vx`.
+    fn assignment(&mut self, vx: u8) -> Result<u16, OctoError> {
+        let op = self.next()?;
+        let vy = self.peek().and_then(|text| self.register_of(text));
+        if let Some(vy) = vy {
+            let alu = match op.text {
+                ":=" => 0x0,
+                "|=" => 0x1,
+                "&=" => 0x2,
+                "^=" => 0x3,
+                "+=" => 0x4,
+                "-=" => 0x5,
+                ">>=" => 0x6,
+                "=-" => 0x7,
+                "<<=" => 0xE,
+                _ => return Err(error(op.line, format!("unknown operator `{}`", op.text))),
+            };
+            self.next()?;
+            return Ok(0x8000 | x(vx) | y(vy) | alu);
+        }
+        match op.text {
+            ":=" => match self.peek() {
+                Some("delay") => {
+                    self.next()?;
+                    Ok(0xF007 | x(vx))
+                }
+                Some("key") => {
+                    self.next()?;
+                    Ok(0xF00A | x(vx))
+                }
+                Some("random") => {
+                    self.next()?;
+                    Ok(0xC000 | x(vx) | self.byte()? as u16)
+                }
+                _ => Ok(0x6000 | x(vx) | self.byte()? as u16),
+            },
+            "+=" => Ok(0x7000 | x(vx) | self.byte()? as u16),
+            "-=" => Ok(0x7000 | x(vx) | self.byte()?.wrapping_neg() as u16),
+            _ => {
+                let token = self.next()?;
+                Err(error(
+                    token.line,
+                    format!("expected a register, found `{}`", token.text),
+                ))
+            }
+        }
+    }
+
+    fn index(&mut self, line: usize) -> Result<(), OctoError> {
+        let op = self.next()?;
+        let word = match (op.text, self.peek()) {
+            ("+=", _) => 0xF01E | x(self.register()?),
+            (":=", Some("hex")) => {
+                self.next()?;
+                0xF029 | x(self.register()?)
+            }
+            (":=", Some("bighex")) => {
+                self.next()?;
+                0xF030 | x(self.register()?)
+            }
+            (":=", Some("long")) => {
+                self.next()?;
+                self.emit_word(0xF000, line)?;
+                let target = self.target()?;
+                return self.emit_target(0x0000, Patch::Long, target, line);
+            }
+            (":=", _) => {
+                let target = self.target()?;
+                return self.emit_target(0xA000, Patch::Nnn, target, line);
+            }
+            _ => return Err(error(op.line, format!("unknown operator `i {}`", op.text))),
+        };
+        self.emit_word(word, line)?;
+        Ok(())
+    }
+
+    /// `if` statements: `if ... then` skips the next statement when the condition is false,
+    /// `if ... begin` jumps past the block.
+    fn conditional(&mut self, line: usize) -> Result<(), OctoError> {
+        let (skip, inverse) = self.condition()?;
+        let token = self.next()?;
+        match token.text {
+            "then" => {
+                self.emit_word(skip, line)?;
+                self.statement()
+            }
+            "begin" => {
+                self.emit_word(inverse, line)?;
+                let jump = self.emit_word(0x1000, line)?;
+                self.blocks.push(Block::If {
+                    jump,
+                    has_else: false,
+                    line,
+                });
+                Ok(())
+            }
+            text => Err(error(
+                token.line,
+                format!("expected `then` or `begin`, found `{text}`"),
+            )),
+        }
+    }
+
+    /// Parses a condition into the skip taken when it is false and the one taken when it is
+    /// true.
+    fn condition(&mut self) -> Result<(u16, u16), OctoError> {
+        let vx = x(self.register()?);
+        let op = self.next()?;
+        match op.text {
+            "key" => return Ok((0xE0A1 | vx, 0xE09E | vx)),
+            "-key" => return Ok((0xE09E | vx, 0xE0A1 | vx)),
+            "==" | "!=" => {}
+            "<" | ">" | "<=" | ">=" => {
+                return Err(error(
+                    op.line,
+                    format!("`{}` is not supported, use `==` or `!=`", op.text),
+                ))
+            }
+            text => return Err(error(op.line, format!("unknown comparison `{text}`"))),
+        }
+        let (skip_equal, skip_not_equal) = match self.peek().and_then(|text| self.register_of(text))
+        {
+            Some(vy) => {
+                self.next()?;
+                (0x5000 | vx | y(vy), 0x9000 | vx | y(vy))
+            }
+            None => {
+                let byte = self.byte()? as u16;
+                (0x3000 | vx | byte, 0x4000 | vx | byte)
+            }
+        };
+        Ok(if op.text == "==" {
+            (skip_not_equal, skip_equal)
+        } else {
+            (skip_equal, skip_not_equal)
+        })
+    }
+}
+
+fn x(register: u8) -> u16 {
+    (register as u16) << 8
+}
+
+fn y(register: u8) -> u16 {
+    (register as u16) << 4
+}
+
+fn error(line: usize, message: impl Into<String>) -> OctoError {
+    OctoError {
+        line,
+        message: message.into(),
+    }
+}
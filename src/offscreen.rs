@@ -0,0 +1,188 @@
+use std::path::Path;
+
+use image::RgbaImage;
+
+use crate::{
+    emulator::Display,
+    frontend::SurfaceTarget,
+    gpu::{self, GpuInitError},
+    renderer::{DisplayRenderer, Palette, PostEffects, Rotation},
+};
+
+const TARGET_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Renders the display through the same pipeline as the window, but into an
+/// offscreen texture that is read back to the CPU. Needs no window or surface.
+pub struct OffscreenRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    renderer: DisplayRenderer,
+    target: wgpu::Texture,
+    readback: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+}
+
+impl OffscreenRenderer {
+    pub fn new(width: u32, height: u32) -> Result<Self, GpuInitError> {
+        pollster::block_on(Self::new_async(width, height))
+    }
+
+    pub async fn new_async(width: u32, height: u32) -> Result<Self, GpuInitError> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let adapter = gpu::request_adapter(&instance, None).await?;
+        let (device, queue) = gpu::request_device(&instance, &adapter).await?;
+
+        let (target, readback, padded_bytes_per_row) = create_target(&device, width, height);
+        let renderer = DisplayRenderer::new(&device, TARGET_FORMAT);
+
+        Ok(Self {
+            device,
+            queue,
+            renderer,
+            target,
+            readback,
+            width,
+            height,
+            padded_bytes_per_row,
+        })
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn palette_mut(&mut self) -> &mut Palette {
+        &mut self.renderer.palette
+    }
+
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.renderer.set_rotation(&self.queue, rotation);
+    }
+
+    pub fn set_effects(&mut self, effects: &PostEffects) {
+        self.renderer.set_effects(&self.queue, effects);
+    }
+
+    /// Draws the display and blocks until the result is available on the CPU.
+    pub fn render(&mut self, display: &Display) -> RgbaImage {
+        let view = self
+            .target
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.renderer.update(&self.queue, &mut encoder, display);
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.renderer.draw(&mut rpass);
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.target,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(self.padded_bytes_per_row),
+                    rows_per_image: std::num::NonZeroU32::new(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = self.readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Failed to map offscreen readback buffer.")
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+
+        let mut pixels = Vec::with_capacity((self.width * self.height * 4) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..(self.width * 4) as usize]);
+            }
+        }
+        self.readback.unmap();
+
+        RgbaImage::from_raw(self.width, self.height, pixels)
+            .expect("Readback size does not match the target size.")
+    }
+
+    pub fn render_to_png(
+        &mut self,
+        display: &Display,
+        path: impl AsRef<Path>,
+    ) -> image::ImageResult<()> {
+        self.render(display).save(path)
+    }
+}
+
+impl SurfaceTarget for OffscreenRenderer {
+    fn resize(&mut self, width: u32, height: u32) {
+        let (target, readback, padded_bytes_per_row) = create_target(&self.device, width, height);
+        self.target = target;
+        self.readback = readback;
+        self.padded_bytes_per_row = padded_bytes_per_row;
+        self.width = width;
+        self.height = height;
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+fn create_target(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+) -> (wgpu::Texture, wgpu::Buffer, u32) {
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TARGET_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        label: Some("CHIP-8 Offscreen target"),
+    });
+
+    // Rows copied out of a texture must be aligned to COPY_BYTES_PER_ROW_ALIGNMENT.
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = (width * 4).div_ceil(align) * align;
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("CHIP-8 Offscreen readback"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    (target, readback, padded_bytes_per_row)
+}
@@ -0,0 +1,119 @@
+//! Display colors. Kept out of the renderer so per-ROM settings and headless captures can
+//! use them without a GPU.
+
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+pub const RGBA_BLACK: [u8; 4] = [0, 0, 0, 255];
+pub const RGBA_WHITE: [u8; 4] = [255, 255, 255, 255];
+
+/// Colors used to expand the CHIP-8 display into RGBA pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub background: [u8; 4],
+    /// Pixels lit on the first plane, the only one before XO-CHIP.
+    pub foreground: [u8; 4],
+    /// Pixels lit on the second XO-CHIP plane only.
+    pub plane2: [u8; 4],
+    /// Pixels lit on both XO-CHIP planes.
+    pub both: [u8; 4],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            background: RGBA_BLACK,
+            foreground: RGBA_WHITE,
+            plane2: [170, 170, 170, 255],
+            both: [85, 85, 85, 255],
+        }
+    }
+}
+
+impl Palette {
+    /// Color of a display pixel, see [`Display`](crate::emulator::Display) for the pixel values.
+    pub fn color(&self, pixel: u8) -> [u8; 4] {
+        match pixel & 0b11 {
+            0 => self.background,
+            1 => self.foreground,
+            2 => self.plane2,
+            _ => self.both,
+        }
+    }
+
+    /// Reads a palette from a JSON file, colors missing from the file keep their default.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Ready-made palettes imitating the screens CHIP-8 games were played on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    /// White on black, the default.
+    Classic,
+    /// Green monochrome CRT.
+    GreenPhosphor,
+    /// Amber monochrome CRT.
+    Amber,
+    /// Greenish handheld LCD.
+    Lcd,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [
+        Theme::Classic,
+        Theme::GreenPhosphor,
+        Theme::Amber,
+        Theme::Lcd,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Classic => "Classic",
+            Theme::GreenPhosphor => "Green phosphor",
+            Theme::Amber => "Amber",
+            Theme::Lcd => "LCD",
+        }
+    }
+
+    pub fn palette(self) -> Palette {
+        match self {
+            Theme::Classic => Palette::default(),
+            Theme::GreenPhosphor => Palette {
+                background: [10, 20, 12, 255],
+                foreground: [51, 255, 102, 255],
+                plane2: [26, 140, 60, 255],
+                both: [170, 255, 190, 255],
+            },
+            Theme::Amber => Palette {
+                background: [24, 14, 0, 255],
+                foreground: [255, 176, 0, 255],
+                plane2: [160, 100, 0, 255],
+                both: [255, 225, 150, 255],
+            },
+            Theme::Lcd => Palette {
+                background: [155, 188, 15, 255],
+                foreground: [15, 56, 15, 255],
+                plane2: [139, 172, 15, 255],
+                both: [48, 98, 48, 255],
+            },
+        }
+    }
+
+    /// Theme with exactly these colors, or `None` if they were customized.
+    pub fn detect(palette: &Palette) -> Option<Theme> {
+        Theme::ALL
+            .into_iter()
+            .find(|theme| theme.palette() == *palette)
+    }
+}
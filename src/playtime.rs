@@ -0,0 +1,144 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// How often accumulated play time is written out while a ROM keeps running.
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PlayRecord {
+    pub total_secs: u64,
+    /// Seconds since the Unix epoch.
+    pub last_played: u64,
+}
+
+/// Play time per ROM hash, stored in [`crate::resources::ResourceLocator::playtime`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Playtime {
+    pub records: BTreeMap<String, PlayRecord>,
+}
+
+impl Playtime {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn get(&self, rom_hash: &str) -> Option<&PlayRecord> {
+        self.records.get(rom_hash)
+    }
+
+    pub fn add(&mut self, rom_hash: &str, played: Duration) {
+        let record = self.records.entry(rom_hash.to_string()).or_default();
+        record.total_secs += played.as_secs();
+        record.last_played = unix_now();
+    }
+}
+
+/// Accumulates the time the emulator spends running a ROM and folds it into [`Playtime`].
+pub struct PlaytimeTracker {
+    pub playtime: Playtime,
+    path: PathBuf,
+    rom_hash: Option<String>,
+    pending: Duration,
+    last_save: Instant,
+}
+
+impl PlaytimeTracker {
+    /// Loads the history from `path`, where it's saved back to.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            playtime: Playtime::load(&path).unwrap_or_else(|err| {
+                log::error!("Failed to load play time: {err}");
+                Playtime::default()
+            }),
+            path,
+            rom_hash: None,
+            pending: Duration::ZERO,
+            last_save: Instant::now(),
+        }
+    }
+
+    /// Called once per UI frame with the loaded ROM and whether it is running.
+    pub fn tick(&mut self, rom_hash: Option<&str>, running: bool, dt: Duration) {
+        if self.rom_hash.as_deref() != rom_hash {
+            self.flush();
+            self.pending = Duration::ZERO;
+            self.rom_hash = rom_hash.map(str::to_string);
+        }
+        if running && self.rom_hash.is_some() {
+            self.pending += dt;
+        }
+        if self.last_save.elapsed() >= SAVE_INTERVAL {
+            self.flush();
+        }
+    }
+
+    /// Commits the whole seconds played so far and saves them.
+    pub fn flush(&mut self) {
+        self.last_save = Instant::now();
+        let Some(rom_hash) = &self.rom_hash else {
+            return;
+        };
+        let played = Duration::from_secs(self.pending.as_secs());
+        if played.is_zero() {
+            return;
+        }
+        self.pending -= played;
+        self.playtime.add(rom_hash, played);
+        if let Err(err) = self.playtime.save(&self.path) {
+            log::error!("Failed to save play time: {err}");
+        }
+    }
+}
+
+impl Drop for PlaytimeTracker {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+/// Formats a duration as `1h 05m` or `3m 20s`.
+pub fn format_duration(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes:02}m")
+    } else {
+        format!("{minutes}m {seconds:02}s")
+    }
+}
+
+/// Formats a Unix timestamp relative to now, e.g. `3 days ago`.
+pub fn format_ago(timestamp: u64) -> String {
+    let secs = unix_now().saturating_sub(timestamp);
+    match secs {
+        0..=59 => String::from("just now"),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86_399 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86_400),
+    }
+}
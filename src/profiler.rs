@@ -0,0 +1,202 @@
+//! Execution profile: how often each address and each kind of instruction runs, for finding
+//! hotspots in a ROM and picking a `cpf` that suits a game.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::emulator::MEMORY_SIZE;
+
+/// Instruction pattern `inst` belongs to, in the usual `8XY4` notation. Words that aren't
+/// instructions are counted as `DATA`.
+pub fn opcode_class(inst: u16) -> &'static str {
+    let n = inst & 0x000F;
+    let nn = inst & 0x00FF;
+    match inst & 0xF000 {
+        0x0000 => match inst {
+            0x00E0 => "00E0",
+            0x00EE => "00EE",
+            0x00FB => "00FB",
+            0x00FC => "00FC",
+            0x00FD => "00FD",
+            0x00FE => "00FE",
+            0x00FF => "00FF",
+            _ if inst & 0xFFF0 == 0x00C0 => "00CN",
+            _ if inst & 0xFFF0 == 0x00D0 => "00DN",
+            _ => "0NNN",
+        },
+        0x1000 => "1NNN",
+        0x2000 => "2NNN",
+        0x3000 => "3XNN",
+        0x4000 => "4XNN",
+        0x5000 => match n {
+            0x0 => "5XY0",
+            0x2 => "5XY2",
+            0x3 => "5XY3",
+            _ => "DATA",
+        },
+        0x6000 => "6XNN",
+        0x7000 => "7XNN",
+        0x8000 => match n {
+            0x0 => "8XY0",
+            0x1 => "8XY1",
+            0x2 => "8XY2",
+            0x3 => "8XY3",
+            0x4 => "8XY4",
+            0x5 => "8XY5",
+            0x6 => "8XY6",
+            0x7 => "8XY7",
+            0xE => "8XYE",
+            _ => "DATA",
+        },
+        0x9000 if n == 0 => "9XY0",
+        0xA000 => "ANNN",
+        0xB000 => "BNNN",
+        0xC000 => "CXNN",
+        0xD000 => "DXYN",
+        0xE000 => match nn {
+            0x9E => "EX9E",
+            0xA1 => "EXA1",
+            _ => "DATA",
+        },
+        0xF000 => match (inst, nn) {
+            (0xF000, _) => "F000",
+            (0xF002, _) => "F002",
+            (_, 0x01) => "FN01",
+            (_, 0x07) => "FX07",
+            (_, 0x0A) => "FX0A",
+            (_, 0x15) => "FX15",
+            (_, 0x18) => "FX18",
+            (_, 0x1E) => "FX1E",
+            (_, 0x29) => "FX29",
+            (_, 0x30) => "FX30",
+            (_, 0x33) => "FX33",
+            (_, 0x3A) => "FX3A",
+            (_, 0x55) => "FX55",
+            (_, 0x65) => "FX65",
+            (_, 0x75) => "FX75",
+            (_, 0x85) => "FX85",
+            _ => "DATA",
+        },
+        _ => "DATA",
+    }
+}
+
+/// Executed instruction counts per address and per [`opcode_class`], with the host time
+/// spent running them. Off by default, see [`Profiler::set_enabled`].
+#[derive(Clone, Debug, Default)]
+pub struct Profiler {
+    /// Whether listings shade addresses by [`Profiler::heat`].
+    pub show_heatmap: bool,
+    enabled: bool,
+    /// Indexed by address, allocated on the first recorded instruction.
+    by_address: Vec<u64>,
+    by_class: BTreeMap<&'static str, u64>,
+    instructions: u64,
+    max_count: u64,
+    host_time: Duration,
+    /// First and last frame an instruction was recorded in.
+    frames: Option<(u128, u128)>,
+}
+
+impl Profiler {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Forgets everything counted so far.
+    pub fn reset(&mut self) {
+        *self = Self {
+            show_heatmap: self.show_heatmap,
+            enabled: self.enabled,
+            ..Self::default()
+        };
+    }
+
+    pub(crate) fn record(&mut self, address: u16, inst: u16, frame: u128, host_time: Duration) {
+        if self.by_address.is_empty() {
+            self.by_address = vec![0; MEMORY_SIZE];
+        }
+        let count = &mut self.by_address[address as usize];
+        *count += 1;
+        self.max_count = self.max_count.max(*count);
+        *self.by_class.entry(opcode_class(inst)).or_default() += 1;
+        self.instructions += 1;
+        self.host_time += host_time;
+        self.frames = Some(match self.frames {
+            Some((first, _)) => (first, frame),
+            None => (frame, frame),
+        });
+    }
+
+    /// Times the instruction at `address` ran.
+    pub fn count(&self, address: u16) -> u64 {
+        self.by_address.get(address as usize).copied().unwrap_or(0)
+    }
+
+    /// How hot `address` is from 0 (never ran) to 1 (the hottest address), on a log scale
+    /// so loops don't wash out everything else.
+    pub fn heat(&self, address: u16) -> f32 {
+        let count = self.count(address);
+        if count == 0 {
+            return 0.0;
+        }
+        ((count as f64).ln_1p() / (self.max_count as f64).ln_1p()) as f32
+    }
+
+    /// The `limit` most executed addresses, hottest first.
+    pub fn hotspots(&self, limit: usize) -> Vec<(u16, u64)> {
+        let mut hotspots: Vec<(u16, u64)> = self
+            .by_address
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(address, count)| (address as u16, *count))
+            .collect();
+        hotspots.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hotspots.truncate(limit);
+        hotspots
+    }
+
+    /// Instruction counts per [`opcode_class`], most executed first.
+    pub fn classes(&self) -> Vec<(&'static str, u64)> {
+        let mut classes: Vec<(&'static str, u64)> = self
+            .by_class
+            .iter()
+            .map(|(class, count)| (*class, *count))
+            .collect();
+        classes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        classes
+    }
+
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Frames the recorded instructions ran over.
+    pub fn frames(&self) -> u64 {
+        self.frames
+            .map_or(0, |(first, last)| last.saturating_sub(first) as u64 + 1)
+    }
+
+    pub fn instructions_per_frame(&self) -> f64 {
+        match self.frames() {
+            0 => 0.0,
+            frames => self.instructions as f64 / frames as f64,
+        }
+    }
+
+    /// Host time spent executing the recorded instructions.
+    pub fn host_time(&self) -> Duration {
+        self.host_time
+    }
+
+    pub fn host_time_per_frame(&self) -> Duration {
+        match self.frames() {
+            0 => Duration::ZERO,
+            frames => self.host_time.div_f64(frames as f64),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::emulator::Emulator;
+
+/// How FX55/FX65 leave the I register after storing or loading registers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndexIncrement {
+    /// I is left untouched, as on SUPER-CHIP and most modern interpreters.
+    #[default]
+    None,
+    /// I += X, as on CHIP-48.
+    X,
+    /// I += X + 1, as on the COSMAC VIP.
+    XPlusOne,
+}
+
+impl IndexIncrement {
+    pub const ALL: [IndexIncrement; 3] = [
+        IndexIncrement::None,
+        IndexIncrement::X,
+        IndexIncrement::XPlusOne,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IndexIncrement::None => "Unchanged",
+            IndexIncrement::X => "I += X",
+            IndexIncrement::XPlusOne => "I += X + 1",
+        }
+    }
+}
+
+/// Where CXNN gets its random bytes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RngMode {
+    /// A generator on the host seeded with a random seed on every reset, unless one is
+    /// locked, see [`crate::emulator::Emulator::locked_seed`].
+    #[default]
+    Host,
+    /// The COSMAC VIP interpreter's routine, kept in the machine state: R9 is bumped every
+    /// frame and mixed with a byte of the page at [`crate::emulator::VIP_RNG_PAGE`]. On a
+    /// VIP that page holds the interpreter itself, here it holds whatever memory has there,
+    /// so the numbers match a real VIP only as far as that page does. The sequence never
+    /// depends on the host, so runs are repeatable.
+    #[serde(alias = "VipLfsr")]
+    Vip,
+}
+
+impl RngMode {
+    pub const ALL: [RngMode; 2] = [RngMode::Host, RngMode::Vip];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RngMode::Host => "Host",
+            RngMode::Vip => "COSMAC VIP",
+        }
+    }
+}
+
+/// Behaviours that differ between CHIP-8 interpreters. ROMs usually only run correctly
+/// with the quirks of the interpreter they were written for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quirks {
+    /// 8XY6/8XYE copy VY into VX before shifting, instead of shifting VX in place.
+    pub shift_swap: bool,
+    /// BNNN jumps to XNN + VX instead of NNN + V0.
+    pub complex_jump: bool,
+    /// How FX55/FX65 leave the I register.
+    pub index_increment: IndexIncrement,
+    /// 8XY1/8XY2/8XY3 reset VF to 0.
+    pub vf_reset: bool,
+    /// DXYN clips sprites at the screen edges instead of wrapping them around.
+    pub clipping: bool,
+    /// DXYN waits for the next frame, so at most one sprite is drawn per frame.
+    pub display_wait: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        QuirkPreset::Modern.quirks()
+    }
+}
+
+impl Quirks {
+    /// Reads quirks from a JSON file, fields missing from the file keep their default.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Interpreters whose quirks can be applied in one go, since ROM packs usually target one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuirkPreset {
+    /// Original COSMAC VIP interpreter.
+    CosmacVip,
+    /// CHIP-48 on the HP-48 calculators.
+    Chip48,
+    /// SUPER-CHIP 1.1, which kept the CHIP-48 quirks apart from FX55/FX65.
+    SuperChip,
+    /// XO-CHIP as implemented by Octo.
+    XoChip,
+    /// Behaviour of most modern interpreters, the emulator's default.
+    Modern,
+}
+
+impl QuirkPreset {
+    pub const ALL: [QuirkPreset; 5] = [
+        QuirkPreset::CosmacVip,
+        QuirkPreset::Chip48,
+        QuirkPreset::SuperChip,
+        QuirkPreset::XoChip,
+        QuirkPreset::Modern,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QuirkPreset::CosmacVip => "COSMAC VIP",
+            QuirkPreset::Chip48 => "CHIP-48",
+            QuirkPreset::SuperChip => "SUPER-CHIP 1.1",
+            QuirkPreset::XoChip => "XO-CHIP",
+            QuirkPreset::Modern => "Modern",
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            QuirkPreset::CosmacVip => Quirks {
+                shift_swap: true,
+                complex_jump: false,
+                index_increment: IndexIncrement::XPlusOne,
+                vf_reset: true,
+                clipping: true,
+                display_wait: true,
+            },
+            // CHIP-48 shifts VX in place, jumps to XNN + VX and bumps I by X only.
+            QuirkPreset::Chip48 => Quirks {
+                shift_swap: false,
+                complex_jump: true,
+                index_increment: IndexIncrement::X,
+                vf_reset: false,
+                clipping: true,
+                display_wait: false,
+            },
+            QuirkPreset::SuperChip => Quirks {
+                index_increment: IndexIncrement::None,
+                ..QuirkPreset::Chip48.quirks()
+            },
+            // XO-CHIP went back to the VIP shifts and FX55/FX65, but wraps sprites.
+            QuirkPreset::XoChip => Quirks {
+                shift_swap: true,
+                complex_jump: false,
+                index_increment: IndexIncrement::XPlusOne,
+                vf_reset: false,
+                clipping: false,
+                display_wait: false,
+            },
+            QuirkPreset::Modern => Quirks {
+                shift_swap: false,
+                complex_jump: false,
+                index_increment: IndexIncrement::None,
+                vf_reset: false,
+                clipping: true,
+                display_wait: false,
+            },
+        }
+    }
+
+    pub fn apply(self, emulator: &mut Emulator) {
+        emulator.quirks = self.quirks();
+    }
+
+    /// The preset matching the emulator's current quirks, if any.
+    pub fn detect(emulator: &Emulator) -> Option<QuirkPreset> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.quirks() == emulator.quirks)
+    }
+}
@@ -0,0 +1,152 @@
+use imgui::Ui;
+
+use crate::emulator::Emulator;
+
+/// Maximum number of candidates listed in the window, the rest are only counted.
+const MAX_LISTED: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Increased,
+    Decreased,
+    Unchanged,
+    Changed,
+    EqualTo(u8),
+}
+
+impl Comparison {
+    fn matches(self, previous: u8, current: u8) -> bool {
+        match self {
+            Comparison::Increased => current > previous,
+            Comparison::Decreased => current < previous,
+            Comparison::Unchanged => current == previous,
+            Comparison::Changed => current != previous,
+            Comparison::EqualTo(value) => current == value,
+        }
+    }
+}
+
+/// Cheat-search style RAM filter: snapshot memory, let the game run, then keep only the
+/// addresses whose value changed in the expected way. Repeating this narrows the candidates
+/// down to the variable holding lives, score, etc.
+#[derive(Default)]
+pub struct RamSearch {
+    snapshot: Vec<u8>,
+    candidates: Vec<u16>,
+    history: Vec<Vec<u16>>,
+    equal_to: i32,
+}
+
+impl RamSearch {
+    /// Starts a new search with every address as a candidate.
+    pub fn start(&mut self, memory: &[u8]) {
+        self.snapshot = memory.to_vec();
+        self.candidates = (0..memory.len()).map(|address| address as u16).collect();
+        self.history.clear();
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.snapshot.is_empty()
+    }
+
+    pub fn candidates(&self) -> &[u16] {
+        &self.candidates
+    }
+
+    /// Keeps the candidates matching `comparison` against the last snapshot, then snapshots again.
+    pub fn filter(&mut self, memory: &[u8], comparison: Comparison) {
+        let previous = std::mem::take(&mut self.candidates);
+        self.candidates = previous
+            .iter()
+            .copied()
+            .filter(|address| {
+                let address = *address as usize;
+                comparison.matches(self.snapshot[address], memory[address])
+            })
+            .collect();
+        self.history.push(previous);
+        self.snapshot = memory.to_vec();
+    }
+
+    /// Reverts the last filter.
+    pub fn undo(&mut self) {
+        if let Some(candidates) = self.history.pop() {
+            self.candidates = candidates;
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, emulator: &Emulator) {
+        ui.window("RAM Search").build(|| {
+            let memory = emulator.memory();
+            if ui.button(if self.is_active() { "Restart" } else { "Start" }) {
+                self.start(memory);
+            }
+            if !self.is_active() {
+                ui.text_wrapped(
+                    "Take a snapshot, play a bit, then filter by how the value changed.",
+                );
+                return;
+            }
+            ui.same_line();
+            ui.disabled(self.history.is_empty(), || {
+                if ui.button("Undo") {
+                    self.undo();
+                }
+            });
+
+            ui.separator();
+            let mut comparison = None;
+            if ui.button("Increased") {
+                comparison = Some(Comparison::Increased);
+            }
+            ui.same_line();
+            if ui.button("Decreased") {
+                comparison = Some(Comparison::Decreased);
+            }
+            ui.same_line();
+            if ui.button("Unchanged") {
+                comparison = Some(Comparison::Unchanged);
+            }
+            ui.same_line();
+            if ui.button("Changed") {
+                comparison = Some(Comparison::Changed);
+            }
+            ui.set_next_item_width(80.0);
+            ui.input_int("##equal_to", &mut self.equal_to).build();
+            self.equal_to = self.equal_to.clamp(0, 255);
+            ui.same_line();
+            if ui.button("Equal to") {
+                comparison = Some(Comparison::EqualTo(self.equal_to as u8));
+            }
+            if let Some(comparison) = comparison {
+                self.filter(memory, comparison);
+            }
+
+            ui.separator();
+            ui.text(format!("{} candidates", self.candidates.len()));
+            let table_flags = imgui::TableFlags::BORDERS_H
+                | imgui::TableFlags::BORDERS_V
+                | imgui::TableFlags::SCROLL_Y;
+            if let Some(_table) = ui.begin_table_with_flags("ram_search_table", 3, table_flags) {
+                ui.table_setup_column("Address");
+                ui.table_setup_column("Previous");
+                ui.table_setup_column("Current");
+                ui.table_setup_scroll_freeze(3, 1);
+                ui.table_headers_row();
+                for address in self.candidates.iter().take(MAX_LISTED) {
+                    let address = *address as usize;
+                    ui.table_next_row();
+                    ui.table_set_column_index(0);
+                    ui.text(format!("0x{:03X}", address));
+                    ui.table_set_column_index(1);
+                    ui.text(self.snapshot[address].to_string());
+                    ui.table_set_column_index(2);
+                    ui.text(memory[address].to_string());
+                }
+            }
+            if self.candidates.len() > MAX_LISTED {
+                ui.text_disabled(format!("... {} more", self.candidates.len() - MAX_LISTED));
+            }
+        });
+    }
+}
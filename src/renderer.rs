@@ -0,0 +1,752 @@
+use std::{borrow::Cow, fs, io, mem, path::Path};
+
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BufferAddress, BufferUsages, ImageDataLayout, Origin3d, SamplerDescriptor, ShaderStages,
+    TextureUsages, TextureViewDescriptor,
+};
+
+use serde::{Deserialize, Serialize};
+
+pub use crate::palette::{Palette, Theme, RGBA_BLACK, RGBA_WHITE};
+use crate::{
+    emulator::{Display, DISPLAY_SIZE, HIRES_DISPLAY_SIZE, PACKED_DISPLAY_WORDS},
+    megachip::MEGA_DISPLAY_SIZE,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    pub pos: [f32; 3],
+    pub uvs: [f32; 2],
+}
+
+/// Post-processing settings as laid out in the shader's `Effects` uniform.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct EffectUniforms {
+    strength: [f32; 4],
+    display: [f32; 4],
+    /// [`PostEffects::fade_frames`], up to [`MAX_FADE_FRAMES`].
+    fade: [f32; 4],
+    /// [`Palette::color`] of each pixel value, from 0 to 1.
+    palette: [[f32; 4]; 4],
+}
+
+/// Embedded so the renderer doesn't depend on the working directory.
+const SHADER_SOURCE: &str = include_str!("../resources/shader.wgsl");
+const PHOSPHOR_SOURCE: &str = include_str!("../resources/phosphor.wgsl");
+
+/// The phosphor texture counts the frames since each pixel was lit in 8 bits.
+const MAX_FADE_FRAMES: u32 = 255;
+
+/// Clockwise rotation of the display, for games designed for a vertical screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::None,
+        Rotation::Cw90,
+        Rotation::Cw180,
+        Rotation::Cw270,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rotation::None => "0",
+            Rotation::Cw90 => "90",
+            Rotation::Cw180 => "180",
+            Rotation::Cw270 => "270",
+        }
+    }
+
+    pub fn quarter_turns(self) -> usize {
+        match self {
+            Rotation::None => 0,
+            Rotation::Cw90 => 1,
+            Rotation::Cw180 => 2,
+            Rotation::Cw270 => 3,
+        }
+    }
+
+    /// Maps a keypad direction as seen on the rotated screen back to the direction the game
+    /// expects, using the usual 2/4/6/8 layout. Other keys are returned unchanged.
+    pub fn unrotate_key(self, key: u8) -> u8 {
+        // Directions in clockwise order: up, right, down, left.
+        const DIRECTIONS: [u8; 4] = [0x2, 0x6, 0x8, 0x4];
+        match DIRECTIONS.iter().position(|direction| *direction == key) {
+            Some(i) => DIRECTIONS[(i + 4 - self.quarter_turns()) % 4],
+            None => key,
+        }
+    }
+}
+
+/// Optional CRT-style post-processing. Each strength goes from 0 (off) to 1.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostEffects {
+    /// Off shows the raw nearest-neighbor image, whatever the strengths.
+    pub enabled: bool,
+    /// Darkens the gaps between pixel rows.
+    pub scanlines: f32,
+    /// Bends the picture like a CRT tube.
+    pub curvature: f32,
+    /// Darkens the corners.
+    pub vignette: f32,
+    /// Phosphor glow bleeding around lit pixels.
+    pub glow: f32,
+    /// Drawn frames pixels take to fade out after turning off, like the slow phosphor of
+    /// the original screens. Hides the flicker of sprites being erased and redrawn every
+    /// frame. 0 turns them off at once. Applies whether the CRT effects are enabled or not.
+    pub fade_frames: u32,
+}
+
+impl Default for PostEffects {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scanlines: 0.4,
+            curvature: 0.1,
+            vignette: 0.3,
+            glow: 0.3,
+            fade_frames: 0,
+        }
+    }
+}
+
+impl PostEffects {
+    /// Reads effect settings from a JSON file, fields missing from the file keep their default.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    fn uniforms(&self, display_size: (usize, usize)) -> EffectUniforms {
+        EffectUniforms {
+            strength: [self.scanlines, self.curvature, self.vignette, self.glow],
+            display: [
+                display_size.0 as f32,
+                display_size.1 as f32,
+                if self.enabled { 1.0 } else { 0.0 },
+                0.0,
+            ],
+            fade: [self.fade_frames.min(MAX_FADE_FRAMES) as f32, 0.0, 0.0, 0.0],
+            // Filled in by the renderer, along with whether it shows MegaChip8 colors.
+            palette: [[0.0; 4]; 4],
+        }
+    }
+}
+
+/// How the display is fitted into the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scaling {
+    /// Fills the whole window, distorting the pixels.
+    Stretch,
+    /// As large as fits with the right aspect ratio, letterboxed.
+    #[default]
+    Fit,
+    /// Largest whole multiple of the high resolution display that fits, so every pixel
+    /// has the same size.
+    Integer,
+}
+
+impl Scaling {
+    pub const ALL: [Scaling; 3] = [Scaling::Stretch, Scaling::Fit, Scaling::Integer];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Scaling::Stretch => "Stretch",
+            Scaling::Fit => "Keep aspect ratio",
+            Scaling::Integer => "Integer scaling",
+        }
+    }
+}
+
+/// Area of the surface the display is drawn in, in pixels from the top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    /// The whole surface.
+    pub fn full(surface: (u32, u32)) -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width: surface.0 as f32,
+            height: surface.1 as f32,
+        }
+    }
+
+    /// Centered area for the display on a surface of the given size. Integer scaling falls
+    /// back to fitting when the surface is smaller than the display.
+    pub fn new(surface: (u32, u32), rotation: Rotation, scaling: Scaling) -> Self {
+        Self::for_display(surface, HIRES_DISPLAY_SIZE, rotation, scaling)
+    }
+
+    /// Like [`Viewport::new`] for a display of another size, e.g. [`MEGA_DISPLAY_SIZE`].
+    pub fn for_display(
+        surface: (u32, u32),
+        display: (usize, usize),
+        rotation: Rotation,
+        scaling: Scaling,
+    ) -> Self {
+        let (mut width, mut height) = (display.0 as f32, display.1 as f32);
+        if rotation.quarter_turns() % 2 == 1 {
+            (width, height) = (height, width);
+        }
+        let (surface_width, surface_height) = (surface.0 as f32, surface.1 as f32);
+        let fit = (surface_width / width).min(surface_height / height);
+        let scale = match scaling {
+            Scaling::Stretch => return Self::full(surface),
+            Scaling::Fit => fit,
+            Scaling::Integer if fit >= 1.0 => fit.floor(),
+            Scaling::Integer => fit,
+        };
+        let (width, height) = (width * scale, height * scale);
+        Self {
+            x: ((surface_width - width) / 2.0).floor(),
+            y: ((surface_height - height) / 2.0).floor(),
+            width,
+            height,
+        }
+    }
+
+    /// Left, top, right and bottom edges in normalized device coordinates.
+    fn bounds(&self, surface: (u32, u32)) -> [f32; 4] {
+        let (surface_width, surface_height) = (surface.0.max(1) as f32, surface.1.max(1) as f32);
+        [
+            self.x / surface_width * 2.0 - 1.0,
+            1.0 - self.y / surface_height * 2.0,
+            (self.x + self.width) / surface_width * 2.0 - 1.0,
+            1.0 - (self.y + self.height) / surface_height * 2.0,
+        ]
+    }
+}
+
+/// Full-screen quad bounds, see [`Viewport::bounds`].
+const FULL_BOUNDS: [f32; 4] = [-1.0, 1.0, 1.0, -1.0];
+
+/// Quad vertices covering `bounds`, with texture coordinates turned by `rotation`.
+fn quad_vertices(rotation: Rotation, bounds: [f32; 4]) -> [Vertex; 4] {
+    // Corners in clockwise order starting top-left, each screen corner samples the
+    // texture corner `quarter_turns` steps counter-clockwise from it.
+    const UVS: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+    let uv = |corner: usize| UVS[(corner + 4 - rotation.quarter_turns()) % 4];
+    let [left, top, right, bottom] = bounds;
+    [
+        Vertex {
+            pos: [right, top, 0.0],
+            uvs: uv(1),
+        },
+        Vertex {
+            pos: [left, bottom, 0.0],
+            uvs: uv(3),
+        },
+        Vertex {
+            pos: [right, bottom, 0.0],
+            uvs: uv(2),
+        },
+        Vertex {
+            pos: [left, top, 0.0],
+            uvs: uv(0),
+        },
+    ]
+}
+
+/// Draws the emulator display as a fullscreen quad into any render pass.
+/// Shared by the windowed frontend and the offscreen renderer so both produce identical output.
+pub struct DisplayRenderer {
+    pub palette: Palette,
+    rotation: Rotation,
+    /// Edges of the quad in normalized device coordinates, see [`Viewport`].
+    bounds: [f32; 4],
+    /// Uniforms last written to `effects_buffer`.
+    effects: EffectUniforms,
+    effects_buffer: wgpu::Buffer,
+    /// See [`PostEffects::fade_frames`].
+    fade_frames: u32,
+    /// Display last packed into `frame_buffer`, it's left alone until it changes. The
+    /// palette is a uniform, so it doesn't matter.
+    uploaded: Option<Display>,
+    /// Phosphor passes still needed for the pixels that turned off to fade out.
+    fading: u32,
+    /// Resolution and [`Display::write_packed`] pixels, the `Frame` uniform of the
+    /// phosphor pass.
+    frame_buffer: wgpu::Buffer,
+    /// Pixel value each pixel was last lit with and the frames since, always at high
+    /// resolution. The phosphor pass reads one and writes the other, the shader looks
+    /// the colors up and fades them.
+    phosphor_views: [wgpu::TextureView; 2],
+    /// Phosphor pass reading each texture.
+    phosphor_bind_groups: [wgpu::BindGroup; 2],
+    /// Draw sampling each texture.
+    texture_bind_groups: [wgpu::BindGroup; 2],
+    /// Phosphor texture last written.
+    current: usize,
+    phosphor_pipeline: wgpu::RenderPipeline,
+    /// Full color MegaChip8 screen, drawn instead while its mode is on.
+    mega_texture_size: wgpu::Extent3d,
+    mega_texture: wgpu::Texture,
+    mega_bind_group: wgpu::BindGroup,
+    /// Whether the last update was [`DisplayRenderer::update_megachip`].
+    showing_megachip: bool,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl DisplayRenderer {
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER_SOURCE)),
+        });
+
+        let phosphor_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(PHOSPHOR_SOURCE)),
+        });
+
+        let texture_size = wgpu::Extent3d {
+            width: HIRES_DISPLAY_SIZE.0 as u32,
+            height: HIRES_DISPLAY_SIZE.1 as u32,
+            depth_or_array_layers: 1,
+        };
+        let mega_texture_size = wgpu::Extent3d {
+            width: MEGA_DISPLAY_SIZE.0 as u32,
+            height: MEGA_DISPLAY_SIZE.1 as u32,
+            depth_or_array_layers: 1,
+        };
+        let texture_sampler = device.create_sampler(&SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: None,
+            });
+        let effects = PostEffects::default().uniforms(DISPLAY_SIZE);
+        let effects_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("CHIP-8 Effects buffer"),
+            contents: bytemuck::bytes_of(&effects),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let create_texture = |size: wgpu::Extent3d, format, usage: TextureUsages, label: &str| {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: TextureUsages::TEXTURE_BINDING | usage,
+                label: Some(label),
+            });
+            let texture_view = texture.create_view(&TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: None,
+                layout: &texture_bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&texture_view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&texture_sampler),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: effects_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+            (texture, texture_view, bind_group)
+        };
+        let [(_, first_view, first_bind_group), (_, second_view, second_bind_group)] =
+            ["CHIP-8 Phosphor 0", "CHIP-8 Phosphor 1"].map(|label| {
+                create_texture(
+                    texture_size,
+                    wgpu::TextureFormat::Rgba8Unorm,
+                    TextureUsages::RENDER_ATTACHMENT,
+                    label,
+                )
+            });
+        let (mega_texture, _, mega_bind_group) = create_texture(
+            mega_texture_size,
+            wgpu::TextureFormat::Rgba8Unorm,
+            TextureUsages::COPY_DST,
+            "MegaChip8 Display diffuse",
+        );
+
+        let frame_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("CHIP-8 Frame buffer"),
+            size: (mem::size_of::<[u32; 4]>() + mem::size_of::<[u32; PACKED_DISPLAY_WORDS]>())
+                as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let phosphor_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: None,
+            });
+        let phosphor_bind_groups = [&first_view, &second_view].map(|previous| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: None,
+                layout: &phosphor_bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(previous),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: frame_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("CHIP-8 Vertex buffer"),
+            contents: bytemuck::cast_slice(&quad_vertices(Rotation::None, FULL_BOUNDS)),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("CHIP-8 Index buffer"),
+            contents: bytemuck::cast_slice(&[0u16, 1, 2, 1, 0, 3]),
+            usage: BufferUsages::INDEX,
+        });
+
+        let vertex_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+            ],
+        };
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[vertex_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let phosphor_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&phosphor_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let phosphor_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("CHIP-8 Phosphor pipeline"),
+            layout: Some(&phosphor_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &phosphor_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &phosphor_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::TextureFormat::Rgba8Unorm.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            palette: Palette::default(),
+            rotation: Rotation::None,
+            bounds: FULL_BOUNDS,
+            effects,
+            effects_buffer,
+            fade_frames: 0,
+            uploaded: None,
+            fading: 0,
+            frame_buffer,
+            phosphor_views: [first_view, second_view],
+            phosphor_bind_groups,
+            texture_bind_groups: [first_bind_group, second_bind_group],
+            current: 0,
+            phosphor_pipeline,
+            mega_texture_size,
+            mega_texture,
+            mega_bind_group,
+            showing_megachip: false,
+            vertex_buffer,
+            index_buffer,
+            render_pipeline,
+        }
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Rotates the quad's texture coordinates, only touching the GPU when the rotation changes.
+    pub fn set_rotation(&mut self, queue: &wgpu::Queue, rotation: Rotation) {
+        if rotation == self.rotation {
+            return;
+        }
+        self.rotation = rotation;
+        self.write_vertices(queue);
+    }
+
+    /// Draws the display in `viewport` of a surface of the given size instead of over the
+    /// whole of it. The area around is left as cleared.
+    pub fn set_viewport(&mut self, queue: &wgpu::Queue, surface: (u32, u32), viewport: Viewport) {
+        let bounds = viewport.bounds(surface);
+        if bounds == self.bounds {
+            return;
+        }
+        self.bounds = bounds;
+        self.write_vertices(queue);
+    }
+
+    fn write_vertices(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(
+            &self.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&quad_vertices(self.rotation, self.bounds)),
+        );
+    }
+
+    /// Post-processing applied from the next draw. Like the display resolution and palette
+    /// it feeds, the uniform buffer is only written when something changed.
+    pub fn set_effects(&mut self, queue: &wgpu::Queue, effects: &PostEffects) {
+        let display_size = (
+            self.effects.display[0] as usize,
+            self.effects.display[1] as usize,
+        );
+        let mut uniforms = effects.uniforms(display_size);
+        uniforms.display[3] = self.effects.display[3];
+        uniforms.palette = self.effects.palette;
+        self.write_effects(queue, uniforms);
+        self.fade_frames = effects.fade_frames;
+    }
+
+    fn write_effects(&mut self, queue: &wgpu::Queue, effects: EffectUniforms) {
+        if effects != self.effects {
+            self.effects = effects;
+            queue.write_buffer(&self.effects_buffer, 0, bytemuck::bytes_of(&effects));
+        }
+    }
+
+    /// Uploads the display packed two bits per pixel, see [`Display::write_packed`], and
+    /// records the phosphor pass into `encoder`. It decodes the pixels and counts the
+    /// frames since each was lit, for the shader to look the colors up in the palette and
+    /// fade them out. Called once per drawn frame, which is what pixels fade out over. The
+    /// upload is skipped when the display didn't change, and the pass too once every
+    /// pixel faded out.
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        display: &Display,
+    ) {
+        self.showing_megachip = false;
+        self.write_display(queue, display.size());
+
+        if self.uploaded.as_ref() != Some(display) {
+            self.uploaded = Some(*display);
+            let (width, height) = display.size();
+            let mut pixels = [0; PACKED_DISPLAY_WORDS];
+            display.write_packed(&mut pixels);
+            let size = [width as u32, height as u32, 0, 0];
+            queue.write_buffer(&self.frame_buffer, 0, bytemuck::cast_slice(&size));
+            queue.write_buffer(
+                &self.frame_buffer,
+                mem::size_of_val(&size) as BufferAddress,
+                bytemuck::cast_slice(&pixels),
+            );
+            // Pixels that just turned off are one frame in.
+            self.fading = self.fade_frames.min(MAX_FADE_FRAMES).saturating_sub(1);
+        } else if self.fading > 0 {
+            self.fading -= 1;
+        } else {
+            return;
+        }
+
+        let target = 1 - self.current;
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("CHIP-8 Phosphor pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.phosphor_views[target],
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&self.phosphor_pipeline);
+        pass.set_bind_group(0, &self.phosphor_bind_groups[self.current], &[]);
+        pass.draw(0..3, 0..1);
+        self.current = target;
+    }
+
+    /// Uploads a MegaChip8 frame, see [`crate::megachip::MegaChip::frame`], and draws it
+    /// instead of the display until the next [`DisplayRenderer::update`]. It's already in
+    /// color, so the palette and phosphor fade don't apply.
+    pub fn update_megachip(&mut self, queue: &wgpu::Queue, frame: &[[u8; 4]]) {
+        self.showing_megachip = true;
+        self.write_display(queue, MEGA_DISPLAY_SIZE);
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.mega_texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(frame),
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * MEGA_DISPLAY_SIZE.0 as u32),
+                rows_per_image: std::num::NonZeroU32::new(MEGA_DISPLAY_SIZE.1 as u32),
+            },
+            self.mega_texture_size,
+        );
+    }
+
+    /// Uniforms for what's drawn next: the resolution the effects work at, e.g. for the
+    /// scanlines, which texture is decoded and the palette.
+    fn write_display(&mut self, queue: &wgpu::Queue, (width, height): (usize, usize)) {
+        let mut effects = self.effects;
+        let megachip = if self.showing_megachip { 1.0 } else { 0.0 };
+        effects.display = [width as f32, height as f32, effects.display[2], megachip];
+        for (uniform, pixel) in effects.palette.iter_mut().zip(0..) {
+            *uniform = self
+                .palette
+                .color(pixel)
+                .map(|channel| channel as f32 / 255.0);
+        }
+        self.write_effects(queue, effects);
+    }
+
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        let bind_group = if self.showing_megachip {
+            &self.mega_bind_group
+        } else {
+            &self.texture_bind_groups[self.current]
+        };
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        rpass.draw_indexed(0..6, 0, 0..1);
+    }
+}
@@ -0,0 +1,74 @@
+//! Input recordings that play back exactly: the keypad state of every frame with the RNG
+//! seed, replayed on a fresh start of the same ROM.
+
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::quirks::{Quirks, RngMode};
+
+pub const REPLAY_EXTENSION: &str = "c8rep";
+
+/// One emulated frame, from a timer tick to the next.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    /// Keys held during the frame, bit N for key N.
+    pub keys: u16,
+    /// Instructions executed in the frame. Kept so playback doesn't depend on the CPU speed
+    /// or on how the host happened to split the frame.
+    pub instructions: u32,
+}
+
+/// Recorded play of a ROM, see [`crate::emulator::Emulator::start_input_recording`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    /// Hash of the ROM it was recorded on, see [`crate::rom::hash`].
+    pub rom_hash: String,
+    /// CXNN generator seed, see [`crate::emulator::Emulator::set_seed`].
+    pub seed: u64,
+    pub quirks: Quirks,
+    pub rng_mode: RngMode,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// What the emulator is doing with a replay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayStatus {
+    Off,
+    Recording {
+        frames: usize,
+    },
+    /// `frame` frames of `frames` have started playing.
+    Playing {
+        frame: usize,
+        frames: usize,
+    },
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) enum ReplayMode {
+    #[default]
+    Off,
+    Recording(Replay),
+    Playing {
+        replay: Replay,
+        /// Next frame to start.
+        next: usize,
+        /// Instructions of the current frame still to run, it is split when a break
+        /// pauses emulation in the middle of it.
+        remaining: u32,
+    },
+}
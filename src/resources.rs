@@ -0,0 +1,161 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "scripting")]
+use crate::script::SCRIPT_EXTENSION;
+use crate::{replay::REPLAY_EXTENSION, session::SESSION_EXTENSION};
+
+const APP_NAME: &str = "chip8_emulator";
+
+/// Finds the bundled resources and the per-user config directory, so the binary works
+/// from any working directory instead of only from the repository root.
+#[derive(Clone, Debug)]
+pub struct ResourceLocator {
+    resources: PathBuf,
+    config: PathBuf,
+}
+
+impl ResourceLocator {
+    pub fn new(resources: impl Into<PathBuf>, config: impl Into<PathBuf>) -> Self {
+        Self {
+            resources: resources.into(),
+            config: config.into(),
+        }
+    }
+
+    /// Looks for a `resources` directory next to the executable and in its parents (to
+    /// cover `target/debug` during development), then in the working directory.
+    pub fn detect() -> Self {
+        let exe_dir = env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf));
+        let candidates = exe_dir
+            .iter()
+            .flat_map(|dir| dir.ancestors().take(4))
+            .map(Path::to_path_buf)
+            .chain(env::current_dir().ok());
+        let mut resources = PathBuf::from("./resources");
+        for dir in candidates {
+            if dir.join("resources").is_dir() {
+                resources = dir.join("resources");
+                break;
+            }
+        }
+        Self::new(resources, config_dir())
+    }
+
+    pub fn resources(&self) -> &Path {
+        &self.resources
+    }
+
+    pub fn roms(&self) -> PathBuf {
+        self.resources.join("roms")
+    }
+
+    /// The CHIP-8 database's `programs.json`, see [`crate::metadata::RomDatabase`].
+    pub fn rom_database(&self) -> PathBuf {
+        self.resources.join("programs.json")
+    }
+
+    pub fn fonts(&self) -> PathBuf {
+        self.resources.join("font")
+    }
+
+    /// Per-user directory for settings, created on demand by whoever writes to it.
+    pub fn config(&self) -> &Path {
+        &self.config
+    }
+
+    pub fn imgui_ini(&self) -> PathBuf {
+        self.config.join("imgui.ini")
+    }
+
+    /// Global keyboard bindings, see [`crate::keymap::KeyBindings`].
+    pub fn key_bindings(&self) -> PathBuf {
+        self.config.join("keybindings.json")
+    }
+
+    /// Quirks applied at startup, see [`crate::quirks::Quirks::load`].
+    pub fn quirks(&self) -> PathBuf {
+        self.config.join("quirks.json")
+    }
+
+    /// Recent ROMs and per-ROM settings, see [`crate::config::Config`].
+    pub fn config_file(&self) -> PathBuf {
+        self.config.join("config.json")
+    }
+
+    /// Display colors, see [`crate::palette::Palette`].
+    pub fn palette(&self) -> PathBuf {
+        self.config.join("palette.json")
+    }
+
+    /// Where the CPU state is written when it halts, see [`crate::crash_dump`].
+    pub fn crash_dumps(&self) -> PathBuf {
+        self.config.join("crash_dumps")
+    }
+
+    /// CRT post-processing settings, see [`crate::renderer::PostEffects`].
+    pub fn effects(&self) -> PathBuf {
+        self.config.join("effects.json")
+    }
+
+    /// Per-ROM key mappings, see [`crate::keymap::KeyProfiles`].
+    pub fn keymaps(&self) -> PathBuf {
+        self.config.join("keymaps.json")
+    }
+
+    /// One file of labels and comments per ROM, see [`crate::annotations::Annotations`].
+    pub fn annotations(&self) -> PathBuf {
+        self.config.join("annotations")
+    }
+
+    /// Play time history, see [`crate::playtime::Playtime`].
+    pub fn playtime(&self) -> PathBuf {
+        self.config.join("playtime.json")
+    }
+
+    /// Save state slots, see [`crate::savestate`].
+    pub fn savestates(&self) -> PathBuf {
+        self.config.join("savestates")
+    }
+
+    /// Where the setup window saves sessions unless told otherwise, see
+    /// [`crate::session::Session`].
+    pub fn session(&self) -> PathBuf {
+        self.config.join(format!("session.{SESSION_EXTENSION}"))
+    }
+
+    /// Where input recordings go unless told otherwise, see [`crate::replay::Replay`].
+    pub fn replay(&self) -> PathBuf {
+        self.config.join(format!("replay.{REPLAY_EXTENSION}"))
+    }
+
+    /// Script the "Script" window offers to run unless told otherwise, see
+    /// [`crate::script::Script`].
+    #[cfg(feature = "scripting")]
+    pub fn script(&self) -> PathBuf {
+        self.config.join(format!("script.{SCRIPT_EXTENSION}"))
+    }
+}
+
+/// Platform config directory: `%APPDATA%` on Windows, `~/Library/Application Support`
+/// on macOS and `$XDG_CONFIG_HOME` (or `~/.config`) elsewhere.
+/// Falls back to the working directory if none of those are set.
+pub fn config_dir() -> PathBuf {
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    match base {
+        Some(base) => base.join(APP_NAME),
+        None => PathBuf::from("."),
+    }
+}
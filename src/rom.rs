@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Identifies a loaded ROM. The hash is used to key anything stored per game,
+/// so renaming or moving the file keeps its data.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RomInfo {
+    pub path: PathBuf,
+    pub hash: String,
+    pub size: usize,
+}
+
+impl RomInfo {
+    pub fn new(path: PathBuf, data: &[u8]) -> Self {
+        Self {
+            path,
+            hash: hash(data),
+            size: data.len(),
+        }
+    }
+}
+
+/// SHA-1 of the ROM contents as lowercase hex.
+pub fn hash(data: &[u8]) -> String {
+    sha1_smol::Sha1::from(data).digest().to_string()
+}
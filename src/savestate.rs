@@ -0,0 +1,50 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::emulator::{Emulator, SaveState};
+
+/// Number of save slots per ROM.
+pub const SLOT_COUNT: u8 = 10;
+
+impl SaveState {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// File in `dir` holding a ROM's save slot, keyed by ROM hash like the other per-game
+/// data. `dir` is normally [`crate::resources::ResourceLocator::savestates`].
+pub fn slot_path(dir: &Path, rom_hash: &str, slot: u8) -> PathBuf {
+    dir.join(format!("{rom_hash}.{slot}.json"))
+}
+
+fn loaded_rom_hash(emulator: &Emulator) -> io::Result<&str> {
+    emulator
+        .rom
+        .as_ref()
+        .map(|rom| rom.hash.as_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No ROM loaded."))
+}
+
+/// Saves the machine state into one of the loaded ROM's slots in `dir`.
+pub fn save_slot(emulator: &Emulator, dir: &Path, slot: u8) -> io::Result<()> {
+    let path = slot_path(dir, loaded_rom_hash(emulator)?, slot);
+    emulator.snapshot().save(path)
+}
+
+/// Restores one of the loaded ROM's slots, leaving the emulator paused like [`Emulator::restore`].
+pub fn load_slot(emulator: &mut Emulator, dir: &Path, slot: u8) -> io::Result<()> {
+    let path = slot_path(dir, loaded_rom_hash(emulator)?, slot);
+    let state = SaveState::load(path)?;
+    emulator.restore(&state);
+    Ok(())
+}
@@ -0,0 +1,282 @@
+//! Rhai (<https://rhai.rs>) scripts run as emulator [`Hooks`], for cheats that freeze a
+//! value, automated tests or bots. A script defines any of
+//!
+//! ```text
+//! fn on_frame() { ... }
+//! fn on_instruction(pc) { ... }
+//! fn on_memory_write(address, value) { ... }
+//! ```
+//!
+//! and reaches the machine through `reg(x)`, `set_reg(x, value)`, `reg_i()`,
+//! `set_reg_i(value)`, `pc()`, `peek(address)`, `poke(address, value)`, `key(k)`,
+//! `press(k)`, `release(k)`, `pixel(x, y)`, `frame()`, `delay_timer()`,
+//! `set_delay_timer(value)`, `sound_timer()`, `set_sound_timer(value)` and `pause()`.
+//! Top-level statements run once, before the first callback. `print` goes to the log.
+
+use std::{
+    fs, io,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Scope, AST, INT};
+
+use crate::{
+    emulator::{Display, Emulator},
+    hooks::Hooks,
+};
+
+pub const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Copy of the machine the script functions work on, since they can't hold the emulator.
+/// Read before each callback, the script's changes are applied after it.
+#[derive(Default)]
+struct Machine {
+    regs: [u8; 16],
+    reg_i: u16,
+    pc: u16,
+    delay_timer: u8,
+    sound_timer: u8,
+    frame: u64,
+    keypad: [bool; 16],
+    memory: Vec<u8>,
+    display: Display,
+    pokes: Vec<(u16, u8)>,
+    /// Keys pressed (true) or released by the script.
+    keys: Vec<(u8, bool)>,
+    pause: bool,
+}
+
+impl Machine {
+    fn read(&mut self, emulator: &Emulator) {
+        self.regs = *emulator.regs();
+        self.reg_i = emulator.reg_i();
+        self.pc = emulator.pc();
+        self.delay_timer = emulator.delay_timer();
+        self.sound_timer = emulator.sound_timer();
+        self.frame = emulator.frame_count() as u64;
+        self.keypad = *emulator.keypad();
+        self.memory.clear();
+        self.memory.extend_from_slice(emulator.memory());
+        self.display = emulator.display;
+    }
+
+    fn write(&mut self, emulator: &mut Emulator) {
+        for (reg, value) in self.regs.iter().enumerate() {
+            emulator.set_reg(reg, *value);
+        }
+        emulator.set_reg_i(self.reg_i);
+        emulator.set_delay_timer(self.delay_timer);
+        emulator.set_sound_timer(self.sound_timer);
+        for (address, value) in self.pokes.drain(..) {
+            // Out of range pokes were already dropped by `poke`.
+            let _ = emulator.poke(address, value);
+        }
+        for (key, down) in self.keys.drain(..) {
+            if down {
+                emulator.press_key(key);
+            } else {
+                emulator.release_key(key);
+            }
+        }
+        if std::mem::take(&mut self.pause) {
+            emulator.pause();
+            emulator.break_reason = Some(String::from("Paused by the script."));
+        }
+    }
+}
+
+type SharedMachine = Arc<Mutex<Machine>>;
+
+fn lock(machine: &SharedMachine) -> MutexGuard<'_, Machine> {
+    machine.lock().expect("Script callback panicked.")
+}
+
+/// A compiled script, install it with [`Emulator::set_hooks`].
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    machine: SharedMachine,
+    started: bool,
+    on_frame: bool,
+    on_instruction: bool,
+    on_memory_write: bool,
+    /// Why the script stopped, it isn't called again once it failed.
+    pub error: Option<String>,
+}
+
+impl Script {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(&fs::read_to_string(path)?)
+    }
+
+    /// Compiles `source`, fails with [`io::ErrorKind::InvalidData`] on syntax errors.
+    pub fn new(source: &str) -> io::Result<Self> {
+        let machine = SharedMachine::default();
+        let mut engine = Engine::new();
+        engine.on_print(|text| log::info!("Script: {text}"));
+        register_api(&mut engine, &machine);
+        let ast = engine
+            .compile(source)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        let defines = |name: &str| ast.iter_functions().any(|function| function.name == name);
+        Ok(Self {
+            on_frame: defines("on_frame"),
+            on_instruction: defines("on_instruction"),
+            on_memory_write: defines("on_memory_write"),
+            engine,
+            ast,
+            scope: Scope::new(),
+            machine,
+            started: false,
+            error: None,
+        })
+    }
+
+    /// Runs `call` on a copy of the machine and applies its changes. An error pauses
+    /// emulation and stops the script.
+    fn run(
+        &mut self,
+        emulator: &mut Emulator,
+        call: impl FnOnce(&mut Self) -> Result<(), Box<EvalAltResult>>,
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+        lock(&self.machine).read(emulator);
+        let result = self.start().and_then(|()| call(self));
+        lock(&self.machine).write(emulator);
+        if let Err(err) = result {
+            log::error!("Script stopped: {err}");
+            emulator.pause();
+            emulator.break_reason = Some(format!("Script error: {err}"));
+            self.error = Some(err.to_string());
+        }
+    }
+
+    fn start(&mut self) -> Result<(), Box<EvalAltResult>> {
+        if !self.started {
+            self.started = true;
+            self.engine.run_ast_with_scope(&mut self.scope, &self.ast)?;
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, args: impl FuncArgs) -> Result<(), Box<EvalAltResult>> {
+        let options = CallFnOptions::new().eval_ast(false);
+        self.engine
+            .call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, name, args)
+            .map(|_| ())
+    }
+}
+
+impl Hooks for Script {
+    fn on_frame(&mut self, emulator: &mut Emulator) {
+        // Also where the top-level statements run when there is no on_frame.
+        let defined = self.on_frame;
+        self.run(emulator, |script| {
+            if defined {
+                script.call("on_frame", ())?;
+            }
+            Ok(())
+        });
+    }
+
+    fn on_instruction(&mut self, emulator: &mut Emulator, pc: u16) {
+        if self.on_instruction {
+            self.run(emulator, |script| {
+                script.call("on_instruction", (pc as INT,))
+            });
+        }
+    }
+
+    fn on_memory_write(&mut self, emulator: &mut Emulator, address: u16, value: u8) {
+        if self.on_memory_write {
+            self.run(emulator, |script| {
+                script.call("on_memory_write", (address as INT, value as INT))
+            });
+        }
+    }
+}
+
+fn register_api(engine: &mut Engine, machine: &SharedMachine) {
+    let shared = machine.clone();
+    engine.register_fn("reg", move |x: INT| -> INT {
+        lock(&shared).regs[(x & 0xF) as usize] as INT
+    });
+    let shared = machine.clone();
+    engine.register_fn("set_reg", move |x: INT, value: INT| {
+        lock(&shared).regs[(x & 0xF) as usize] = value as u8;
+    });
+    let shared = machine.clone();
+    engine.register_fn("reg_i", move || -> INT { lock(&shared).reg_i as INT });
+    let shared = machine.clone();
+    engine.register_fn("set_reg_i", move |value: INT| {
+        lock(&shared).reg_i = value as u16;
+    });
+    let shared = machine.clone();
+    engine.register_fn("pc", move || -> INT { lock(&shared).pc as INT });
+    let shared = machine.clone();
+    engine.register_fn("peek", move |address: INT| -> INT {
+        let machine = lock(&shared);
+        usize::try_from(address)
+            .ok()
+            .and_then(|address| machine.memory.get(address))
+            .map_or(0, |&value| value as INT)
+    });
+    let shared = machine.clone();
+    engine.register_fn("poke", move |address: INT, value: INT| {
+        let mut machine = lock(&shared);
+        let Ok(address) = u16::try_from(address) else {
+            return;
+        };
+        if let Some(byte) = machine.memory.get_mut(address as usize) {
+            *byte = value as u8;
+            machine.pokes.push((address, value as u8));
+        }
+    });
+    let shared = machine.clone();
+    engine.register_fn("key", move |key: INT| -> bool {
+        lock(&shared).keypad[(key & 0xF) as usize]
+    });
+    let shared = machine.clone();
+    engine.register_fn("press", move |key: INT| {
+        lock(&shared).keys.push(((key & 0xF) as u8, true));
+    });
+    let shared = machine.clone();
+    engine.register_fn("release", move |key: INT| {
+        lock(&shared).keys.push(((key & 0xF) as u8, false));
+    });
+    let shared = machine.clone();
+    engine.register_fn("pixel", move |x: INT, y: INT| -> bool {
+        let machine = lock(&shared);
+        let (width, height) = machine.display.size();
+        match (usize::try_from(x), usize::try_from(y)) {
+            (Ok(x), Ok(y)) if x < width && y < height => machine.display[x][y] != 0,
+            _ => false,
+        }
+    });
+    let shared = machine.clone();
+    engine.register_fn("frame", move || -> INT { lock(&shared).frame as INT });
+    let shared = machine.clone();
+    engine.register_fn("delay_timer", move || -> INT {
+        lock(&shared).delay_timer as INT
+    });
+    let shared = machine.clone();
+    engine.register_fn("set_delay_timer", move |value: INT| {
+        lock(&shared).delay_timer = value as u8;
+    });
+    let shared = machine.clone();
+    engine.register_fn("sound_timer", move || -> INT {
+        lock(&shared).sound_timer as INT
+    });
+    let shared = machine.clone();
+    engine.register_fn("set_sound_timer", move |value: INT| {
+        lock(&shared).sound_timer = value as u8;
+    });
+    let shared = machine.clone();
+    engine.register_fn("pause", move || {
+        lock(&shared).pause = true;
+    });
+}
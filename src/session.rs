@@ -0,0 +1,116 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    emulator::{Emulator, SaveState, DEFAULT_LOAD_OFFSET},
+    machine::MachineProfile,
+    quirks::{Quirks, RngMode},
+    rom::{self, RomInfo},
+    timing::Clock,
+};
+
+pub const SESSION_EXTENSION: &str = "c8session";
+const SESSION_VERSION: u32 = 1;
+
+/// Emulation settings that aren't part of the machine state but affect how it runs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionSettings {
+    pub max_fps: i32,
+    pub cpf: i32,
+    /// Flattened so sessions saved before the other quirks existed still load.
+    #[serde(flatten)]
+    pub quirks: Quirks,
+    #[serde(default)]
+    pub rng_mode: RngMode,
+    #[serde(default)]
+    pub locked_seed: Option<u64>,
+    #[serde(default = "default_load_offset")]
+    pub load_offset: u16,
+    #[serde(default)]
+    pub profile: MachineProfile,
+    #[serde(default)]
+    pub clock: Clock,
+}
+
+fn default_load_offset() -> u16 {
+    DEFAULT_LOAD_OFFSET
+}
+
+/// Everything needed to pick a debugging session back up: the ROM, the machine state,
+/// the emulation settings and the window layout.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    pub rom: Option<RomInfo>,
+    pub state: SaveState,
+    pub settings: SessionSettings,
+    /// imgui ini data, so the debug windows come back where they were.
+    pub ui_layout: String,
+}
+
+impl Session {
+    pub fn capture(emulator: &Emulator, ui_layout: String) -> Self {
+        Self {
+            version: SESSION_VERSION,
+            rom: emulator.rom.clone(),
+            state: emulator.snapshot(),
+            settings: SessionSettings {
+                max_fps: emulator.max_fps,
+                cpf: emulator.cpf,
+                quirks: emulator.quirks,
+                rng_mode: emulator.rng_mode,
+                locked_seed: emulator.locked_seed,
+                load_offset: emulator.load_offset,
+                profile: emulator.profile,
+                clock: emulator.clock.clone(),
+            },
+            ui_layout,
+        }
+    }
+
+    /// Restores the session into the emulator, which is left paused.
+    /// The memory snapshot already contains the ROM, so the file is only checked for changes.
+    pub fn apply(&self, emulator: &mut Emulator) {
+        if let Some(rom) = &self.rom {
+            match fs::read(&rom.path) {
+                Ok(data) if rom::hash(&data) != rom.hash => log::warn!(
+                    "ROM {:?} changed since the session was saved, restoring the saved memory.",
+                    rom.path
+                ),
+                Err(err) => log::warn!("Session ROM {:?} is not available: {err}", rom.path),
+                _ => {}
+            }
+        }
+
+        emulator.max_fps = self.settings.max_fps;
+        emulator.cpf = self.settings.cpf;
+        emulator.quirks = self.settings.quirks;
+        emulator.rng_mode = self.settings.rng_mode;
+        emulator.locked_seed = self.settings.locked_seed;
+        emulator.load_offset = self.settings.load_offset;
+        emulator.profile = self.settings.profile;
+        emulator.clock = self.settings.clock.clone();
+        emulator.restore(&self.state);
+        emulator.rom = self.rom.clone();
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let session: Session = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if session.version > SESSION_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Session version {} is not supported.", session.version),
+            ));
+        }
+        Ok(session)
+    }
+}
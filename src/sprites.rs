@@ -0,0 +1,26 @@
+//! Reading sprites out of memory the way DXYN does, for the "Sprites" window.
+
+/// Rows of a sprite starting at `address`, bit 7 (or bit 15 when `wide`) being the leftmost
+/// pixel. Wide rows are 16 pixels from two bytes, like SUPER-CHIP's 16x16 DXY0 sprites.
+/// Bytes past the end of memory read as 0.
+pub fn sprite_rows(memory: &[u8], address: u16, height: usize, wide: bool) -> Vec<u16> {
+    let byte = |offset: usize| memory.get(address as usize + offset).copied().unwrap_or(0) as u16;
+    (0..height)
+        .map(|row| {
+            if wide {
+                byte(row * 2) << 8 | byte(row * 2 + 1)
+            } else {
+                byte(row)
+            }
+        })
+        .collect()
+}
+
+/// Bytes a sprite of `height` rows takes.
+pub fn sprite_size(height: usize, wide: bool) -> usize {
+    if wide {
+        height * 2
+    } else {
+        height
+    }
+}
@@ -0,0 +1,203 @@
+//! What a debugger step changed, so the debug windows can highlight it: registers,
+//! memory, the stack and display rows. Worked out by comparing the machine before and
+//! after the step, so it works whether or not the history is recording.
+
+use std::fmt;
+
+use crate::emulator::Emulator;
+
+/// Machine state a [`StepDiff`] compares against, taken before a step.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pc: u16,
+    reg_i: u16,
+    regs: [u8; 16],
+    stack: Vec<u16>,
+    delay_timer: u8,
+    sound_timer: u8,
+    mem: Vec<u8>,
+    hires: bool,
+    /// Column by column, like [`crate::emulator::Display`].
+    display: Vec<Vec<u8>>,
+}
+
+impl Snapshot {
+    pub fn of(emulator: &Emulator) -> Self {
+        Self {
+            pc: emulator.pc(),
+            reg_i: emulator.reg_i(),
+            regs: *emulator.regs(),
+            stack: emulator.stack().to_vec(),
+            delay_timer: emulator.delay_timer(),
+            sound_timer: emulator.sound_timer(),
+            mem: emulator.memory().to_vec(),
+            hires: emulator.display.hires,
+            display: emulator.display.iter().map(<[u8]>::to_vec).collect(),
+        }
+    }
+}
+
+/// Changes made by the last step, see [`Emulator::last_step`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StepDiff {
+    /// Where the step started.
+    pub pc: u16,
+    /// Which of V0-VF changed.
+    pub regs: [bool; 16],
+    pub reg_i: bool,
+    pub delay_timer: bool,
+    pub sound_timer: bool,
+    /// Addresses whose value changed, in order.
+    pub memory: Vec<u16>,
+    /// Return addresses pushed, bottom first.
+    pub pushed: Vec<u16>,
+    /// Return addresses popped, bottom first.
+    pub popped: Vec<u16>,
+    /// Display rows with a pixel that changed, in order. Every row when the resolution
+    /// switched.
+    pub rows: Vec<usize>,
+}
+
+impl StepDiff {
+    /// What changed between `before` and the emulator's current state.
+    pub fn between(before: &Snapshot, emulator: &Emulator) -> Self {
+        let regs = emulator.regs();
+        let memory = before
+            .mem
+            .iter()
+            .zip(emulator.memory())
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(address, _)| address as u16)
+            .collect();
+
+        let stack = emulator.stack();
+        let common = before
+            .stack
+            .iter()
+            .zip(stack)
+            .take_while(|(old, new)| old == new)
+            .count();
+
+        let (_, height) = emulator.display.size();
+        let rows = if before.hires != emulator.display.hires {
+            (0..height).collect()
+        } else {
+            (0..height)
+                .filter(|&y| {
+                    before
+                        .display
+                        .iter()
+                        .zip(emulator.display.iter())
+                        .any(|(old, new)| old[y] != new[y])
+                })
+                .collect()
+        };
+
+        Self {
+            pc: before.pc,
+            regs: std::array::from_fn(|i| before.regs[i] != regs[i]),
+            reg_i: before.reg_i != emulator.reg_i(),
+            delay_timer: before.delay_timer != emulator.delay_timer(),
+            sound_timer: before.sound_timer != emulator.sound_timer(),
+            memory,
+            pushed: stack[common..].to_vec(),
+            popped: before.stack[common..].to_vec(),
+            rows,
+        }
+    }
+
+    pub fn memory_changed(&self, address: u16) -> bool {
+        self.memory.binary_search(&address).is_ok()
+    }
+
+    pub fn row_changed(&self, y: usize) -> bool {
+        self.rows.binary_search(&y).is_ok()
+    }
+
+    /// Whether the step changed none of what is tracked. The PC moving isn't counted.
+    pub fn is_empty(&self) -> bool {
+        !self.regs.contains(&true)
+            && !self.reg_i
+            && !self.delay_timer
+            && !self.sound_timer
+            && self.memory.is_empty()
+            && self.pushed.is_empty()
+            && self.popped.is_empty()
+            && self.rows.is_empty()
+    }
+}
+
+/// Sorted values as comma separated ranges, e.g. `0x300-0x302, 0x310`.
+fn ranges(values: impl IntoIterator<Item = usize>, hex: bool) -> String {
+    let show = |value: usize| {
+        if hex {
+            format!("0x{value:03X}")
+        } else {
+            value.to_string()
+        }
+    };
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for value in values {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == value => *end = value,
+            _ => runs.push((value, value)),
+        }
+    }
+    runs.into_iter()
+        .map(|(start, end)| {
+            if start == end {
+                show(start)
+            } else {
+                format!("{}-{}", show(start), show(end))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// One line summary, e.g. `V3, I changed; wrote 0x300-0x302; drew rows 4-8`.
+impl fmt::Display for StepDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "nothing changed");
+        }
+        let mut registers: Vec<String> = (0..16)
+            .filter(|&i| self.regs[i])
+            .map(|i| format!("V{i:X}"))
+            .collect();
+        for (changed, name) in [
+            (self.reg_i, "I"),
+            (self.delay_timer, "DT"),
+            (self.sound_timer, "ST"),
+        ] {
+            if changed {
+                registers.push(name.to_string());
+            }
+        }
+        let mut parts = Vec::new();
+        if !registers.is_empty() {
+            parts.push(format!("{} changed", registers.join(", ")));
+        }
+        if !self.memory.is_empty() {
+            let addresses = self.memory.iter().map(|&address| address as usize);
+            parts.push(format!("wrote {}", ranges(addresses, true)));
+        }
+        for (addresses, verb) in [(&self.pushed, "pushed"), (&self.popped, "popped")] {
+            if !addresses.is_empty() {
+                let addresses: Vec<String> = addresses
+                    .iter()
+                    .map(|address| format!("0x{address:03X}"))
+                    .collect();
+                parts.push(format!("{verb} {}", addresses.join(", ")));
+            }
+        }
+        if !self.rows.is_empty() {
+            parts.push(format!(
+                "drew rows {}",
+                ranges(self.rows.iter().copied(), false)
+            ));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
@@ -0,0 +1,133 @@
+//! Symbol files: label names for ROM addresses, loaded into the debugger's annotations so
+//! listings show `draw_player` instead of `0x2A4`.
+//!
+//! Two line based formats are read, with `#` comments and blank lines ignored:
+//! Octo style `name 0x2A4` (or `:const name 0x2A4`, `name = 0x2A4`) and plain text
+//! `2A4 name`. The order is worked out per line, so files can mix them. Addresses are hex,
+//! with or without a `0x` or `$` prefix.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+    path::{Path, PathBuf},
+};
+
+pub const SYMBOL_EXTENSION: &str = "sym";
+
+/// Why a symbol file didn't parse, and the line it happened on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolError {
+    /// 1-based.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SymbolError {}
+
+/// Order symbols are written in, see [`write`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolFormat {
+    /// `name 0x2A4`
+    Octo,
+    /// `0x2A4 name`
+    Text,
+}
+
+impl SymbolFormat {
+    /// Octo for `.sym` files, text for anything else.
+    pub fn for_path(path: &Path) -> Self {
+        let sym = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(SYMBOL_EXTENSION));
+        if sym {
+            SymbolFormat::Octo
+        } else {
+            SymbolFormat::Text
+        }
+    }
+}
+
+/// Address a token spells, with how sure that is: 2 with an explicit prefix, 1 when it
+/// starts with a digit and 0 for names that happen to be hex, like `add`.
+fn parse_address(text: &str) -> Option<(u16, u8)> {
+    let prefixed = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_prefix('$'));
+    let digits = prefixed.unwrap_or(text);
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let certainty = if prefixed.is_some() {
+        2
+    } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+        1
+    } else {
+        0
+    };
+    Some((u16::from_str_radix(digits, 16).ok()?, certainty))
+}
+
+/// Parses a symbol file into labels by address. When an address has several names the
+/// last one wins.
+pub fn parse(text: &str) -> Result<BTreeMap<u16, String>, SymbolError> {
+    let mut labels = BTreeMap::new();
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| SymbolError {
+            line: index + 1,
+            message,
+        };
+        let code = line.split('#').next().unwrap_or_default().replace('=', " ");
+        let mut tokens: Vec<&str> = code.split_whitespace().collect();
+        if tokens.first() == Some(&":const") {
+            tokens.remove(0);
+        }
+        let [first, second] = tokens[..] else {
+            if tokens.is_empty() {
+                continue;
+            }
+            return Err(error(format!(
+                "expected an address and a name, found `{}`",
+                line.trim()
+            )));
+        };
+        let (address, name) = match (parse_address(first), parse_address(second)) {
+            (Some((address, _)), None) => (address, second),
+            (None, Some((address, _))) => (address, first),
+            (Some((address, certainty)), Some((_, other))) if certainty > other => {
+                (address, second)
+            }
+            (Some(_), Some((address, _))) => (address, first),
+            (None, None) => return Err(error(format!("no address in `{}`", line.trim()))),
+        };
+        labels.insert(address, name.to_string());
+    }
+    Ok(labels)
+}
+
+/// Writes labels in a format [`parse`] reads back, in address order.
+pub fn write(labels: &BTreeMap<u16, String>, format: SymbolFormat) -> String {
+    let mut text = String::new();
+    for (address, name) in labels {
+        // Names are single tokens in symbol files.
+        let name = name.split_whitespace().collect::<Vec<_>>().join("_");
+        let _ = match format {
+            SymbolFormat::Octo => writeln!(text, "{name} 0x{address:03X}"),
+            SymbolFormat::Text => writeln!(text, "0x{address:03X} {name}"),
+        };
+    }
+    text
+}
+
+/// Symbol file next to a ROM with the same name, e.g. `game.sym` for `game.ch8`.
+pub fn sidecar(rom_path: &Path) -> Option<PathBuf> {
+    let path = rom_path.with_extension(SYMBOL_EXTENSION);
+    path.is_file().then_some(path)
+}
@@ -0,0 +1,64 @@
+//! Pieces of the terminal frontend (`chip_8_tui`) that don't need a terminal: drawing the
+//! display with text and mapping typed characters to the keypad.
+
+use crate::emulator::Display;
+
+/// Keypad value for each key of the usual layout, by row:
+///
+/// ```text
+/// 1 2 3 4      1 2 3 C
+/// q w e r  ->  4 5 6 D
+/// a s d f      7 8 9 E
+/// z x c v      A 0 B F
+/// ```
+const KEYPAD_LAYOUT: [(char, u8); 16] = [
+    ('1', 0x1),
+    ('2', 0x2),
+    ('3', 0x3),
+    ('4', 0xC),
+    ('q', 0x4),
+    ('w', 0x5),
+    ('e', 0x6),
+    ('r', 0xD),
+    ('a', 0x7),
+    ('s', 0x8),
+    ('d', 0x9),
+    ('f', 0xE),
+    ('z', 0xA),
+    ('x', 0x0),
+    ('c', 0xB),
+    ('v', 0xF),
+];
+
+/// Keypad key for a typed character, ignoring case so caps lock doesn't matter.
+pub fn keypad_key(c: char) -> Option<u8> {
+    let c = c.to_ascii_lowercase();
+    KEYPAD_LAYOUT
+        .iter()
+        .find(|(key, _)| *key == c)
+        .map(|(_, value)| *value)
+}
+
+/// The display as lines of half-block characters, two pixel rows per line, so it keeps its
+/// aspect ratio in a terminal whose cells are about twice as tall as they are wide. Any
+/// lit plane counts as lit.
+pub fn half_blocks(display: &Display) -> Vec<String> {
+    let (width, height) = display.size();
+    (0..height)
+        .step_by(2)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    let top = display[x][y] != 0;
+                    let bottom = y + 1 < height && display[x][y + 1] != 0;
+                    match (top, bottom) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
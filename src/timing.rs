@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Rate of the delay and sound timers, fixed by the hardware.
+pub const TIMER_HZ: f64 = 60.0;
+/// Default CPU speed, the usual 10 instructions per 60 Hz frame.
+pub const DEFAULT_CPU_HZ: f64 = 600.0;
+/// Longest stretch of wall time run at once. Anything longer (a debugger stop, a suspended
+/// process) is dropped instead of running minutes of emulation in one go.
+pub const MAX_ELAPSED: Duration = Duration::from_millis(250);
+
+/// Speed multipliers offered in the UI for slow motion and turbo.
+pub const SPEED_PRESETS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+/// Speed while the fast-forward key is held, see [`Clock::speed_override`].
+pub const TURBO_SPEED: f64 = 8.0;
+/// Speed while slow motion is toggled on, see [`Clock::speed_override`].
+pub const SLOW_MOTION_SPEED: f64 = 0.25;
+
+/// Something the emulator has to do next, see [`Clock::next_tick`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tick {
+    Instruction,
+    Timer,
+}
+
+/// Turns elapsed wall time into CPU instructions and 60 Hz timer ticks, so emulation speed
+/// doesn't depend on how often or how regularly the emulator gets to run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Clock {
+    /// Instructions per second.
+    pub cpu_hz: f64,
+    /// Multiplier applied to both clocks, below 1 for slow motion and above for turbo.
+    pub speed: f64,
+    /// Speed set by the fast-forward and slow motion hotkeys, used instead of `speed`
+    /// while set, so `speed` is back in effect once they're let go.
+    #[serde(skip)]
+    pub speed_override: Option<f64>,
+    /// Emulated time not yet spent on instructions, in seconds.
+    #[serde(skip)]
+    cpu_pending: f64,
+    /// Emulated time not yet spent on timer ticks, in seconds.
+    #[serde(skip)]
+    timer_pending: f64,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            cpu_hz: DEFAULT_CPU_HZ,
+            speed: 1.0,
+            speed_override: None,
+            cpu_pending: 0.0,
+            timer_pending: 0.0,
+        }
+    }
+}
+
+impl Clock {
+    /// Speed multiplier in effect, the hotkey override if any.
+    pub fn effective_speed(&self) -> f64 {
+        self.speed_override.unwrap_or(self.speed)
+    }
+
+    /// Adds elapsed wall time, scaled by the speed multiplier in effect.
+    pub fn advance(&mut self, elapsed: Duration) {
+        let emulated = elapsed.min(MAX_ELAPSED).as_secs_f64() * self.effective_speed().max(0.0);
+        self.cpu_pending += emulated;
+        self.timer_pending += emulated;
+    }
+
+    /// Pops the next instruction or timer tick that is due, in the order they would have
+    /// happened on real hardware. Returns `None` once the elapsed time is used up.
+    pub fn next_tick(&mut self) -> Option<Tick> {
+        let cpu_period = 1.0 / self.cpu_hz.max(1.0);
+        let timer_period = 1.0 / TIMER_HZ;
+        // How long ago each became due, the one due first runs first.
+        let cpu_overdue = self.cpu_pending - cpu_period;
+        let timer_overdue = self.timer_pending - timer_period;
+        if cpu_overdue < 0.0 && timer_overdue < 0.0 {
+            return None;
+        }
+        if timer_overdue >= cpu_overdue {
+            self.timer_pending -= timer_period;
+            Some(Tick::Timer)
+        } else {
+            self.cpu_pending -= cpu_period;
+            Some(Tick::Instruction)
+        }
+    }
+
+    /// Forgets any pending time, e.g. after pausing or loading a ROM.
+    pub fn reset(&mut self) {
+        self.cpu_pending = 0.0;
+        self.timer_pending = 0.0;
+    }
+}
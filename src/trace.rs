@@ -0,0 +1,201 @@
+//! Instruction trace, for comparing a run against other emulators when a game misbehaves.
+
+use std::{collections::VecDeque, fmt, fs, io, path::Path};
+
+use crate::disassembler::Instruction;
+
+/// Instructions kept by default once tracing is on.
+pub const DEFAULT_TRACE_CAPACITY: usize = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+}
+
+/// Register an instruction changed, with its value before and after.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegisterChange {
+    pub register: Register,
+    pub old: u16,
+    pub new: u16,
+}
+
+impl fmt::Display for RegisterChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.register {
+            Register::V(reg) => write!(f, "V{reg:X}: {:02X} -> {:02X}", self.old, self.new),
+            Register::I => write!(f, "I: {:04X} -> {:04X}", self.old, self.new),
+        }
+    }
+}
+
+/// Registers that differ between two states, V0 to VF then I.
+pub fn register_changes(
+    (old_regs, old_i): (&[u8; 16], u16),
+    (new_regs, new_i): (&[u8; 16], u16),
+) -> Vec<RegisterChange> {
+    let mut changes: Vec<RegisterChange> = (0..16)
+        .filter(|&reg| old_regs[reg] != new_regs[reg])
+        .map(|reg| RegisterChange {
+            register: Register::V(reg as u8),
+            old: old_regs[reg] as u16,
+            new: new_regs[reg] as u16,
+        })
+        .collect();
+    if old_i != new_i {
+        changes.push(RegisterChange {
+            register: Register::I,
+            old: old_i,
+            new: new_i,
+        });
+    }
+    changes
+}
+
+/// One executed instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Frame the instruction ran in, see [`crate::emulator::Emulator::frame_count`].
+    pub frame: u64,
+    pub instruction: Instruction,
+    pub changes: Vec<RegisterChange>,
+}
+
+impl TraceEntry {
+    /// Register changes separated by commas, empty if none changed.
+    pub fn changes_text(&self) -> String {
+        self.changes
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>8}  {:04X}  {:<24}",
+            self.frame,
+            self.instruction.address,
+            self.instruction.to_string()
+        )?;
+        if !self.changes.is_empty() {
+            write!(f, "  {}", self.changes_text())?;
+        }
+        Ok(())
+    }
+}
+
+/// Ring buffer of the last executed instructions. Off by default, recording decodes every
+/// instruction. Once full the oldest instruction is dropped.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    enabled: bool,
+    entries: VecDeque<TraceEntry>,
+    capacity: usize,
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRACE_CAPACITY)
+    }
+}
+
+impl Trace {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            enabled: false,
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Recorded instructions, oldest first.
+    pub fn entries(&self) -> &VecDeque<TraceEntry> {
+        &self.entries
+    }
+
+    pub(crate) fn push(&mut self, entry: TraceEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// The trace as aligned text, one instruction per line.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for entry in &self.entries {
+            text += &entry.to_string();
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The trace as CSV with a header row.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("frame,pc,opcode,operand,mnemonic,changes\n");
+        for entry in &self.entries {
+            let instruction = &entry.instruction;
+            let operand = instruction
+                .operand
+                .map(|operand| format!("{operand:04X}"))
+                .unwrap_or_default();
+            csv += &format!(
+                "{},{:04X},{:04X},{operand},\"{}\",\"{}\"\n",
+                entry.frame,
+                instruction.address,
+                instruction.opcode,
+                instruction.mnemonic,
+                entry.changes_text()
+            );
+        }
+        csv
+    }
+
+    /// Writes the trace as CSV if `path` ends in `.csv`, as text otherwise.
+    pub fn export(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let csv = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        fs::write(path, if csv { self.to_csv() } else { self.to_text() })
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    cell::UnsafeCell,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+};
+
+const INDEX_MASK: u8 = 0b011;
+const FRESH: u8 = 0b100;
+
+/// Single producer, single consumer triple buffer.
+///
+/// The writer and the reader each own one slot, the third one is the "middle" slot they
+/// exchange through an atomic swap. Neither side ever blocks, the reader always sees a
+/// complete frame and the writer can publish as often as it likes.
+struct Shared<T> {
+    slots: [UnsafeCell<T>; 3],
+    /// Index of the middle slot, plus `FRESH` when it holds data the reader hasn't seen.
+    middle: AtomicU8,
+}
+
+// SAFETY: Each slot is only accessed by whoever currently owns its index. Ownership is
+// transferred exclusively through the `middle` swap, so no slot is accessed concurrently.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+pub struct TripleBufferWriter<T> {
+    shared: Arc<Shared<T>>,
+    index: u8,
+}
+
+pub struct TripleBufferReader<T> {
+    shared: Arc<Shared<T>>,
+    index: u8,
+}
+
+pub fn triple_buffer<T: Clone>(initial: T) -> (TripleBufferWriter<T>, TripleBufferReader<T>) {
+    let shared = Arc::new(Shared {
+        slots: [
+            UnsafeCell::new(initial.clone()),
+            UnsafeCell::new(initial.clone()),
+            UnsafeCell::new(initial),
+        ],
+        middle: AtomicU8::new(1),
+    });
+    (
+        TripleBufferWriter {
+            shared: shared.clone(),
+            index: 0,
+        },
+        TripleBufferReader { shared, index: 2 },
+    )
+}
+
+impl<T> TripleBufferWriter<T> {
+    /// Copies `value` into the back slot and makes it the newest frame.
+    pub fn write(&mut self, value: T) {
+        // SAFETY: The writer exclusively owns `self.index`.
+        unsafe { *self.shared.slots[self.index as usize].get() = value };
+        let previous = self
+            .shared
+            .middle
+            .swap(self.index | FRESH, Ordering::AcqRel);
+        self.index = previous & INDEX_MASK;
+    }
+}
+
+impl<T> TripleBufferReader<T> {
+    /// Returns the newest published frame, or the last one read if nothing new arrived.
+    pub fn read(&mut self) -> &T {
+        if self.shared.middle.load(Ordering::Relaxed) & FRESH != 0 {
+            let previous = self.shared.middle.swap(self.index, Ordering::AcqRel);
+            self.index = previous & INDEX_MASK;
+        }
+        // SAFETY: The reader exclusively owns `self.index`.
+        unsafe { &*self.shared.slots[self.index as usize].get() }
+    }
+
+    /// Whether a frame was published since the last `read`.
+    pub fn has_new(&self) -> bool {
+        self.shared.middle.load(Ordering::Relaxed) & FRESH != 0
+    }
+}
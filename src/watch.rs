@@ -0,0 +1,353 @@
+//! Watch expressions: small formulas over the machine state like `V3`, `[I+2]` or
+//! `V1*10+V2`, shown live in the "Watches" window and optionally breaking when they change.
+//!
+//! Operands are decimal or `0x` hex numbers, the registers `V0` to `VF`, `I`, `PC`, `SP`
+//! (stack depth), `DT` and `ST`, and memory bytes as `[address]` or `mem[address]`. The
+//! operators, loosest first, are `|`, `^`, `&`, `==` `!=`, `<` `<=` `>` `>=`, `<<` `>>`,
+//! `+` `-` and `*` `/` `%`, plus unary `-` and `~`. Comparisons give 1 or 0.
+
+use std::fmt;
+
+use crate::emulator::Emulator;
+
+/// Why an expression didn't parse, and where.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchError {
+    /// 1-based character the problem was found at.
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for WatchError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operand {
+    Register(usize),
+    Index,
+    Pc,
+    StackDepth,
+    DelayTimer,
+    SoundTimer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    Xor,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    ShiftLeft,
+    ShiftRight,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// Operator spellings, two character ones first so `<<` isn't read as `<`.
+const OPERATORS: [(&str, BinaryOp); 16] = [
+    ("<<", BinaryOp::ShiftLeft),
+    (">>", BinaryOp::ShiftRight),
+    ("<=", BinaryOp::LessEqual),
+    (">=", BinaryOp::GreaterEqual),
+    ("==", BinaryOp::Equal),
+    ("!=", BinaryOp::NotEqual),
+    ("<", BinaryOp::Less),
+    (">", BinaryOp::Greater),
+    ("|", BinaryOp::Or),
+    ("^", BinaryOp::Xor),
+    ("&", BinaryOp::And),
+    ("+", BinaryOp::Add),
+    ("-", BinaryOp::Sub),
+    ("*", BinaryOp::Mul),
+    ("/", BinaryOp::Div),
+    ("%", BinaryOp::Rem),
+];
+
+impl BinaryOp {
+    /// Higher binds tighter.
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::Xor => 2,
+            BinaryOp::And => 3,
+            BinaryOp::Equal | BinaryOp::NotEqual => 4,
+            BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => 5,
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => 6,
+            BinaryOp::Add | BinaryOp::Sub => 7,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 8,
+        }
+    }
+
+    /// `None` on overflow, division by zero or an out of range shift.
+    fn apply(self, lhs: i64, rhs: i64) -> Option<i64> {
+        let shift = || u32::try_from(rhs).ok();
+        match self {
+            BinaryOp::Or => Some(lhs | rhs),
+            BinaryOp::Xor => Some(lhs ^ rhs),
+            BinaryOp::And => Some(lhs & rhs),
+            BinaryOp::Equal => Some((lhs == rhs) as i64),
+            BinaryOp::NotEqual => Some((lhs != rhs) as i64),
+            BinaryOp::Less => Some((lhs < rhs) as i64),
+            BinaryOp::LessEqual => Some((lhs <= rhs) as i64),
+            BinaryOp::Greater => Some((lhs > rhs) as i64),
+            BinaryOp::GreaterEqual => Some((lhs >= rhs) as i64),
+            BinaryOp::ShiftLeft => lhs.checked_shl(shift()?),
+            BinaryOp::ShiftRight => lhs.checked_shr(shift()?),
+            BinaryOp::Add => lhs.checked_add(rhs),
+            BinaryOp::Sub => lhs.checked_sub(rhs),
+            BinaryOp::Mul => lhs.checked_mul(rhs),
+            BinaryOp::Div => lhs.checked_div(rhs),
+            BinaryOp::Rem => lhs.checked_rem(rhs),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Node {
+    Number(i64),
+    Operand(Operand),
+    /// Byte of memory at the address.
+    Memory(Box<Node>),
+    Negate(Box<Node>),
+    Not(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn evaluate(&self, emulator: &Emulator) -> Option<i64> {
+        match self {
+            Node::Number(value) => Some(*value),
+            Node::Operand(operand) => Some(match *operand {
+                Operand::Register(x) => emulator.regs()[x] as i64,
+                Operand::Index => emulator.reg_i() as i64,
+                Operand::Pc => emulator.pc() as i64,
+                Operand::StackDepth => emulator.stack().len() as i64,
+                Operand::DelayTimer => emulator.delay_timer() as i64,
+                Operand::SoundTimer => emulator.sound_timer() as i64,
+            }),
+            Node::Memory(address) => {
+                let address = usize::try_from(address.evaluate(emulator)?).ok()?;
+                emulator.memory().get(address).map(|&byte| byte as i64)
+            }
+            Node::Negate(value) => value.evaluate(emulator)?.checked_neg(),
+            Node::Not(value) => Some(!value.evaluate(emulator)?),
+            Node::Binary(op, lhs, rhs) => {
+                op.apply(lhs.evaluate(emulator)?, rhs.evaluate(emulator)?)
+            }
+        }
+    }
+}
+
+/// Recursive descent over the expression text, with precedence climbing for the binary
+/// operators.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn error(&self, message: impl Into<String>) -> WatchError {
+        WatchError {
+            column: self.text[..self.pos].chars().count() + 1,
+            message: message.into(),
+        }
+    }
+
+    /// Consumes `token` if the text continues with it.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), WatchError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{token}`")))
+        }
+    }
+
+    fn expression(&mut self, min_precedence: u8) -> Result<Node, WatchError> {
+        let mut lhs = self.unary()?;
+        loop {
+            self.skip_whitespace();
+            let Some(&(token, op)) = OPERATORS
+                .iter()
+                .find(|(token, _)| self.rest().starts_with(token))
+            else {
+                break;
+            };
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.pos += token.len();
+            let rhs = self.expression(op.precedence() + 1)?;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Node, WatchError> {
+        if self.eat("-") {
+            Ok(Node::Negate(Box::new(self.unary()?)))
+        } else if self.eat("~") {
+            Ok(Node::Not(Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn memory(&mut self) -> Result<Node, WatchError> {
+        let address = self.expression(0)?;
+        self.expect("]")?;
+        Ok(Node::Memory(Box::new(address)))
+    }
+
+    fn primary(&mut self) -> Result<Node, WatchError> {
+        if self.eat("(") {
+            let node = self.expression(0)?;
+            self.expect(")")?;
+            return Ok(node);
+        }
+        if self.eat("[") {
+            return self.memory();
+        }
+        let start = self.pos;
+        let word_len = self
+            .rest()
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(self.rest().len());
+        if word_len == 0 {
+            return Err(self.error("expected a number, register or memory access"));
+        }
+        let word = &self.text[start..start + word_len];
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            let value = match word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+                Some(hex) => i64::from_str_radix(hex, 16),
+                None => word.parse(),
+            };
+            let value = value.map_err(|_| self.error(format!("invalid number `{word}`")))?;
+            self.pos += word_len;
+            return Ok(Node::Number(value));
+        }
+        let upper = word.to_ascii_uppercase();
+        let operand = match upper.as_str() {
+            "MEM" => {
+                self.pos += word_len;
+                self.expect("[")?;
+                return self.memory();
+            }
+            "I" => Operand::Index,
+            "PC" => Operand::Pc,
+            "SP" => Operand::StackDepth,
+            "DT" => Operand::DelayTimer,
+            "ST" => Operand::SoundTimer,
+            register => match register.strip_prefix('V') {
+                Some(digit) if digit.len() == 1 => match usize::from_str_radix(digit, 16) {
+                    Ok(x) => Operand::Register(x),
+                    Err(_) => return Err(self.error(format!("unknown name `{word}`"))),
+                },
+                _ => return Err(self.error(format!("unknown name `{word}`"))),
+            },
+        };
+        self.pos += word_len;
+        Ok(Node::Operand(operand))
+    }
+}
+
+/// A parsed watch expression, see the module docs for the syntax.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expression {
+    text: String,
+    root: Node,
+}
+
+impl Expression {
+    pub fn parse(text: &str) -> Result<Self, WatchError> {
+        let mut parser = Parser { text, pos: 0 };
+        let root = parser.expression(0)?;
+        parser.skip_whitespace();
+        if !parser.rest().is_empty() {
+            return Err(parser.error("expected an operator"));
+        }
+        Ok(Self {
+            text: text.trim().to_string(),
+            root,
+        })
+    }
+
+    /// The expression as it was typed.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Value against the current machine state, `None` if it reads past the end of memory,
+    /// divides by zero or overflows.
+    pub fn evaluate(&self, emulator: &Emulator) -> Option<i64> {
+        self.root.evaluate(emulator)
+    }
+}
+
+/// An expression in the "Watches" window. The emulator checks the ones with
+/// [`Watch::break_on_change`] after every instruction, see [`Emulator::watches`].
+#[derive(Clone, Debug)]
+pub struct Watch {
+    pub expression: Expression,
+    pub break_on_change: bool,
+    /// Value at the last check, while breaking on change.
+    last: Option<i64>,
+}
+
+impl Watch {
+    pub fn new(expression: Expression) -> Self {
+        Self {
+            expression,
+            break_on_change: false,
+            last: None,
+        }
+    }
+
+    /// Evaluates the watch, returning the old and new value if it changed since the last
+    /// check. Values that fail to evaluate are skipped.
+    pub(crate) fn check(&mut self, emulator: &Emulator) -> Option<(i64, i64)> {
+        if !self.break_on_change {
+            self.last = None;
+            return None;
+        }
+        let value = self.expression.evaluate(emulator)?;
+        let old = self.last.replace(value)?;
+        (old != value).then_some((old, value))
+    }
+
+    /// Forgets the last value, so the next check starts over.
+    pub(crate) fn reset(&mut self) {
+        self.last = None;
+    }
+}
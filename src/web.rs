@@ -0,0 +1,165 @@
+//! Browser glue for the wasm32 build. There is no file system in the page, so ROMs come
+//! from the bundle, an `<input type="file">` or files dropped on the page.
+
+use std::{cell::RefCell, rc::Rc};
+
+use wasm_bindgen::{closure::Closure, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Document, DragEvent, Event, File, HtmlInputElement};
+use winit::{platform::web::WindowExtWebSys, window::Window};
+
+/// Id of the hidden file input behind the "Browse..." button.
+const ROM_INPUT_ID: &str = "chip8-rom-input";
+
+/// The ROMs in `resources/roms`, embedded since the page can't list a directory.
+pub const BUNDLED_ROMS: [(&str, &[u8]); 6] = [
+    (
+        "BC_test.ch8",
+        include_bytes!("../resources/roms/BC_test.ch8"),
+    ),
+    (
+        "IBM Logo.ch8",
+        include_bytes!("../resources/roms/IBM Logo.ch8"),
+    ),
+    (
+        "Trip8 Demo (2008) [Revival Studios].ch8",
+        include_bytes!("../resources/roms/Trip8 Demo (2008) [Revival Studios].ch8"),
+    ),
+    (
+        "br8kout.ch8",
+        include_bytes!("../resources/roms/br8kout.ch8"),
+    ),
+    ("snake.ch8", include_bytes!("../resources/roms/snake.ch8")),
+    (
+        "test_opcode.ch8",
+        include_bytes!("../resources/roms/test_opcode.ch8"),
+    ),
+];
+
+pub fn bundled_rom(name: &str) -> Option<&'static [u8]> {
+    BUNDLED_ROMS
+        .iter()
+        .find(|(bundled, _)| *bundled == name)
+        .map(|(_, data)| *data)
+}
+
+/// ROM read in the browser, waiting for the event loop to open it.
+pub struct PickedRom {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// ROMs picked or dropped since the event loop last looked. Filled from browser callbacks,
+/// which can't reach the frontend directly.
+#[derive(Clone, Default)]
+pub struct RomInbox(Rc<RefCell<Vec<PickedRom>>>);
+
+impl RomInbox {
+    pub fn take(&self) -> Vec<PickedRom> {
+        std::mem::take(&mut *self.0.borrow_mut())
+    }
+
+    fn read(&self, file: File) {
+        let inbox = self.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let name = file.name();
+            match JsFuture::from(file.array_buffer()).await {
+                Ok(buffer) => {
+                    let data = js_sys::Uint8Array::new(&buffer).to_vec();
+                    inbox.0.borrow_mut().push(PickedRom { name, data });
+                }
+                Err(err) => log::error!("Failed to read {name}: {err:?}"),
+            }
+        });
+    }
+}
+
+fn document() -> Document {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .expect("Not running in a web page.")
+}
+
+/// Adds the window's canvas to the page, winit creates it detached.
+pub fn attach_canvas(window: &Window) {
+    let body = document().body().expect("Page has no body.");
+    body.append_child(&window.canvas())
+        .expect("Failed to add the canvas to the page.");
+}
+
+/// Adds the hidden file input used by [`pick_rom`] and accepts ROMs dropped anywhere on the
+/// page. Both end up in the returned inbox.
+pub fn listen_for_roms() -> RomInbox {
+    let document = document();
+    let body = document.body().expect("Page has no body.");
+    let inbox = RomInbox::default();
+
+    let input: HtmlInputElement = document
+        .create_element("input")
+        .expect("Failed to create the file input.")
+        .dyn_into()
+        .expect("Created element isn't an input.");
+    input.set_id(ROM_INPUT_ID);
+    input.set_type("file");
+    input.set_accept(".ch8,.c8,.sc8,.xo8,.eti,.660,.8o");
+    input.set_hidden(true);
+    body.append_child(&input)
+        .expect("Failed to add the file input to the page.");
+
+    let on_change = {
+        let inbox = inbox.clone();
+        let input = input.clone();
+        Closure::wrap(Box::new(move |_: Event| {
+            if let Some(file) = input.files().and_then(|files| files.get(0)) {
+                inbox.read(file);
+            }
+            // Lets the same file be picked again.
+            input.set_value("");
+        }) as Box<dyn FnMut(Event)>)
+    };
+    input
+        .add_event_listener_with_callback("change", on_change.as_ref().unchecked_ref())
+        .expect("Failed to listen to the file input.");
+    // The listeners live as long as the page.
+    on_change.forget();
+
+    // The browser only fires drop if dragover is cancelled.
+    let on_drag_over = Closure::wrap(
+        Box::new(|event: DragEvent| event.prevent_default()) as Box<dyn FnMut(DragEvent)>
+    );
+    let on_drop = {
+        let inbox = inbox.clone();
+        Closure::wrap(Box::new(move |event: DragEvent| {
+            event.prevent_default();
+            let file = event
+                .data_transfer()
+                .and_then(|transfer| transfer.files())
+                .and_then(|files| files.get(0));
+            if let Some(file) = file {
+                inbox.read(file);
+            }
+        }) as Box<dyn FnMut(DragEvent)>)
+    };
+    document
+        .add_event_listener_with_callback("dragover", on_drag_over.as_ref().unchecked_ref())
+        .expect("Failed to listen for dragged files.");
+    document
+        .add_event_listener_with_callback("drop", on_drop.as_ref().unchecked_ref())
+        .expect("Failed to listen for dropped files.");
+    on_drag_over.forget();
+    on_drop.forget();
+
+    inbox
+}
+
+/// Opens the browser's file picker, the ROM arrives in the inbox from [`listen_for_roms`].
+/// Browsers only allow this shortly after a click or key press.
+pub fn pick_rom() {
+    match document()
+        .get_element_by_id(ROM_INPUT_ID)
+        .and_then(|input| input.dyn_into::<HtmlInputElement>().ok())
+    {
+        Some(input) => input.click(),
+        None => log::error!("ROM file input is missing, call listen_for_roms first."),
+    }
+}
@@ -0,0 +1,364 @@
+use std::{
+    cell::RefCell,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex, MutexGuard,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use web_time::Instant;
+
+use crate::{
+    emulator::{Display, Emulator, RunState},
+    triple_buffer::{triple_buffer, TripleBufferReader, TripleBufferWriter},
+};
+
+/// Messages sent from the UI thread to the emulation thread.
+pub enum EmulatorCommand {
+    KeyDown(u8),
+    KeyUp(u8),
+    OpenRom(PathBuf),
+    /// Opens a ROM that was already read, `name` stands in for its path.
+    LoadRom {
+        name: PathBuf,
+        data: Vec<u8>,
+    },
+    /// Runs backwards through the history while held, see [`Emulator::rewind_frame`].
+    Rewind(bool),
+    Pause,
+    Resume,
+    /// Restarts the loaded ROM, see [`Emulator::reload_rom`].
+    Reset,
+    /// Emulation speed multiplier, see [`crate::timing::Clock::speed`].
+    SetSpeed(f64),
+    /// Hotkey speed used instead, see [`crate::timing::Clock::speed_override`].
+    OverrideSpeed(Option<f64>),
+    /// Replies once every command sent before it has been applied.
+    Sync(Sender<()>),
+    Shutdown,
+}
+
+/// Snapshot of the emulator taken after every frame, readable from any thread without
+/// locking the emulator, see [`EmulationThread::status`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmulatorStatus {
+    pub state: RunState,
+    pub frame: u128,
+    pub pc: u16,
+    /// Speed multiplier in effect, see [`crate::timing::Clock::effective_speed`].
+    pub speed: f64,
+    /// Path of the loaded ROM.
+    pub rom: Option<PathBuf>,
+    /// Why the emulator stopped, see [`Emulator::break_reason`].
+    pub break_reason: Option<String>,
+    /// Whether the CPU halted on an error.
+    pub halted: bool,
+    /// Whether the ROM switched to MegaChip8's screen, see [`Emulator::megachip`].
+    pub megachip: bool,
+}
+
+impl EmulatorStatus {
+    fn of(emulator: &Emulator) -> Self {
+        Self {
+            state: emulator.state,
+            frame: emulator.frame_count(),
+            pc: emulator.pc(),
+            speed: emulator.clock.effective_speed(),
+            rom: emulator.rom.as_ref().map(|rom| rom.path.clone()),
+            break_reason: emulator.break_reason.clone(),
+            halted: emulator.halted().is_some(),
+            megachip: emulator.megachip().is_some(),
+        }
+    }
+}
+
+type SharedStatus = Arc<Mutex<EmulatorStatus>>;
+
+/// Cloneable, thread-safe handle on an emulation thread: sends commands and reads the
+/// status, e.g. from a benchmark or a remote control thread. Commands sent after the thread
+/// shut down are dropped.
+#[derive(Clone)]
+pub struct EmulatorHandle {
+    commands: Sender<EmulatorCommand>,
+    status: SharedStatus,
+}
+
+impl EmulatorHandle {
+    pub fn send(&self, command: EmulatorCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    /// Blocks until the emulation thread has applied every command sent so far.
+    pub fn sync(&self) {
+        let (tx, rx) = mpsc::channel();
+        self.send(EmulatorCommand::Sync(tx));
+        let _ = rx.recv();
+    }
+
+    pub fn status(&self) -> EmulatorStatus {
+        self.status
+            .lock()
+            .expect("Emulation thread panicked.")
+            .clone()
+    }
+}
+
+/// Runs the emulator on its own thread, so a slow UI frame or a window drag doesn't stall
+/// the game. The thread wakes up `max_fps` times per second, runs the emulator for the time
+/// elapsed since (see [`Emulator::run_for`]) and publishes the frame.
+///
+/// Input and control go through a command channel and finished frames are published through
+/// a triple buffer, so the renderer always reads a complete frame without ever blocking the
+/// emulation thread. The debug windows still need full access to the machine, for that
+/// [`EmulationThread::lock`] briefly locks the shared emulator, while
+/// [`EmulationThread::status`] and [`EmulatorHandle`] read a snapshot without locking it.
+///
+/// Where threads aren't available (the browser) the same interface runs the emulator on the
+/// calling thread instead, see [`EmulationThread::inline`].
+pub struct EmulationThread {
+    emulator: Arc<Mutex<Emulator>>,
+    frames: TripleBufferReader<Display>,
+    status: SharedStatus,
+    worker: Worker,
+}
+
+enum Worker {
+    Thread {
+        commands: Sender<EmulatorCommand>,
+        thread: Option<JoinHandle<()>>,
+    },
+    /// Commands are applied as they are sent and frames run when one is read.
+    Inline(RefCell<Runner>),
+}
+
+impl EmulationThread {
+    /// Runs the emulator on its own thread, or inline when targeting the web.
+    pub fn start(emulator: Emulator) -> Self {
+        if cfg!(target_arch = "wasm32") {
+            Self::inline(emulator)
+        } else {
+            Self::spawn(emulator)
+        }
+    }
+
+    pub fn spawn(emulator: Emulator) -> Self {
+        let (frame_tx, frames) = triple_buffer(emulator.display);
+        let status = Arc::new(Mutex::new(EmulatorStatus::of(&emulator)));
+        let emulator = Arc::new(Mutex::new(emulator));
+        let (commands, command_rx) = mpsc::channel();
+
+        let runner = Runner::new(emulator.clone(), frame_tx, status.clone());
+        let thread = thread::Builder::new()
+            .name(String::from("chip8-emulation"))
+            .spawn(move || run(runner, command_rx))
+            .expect("Failed to spawn emulation thread.");
+
+        Self {
+            emulator,
+            frames,
+            status,
+            worker: Worker::Thread {
+                commands,
+                thread: Some(thread),
+            },
+        }
+    }
+
+    /// Runs the emulator on the calling thread: commands are applied as soon as they are
+    /// sent, and [`EmulationThread::latest_frame`] runs the time elapsed since it was last
+    /// called. Call it once per displayed frame.
+    pub fn inline(emulator: Emulator) -> Self {
+        let (frame_tx, frames) = triple_buffer(emulator.display);
+        let status = Arc::new(Mutex::new(EmulatorStatus::of(&emulator)));
+        let emulator = Arc::new(Mutex::new(emulator));
+        let runner = Runner::new(emulator.clone(), frame_tx, status.clone());
+        Self {
+            emulator,
+            frames,
+            status,
+            worker: Worker::Inline(RefCell::new(runner)),
+        }
+    }
+
+    pub fn send(&self, command: EmulatorCommand) {
+        match &self.worker {
+            Worker::Thread { commands, .. } => {
+                // The thread only exits on Shutdown, a failed send means it already panicked.
+                if commands.send(command).is_err() {
+                    log::error!("Emulation thread is no longer running.");
+                }
+            }
+            Worker::Inline(runner) => {
+                runner.borrow_mut().apply(command);
+            }
+        }
+    }
+
+    /// Blocks until the emulation thread has applied every command sent so far.
+    pub fn sync(&self) {
+        let (tx, rx) = mpsc::channel();
+        self.send(EmulatorCommand::Sync(tx));
+        let _ = rx.recv();
+    }
+
+    /// Handle for other threads, `None` in inline mode where commands run on this thread.
+    pub fn handle(&self) -> Option<EmulatorHandle> {
+        match &self.worker {
+            Worker::Thread { commands, .. } => Some(EmulatorHandle {
+                commands: commands.clone(),
+                status: self.status.clone(),
+            }),
+            Worker::Inline(_) => None,
+        }
+    }
+
+    /// State as of the last frame or command, without waiting for the emulator lock.
+    pub fn status(&self) -> EmulatorStatus {
+        self.status
+            .lock()
+            .expect("Emulation thread panicked.")
+            .clone()
+    }
+
+    /// Full access to the machine, blocks the emulation thread while held.
+    pub fn lock(&self) -> MutexGuard<'_, Emulator> {
+        self.emulator.lock().expect("Emulation thread panicked.")
+    }
+
+    /// Returns the most recent frame produced by the emulation thread.
+    pub fn latest_frame(&mut self) -> &Display {
+        if let Worker::Inline(runner) = &mut self.worker {
+            runner.get_mut().run_frame();
+        }
+        self.frames.read()
+    }
+}
+
+impl Drop for EmulationThread {
+    fn drop(&mut self) {
+        if let Worker::Thread { commands, thread } = &mut self.worker {
+            let _ = commands.send(EmulatorCommand::Shutdown);
+            if let Some(thread) = thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Emulation loop state, shared by the thread and the inline mode.
+struct Runner {
+    emulator: Arc<Mutex<Emulator>>,
+    frames: TripleBufferWriter<Display>,
+    status: SharedStatus,
+    last_run: Instant,
+    rewinding: bool,
+}
+
+impl Runner {
+    fn new(
+        emulator: Arc<Mutex<Emulator>>,
+        frames: TripleBufferWriter<Display>,
+        status: SharedStatus,
+    ) -> Self {
+        Self {
+            emulator,
+            frames,
+            status,
+            last_run: Instant::now(),
+            rewinding: false,
+        }
+    }
+
+    /// Runs the emulator for the time elapsed since the last frame and publishes the result.
+    /// Returns the frame rate to run at.
+    fn run_frame(&mut self) -> i32 {
+        let now = Instant::now();
+        let elapsed = now - self.last_run;
+        self.last_run = now;
+        let (display, max_fps) = {
+            let mut emulator = self.emulator.lock().expect("UI thread panicked.");
+            if let RunState::Running = emulator.state {
+                if self.rewinding {
+                    emulator.rewind_frame();
+                } else {
+                    emulator.run_for(elapsed);
+                }
+            }
+            self.publish_status(&emulator);
+            (emulator.display, emulator.max_fps.max(1))
+        };
+        self.frames.write(display);
+        max_fps
+    }
+
+    fn apply(&mut self, command: EmulatorCommand) {
+        let mut emulator = self.emulator.lock().expect("UI thread panicked.");
+        match command {
+            EmulatorCommand::KeyDown(key) => emulator.press_key(key),
+            EmulatorCommand::KeyUp(key) => emulator.release_key(key),
+            EmulatorCommand::OpenRom(path) => {
+                emulator.reset();
+                emulator.load_font();
+                if let Err(err) = emulator.load_rom(path.to_string_lossy().into_owned()) {
+                    log::error!("Failed to open {}: {err}", path.display());
+                    emulator.break_reason = Some(format!("Failed to open ROM: {err}"));
+                }
+            }
+            EmulatorCommand::LoadRom { name, data } => {
+                emulator.reset();
+                emulator.load_font();
+                if let Err(err) = emulator.load_rom_data(&name, &data) {
+                    log::error!("Failed to open {}: {err}", name.display());
+                    emulator.break_reason = Some(format!("Failed to open ROM: {err}"));
+                }
+            }
+            EmulatorCommand::Rewind(held) => self.rewinding = held,
+            EmulatorCommand::Pause => emulator.pause(),
+            EmulatorCommand::Resume => emulator.resume(),
+            EmulatorCommand::Reset => {
+                if let Err(err) = emulator.reload_rom() {
+                    emulator.break_reason = Some(format!("Failed to reload ROM: {err}"));
+                }
+            }
+            EmulatorCommand::SetSpeed(speed) => emulator.clock.speed = speed,
+            EmulatorCommand::OverrideSpeed(speed) => emulator.clock.speed_override = speed,
+            EmulatorCommand::Sync(reply) => {
+                self.publish_status(&emulator);
+                let _ = reply.send(());
+            }
+            EmulatorCommand::Shutdown => {}
+        }
+        self.publish_status(&emulator);
+    }
+
+    fn publish_status(&self, emulator: &Emulator) {
+        *self.status.lock().expect("UI thread panicked.") = EmulatorStatus::of(emulator);
+    }
+}
+
+fn run(mut runner: Runner, commands: Receiver<EmulatorCommand>) {
+    let mut next_tick = Instant::now();
+    loop {
+        // Wait for the next tick, waking up early to apply commands as they arrive.
+        loop {
+            let timeout = next_tick.saturating_duration_since(Instant::now());
+            match commands.recv_timeout(timeout) {
+                Ok(EmulatorCommand::Shutdown) | Err(RecvTimeoutError::Disconnected) => return,
+                Ok(command) => runner.apply(command),
+                Err(RecvTimeoutError::Timeout) => break,
+            }
+        }
+
+        let max_fps = runner.run_frame();
+        let frame_time = Duration::from_secs_f64(1.0 / max_fps as f64);
+        next_tick += frame_time;
+        // Don't try to catch up after a long stall (debugger, suspended process), just resync.
+        let now = Instant::now();
+        if now > next_tick + frame_time * 4 {
+            next_tick = now;
+        }
+    }
+}
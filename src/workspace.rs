@@ -0,0 +1,394 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime},
+};
+
+use imgui::Ui;
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+pub const WORKSPACE_EXTENSION: &str = "c8ws";
+
+/// A homebrew project: the ROM being developed together with its source, symbols,
+/// notes and recorded test inputs. Paths are stored relative to the workspace file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Workspace {
+    pub name: String,
+    pub rom: PathBuf,
+    pub source: Option<PathBuf>,
+    pub symbols: Option<PathBuf>,
+    pub movies: Vec<PathBuf>,
+    pub notes: String,
+    /// External assembler invocation, `{source}` and `{rom}` are replaced by the paths.
+    /// For example `octo-cli {source} {rom}`.
+    pub assemble_command: String,
+}
+
+/// A label from the symbol map.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub address: u16,
+    pub name: String,
+}
+
+impl Workspace {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Resolves a workspace path against the directory holding the workspace file.
+    pub fn resolve(&self, workspace_path: &Path, path: &Path) -> PathBuf {
+        match workspace_path.parent() {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Runs the configured assembler and returns its combined output.
+    pub fn assemble(&self, workspace_path: &Path) -> io::Result<String> {
+        let source = self.source.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "Workspace has no source file.")
+        })?;
+        let source = self.resolve(workspace_path, source);
+        let rom = self.resolve(workspace_path, &self.rom);
+
+        let mut parts = self.assemble_command.split_whitespace().map(|part| {
+            part.replace("{source}", &source.to_string_lossy())
+                .replace("{rom}", &rom.to_string_lossy())
+        });
+        let program = parts.next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "No assemble command configured.",
+            )
+        })?;
+        let output = Command::new(program).args(parts).output()?;
+
+        let mut log = String::from_utf8_lossy(&output.stdout).into_owned();
+        log += &String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "Assembler failed ({}):\n{log}",
+                output.status
+            )));
+        }
+        Ok(log)
+    }
+
+    pub fn load_symbols(&self, workspace_path: &Path) -> io::Result<Vec<Symbol>> {
+        match &self.symbols {
+            Some(path) => Ok(parse_symbols(&fs::read_to_string(
+                self.resolve(workspace_path, path),
+            )?)),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Parses `address name` lines, with the address in hex (`0x` prefix optional).
+/// Blank lines and lines starting with `#` are ignored.
+pub fn parse_symbols(text: &str) -> Vec<Symbol> {
+    let mut symbols: Vec<Symbol> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (address, name) = line.split_once(char::is_whitespace)?;
+            let address = address.trim_start_matches("0x").trim_start_matches("0X");
+            Some(Symbol {
+                address: u16::from_str_radix(address, 16).ok()?,
+                name: name.trim().to_string(),
+            })
+        })
+        .collect();
+    symbols.sort_by_key(|symbol| symbol.address);
+    symbols
+}
+
+/// Polls a file's modification time to notice saves from an external editor.
+pub struct FileWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl FileWatcher {
+    const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            modified: modified_time(&path),
+            path,
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file was written since the last call, checked at most every
+    /// [`Self::POLL_INTERVAL`].
+    pub fn poll(&mut self) -> bool {
+        if self.last_check.elapsed() < Self::POLL_INTERVAL {
+            return false;
+        }
+        self.last_check = Instant::now();
+        let modified = modified_time(&self.path);
+        if modified.is_some() && modified != self.modified {
+            self.modified = modified;
+            return true;
+        }
+        false
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// What the project panel asks the frontend to do.
+pub enum ProjectAction {
+    Run(PathBuf),
+    /// Restart with a freshly assembled ROM after the source was saved.
+    HotRestart {
+        rom: PathBuf,
+        keep_breakpoints: bool,
+    },
+}
+
+/// UI state for the "Project" window.
+pub struct ProjectPanel {
+    pub path: String,
+    pub workspace: Option<Workspace>,
+    symbols: Vec<Symbol>,
+    source: String,
+    log: String,
+    /// Watches the source file while assemble-on-save is enabled.
+    watcher: Option<FileWatcher>,
+    keep_breakpoints: bool,
+}
+
+impl Default for ProjectPanel {
+    fn default() -> Self {
+        Self {
+            path: format!("./project.{WORKSPACE_EXTENSION}"),
+            workspace: None,
+            symbols: Vec::new(),
+            source: String::new(),
+            log: String::new(),
+            watcher: None,
+            keep_breakpoints: true,
+        }
+    }
+}
+
+impl ProjectPanel {
+    fn open(&mut self) {
+        match Workspace::load(&self.path) {
+            Ok(workspace) => {
+                self.workspace = Some(workspace);
+                self.reload_files();
+                self.log = format!("Opened {}.", self.path);
+            }
+            Err(err) => self.log = format!("Failed to open workspace: {err}"),
+        }
+    }
+
+    fn reload_files(&mut self) {
+        let Some(workspace) = &self.workspace else {
+            return;
+        };
+        let path = Path::new(&self.path);
+        self.symbols = workspace.load_symbols(path).unwrap_or_else(|err| {
+            self.log = format!("Failed to read symbols: {err}");
+            Vec::new()
+        });
+        self.source = workspace
+            .source
+            .as_ref()
+            .and_then(|source| fs::read_to_string(workspace.resolve(path, source)).ok())
+            .unwrap_or_default();
+    }
+
+    /// Starts or stops watching the workspace source for assemble-on-save.
+    fn set_watching(&mut self, enabled: bool) {
+        self.watcher = None;
+        if !enabled {
+            return;
+        }
+        let path = Path::new(&self.path);
+        let source = self
+            .workspace
+            .as_ref()
+            .and_then(|workspace| Some(workspace.resolve(path, workspace.source.as_ref()?)));
+        match source {
+            Some(source) => {
+                self.log = format!("Watching {}.", source.display());
+                self.watcher = Some(FileWatcher::new(source));
+            }
+            None => self.log = String::from("Workspace has no source file to watch."),
+        }
+    }
+
+    /// Reassembles and asks for a restart when the watched source was saved.
+    fn check_watcher(&mut self) -> Option<ProjectAction> {
+        if !self.watcher.as_mut()?.poll() {
+            return None;
+        }
+        let workspace = self.workspace.as_ref()?;
+        let path = Path::new(&self.path);
+        match workspace.assemble(path) {
+            Ok(log) => {
+                self.log = log;
+                Some(ProjectAction::HotRestart {
+                    rom: workspace.resolve(path, &workspace.rom),
+                    keep_breakpoints: self.keep_breakpoints,
+                })
+            }
+            Err(err) => {
+                self.log = err.to_string();
+                None
+            }
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui) -> Option<ProjectAction> {
+        let mut action = self.check_watcher();
+        let mut watching = self.watcher.is_some();
+        ui.window("Project").build(|| {
+            ui.input_text("Workspace", &mut self.path).build();
+            if ui.button("Open") {
+                self.open();
+                watching = false;
+            }
+            ui.same_line();
+            if ui.button("New") {
+                self.workspace = Some(Workspace {
+                    name: String::from("Untitled"),
+                    rom: PathBuf::from("game.ch8"),
+                    source: Some(PathBuf::from("game.8o")),
+                    ..Default::default()
+                });
+                self.reload_files();
+            }
+
+            let path = PathBuf::from(&self.path);
+            let Some(workspace) = &mut self.workspace else {
+                if !self.log.is_empty() {
+                    ui.text_wrapped(&self.log);
+                }
+                return;
+            };
+
+            ui.same_line();
+            if ui.button("Save") {
+                self.log = match workspace.save(&path) {
+                    Ok(()) => format!("Saved {}.", self.path),
+                    Err(err) => format!("Failed to save workspace: {err}"),
+                };
+            }
+
+            ui.separator();
+            ui.input_text("Name", &mut workspace.name).build();
+            path_input(ui, "ROM", &mut workspace.rom);
+            optional_path_input(ui, "Source", &mut workspace.source);
+            optional_path_input(ui, "Symbols", &mut workspace.symbols);
+            ui.input_text("Assembler", &mut workspace.assemble_command)
+                .hint("octo-cli {source} {rom}")
+                .build();
+
+            if ui.button("Assemble & Run") {
+                match workspace.assemble(&path) {
+                    Ok(log) => {
+                        self.log = log;
+                        action = Some(ProjectAction::Run(workspace.resolve(&path, &workspace.rom)));
+                    }
+                    Err(err) => self.log = err.to_string(),
+                }
+            }
+            ui.same_line();
+            if ui.button("Run") {
+                action = Some(ProjectAction::Run(workspace.resolve(&path, &workspace.rom)));
+            }
+            ui.checkbox("Assemble on save", &mut watching);
+            ui.same_line();
+            ui.checkbox("Keep breakpoints", &mut self.keep_breakpoints);
+
+            if let Some(_tab_bar) = ui.tab_bar("project_tabs") {
+                if let Some(_tab) = ui.tab_item("Notes") {
+                    ui.input_text_multiline("##notes", &mut workspace.notes, [-1.0, 200.0])
+                        .build();
+                }
+                if let Some(_tab) = ui.tab_item("Movies") {
+                    let mut remove = None;
+                    for (i, movie) in workspace.movies.iter_mut().enumerate() {
+                        let _id = ui.push_id_usize(i);
+                        path_input(ui, "##movie", movie);
+                        ui.same_line();
+                        if ui.small_button("Remove") {
+                            remove = Some(i);
+                        }
+                    }
+                    if let Some(i) = remove {
+                        workspace.movies.remove(i);
+                    }
+                    if ui.button("Add movie") {
+                        workspace.movies.push(PathBuf::new());
+                    }
+                }
+                if let Some(_tab) = ui.tab_item("Symbols") {
+                    for symbol in &self.symbols {
+                        ui.text(format!("0x{:03X}  {}", symbol.address, symbol.name));
+                    }
+                }
+                if let Some(_tab) = ui.tab_item("Source") {
+                    ui.child_window("source").build(|| {
+                        for (i, line) in self.source.lines().enumerate() {
+                            ui.text(format!("{:4}  {line}", i + 1));
+                        }
+                    });
+                }
+            }
+
+            if !self.log.is_empty() {
+                ui.separator();
+                ui.text_wrapped(&self.log);
+            }
+        });
+        if watching != self.watcher.is_some() {
+            self.set_watching(watching);
+        }
+        if action.is_some() {
+            self.reload_files();
+        }
+        action
+    }
+}
+
+fn path_input(ui: &Ui, label: &str, path: &mut PathBuf) {
+    let mut text = path.to_string_lossy().into_owned();
+    if ui.input_text(label, &mut text).build() {
+        *path = PathBuf::from(text);
+    }
+}
+
+fn optional_path_input(ui: &Ui, label: &str, path: &mut Option<PathBuf>) {
+    let mut text = path
+        .as_ref()
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if ui.input_text(label, &mut text).build() {
+        *path = if text.is_empty() {
+            None
+        } else {
+            Some(PathBuf::from(text))
+        };
+    }
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use chip_8_emulator::emulator::{Emulator, MemoryWatch, RegisterBreak, RunState};
+
+fn load(code: &[u16]) -> Emulator {
+    let mut emulator = common::load(code);
+    emulator.resume();
+    emulator
+}
+
+/// Counts V0 up forever, storing it at 0x300 every iteration.
+const COUNTER: [u16; 4] = [0xA300, 0x7001, 0xF055, 0x1202];
+
+#[test]
+fn breakpoint_pauses_before_the_instruction() {
+    let mut emulator = load(&COUNTER);
+    emulator.toggle_breakpoint(0x204);
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Paused));
+    assert_eq!(emulator.pc(), 0x204);
+    assert_eq!(emulator.memory()[0x300], 0);
+    assert!(emulator.take_break_hit());
+    assert!(!emulator.take_break_hit());
+
+    // Resuming executes the instruction under the breakpoint and stops there again.
+    emulator.resume();
+    emulator.step_frame();
+    assert_eq!(emulator.pc(), 0x204);
+    assert_eq!(emulator.memory()[0x300], 1);
+    assert_eq!(emulator.regs()[0], 2);
+}
+
+#[test]
+fn toggling_removes_the_breakpoint() {
+    let mut emulator = load(&COUNTER);
+    emulator.toggle_breakpoint(0x204);
+    emulator.toggle_breakpoint(0x204);
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Running));
+    assert!(emulator.break_reason.is_none());
+}
+
+#[test]
+fn memory_watch_on_value() {
+    let mut emulator = load(&COUNTER);
+    emulator
+        .memory_watches
+        .insert(0x300, MemoryWatch::OnValue(3));
+    assert!(!emulator.run_until(|emulator| emulator.regs()[0] == 5, 100));
+    assert!(matches!(emulator.state, RunState::Paused));
+    // Paused right after the FX55 that wrote the value.
+    assert_eq!(emulator.pc(), 0x206);
+    assert_eq!(emulator.memory()[0x300], 3);
+    assert!(emulator.break_reason.as_ref().unwrap().contains("0x300"));
+}
+
+#[test]
+fn memory_watch_on_write_ignores_other_addresses() {
+    let mut emulator = load(&COUNTER);
+    emulator.memory_watches.insert(0x301, MemoryWatch::OnWrite);
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Running));
+
+    emulator.memory_watches.insert(0x300, MemoryWatch::OnWrite);
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Paused));
+}
+
+#[test]
+fn clear_breakpoints_removes_everything() {
+    let mut emulator = load(&COUNTER);
+    emulator.toggle_breakpoint(0x204);
+    emulator.memory_watches.insert(0x300, MemoryWatch::OnWrite);
+    emulator.register_breaks[0] = RegisterBreak::OnChange;
+    emulator.clear_breakpoints();
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Running));
+}
+
+/// Sets V1 to 5, to 5 again, then to 6, and stops.
+const REWRITES: [u16; 4] = [0x6105, 0x6105, 0x6106, 0x1206];
+
+#[test]
+fn register_break_on_change() {
+    let mut emulator = load(&REWRITES);
+    emulator.register_breaks[1] = RegisterBreak::OnChange;
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Paused));
+    assert_eq!(emulator.pc(), 0x202);
+    assert_eq!(
+        emulator.break_reason.as_deref(),
+        Some("V1 changed 0x00 -> 0x05 near 0x200")
+    );
+
+    // Writing the same value again isn't a change.
+    emulator.resume();
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Paused));
+    assert_eq!(emulator.pc(), 0x206);
+    assert_eq!(emulator.regs()[1], 6);
+}
+
+#[test]
+fn register_break_on_value() {
+    let mut emulator = load(&REWRITES);
+    emulator.register_breaks[1] = RegisterBreak::OnValue(6);
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Paused));
+    assert_eq!(emulator.pc(), 0x206);
+    assert_eq!(emulator.regs()[1], 6);
+}
+
+#[test]
+fn register_break_on_value_ignores_rewriting_the_same_value() {
+    let mut emulator = load(&REWRITES);
+    emulator.register_breaks[1] = RegisterBreak::OnValue(5);
+    emulator.step_frame();
+    assert_eq!(emulator.pc(), 0x202);
+
+    emulator.resume();
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Running));
+    assert!(emulator.break_reason.is_none());
+    assert_eq!(emulator.regs()[1], 6);
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use chip_8_emulator::buzzer::{AudioSettings, BuzzerQueue, BuzzerTick, Synth, MAX_QUEUED_TICKS};
+
+/// Samples in one 60 Hz tick at normal speed.
+const TICK_SAMPLES: usize = 735;
+
+fn synth(queue: &BuzzerQueue) -> Synth {
+    let settings = AudioSettings {
+        volume: 1.0,
+        ..AudioSettings::default()
+    };
+    Synth::new(queue.clone(), Arc::new(Mutex::new(settings)))
+}
+
+fn tick(active: bool, speed: f64) -> BuzzerTick {
+    BuzzerTick {
+        active,
+        pattern: None,
+        speed,
+        sample: None,
+    }
+}
+
+fn sounding(samples: impl Iterator<Item = f32>) -> usize {
+    samples.filter(|sample| *sample != 0.0).count()
+}
+
+#[test]
+fn each_tick_plays_for_a_sixtieth_of_a_second() {
+    let queue = BuzzerQueue::default();
+    let mut synth = synth(&queue);
+    queue.push(tick(true, 1.0));
+    queue.push(tick(false, 1.0));
+    queue.push(tick(true, 1.0));
+    let samples: Vec<f32> = synth.by_ref().take(TICK_SAMPLES * 4).collect();
+    assert_eq!(
+        sounding(samples[..TICK_SAMPLES].iter().copied()),
+        TICK_SAMPLES
+    );
+    assert_eq!(
+        sounding(samples[TICK_SAMPLES..TICK_SAMPLES * 2].iter().copied()),
+        0
+    );
+    assert_eq!(
+        sounding(samples[TICK_SAMPLES * 2..].iter().copied()),
+        TICK_SAMPLES
+    );
+    // Nothing queued plays silence.
+    assert_eq!(sounding(synth.take(1000)), 0);
+}
+
+#[test]
+fn fast_forward_shortens_ticks() {
+    let queue = BuzzerQueue::default();
+    let mut synth = synth(&queue);
+    for _ in 0..4 {
+        queue.push(tick(true, 4.0));
+    }
+    assert_eq!(
+        sounding(synth.by_ref().take(TICK_SAMPLES * 2)),
+        TICK_SAMPLES
+    );
+}
+
+#[test]
+fn queue_drops_the_oldest_ticks() {
+    let queue = BuzzerQueue::default();
+    for i in 0..MAX_QUEUED_TICKS + 2 {
+        queue.push(tick(i % 2 == 0, 1.0));
+    }
+    assert_eq!(queue.len(), MAX_QUEUED_TICKS);
+    assert!(queue.pop().unwrap().active);
+}
+
+#[test]
+fn emulator_queues_a_tick_per_frame() {
+    // V0 = 3, sound timer = V0, then loops.
+    let mut emulator = common::load(&[0x6003, 0xF018, 0x1204]);
+    let queue = BuzzerQueue::default();
+    emulator.buzzer = Some(queue.clone());
+    for _ in 0..5 {
+        emulator.step_frame();
+    }
+    let ticks: Vec<bool> = std::iter::from_fn(|| queue.pop())
+        .map(|tick| tick.active)
+        .collect();
+    assert_eq!(ticks.len(), 5);
+    assert_eq!(ticks.iter().filter(|active| **active).count(), 3);
+}
+
+#[test]
+fn digitized_sound_is_stretched_over_the_tick() {
+    let queue = BuzzerQueue::default();
+    let mut synth = synth(&queue);
+    queue.push(BuzzerTick {
+        sample: Some(Arc::from([0.5, -0.5])),
+        ..tick(false, 1.0)
+    });
+    let samples: Vec<f32> = synth.by_ref().take(TICK_SAMPLES).collect();
+    assert!(samples[..TICK_SAMPLES / 2]
+        .iter()
+        .all(|sample| *sample == 0.5));
+    assert!(samples[TICK_SAMPLES / 2 + 1..]
+        .iter()
+        .all(|sample| *sample == -0.5));
+    assert_eq!(sounding(synth.take(100)), 0);
+}
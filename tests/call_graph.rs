@@ -0,0 +1,33 @@
+mod common;
+
+use chip_8_emulator::call_graph::stack_frames;
+
+// 0x200 calls 0x206, which calls 0x20A, which loops.
+const NESTED: [u16; 6] = [0x2206, 0x1200, 0x0000, 0x220A, 0x00EE, 0x120A];
+
+#[test]
+fn stack_frames_list_the_innermost_call_first() {
+    let mut emulator = common::load(&NESTED);
+    for _ in 0..2 {
+        emulator.step_instruction();
+    }
+    assert_eq!(emulator.pc(), 0x20A);
+
+    let frames = stack_frames(emulator.memory(), emulator.stack());
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].return_address, 0x208);
+    assert_eq!(frames[0].call_site.address, 0x206);
+    assert_eq!(frames[0].subroutine, Some(0x20A));
+    assert_eq!(frames[1].return_address, 0x202);
+    assert_eq!(frames[1].subroutine, Some(0x206));
+}
+
+#[test]
+fn call_graph_counts_calls_per_caller() {
+    let mut emulator = common::load(&NESTED);
+    for _ in 0..2 {
+        emulator.step_instruction();
+    }
+    let edges: Vec<_> = emulator.call_graph.edges().collect();
+    assert_eq!(edges, [(0x200, 0x206, 1), (0x206, 0x20A, 1)]);
+}
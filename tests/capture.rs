@@ -0,0 +1,101 @@
+use std::{fs::File, io::BufReader, time::Duration};
+
+use chip_8_emulator::{
+    capture::{capture_size, render, save_screenshot, Clip, ClipFormat},
+    emulator::Display,
+    palette::{Palette, RGBA_BLACK, RGBA_WHITE},
+};
+use image::{codecs::gif::GifDecoder, AnimationDecoder};
+
+const FRAME: Duration = Duration::from_micros(16_667);
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("chip8_capture_{}_{name}", std::process::id()))
+}
+
+/// Display with only the top left pixel lit.
+fn dot(hires: bool) -> Display {
+    let mut display = Display::new(hires);
+    display[0][0] = 1;
+    display
+}
+
+#[test]
+fn hires_pixels_are_half_the_size_of_lores_pixels() {
+    let (width, height) = capture_size(4);
+    assert_eq!((width, height), (256, 128));
+    let palette = Palette::default();
+
+    let lores = render(&dot(false), &palette, width, height);
+    assert_eq!(lores.get_pixel(3, 3).0, RGBA_WHITE);
+    assert_eq!(lores.get_pixel(4, 0).0, RGBA_BLACK);
+
+    let hires = render(&dot(true), &palette, width, height);
+    assert_eq!(hires.get_pixel(1, 1).0, RGBA_WHITE);
+    assert_eq!(hires.get_pixel(2, 0).0, RGBA_BLACK);
+}
+
+#[test]
+fn unchanged_frames_extend_the_previous_one() {
+    let mut clip = Clip::default();
+    clip.push(&Display::default(), FRAME);
+    clip.push(&Display::default(), FRAME);
+    clip.push(&dot(false), FRAME);
+    assert_eq!(clip.len(), 2);
+    assert_eq!(clip.frames()[0].duration, FRAME * 2);
+    assert_eq!(clip.duration(), FRAME * 3);
+}
+
+#[test]
+fn frame_delays_follow_the_running_total() {
+    let mut clip = Clip::default();
+    for frame in 0..6 {
+        clip.push(&dot(frame % 2 == 0), FRAME);
+    }
+    let centis: Vec<u32> = clip
+        .frame_delays(Duration::from_millis(10))
+        .into_iter()
+        .map(|(_, delay)| delay)
+        .collect();
+    assert_eq!(centis, [2, 1, 2, 2, 1, 2]);
+    assert_eq!(centis.iter().sum::<u32>(), 10);
+}
+
+#[test]
+fn clips_are_saved_in_the_format_of_their_extension() {
+    assert_eq!(
+        ClipFormat::from_path("clip.GIF".as_ref()),
+        Some(ClipFormat::Gif)
+    );
+    let mut clip = Clip::default();
+    clip.push(&Display::default(), FRAME * 3);
+    clip.push(&dot(false), FRAME * 3);
+
+    let gif = temp_path("clip.gif");
+    clip.save(&gif, &Palette::default(), 2).unwrap();
+    let decoder = GifDecoder::new(BufReader::new(File::open(&gif).unwrap())).unwrap();
+    let frames = decoder.into_frames().collect_frames().unwrap();
+    std::fs::remove_file(&gif).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].buffer().dimensions(), capture_size(2));
+    assert_eq!(frames[1].delay().numer_denom_ms(), (50, 1));
+
+    let apng = temp_path("clip.png");
+    clip.save(&apng, &Palette::default(), 2).unwrap();
+    let reader = png::Decoder::new(File::open(&apng).unwrap())
+        .read_info()
+        .unwrap();
+    let animation = reader.info().animation_control().copied();
+    std::fs::remove_file(&apng).unwrap();
+    assert_eq!(animation.map(|control| control.num_frames), Some(2));
+}
+
+#[test]
+fn screenshots_are_scaled() {
+    let path = temp_path("screenshot.png");
+    save_screenshot(&dot(false), &Palette::default(), 3, &path).unwrap();
+    let image = image::open(&path).unwrap().to_rgba8();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(image.dimensions(), (192, 96));
+    assert_eq!(image.get_pixel(2, 2).0, RGBA_WHITE);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use chip_8_emulator::{
+    cheats::{Cheat, CheatMode},
+    emulator::Emulator,
+};
+
+/// V0 counts up, I = 0x300, FX55 stores V0 there and loops.
+const COUNTER: [u16; 4] = [0xA300, 0x7001, 0xF055, 0x1202];
+/// I = 0x300, V0 = mem[0x300] in a loop.
+const READER: [u16; 3] = [0xA300, 0xF065, 0x1202];
+
+fn load(code: &[u16], mode: CheatMode) -> Emulator {
+    let mut emulator = common::load(code);
+    emulator.cpf = 4;
+    emulator.cheats.push(Cheat {
+        name: String::from("Lives"),
+        address: 0x300,
+        value: 0x63,
+        mode,
+        enabled: true,
+    });
+    emulator
+}
+
+#[test]
+fn instruction_cheats_freeze_memory() {
+    let mut emulator = load(&COUNTER, CheatMode::Instruction);
+    // The game never sees what it stored.
+    for _ in 0..10 {
+        emulator.step_instruction();
+        assert_eq!(emulator.memory()[0x300], 0x63);
+    }
+    assert!(emulator.cheats[0].enabled);
+}
+
+#[test]
+fn frame_cheats_are_written_every_frame() {
+    let mut emulator = load(&READER, CheatMode::Frame);
+    emulator.step_frame();
+    emulator.step_frame();
+    assert_eq!(emulator.regs()[0], 0x63);
+    emulator.poke(0x300, 5).unwrap();
+    emulator.step_frame();
+    assert_eq!(emulator.regs()[0], 0x63);
+    assert!(emulator.cheats[0].enabled);
+}
+
+#[test]
+fn once_cheats_disable_themselves() {
+    let mut emulator = load(&READER, CheatMode::Once);
+    emulator.step_frame();
+    emulator.step_frame();
+    assert_eq!(emulator.regs()[0], 0x63);
+    assert!(!emulator.cheats[0].enabled);
+    emulator.poke(0x300, 5).unwrap();
+    emulator.step_frame();
+    assert_eq!(emulator.regs()[0], 5);
+}
+
+#[test]
+fn disabled_cheats_do_nothing() {
+    let mut emulator = load(&COUNTER, CheatMode::Instruction);
+    emulator.cheats[0].enabled = false;
+    emulator.step_frame();
+    assert_eq!(emulator.memory()[0x300], 1);
+}
+
+#[test]
+fn parses_codes() {
+    assert_eq!(Cheat::parse_code("3A2 = 09"), Some((0x3A2, 0x09)));
+    assert_eq!(Cheat::parse_code("0x3a2:0xFF"), Some((0x3A2, 0xFF)));
+    assert_eq!(Cheat::parse_code("3A2=100"), None);
+    assert_eq!(Cheat::parse_code("3A2"), None);
+    assert_eq!(Cheat::parse_code("zz = 1"), None);
+    let cheat = Cheat {
+        address: 0x2F,
+        value: 7,
+        ..Cheat::default()
+    };
+    assert_eq!(Cheat::parse_code(&cheat.code()), Some((0x2F, 7)));
+}
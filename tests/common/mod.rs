@@ -0,0 +1,24 @@
+//! Test ROMs built from instruction words, shared by the integration tests.
+
+// Each test crate only uses some of the helpers.
+#![allow(dead_code)]
+
+use chip_8_emulator::emulator::Emulator;
+
+/// ROM bytes of `code`, one big-endian instruction per word.
+pub fn rom(code: &[u16]) -> Vec<u8> {
+    code.iter().flat_map(|inst| inst.to_be_bytes()).collect()
+}
+
+/// A new emulator with the font and `code` loaded as a CHIP-8 ROM.
+pub fn load(code: &[u16]) -> Emulator {
+    load_with(Emulator::new(), code)
+}
+
+/// Loads the font and `code` into `emulator`, for one set up before the ROM is loaded,
+/// e.g. seeded or with quirks applied.
+pub fn load_with(mut emulator: Emulator, code: &[u16]) -> Emulator {
+    emulator.load_font();
+    emulator.load_rom_data("test.ch8", &rom(code)).unwrap();
+    emulator
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use std::time::Duration;
+
+use chip_8_emulator::{
+    compare::{differences, Comparison, Difference},
+    emulator::Emulator,
+    quirks::QuirkPreset,
+};
+
+const SHIFT: [u16; 4] = [
+    0x6001, // V0 = 1
+    0x6106, // V1 = 6
+    0x8016, // V0 = V0 >> 1, or V1 >> 1 on the VIP
+    0x1206, // loop
+];
+
+#[test]
+fn pauses_at_the_first_divergence() {
+    let emulator = common::load(&SHIFT);
+    let mut comparison = Comparison::new(
+        &emulator,
+        QuirkPreset::Modern.quirks(),
+        QuirkPreset::CosmacVip.quirks(),
+    )
+    .unwrap();
+    comparison.run_for(Duration::from_millis(100));
+    assert!(comparison.paused);
+    let divergence = comparison.divergence().unwrap();
+    assert_eq!(divergence.frame, 1);
+    assert_eq!(
+        divergence.differences,
+        [
+            Difference::Register {
+                index: 0,
+                left: 0,
+                right: 3
+            },
+            Difference::Register {
+                index: 0xF,
+                left: 1,
+                right: 0
+            },
+        ]
+    );
+    assert_eq!(divergence.differences[0].to_string(), "V0 0x00 / 0x03");
+
+    comparison.restart().unwrap();
+    assert!(comparison.divergence().is_none());
+    assert_eq!(comparison.right.quirks, QuirkPreset::CosmacVip.quirks());
+}
+
+#[test]
+fn same_quirks_never_diverge() {
+    let emulator = common::load(&[
+        0xC0FF, // V0 = random
+        0xF029, // I = font of V0
+        0xD005, // draw it
+        0x1200, // again
+    ]);
+    let quirks = QuirkPreset::Modern.quirks();
+    let mut comparison = Comparison::new(&emulator, quirks, quirks).unwrap();
+    comparison.run_for(Duration::from_millis(200));
+    assert!(!comparison.paused);
+    assert!(comparison.divergence().is_none());
+    assert!(comparison.left.frame_count() >= 10);
+    assert!(differences(&comparison.left, &comparison.right).is_empty());
+}
+
+#[test]
+fn needs_a_loaded_rom() {
+    let quirks = QuirkPreset::Modern.quirks();
+    assert!(Comparison::new(&Emulator::new(), quirks, quirks).is_err());
+}
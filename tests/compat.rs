@@ -0,0 +1,98 @@
+use std::{fs, path::Path};
+
+use chip_8_emulator::{
+    compat::{CompatReport, CompatWarning},
+    emulator::Emulator,
+    machine::MachineProfile,
+    metadata::RomDatabase,
+    quirks::QuirkPreset,
+    rom,
+};
+
+const DATABASE: &str = r#"[
+    {
+        "title": "Spacefight",
+        "authors": ["A", "B"],
+        "roms": {"HASH": {"platforms": ["superchip"]}}
+    }
+]"#;
+
+fn database(data: &[u8]) -> RomDatabase {
+    RomDatabase::from_json(&DATABASE.replace("HASH", &rom::hash(data))).unwrap()
+}
+
+#[test]
+fn known_roms_get_their_title() {
+    let data = [0x12, 0x00];
+    let emulator = Emulator::new();
+    let report = CompatReport::check(Path::new("game.ch8"), &data, &emulator, &database(&data));
+    assert_eq!(report.title(), "Spacefight by A, B");
+
+    let unknown = CompatReport::check(
+        Path::new("roms/game.ch8"),
+        &data,
+        &emulator,
+        &RomDatabase::default(),
+    );
+    assert_eq!(unknown.metadata, None);
+    assert_eq!(unknown.title(), "game.ch8");
+}
+
+#[test]
+fn warns_about_the_wrong_quirks() {
+    let data = [0x12, 0x00];
+    let mut emulator = Emulator::new();
+    let report = CompatReport::check(Path::new("game.ch8"), &data, &emulator, &database(&data));
+    let expected = QuirkPreset::SuperChip.quirks();
+    assert_eq!(
+        report.warnings,
+        [CompatWarning::Quirks {
+            platform: String::from("superchip"),
+            quirks: expected,
+        }]
+    );
+    assert!(report.warnings[0].to_string().contains("SUPER-CHIP 1.1"));
+
+    QuirkPreset::SuperChip.apply(&mut emulator);
+    let report = CompatReport::check(Path::new("game.ch8"), &data, &emulator, &database(&data));
+    assert!(report.warnings.is_empty());
+}
+
+#[test]
+fn warns_when_the_rom_does_not_fit_the_profile() {
+    let data = vec![0; 0xD00];
+    let mut emulator = Emulator::new();
+    let database = RomDatabase::default();
+    let report = CompatReport::check(Path::new("big.ch8"), &data, &emulator, &database);
+    assert!(report.warnings.is_empty());
+
+    MachineProfile::CosmacVip.apply(&mut emulator);
+    let report = CompatReport::check(Path::new("big.ch8"), &data, &emulator, &database);
+    assert_eq!(
+        report.warnings,
+        [CompatWarning::TooLarge {
+            size: 0xD00,
+            max: 0xCA0,
+            profile: MachineProfile::CosmacVip,
+        }]
+    );
+    // The extension switches to the ETI-660 layout, which has even less room.
+    let report = CompatReport::check(Path::new("big.eti"), &data, &emulator, &database);
+    assert!(matches!(
+        report.warnings[..],
+        [CompatWarning::TooLarge {
+            max: 0x8A0,
+            profile: MachineProfile::Eti660,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn bundled_database_knows_the_bundled_roms() {
+    let database = RomDatabase::bundled();
+    let data = fs::read("resources/roms/IBM Logo.ch8").unwrap();
+    let metadata = database.get(&rom::hash(&data)).unwrap();
+    assert_eq!(metadata.title.as_deref(), Some("IBM Logo"));
+    assert_eq!(database.len(), 6);
+}
@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use chip_8_emulator::{
+    buzzer::{AudioSettings, Waveform},
+    config::{Config, RomSettings, RECENT_LIMIT},
+    emulator::Emulator,
+    palette::{Palette, Theme},
+    quirks::QuirkPreset,
+};
+
+#[test]
+fn recent_roms_are_most_recent_first_without_duplicates() {
+    let mut config = Config::default();
+    config.add_recent("a.ch8");
+    config.add_recent("b.ch8");
+    config.add_recent("a.ch8");
+    assert_eq!(config.recent_roms, ["a.ch8", "b.ch8"].map(PathBuf::from));
+
+    for i in 0..20 {
+        config.add_recent(format!("{i}.ch8"));
+    }
+    assert_eq!(config.recent_roms.len(), RECENT_LIMIT);
+    assert_eq!(config.recent_roms[0], PathBuf::from("19.ch8"));
+}
+
+#[test]
+fn rom_settings_override_only_what_they_set() {
+    let mut emulator = Emulator::new();
+    let mut palette = Palette::default();
+    let settings = RomSettings {
+        cpu_hz: Some(1000.0),
+        palette: Some(Theme::Amber.palette()),
+        ..RomSettings::default()
+    };
+    let quirks = emulator.quirks;
+    settings.apply(&mut emulator, &mut palette);
+    assert_eq!(emulator.clock.cpu_hz, 1000.0);
+    assert_eq!(emulator.quirks, quirks);
+    assert_eq!(palette, Theme::Amber.palette());
+}
+
+#[test]
+fn empty_rom_settings_are_forgotten() {
+    let mut config = Config::default();
+    let mut emulator = Emulator::new();
+    QuirkPreset::CosmacVip.apply(&mut emulator);
+    let settings = RomSettings::capture(&emulator, &Palette::default());
+    config.set_rom_settings("hash", settings.clone());
+    assert_eq!(config.rom_settings("hash"), Some(&settings));
+
+    config.set_rom_settings("hash", RomSettings::default());
+    assert_eq!(config.rom_settings("hash"), None);
+}
+
+#[test]
+fn config_round_trips_through_the_file() {
+    let path = std::env::temp_dir().join(format!("chip8_config_{}.json", std::process::id()));
+    assert!(Config::load(&path).unwrap().recent_roms.is_empty());
+
+    let mut config = Config::default();
+    config.add_recent("game.ch8");
+    config.set_rom_settings(
+        "hash",
+        RomSettings {
+            cpu_hz: Some(900.0),
+            ..RomSettings::default()
+        },
+    );
+    config.window_size = Some((960, 540));
+    config.audio.waveform = Waveform::Sine;
+    config.audio.volume = 0.5;
+    config.audio.frequency = 660.0;
+    config.rom_dir = Some(PathBuf::from("roms/games"));
+    config.save(&path).unwrap();
+    let loaded = Config::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.recent_roms, config.recent_roms);
+    assert_eq!(loaded.rom_settings, config.rom_settings);
+    assert_eq!(loaded.window_size, config.window_size);
+    assert_eq!(loaded.audio, config.audio);
+    assert_eq!(loaded.rom_dir, config.rom_dir);
+}
+
+#[test]
+fn settings_missing_from_an_older_file_keep_their_defaults() {
+    let path = std::env::temp_dir().join(format!("chip8_old_config_{}.json", std::process::id()));
+    std::fs::write(&path, r#"{ "recent_roms": ["game.ch8"] }"#).unwrap();
+    let loaded = Config::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.recent_roms, [PathBuf::from("game.ch8")]);
+    assert_eq!(loaded.window_size, None);
+    assert_eq!(loaded.audio, AudioSettings::default());
+    assert_eq!(loaded.rom_dir, None);
+}
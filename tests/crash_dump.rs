@@ -0,0 +1,64 @@
+mod common;
+
+use chip_8_emulator::crash_dump::CrashDump;
+
+#[test]
+fn dumps_only_halted_emulators() {
+    let emulator = common::load(&[0x1200]);
+    assert_eq!(CrashDump::capture(&emulator), None);
+}
+
+#[test]
+fn dump_shows_registers_stack_and_code_around_pc() {
+    let mut emulator = common::load(&[
+        0x6A42, // 200: VA = 0x42
+        0x2206, // 202: call 0x206
+        0x0000, // 204
+        0xA123, // 206: I = 0x123
+        0x5AB1, // 208: unknown
+        0x1208, // 20A
+    ]);
+    for _ in 0..3 {
+        emulator.step_instruction();
+    }
+    assert!(emulator.halted().is_none());
+    emulator.step_instruction();
+
+    let dump = CrashDump::capture(&emulator).unwrap();
+    assert_eq!(dump.opcode, 0x5AB1);
+    assert_eq!(dump.registers.pc, 0x208);
+    assert_eq!(dump.stack, [0x204]);
+    assert_eq!(dump.disassembly.first().unwrap().address, 0x1F8);
+    assert_eq!(dump.disassembly.last().unwrap().address, 0x218);
+
+    let text = dump.to_string();
+    assert!(text.starts_with("CPU halted at 0x208: unknown opcode 5AB1\n"));
+    assert!(text.contains("ROM: test.ch8 ("));
+    assert!(text.contains("PC: 0x208  I: 0x123\n"));
+    assert!(text.contains("VA: 42"));
+    assert!(text.contains("Stack (1): 0x204\n"));
+    assert!(text.contains("> 0x208  5AB1  DW 0x5AB1\n"));
+    assert!(text.contains("  0x206  A123  LD I, 0x123\n"));
+}
+
+#[test]
+fn halts_write_a_dump_when_asked() {
+    let dir = std::env::temp_dir().join(format!("chip8_crash_dumps_{}", std::process::id()));
+    let mut emulator = common::load(&[0x00EE]);
+    emulator.crash_dumps = Some(dir.clone());
+    emulator.step_instruction();
+
+    let path = emulator.crash_dump_path().unwrap().to_path_buf();
+    assert!(path.starts_with(&dir));
+    assert!(path
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .starts_with("test-"));
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("CPU halted at 0x200: return with an empty stack\n"));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    emulator.reload_rom().unwrap();
+    assert_eq!(emulator.crash_dump_path(), None);
+}
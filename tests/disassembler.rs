@@ -0,0 +1,37 @@
+use chip_8_emulator::disassembler::{disassemble, disassemble_at, mnemonic};
+
+#[test]
+fn decodes_mnemonics() {
+    assert_eq!(mnemonic(0x00E0), "CLS");
+    assert_eq!(mnemonic(0x6A02), "LD VA, 0x02");
+    assert_eq!(mnemonic(0x8AB4), "ADD VA, VB");
+    assert_eq!(mnemonic(0xD125), "DRW V1, V2, 5");
+    assert_eq!(mnemonic(0x2F00), "CALL 0xF00");
+    assert_eq!(mnemonic(0xF365), "LD V3, [I]");
+    assert_eq!(mnemonic(0x00C4), "SCD 4");
+    assert_eq!(mnemonic(0x5122), "LD [I], V1-V2");
+}
+
+#[test]
+fn unknown_words_are_data() {
+    assert_eq!(mnemonic(0x8128), "DW 0x8128");
+    assert_eq!(mnemonic(0xE1FF), "DW 0xE1FF");
+    assert_eq!(mnemonic(0xF1FF), "DW 0xF1FF");
+}
+
+#[test]
+fn formats_opcode_and_mnemonic() {
+    let memory = [0x6A, 0x02];
+    assert_eq!(disassemble_at(&memory, 0).to_string(), "6A02  LD VA, 0x02");
+}
+
+#[test]
+fn long_load_spans_two_words() {
+    let memory = [0xF0, 0x00, 0x12, 0x34, 0x00, 0xE0];
+    let listing = disassemble(&memory, 0, 10);
+    assert_eq!(listing.len(), 2);
+    assert_eq!(listing[0].mnemonic, "LD I, 0x1234");
+    assert_eq!(listing[0].to_string(), "F000 1234  LD I, 0x1234");
+    assert_eq!(listing[1].address, 4);
+    assert_eq!(listing[1].mnemonic, "CLS");
+}
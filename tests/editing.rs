@@ -0,0 +1,56 @@
+mod common;
+
+use chip_8_emulator::{
+    emulator::{Emulator, MemoryWatch, MEMORY_SIZE},
+    error::EmulatorError,
+};
+
+fn load(code: &[u16]) -> Emulator {
+    let mut emulator = common::load(code);
+    emulator.pause();
+    emulator
+}
+
+#[test]
+fn edited_registers_are_used_by_the_program() {
+    // V0 += V1, then I += V0.
+    let mut emulator = load(&[0x8014, 0xF01E]);
+    emulator.set_reg(0, 2);
+    emulator.set_reg(1, 3);
+    emulator.set_reg_i(0x300);
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert_eq!(emulator.regs()[0], 5);
+    assert_eq!(emulator.reg_i(), 0x305);
+}
+
+#[test]
+fn moving_pc_skips_instructions() {
+    let mut emulator = load(&[0x6001, 0x6002, 0x6003]);
+    emulator.set_pc(0x204).unwrap();
+    emulator.step_instruction();
+    assert_eq!(emulator.regs()[0], 3);
+}
+
+#[test]
+fn pc_must_leave_room_for_an_instruction() {
+    let mut emulator = load(&[0x6001]);
+    let last = (MEMORY_SIZE - 1) as u16;
+    assert!(matches!(
+        emulator.set_pc(last),
+        Err(EmulatorError::MemoryOutOfBounds(address)) if address == last
+    ));
+    assert_eq!(emulator.pc(), 0x200);
+}
+
+#[test]
+fn poked_instruction_runs_without_triggering_watches() {
+    let mut emulator = load(&[0x6001]);
+    emulator.memory_watches.insert(0x201, MemoryWatch::OnWrite);
+    emulator.poke(0x201, 0x07).unwrap();
+    emulator.set_delay_timer(9);
+    emulator.step_instruction();
+    assert_eq!(emulator.regs()[0], 7);
+    assert_eq!(emulator.delay_timer(), 9);
+    assert!(emulator.break_reason.is_none());
+}
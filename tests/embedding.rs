@@ -0,0 +1,118 @@
+mod common;
+
+use chip_8_emulator::emulator::{Display, Emulator, Registers, PACKED_DISPLAY_WORDS};
+
+fn emulator(code: &[u16]) -> Emulator {
+    let mut emulator = common::load(code);
+    emulator.pause();
+    emulator
+}
+
+#[test]
+fn framebuffer_is_row_major() {
+    let mut emulator = emulator(&[]);
+    emulator.display[3][0] = 1;
+    emulator.display[0][1] = 2;
+    let framebuffer = emulator.framebuffer();
+    assert_eq!(framebuffer.len(), 64 * 32);
+    assert_eq!(framebuffer[3], 1);
+    assert_eq!(framebuffer[64], 2);
+    assert_eq!(framebuffer.iter().filter(|pixel| **pixel != 0).count(), 2);
+    let lit: Vec<_> = emulator.display.lit_pixels().collect();
+    assert_eq!(lit, [(0, 1, 2), (3, 0, 1)]);
+}
+
+#[test]
+fn packed_display_holds_two_bits_per_pixel() {
+    let mut display = Display::new(false);
+    display[0][0] = 1;
+    display[15][0] = 3;
+    display[1][1] = 2;
+    let mut words = [u32::MAX; PACKED_DISPLAY_WORDS];
+    display.write_packed(&mut words);
+    assert_eq!(words[0], 1 | 3 << 30);
+    // The second row starts 64 pixels, four words, in.
+    assert_eq!(words[4], 2 << 2);
+    assert!(words
+        .iter()
+        .enumerate()
+        .all(|(i, word)| i == 0 || i == 4 || *word == 0));
+
+    display.hires = true;
+    display[127][63] = 1;
+    display.write_packed(&mut words);
+    assert_eq!(words[PACKED_DISPLAY_WORDS - 1], 1 << 30);
+}
+
+#[test]
+fn registers_snapshot_the_cpu() {
+    let mut emulator = emulator(&[
+        0x6A42, // VA = 0x42
+        0xA123, // I = 0x123
+        0x2208, // call 0x208
+        0x0000, //
+        0x6F01, // VF = 1
+    ]);
+    for _ in 0..4 {
+        emulator.step_instruction();
+    }
+    let registers = emulator.registers();
+    assert_eq!(
+        registers,
+        Registers {
+            pc: 0x20A,
+            i: 0x123,
+            v: *emulator.regs(),
+            delay_timer: 0,
+            sound_timer: 0,
+            stack_depth: 1,
+        }
+    );
+    assert_eq!(registers.v[0xA], 0x42);
+    assert_eq!(registers.v[0xF], 1);
+}
+
+#[test]
+fn display_changes_are_reported_once() {
+    let mut emulator = emulator(&[
+        0xF029, // I = font of V0
+        0xD005, // draw it
+        0x6001, // V0 = 1
+    ]);
+    assert!(emulator.display_changed_since_last_read());
+    assert!(!emulator.display_changed_since_last_read());
+    emulator.step_instruction();
+    assert!(!emulator.display_changed_since_last_read());
+    emulator.step_instruction();
+    assert!(emulator.display_changed_since_last_read());
+    emulator.step_instruction();
+    assert!(!emulator.display_changed_since_last_read());
+    // Direct edits go unnoticed until they're flagged.
+    assert_eq!(emulator.framebuffer()[0], 1);
+    emulator.display[0][0] ^= 1;
+    assert!(!emulator.display_changed_since_last_read());
+    assert_eq!(emulator.framebuffer()[0], 1);
+    emulator.mark_display_changed();
+    assert!(emulator.display_changed_since_last_read());
+    assert_eq!(emulator.framebuffer()[0], 0);
+}
+
+#[test]
+fn framebuffer_follows_clears_and_scrolls() {
+    let mut emulator = emulator(&[
+        0xF029, // I = font of V0
+        0xD005, // draw it
+        0x00C1, // scroll down a row
+        0x00E0, // clear
+    ]);
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert_eq!(emulator.framebuffer()[0], 1);
+    emulator.step_instruction();
+    assert!(emulator.display_changed_since_last_read());
+    assert_eq!(emulator.framebuffer()[0], 0);
+    assert_eq!(emulator.framebuffer()[64], 1);
+    emulator.step_instruction();
+    assert!(emulator.display_changed_since_last_read());
+    assert!(emulator.framebuffer().iter().all(|pixel| *pixel == 0));
+}
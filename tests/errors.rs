@@ -0,0 +1,158 @@
+mod common;
+
+use std::path::PathBuf;
+
+use chip_8_emulator::{
+    emulator::{Emulator, RunState, MEMORY_SIZE},
+    error::{EmulatorError, UnknownOpcodePolicy},
+};
+
+fn rom_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("chip8_errors_{name}_{}.ch8", std::process::id()))
+}
+
+#[test]
+fn return_with_empty_stack_halts() {
+    let mut emulator = common::load(&[0x6001, 0x00EE]);
+    emulator.step_frame();
+    let halt = emulator.halted().unwrap();
+    assert_eq!(halt.address, 0x202);
+    assert!(matches!(halt.error, EmulatorError::StackUnderflow));
+    assert_eq!(
+        halt.to_string(),
+        "CPU halted at 0x202: return with an empty stack"
+    );
+    assert_eq!(emulator.pc(), 0x202);
+    assert!(matches!(emulator.state, RunState::Paused));
+}
+
+#[test]
+fn runaway_recursion_halts() {
+    let mut emulator = common::load(&[0x2200]);
+    assert!(!emulator.run_until(|_| false, 100));
+    let halt = emulator.halted().unwrap();
+    assert!(matches!(halt.error, EmulatorError::StackOverflow));
+    assert_eq!(emulator.stack().len(), 16);
+}
+
+#[test]
+fn unknown_opcode_halts() {
+    let mut emulator = common::load(&[0x6001, 0x5121]);
+    emulator.step_instruction();
+    emulator.step_instruction();
+    let halt = emulator.halted().unwrap();
+    assert_eq!(halt.address, 0x202);
+    assert!(matches!(halt.error, EmulatorError::UnknownOpcode(0x5121)));
+}
+
+#[test]
+fn running_into_zeroed_memory_halts() {
+    let mut emulator = common::load(&[0x6001]);
+    emulator.step_instruction();
+    emulator.step_instruction();
+    let halt = emulator.halted().unwrap();
+    assert_eq!(halt.address, 0x202);
+    assert!(matches!(halt.error, EmulatorError::UnknownOpcode(0x0000)));
+
+    // Unsupported 0NNN machine code calls go through the policy too.
+    let mut emulator = common::load(&[0x0123, 0x6001]);
+    emulator.unknown_opcodes = UnknownOpcodePolicy::Skip;
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert!(emulator.halted().is_none());
+    assert_eq!(emulator.regs()[0], 1);
+}
+
+#[test]
+fn unknown_opcodes_can_be_skipped() {
+    let mut emulator = common::load(&[0x5121, 0x6001]);
+    emulator.unknown_opcodes = UnknownOpcodePolicy::Skip;
+    emulator.step_instruction();
+    assert!(emulator.halted().is_none());
+    assert_eq!(emulator.pc(), 0x202);
+    emulator.step_instruction();
+    assert_eq!(emulator.regs()[0], 1);
+}
+
+#[test]
+fn unknown_opcodes_can_break_into_the_debugger() {
+    let mut emulator = common::load(&[0x6001, 0x5121]);
+    emulator.unknown_opcodes = UnknownOpcodePolicy::Break;
+    emulator.resume();
+    emulator.step_frame();
+    assert!(emulator.halted().is_none());
+    assert!(matches!(emulator.state, RunState::Paused));
+    assert_eq!(emulator.pc(), 0x202);
+    assert_eq!(
+        emulator.break_reason.as_deref(),
+        Some("Unknown opcode 5121 at 0x202")
+    );
+    // Once it's dealt with, the program goes on.
+    emulator.set_pc(0x200).unwrap();
+    emulator.resume();
+    assert!(matches!(emulator.state, RunState::Running));
+}
+
+#[test]
+fn store_past_end_of_memory_halts_without_writing() {
+    // I = 0xFFFE with F000 FFFE, then store V0..V3.
+    let mut emulator = common::load(&[0xF000, 0xFFFE, 0xF355]);
+    emulator.step_instruction();
+    emulator.step_instruction();
+    let halt = emulator.halted().unwrap();
+    assert!(matches!(
+        halt.error,
+        EmulatorError::MemoryOutOfBounds(0xFFFE)
+    ));
+    assert_eq!(emulator.memory()[MEMORY_SIZE - 1], 0);
+}
+
+#[test]
+fn halted_emulator_only_runs_again_after_reset() {
+    let path = rom_path("reset");
+    std::fs::write(&path, [0x00, 0xEE]).unwrap();
+    let mut emulator = Emulator::new();
+    emulator
+        .load_rom(path.to_string_lossy().into_owned())
+        .unwrap();
+    emulator.step_frame();
+    emulator.resume();
+    assert!(matches!(emulator.state, RunState::Paused));
+
+    let reloaded = emulator.reload_rom();
+    std::fs::remove_file(&path).unwrap();
+    reloaded.unwrap();
+    assert!(emulator.halted().is_none());
+    assert!(matches!(emulator.state, RunState::Running));
+}
+
+#[test]
+fn step_back_clears_halt() {
+    let mut emulator = common::load(&[0x6001, 0x00EE]);
+    emulator.step_frame();
+    assert!(emulator.halted().is_some());
+    assert!(emulator.step_back());
+    assert!(emulator.halted().is_none());
+    assert_eq!(emulator.pc(), 0x200);
+}
+
+#[test]
+fn missing_rom_is_an_io_error() {
+    let mut emulator = Emulator::new();
+    let result = emulator.load_rom(rom_path("missing").to_string_lossy().into_owned());
+    assert!(matches!(result, Err(EmulatorError::IoError(_))));
+    assert!(matches!(emulator.state, RunState::NoROM));
+}
+
+#[test]
+fn oversized_rom_is_rejected() {
+    let path = rom_path("large");
+    std::fs::write(&path, vec![0; MEMORY_SIZE]).unwrap();
+    let mut emulator = Emulator::new();
+    let result = emulator.load_rom(path.to_string_lossy().into_owned());
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        result,
+        Err(EmulatorError::RomTooLarge { size, max }) if size == MEMORY_SIZE && max == MEMORY_SIZE - 0x200
+    ));
+}
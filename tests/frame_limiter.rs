@@ -0,0 +1,37 @@
+use std::time::{Duration, Instant};
+
+use chip_8_emulator::frame_limiter::{FrameLimiter, FRAME_TIME_HISTORY};
+
+#[test]
+fn waits_for_each_frame() {
+    let mut limiter = FrameLimiter::default();
+    let started = Instant::now();
+    for _ in 0..6 {
+        limiter.wait(Some(200.0));
+    }
+    // The first frame isn't held, the next five are 5 ms apart.
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(25), "{elapsed:?}");
+    assert!(elapsed < Duration::from_secs(1), "{elapsed:?}");
+    assert_eq!(limiter.frame_times().len(), 5);
+    assert!(limiter.frame_times().iter().all(|ms| *ms >= 4.0));
+    assert!(limiter.fps() <= 201.0);
+}
+
+#[test]
+fn uncapped_frames_are_not_held() {
+    let mut limiter = FrameLimiter::default();
+    let started = Instant::now();
+    for _ in 0..1000 {
+        limiter.wait(None);
+    }
+    assert!(started.elapsed() < Duration::from_millis(500));
+    assert_eq!(limiter.frame_times().len(), FRAME_TIME_HISTORY);
+}
+
+#[test]
+fn no_frames_no_fps() {
+    let limiter = FrameLimiter::default();
+    assert!(limiter.frame_times().is_empty());
+    assert_eq!(limiter.fps(), 0.0);
+}
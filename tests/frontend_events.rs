@@ -0,0 +1,308 @@
+//! Drives the frontend with synthetic winit events against a headless surface.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use chip_8_emulator::{
+    emulator::RunState,
+    frontend::{EventResponse, Frontend, Panel, SurfaceTarget},
+    offscreen::OffscreenRenderer,
+    renderer::Rotation,
+    timing::{SLOW_MOTION_SPEED, TURBO_SPEED},
+};
+use winit::{
+    dpi::PhysicalSize,
+    event::{DeviceId, ElementState, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent},
+};
+
+/// Records resizes without touching the GPU, for tests that only care about input.
+struct NullSurface {
+    size: (u32, u32),
+}
+
+impl SurfaceTarget for NullSurface {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.size = (width, height);
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.size
+    }
+}
+
+fn null_surface() -> NullSurface {
+    NullSurface { size: (640, 320) }
+}
+
+fn key_event(scancode: u32, state: ElementState) -> WindowEvent<'static> {
+    keycode_event(scancode, None, state)
+}
+
+fn keycode_event(
+    scancode: u32,
+    virtual_keycode: Option<VirtualKeyCode>,
+    state: ElementState,
+) -> WindowEvent<'static> {
+    #[allow(deprecated)]
+    WindowEvent::KeyboardInput {
+        // SAFETY: The dummy id is only compared against other ids, never dereferenced.
+        device_id: unsafe { DeviceId::dummy() },
+        input: KeyboardInput {
+            scancode,
+            state,
+            virtual_keycode,
+            modifiers: ModifiersState::empty(),
+        },
+        is_synthetic: true,
+    }
+}
+
+fn rom_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("resources")
+        .join("roms")
+        .join(name)
+}
+
+#[test]
+fn pressing_mapped_key_sets_keypad() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+
+    // Scancode 0x11 is W on a QWERTY layout, mapped to CHIP-8 key 5.
+    frontend.handle_window_event(&key_event(0x11, ElementState::Pressed), &mut surface);
+    frontend.emulator.sync();
+    assert!(frontend.emulator.lock().is_key_down(0x5));
+
+    frontend.handle_window_event(&key_event(0x11, ElementState::Released), &mut surface);
+    frontend.emulator.sync();
+    assert!(!frontend.emulator.lock().is_key_down(0x5));
+}
+
+#[test]
+fn sticky_keys_toggle_on_tap() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+    frontend.set_sticky_keys(true);
+
+    // Key repeat sends several presses before the release, only the first one counts.
+    frontend.handle_window_event(&key_event(0x11, ElementState::Pressed), &mut surface);
+    frontend.handle_window_event(&key_event(0x11, ElementState::Pressed), &mut surface);
+    frontend.handle_window_event(&key_event(0x11, ElementState::Released), &mut surface);
+    frontend.emulator.sync();
+    assert!(frontend.emulator.lock().is_key_down(0x5));
+    assert_eq!(frontend.latched_key(), Some(0x5));
+
+    frontend.handle_window_event(&key_event(0x11, ElementState::Pressed), &mut surface);
+    frontend.handle_window_event(&key_event(0x11, ElementState::Released), &mut surface);
+    frontend.emulator.sync();
+    assert!(!frontend.emulator.lock().is_key_down(0x5));
+    assert_eq!(frontend.latched_key(), None);
+}
+
+#[test]
+fn disabling_sticky_keys_releases_latched_key() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+    frontend.set_sticky_keys(true);
+
+    frontend.handle_window_event(&key_event(0x11, ElementState::Pressed), &mut surface);
+    frontend.handle_window_event(&key_event(0x11, ElementState::Released), &mut surface);
+    frontend.set_sticky_keys(false);
+    frontend.emulator.sync();
+    assert!(!frontend.emulator.lock().is_key_down(0x5));
+}
+
+#[test]
+fn unmapped_key_is_ignored() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+
+    frontend.handle_window_event(&key_event(0x2D, ElementState::Pressed), &mut surface);
+    frontend.handle_window_event(&key_event(0x01, ElementState::Pressed), &mut surface);
+    frontend.emulator.sync();
+    let emulator = frontend.emulator.lock();
+    assert!(emulator.is_key_down(0x0));
+    assert_eq!(emulator.keypad().iter().filter(|down| **down).count(), 1);
+}
+
+#[test]
+fn releasing_other_key_keeps_current() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+
+    frontend.handle_window_event(&key_event(0x11, ElementState::Pressed), &mut surface);
+    frontend.handle_window_event(&key_event(0x1E, ElementState::Released), &mut surface);
+    frontend.emulator.sync();
+    assert!(frontend.emulator.lock().is_key_down(0x5));
+}
+
+#[test]
+fn close_requests_exit() {
+    let mut frontend = Frontend::new();
+    let response = frontend.handle_window_event(&WindowEvent::CloseRequested, &mut null_surface());
+    assert_eq!(response, EventResponse::Exit);
+}
+
+#[test]
+fn resize_reconfigures_surface() {
+    let mut frontend = Frontend::new();
+    let mut surface = match OffscreenRenderer::new(640, 320) {
+        Ok(surface) => surface,
+        Err(err) => {
+            eprintln!("Skipping headless surface test, no GPU available: {err}");
+            return;
+        }
+    };
+
+    let response = frontend.handle_window_event(
+        &WindowEvent::Resized(PhysicalSize::new(300, 150)),
+        &mut surface,
+    );
+    assert_eq!(response, EventResponse::Redraw);
+    assert_eq!(surface.size(), (300, 150));
+
+    // The reconfigured target must still be renderable at the new size.
+    let image = surface.render(frontend.emulator.latest_frame());
+    assert_eq!(image.dimensions(), (300, 150));
+}
+
+#[test]
+fn resize_to_zero_is_clamped() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+
+    frontend.handle_window_event(&WindowEvent::Resized(PhysicalSize::new(0, 0)), &mut surface);
+    assert_eq!(surface.size(), (1, 1));
+}
+
+#[test]
+fn dropping_file_loads_rom() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+    assert!(matches!(frontend.emulator.lock().state, RunState::NoROM));
+
+    let response = frontend.handle_window_event(
+        &WindowEvent::DroppedFile(rom_path("IBM Logo.ch8")),
+        &mut surface,
+    );
+    assert_eq!(response, EventResponse::Redraw);
+    frontend.emulator.sync();
+    assert!(matches!(frontend.emulator.lock().state, RunState::Running));
+
+    // Let the emulation thread run the ROM until its frames show the logo.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let lit = frontend
+            .emulator
+            .latest_frame()
+            .iter()
+            .flatten()
+            .filter(|pixel| **pixel == 1)
+            .count();
+        if lit > 0 {
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "IBM logo should have drawn pixels"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn rotated_input_turns_arrow_keys() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+    frontend.rotation = Rotation::Cw90;
+    frontend.rotate_input = true;
+
+    // Scancode 0x12 is E, mapped to key 6 ("right"). On a display turned 90 degrees
+    // clockwise the game's "up" points right.
+    frontend.handle_window_event(&key_event(0x12, ElementState::Pressed), &mut surface);
+    frontend.emulator.sync();
+    assert!(frontend.emulator.lock().is_key_down(0x2));
+
+    // Non-direction keys are untouched.
+    frontend.handle_window_event(&key_event(0x11, ElementState::Pressed), &mut surface);
+    frontend.emulator.sync();
+    assert!(frontend.emulator.lock().is_key_down(0x5));
+}
+
+#[test]
+fn focus_loss_pauses_and_focus_gain_resumes() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+    frontend.pause_on_focus_loss = true;
+    frontend.open_rom(rom_path("IBM Logo.ch8"));
+    frontend.emulator.sync();
+
+    frontend.handle_window_event(&WindowEvent::Focused(false), &mut surface);
+    assert!(matches!(frontend.emulator.lock().state, RunState::Paused));
+    frontend.handle_window_event(&WindowEvent::Focused(true), &mut surface);
+    assert!(matches!(frontend.emulator.lock().state, RunState::Running));
+}
+
+#[test]
+fn focus_gain_keeps_user_pause() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+    frontend.pause_on_focus_loss = true;
+    frontend.open_rom(rom_path("IBM Logo.ch8"));
+    frontend.emulator.sync();
+    frontend.emulator.lock().pause();
+
+    frontend.handle_window_event(&WindowEvent::Focused(false), &mut surface);
+    frontend.handle_window_event(&WindowEvent::Focused(true), &mut surface);
+    assert!(matches!(frontend.emulator.lock().state, RunState::Paused));
+}
+
+#[test]
+fn f11_toggles_game_only_mode() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+    assert!(frontend.shows(Panel::Memory));
+    frontend.set_shown(Panel::Memory, false);
+    assert!(!frontend.shows(Panel::Memory));
+    assert!(frontend.shows(Panel::Keypad));
+
+    let f11 = keycode_event(87, Some(VirtualKeyCode::F11), ElementState::Pressed);
+    assert_eq!(
+        frontend.handle_window_event(&f11, &mut surface),
+        EventResponse::Redraw
+    );
+    assert!(frontend.game_only);
+    assert!(!frontend.shows(Panel::Keypad));
+    frontend.handle_window_event(&f11, &mut surface);
+    assert!(!frontend.game_only);
+    assert!(frontend.shows(Panel::Keypad));
+    assert!(!frontend.shows(Panel::Memory));
+}
+
+#[test]
+fn tab_fast_forwards_while_held_and_f4_toggles_slow_motion() {
+    let mut frontend = Frontend::new();
+    let mut surface = null_surface();
+    let speed = |frontend: &Frontend| {
+        frontend.emulator.sync();
+        frontend.emulator.status().speed
+    };
+
+    let f4 = keycode_event(62, Some(VirtualKeyCode::F4), ElementState::Pressed);
+    frontend.handle_window_event(&f4, &mut surface);
+    assert!(frontend.slow_motion());
+    assert_eq!(speed(&frontend), SLOW_MOTION_SPEED);
+
+    let tab = |state| keycode_event(15, Some(VirtualKeyCode::Tab), state);
+    frontend.handle_window_event(&tab(ElementState::Pressed), &mut surface);
+    assert_eq!(speed(&frontend), TURBO_SPEED);
+    frontend.handle_window_event(&tab(ElementState::Released), &mut surface);
+    assert_eq!(speed(&frontend), SLOW_MOTION_SPEED);
+
+    frontend.handle_window_event(&f4, &mut surface);
+    assert_eq!(speed(&frontend), 1.0);
+    assert_eq!(frontend.emulator.lock().clock.speed, 1.0);
+}
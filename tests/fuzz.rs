@@ -0,0 +1,170 @@
+//! Property tests feeding random instruction streams to the CPU core. The `fuzz/` crate
+//! runs the same checks under cargo-fuzz for longer.
+
+mod common;
+
+use chip_8_emulator::{
+    emulator::{Emulator, STACK_SIZE},
+    error::EmulatorError,
+    quirks::{QuirkPreset, RngMode},
+};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Random programs run per test, each seeded from its index so failures can be replayed.
+const CASES: u64 = 100;
+const STEPS: usize = 1000;
+
+fn random_emulator(rng: &mut ChaCha8Rng) -> Emulator {
+    let mut emulator = Emulator::with_seed(rng.gen());
+    QuirkPreset::ALL.choose(rng).unwrap().apply(&mut emulator);
+    emulator.rng_mode = *RngMode::ALL.choose(rng).unwrap();
+    emulator.load_font();
+    let size = rng.gen_range(2..=0x1000);
+    let rom: Vec<u8> = (0..size).map(|_| rng.gen()).collect();
+    emulator.load_rom_data("fuzz.ch8", &rom).unwrap();
+    emulator
+}
+
+/// Runs a random program, pressing random keys, checking the machine stays consistent
+/// after every instruction.
+fn check_random_program(case: u64) {
+    let mut rng = ChaCha8Rng::seed_from_u64(case);
+    let mut emulator = random_emulator(&mut rng);
+    for step in 0..STEPS {
+        if rng.gen_ratio(1, 50) {
+            let key = rng.gen_range(0..16);
+            if emulator.is_key_down(key) {
+                emulator.release_key(key);
+            } else {
+                emulator.press_key(key);
+            }
+        }
+
+        let pc = emulator.pc();
+        // Whole frames now and then, so the timers run down.
+        let frame = step % 100 == 99;
+        if frame {
+            emulator.step_frame();
+        } else {
+            emulator.step_instruction();
+        }
+        assert!(
+            emulator.stack().len() <= STACK_SIZE,
+            "case {case}: stack grew to {}",
+            emulator.stack().len()
+        );
+        if let Some(halt) = emulator.halted() {
+            assert!(
+                !matches!(
+                    halt.error,
+                    EmulatorError::IoError(_) | EmulatorError::RomTooLarge { .. }
+                ),
+                "case {case}: unexpected halt {halt}"
+            );
+            // A halted CPU stays on the failing instruction.
+            if !frame {
+                assert_eq!(emulator.pc(), pc, "case {case}: PC moved on {halt}");
+            }
+            break;
+        }
+    }
+}
+
+#[test]
+fn random_programs_never_panic() {
+    for case in 0..CASES {
+        check_random_program(case);
+    }
+}
+
+/// Runs `inst` under `preset` with its VX and VY set to `vx` and `vy`, returns VX and VF.
+fn arithmetic(preset: QuirkPreset, inst: u16, vx: u8, vy: u8) -> (u8, u8) {
+    let (x, y) = ((inst >> 8) & 0xF, (inst >> 4) & 0xF);
+    let code = [
+        0x6000 | x << 8 | vx as u16,
+        0x6000 | y << 8 | vy as u16,
+        inst,
+    ];
+    let mut emulator = common::load(&code);
+    preset.apply(&mut emulator);
+    for _ in 0..code.len() {
+        emulator.step_instruction();
+    }
+    (emulator.regs()[x as usize], emulator.regs()[0xF])
+}
+
+#[test]
+fn arithmetic_sets_vf_to_carry_and_borrow() {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    for _ in 0..1000 {
+        let (vx, vy): (u8, u8) = (rng.gen(), rng.gen());
+        for preset in [QuirkPreset::CosmacVip, QuirkPreset::Chip48] {
+            let quirks = preset.quirks();
+            // With X = F the flag is written last and is all that's left.
+            for x in [0, 0xF] {
+                let op = |n: u16| 0x8010 | x << 8 | n;
+                let flagged = |value: u8, flag: u8| (if x == 0xF { flag } else { value }, flag);
+                let (sum, carry) = vx.overflowing_add(vy);
+                assert_eq!(
+                    arithmetic(preset, op(4), vx, vy),
+                    flagged(sum, carry as u8),
+                    "V{x:X}: {vx} + {vy}"
+                );
+                let (difference, borrow) = vx.overflowing_sub(vy);
+                assert_eq!(
+                    arithmetic(preset, op(5), vx, vy),
+                    flagged(difference, !borrow as u8),
+                    "V{x:X}: {vx} - {vy}"
+                );
+                let (difference, borrow) = vy.overflowing_sub(vx);
+                assert_eq!(
+                    arithmetic(preset, op(7), vx, vy),
+                    flagged(difference, !borrow as u8),
+                    "V{x:X}: {vy} - {vx}"
+                );
+                let shifted = if quirks.shift_swap { vy } else { vx };
+                assert_eq!(
+                    arithmetic(preset, op(6), vx, vy),
+                    flagged(shifted >> 1, shifted & 1),
+                    "V{x:X}: {shifted} >> 1 under {}",
+                    preset.name()
+                );
+                assert_eq!(
+                    arithmetic(preset, op(0xE), vx, vy),
+                    flagged(shifted << 1, shifted >> 7),
+                    "V{x:X}: {shifted} << 1 under {}",
+                    preset.name()
+                );
+                // The logic instructions leave VF alone unless the vF reset quirk clears it.
+                let logic = |result: u8| {
+                    let flag = if x == 0xF && !quirks.vf_reset {
+                        result
+                    } else {
+                        0
+                    };
+                    flagged(result, flag)
+                };
+                for (n, result) in [(1, vx | vy), (2, vx & vy), (3, vx ^ vy)] {
+                    assert_eq!(
+                        arithmetic(preset, op(n), vx, vy),
+                        logic(result),
+                        "V{x:X}: 8XY{n} of {vx} and {vy} under {}",
+                        preset.name()
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn vf_as_operand_is_overwritten_by_the_flag() {
+    // VF += V1 leaves only the carry in VF.
+    let code: [u16; 3] = [0x6FFF, 0x6102, 0x8F14];
+    let mut emulator = common::load(&code);
+    for _ in 0..code.len() {
+        emulator.step_instruction();
+    }
+    assert_eq!(emulator.regs()[0xF], 1);
+}
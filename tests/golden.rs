@@ -0,0 +1,200 @@
+//! Renders known display buffers through the full wgpu pipeline offscreen and compares
+//! the result against the images in `tests/golden`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to regenerate the images after an intended rendering change.
+//! They fail without a GPU adapter, set `SKIP_GOLDEN=1` to skip them on machines that
+//! have none.
+
+use std::path::PathBuf;
+
+use chip_8_emulator::{
+    emulator::{Display, Emulator, DISPLAY_SIZE},
+    offscreen::OffscreenRenderer,
+    renderer::{Palette, PostEffects},
+};
+use image::RgbaImage;
+
+const SCALE: u32 = 4;
+/// Maximum per-channel difference for a pixel to be considered equal.
+const CHANNEL_TOLERANCE: u8 = 2;
+/// Fraction of pixels allowed to exceed the channel tolerance.
+const PIXEL_TOLERANCE: f64 = 0.001;
+
+fn renderer() -> Option<OffscreenRenderer> {
+    match OffscreenRenderer::new(DISPLAY_SIZE.0 as u32 * SCALE, DISPLAY_SIZE.1 as u32 * SCALE) {
+        Ok(renderer) => Some(renderer),
+        Err(err) if std::env::var_os("SKIP_GOLDEN").is_some() => {
+            eprintln!("Skipping golden image test, no GPU available: {err}");
+            None
+        }
+        Err(err) => panic!(
+            "No GPU available for the golden image tests, set SKIP_GOLDEN=1 to skip them: {err}"
+        ),
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{name}.png"))
+}
+
+fn check_golden(name: &str, actual: &RgbaImage) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        actual.save(&path).unwrap();
+        return;
+    }
+
+    let expected = image::open(&path)
+        .unwrap_or_else(|err| panic!("Missing golden image {path:?}: {err}"))
+        .to_rgba8();
+    assert_eq!(
+        expected.dimensions(),
+        actual.dimensions(),
+        "Golden image {name} has a different size"
+    );
+
+    let mismatched = expected
+        .pixels()
+        .zip(actual.pixels())
+        .filter(|(e, a)| {
+            e.0.iter()
+                .zip(a.0.iter())
+                .any(|(e, a)| e.abs_diff(*a) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    let ratio = mismatched as f64 / (expected.width() * expected.height()) as f64;
+    if ratio > PIXEL_TOLERANCE {
+        let actual_path = path.with_extension("actual.png");
+        actual.save(&actual_path).unwrap();
+        panic!(
+            "Golden image {name} differs in {mismatched} pixels ({:.2}%), output saved to {actual_path:?}",
+            ratio * 100.0
+        );
+    }
+}
+
+fn checkerboard() -> Display {
+    let mut display = Display::default();
+    for (x, column) in display.iter_mut().enumerate() {
+        for (y, pixel) in column.iter_mut().enumerate() {
+            *pixel = ((x + y) % 2) as u8;
+        }
+    }
+    display
+}
+
+/// Single lit pixels in each corner, catches flipped or offset UVs.
+fn corners() -> Display {
+    let mut display = Display::default();
+    let (w, h) = DISPLAY_SIZE;
+    display[0][0] = 1;
+    display[w - 1][0] = 1;
+    display[0][h - 1] = 1;
+    display[w - 1][h - 1] = 1;
+    display[1][0] = 1;
+    display
+}
+
+fn ibm_logo() -> Display {
+    let mut emulator = Emulator::new();
+    emulator.load_font();
+    emulator
+        .load_rom(format!(
+            "{}/resources/roms/IBM Logo.ch8",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap();
+    for _ in 0..60 {
+        emulator.step_frame();
+    }
+    emulator.display
+}
+
+#[test]
+fn golden_blank() {
+    let Some(mut renderer) = renderer() else {
+        return;
+    };
+    let image = renderer.render(&Display::default());
+    check_golden("blank", &image);
+}
+
+#[test]
+fn golden_checkerboard() {
+    let Some(mut renderer) = renderer() else {
+        return;
+    };
+    let image = renderer.render(&checkerboard());
+    check_golden("checkerboard", &image);
+}
+
+#[test]
+fn golden_corners() {
+    let Some(mut renderer) = renderer() else {
+        return;
+    };
+    let image = renderer.render(&corners());
+    check_golden("corners", &image);
+}
+
+#[test]
+fn golden_ibm_logo() {
+    let Some(mut renderer) = renderer() else {
+        return;
+    };
+    let image = renderer.render(&ibm_logo());
+    check_golden("ibm_logo", &image);
+}
+
+#[test]
+fn golden_ibm_logo_amber_palette() {
+    let Some(mut renderer) = renderer() else {
+        return;
+    };
+    *renderer.palette_mut() = Palette {
+        background: [40, 20, 0, 255],
+        foreground: [255, 176, 0, 255],
+        ..Palette::default()
+    };
+    let image = renderer.render(&ibm_logo());
+    check_golden("ibm_logo_amber", &image);
+}
+
+/// Red at the center of the second CHIP-8 pixel of the top row.
+fn second_pixel(renderer: &mut OffscreenRenderer, display: &Display) -> u8 {
+    renderer.render(display).get_pixel(SCALE * 3 / 2, SCALE / 2)[0]
+}
+
+#[test]
+fn phosphor_fades_pixels_out_over_the_frames() {
+    let Some(mut renderer) = renderer() else {
+        return;
+    };
+    renderer.set_effects(&PostEffects {
+        fade_frames: 4,
+        ..PostEffects::default()
+    });
+    let mut lit = Display::default();
+    lit[1][0] = 1;
+    let blank = Display::default();
+
+    // White on black while lit, then darker every drawn frame.
+    let mut previous = second_pixel(&mut renderer, &lit);
+    assert_eq!(previous, 255);
+    for _ in 0..3 {
+        let fading = second_pixel(&mut renderer, &blank);
+        assert!(fading > 0 && fading < previous, "{fading} after {previous}");
+        previous = fading;
+    }
+    assert_eq!(second_pixel(&mut renderer, &blank), 0);
+    assert_eq!(second_pixel(&mut renderer, &blank), 0);
+
+    // Without fading a pixel turns off at once.
+    renderer.set_effects(&PostEffects::default());
+    assert_eq!(second_pixel(&mut renderer, &lit), 255);
+    assert_eq!(second_pixel(&mut renderer, &blank), 0);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use chip_8_emulator::{
+    emulator::Display,
+    headless::{self, RegisterDump, RunLength},
+};
+
+#[test]
+fn runs_for_a_number_of_cycles() {
+    // Count up in V0 forever.
+    let mut emulator = common::load(&[0x7001, 0x1200]);
+    headless::run(&mut emulator, RunLength::Cycles(20));
+    assert_eq!(emulator.regs()[0], 10);
+}
+
+#[test]
+fn runs_for_emulated_seconds() {
+    let mut emulator = common::load(&[0x7001, 0x1200]);
+    headless::run(&mut emulator, RunLength::Seconds(0.5));
+    assert!((29..=30).contains(&emulator.frame_count()));
+}
+
+#[test]
+fn stops_when_the_program_exits() {
+    let mut emulator = common::load(&[0x6005, 0x00FD, 0x6006]);
+    headless::run(&mut emulator, RunLength::Seconds(1.0));
+    assert_eq!(emulator.regs()[0], 5);
+    assert_eq!(emulator.pc(), 0x202);
+}
+
+#[test]
+fn dump_includes_registers_and_halt() {
+    let mut emulator = common::load(&[0x6A02, 0xA123, 0x00EE]);
+    headless::run(&mut emulator, RunLength::Cycles(10));
+    let json = serde_json::to_value(RegisterDump::new(&emulator)).unwrap();
+    assert_eq!(json["pc"], 0x204);
+    assert_eq!(json["i"], 0x123);
+    assert_eq!(json["v"][10], 2);
+    assert_eq!(
+        json["halted"],
+        "CPU halted at 0x204: return with an empty stack"
+    );
+}
+
+#[test]
+fn ascii_shows_lit_pixels() {
+    let mut display = Display::default();
+    display[0][0] = 1;
+    display[2][1] = 3;
+    let ascii = headless::ascii(&display);
+    let lines: Vec<&str> = ascii.lines().collect();
+    assert_eq!(lines.len(), 32);
+    assert!(lines.iter().all(|line| line.len() == 64));
+    assert!(lines[0].starts_with("#..."));
+    assert!(lines[1].starts_with("..@."));
+}
+
+#[test]
+fn bench_counts_instructions() {
+    let mut emulator = common::load(&[0x7001, 0x1200]);
+    let result = headless::bench(&mut emulator, 1000);
+    assert_eq!(result.instructions, 1000);
+    assert!(result.mips() > 0.0);
+
+    // Stops at the first instruction that halts the CPU.
+    let mut emulator = common::load(&[0x7001, 0x5001]);
+    let result = headless::bench(&mut emulator, 1000);
+    assert!(result.instructions <= 2);
+    assert!(emulator.halted().is_some());
+}
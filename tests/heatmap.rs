@@ -0,0 +1,70 @@
+mod common;
+
+use chip_8_emulator::{
+    emulator::Emulator,
+    heatmap::{Access, HEATMAP_SIZE, PAGE_SIZE},
+};
+
+/// I = 0x300, FX55 stores V0 there, FX65 reads it back, draws it and loops.
+const LOOP: [u16; 5] = [0xA300, 0xF055, 0xF065, 0xD001, 0x1202];
+
+fn load() -> Emulator {
+    let mut emulator = common::load(&LOOP);
+    emulator.cpf = 4;
+    emulator
+}
+
+#[test]
+fn records_nothing_until_enabled() {
+    let mut emulator = load();
+    emulator.step_frame();
+    assert_eq!(emulator.heatmap.count(0x200, Access::Execute), 0.0);
+    assert!(emulator
+        .heatmap
+        .rgba(0)
+        .chunks_exact(4)
+        .all(|pixel| pixel == [0, 0, 0, 255]));
+}
+
+#[test]
+fn counts_reads_writes_and_execution() {
+    let mut emulator = load();
+    emulator.heatmap.set_enabled(true);
+    emulator.heatmap.decay = 1.0;
+    for _ in 0..9 {
+        emulator.step_instruction();
+    }
+    let heatmap = &emulator.heatmap;
+    assert_eq!(heatmap.count(0x200, Access::Execute), 1.0);
+    assert_eq!(heatmap.count(0x201, Access::Execute), 1.0);
+    assert_eq!(heatmap.count(0x202, Access::Execute), 2.0);
+    assert_eq!(heatmap.count(0x300, Access::Write), 2.0);
+    // Read by FX65 and DXY1 each time around.
+    assert_eq!(heatmap.count(0x300, Access::Read), 4.0);
+    assert_eq!(heatmap.count(0x301, Access::Read), 0.0);
+    assert_eq!(heatmap.count(0x200, Access::Read), 0.0);
+
+    let pixels = heatmap.rgba(0);
+    assert_eq!(pixels.len(), PAGE_SIZE * 4);
+    let pixel = |address: usize| &pixels[address * 4..address * 4 + 4];
+    assert_eq!(pixel(0x300), [255, 0, 255, 255]);
+    assert_eq!(pixel(0x202)[1], 255);
+    assert_eq!(pixel(0x400), [0, 0, 0, 255]);
+    assert_eq!(heatmap.rgba(1).len(), HEATMAP_SIZE * HEATMAP_SIZE * 4);
+}
+
+#[test]
+fn counts_fade_every_frame() {
+    let mut emulator = load();
+    emulator.heatmap.set_enabled(true);
+    emulator.heatmap.decay = 0.5;
+    emulator.step_frame();
+    let before = emulator.heatmap.count(0x200, Access::Execute);
+    assert!(before > 0.0);
+    emulator.cpf = 0;
+    emulator.step_frame();
+    assert_eq!(emulator.heatmap.count(0x200, Access::Execute), before * 0.5);
+
+    emulator.reset();
+    assert_eq!(emulator.heatmap.count(0x300, Access::Write), 0.0);
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use chip_8_emulator::{
+    emulator::Emulator,
+    hooks::{Hooks, MemoryHook},
+};
+
+/// V0 counts up, I = 0x300, FX55 stores V0 there and loops.
+const COUNTER: [u16; 4] = [0xA300, 0x7001, 0xF055, 0x1202];
+
+#[derive(Default)]
+struct Counts {
+    frames: u32,
+    instructions: Vec<u16>,
+    writes: Vec<(u16, u8)>,
+}
+
+/// Records the callbacks where the test can still see them.
+struct Record(Arc<Mutex<Counts>>);
+
+impl Hooks for Record {
+    fn on_frame(&mut self, _emulator: &mut Emulator) {
+        self.0.lock().unwrap().frames += 1;
+    }
+
+    fn on_instruction(&mut self, _emulator: &mut Emulator, pc: u16) {
+        self.0.lock().unwrap().instructions.push(pc);
+    }
+
+    fn on_memory_write(&mut self, _emulator: &mut Emulator, address: u16, value: u8) {
+        self.0.lock().unwrap().writes.push((address, value));
+    }
+}
+
+/// Keeps V0 at 0x42, like a cheat freezing the lives counter.
+struct Freeze;
+
+impl Hooks for Freeze {
+    fn on_instruction(&mut self, emulator: &mut Emulator, _pc: u16) {
+        emulator.set_reg(0, 0x42);
+    }
+}
+
+fn load() -> Emulator {
+    let mut emulator = common::load(&COUNTER);
+    emulator.cpf = 3;
+    emulator
+}
+
+#[test]
+fn hooks_see_frames_instructions_and_writes() {
+    let mut emulator = load();
+    let counts = Arc::new(Mutex::new(Counts::default()));
+    emulator.set_hooks(Some(Box::new(Record(counts.clone()))));
+    emulator.step_frame();
+    emulator.step_frame();
+
+    let counts = counts.lock().unwrap();
+    assert_eq!(counts.frames, 2);
+    assert_eq!(
+        counts.instructions,
+        [0x200, 0x202, 0x204, 0x206, 0x202, 0x204]
+    );
+    assert_eq!(counts.writes, [(0x300, 1), (0x300, 2)]);
+}
+
+#[test]
+fn hooks_can_change_the_machine() {
+    let mut emulator = load();
+    emulator.set_hooks(Some(Box::new(Freeze)));
+    for _ in 0..6 {
+        emulator.step_instruction();
+    }
+    // V0 is set back to 0x42 before the store.
+    assert_eq!(emulator.memory()[0x300], 0x42);
+
+    emulator.set_hooks(None);
+    assert!(!emulator.has_hooks());
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert_eq!(emulator.regs()[0], 0x43);
+}
+
+/// Serial port at 0xFF0: bytes written are sent, reads take the next received byte.
+struct Serial {
+    sent: Arc<Mutex<Vec<u8>>>,
+    received: Vec<u8>,
+}
+
+impl MemoryHook for Serial {
+    fn on_read(&mut self, _address: u16, value: u8) -> u8 {
+        if self.received.is_empty() {
+            value
+        } else {
+            self.received.remove(0)
+        }
+    }
+
+    fn on_write(&mut self, _address: u16, value: u8) {
+        self.sent.lock().unwrap().push(value);
+    }
+}
+
+#[test]
+fn mapped_peripherals_see_reads_and_writes() {
+    // I = 0xFF0, V0 = 'h', send it, then receive into V1 twice with 5XY3.
+    let code = [0xAFF0u16, 0x6068, 0xF055, 0xAFF0, 0x5113, 0x5223, 0x120C];
+    let mut emulator = common::load(&code);
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    emulator.map_memory(
+        0xFF0..0xFF1,
+        Box::new(Serial {
+            sent: sent.clone(),
+            received: vec![b'o', b'k'],
+        }),
+    );
+    for _ in 0..6 {
+        emulator.step_instruction();
+    }
+    assert_eq!(*sent.lock().unwrap(), b"h");
+    assert_eq!(emulator.regs()[1], b'o');
+    assert_eq!(emulator.regs()[2], b'k');
+    // Memory keeps what was written, reads don't change it.
+    assert_eq!(emulator.memory()[0xFF0], b'h');
+    let mapped: Vec<_> = emulator.mapped_memory().collect();
+    assert_eq!(mapped.len(), 1);
+    assert_eq!(mapped[0], 0xFF0..0xFF1);
+
+    emulator.unmap_memory(0xFF0);
+    emulator.reload_rom().unwrap();
+    for _ in 0..5 {
+        emulator.step_instruction();
+    }
+    assert_eq!(emulator.regs()[1], b'h');
+    assert_eq!(sent.lock().unwrap().len(), 1);
+}
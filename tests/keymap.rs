@@ -0,0 +1,106 @@
+use chip_8_emulator::{
+    frontend::map_key,
+    keymap::{KeyBindings, KeyProfiles},
+};
+
+// Scancodes of W, A, S and D on a QWERTY layout.
+const W: u32 = 0x11;
+const A: u32 = 0x1E;
+const S: u32 = 0x1F;
+const D: u32 = 0x20;
+
+#[test]
+fn profile_overrides_default_mapping() {
+    let bindings = KeyBindings::default();
+    let mut profiles = KeyProfiles::default();
+    profiles.sync_rom(Some("game"));
+    for (scancode, key) in [(W, 0x2), (A, 0x4), (S, 0x8), (D, 0x6)] {
+        profiles.bind(scancode, key);
+    }
+
+    assert_eq!(profiles.map(W, &bindings), Some(0x2));
+    assert_eq!(profiles.map(D, &bindings), Some(0x6));
+    // Keys the profile doesn't mention keep their default binding.
+    assert_eq!(profiles.map(0x2D, &bindings), map_key(0x2D));
+}
+
+#[test]
+fn profile_only_applies_to_its_rom() {
+    let bindings = KeyBindings::default();
+    let mut profiles = KeyProfiles::default();
+    profiles.sync_rom(Some("game"));
+    profiles.bind(W, 0x2);
+
+    profiles.sync_rom(Some("other"));
+    assert_eq!(profiles.map(W, &bindings), map_key(W));
+    profiles.sync_rom(None);
+    assert_eq!(profiles.map(W, &bindings), map_key(W));
+    profiles.sync_rom(Some("game"));
+    assert_eq!(profiles.map(W, &bindings), Some(0x2));
+}
+
+#[test]
+fn rebinding_a_key_replaces_its_old_scancode() {
+    let bindings = KeyBindings::default();
+    let mut profiles = KeyProfiles::default();
+    profiles.sync_rom(Some("game"));
+    profiles.bind(W, 0x2);
+    profiles.bind(S, 0x2);
+
+    assert_eq!(profiles.map(S, &bindings), Some(0x2));
+    assert_eq!(profiles.map(W, &bindings), map_key(W));
+
+    profiles.clear();
+    assert_eq!(profiles.map(S, &bindings), map_key(S));
+}
+
+#[test]
+fn global_bindings_default_to_qwerty_layout() {
+    let bindings = KeyBindings::default();
+    for scancode in 0..0x100 {
+        assert_eq!(bindings.map(scancode), map_key(scancode));
+    }
+}
+
+#[test]
+fn global_bindings_can_be_remapped() {
+    let mut bindings = KeyBindings::default();
+    let mut profiles = KeyProfiles::default();
+    profiles.sync_rom(Some("game"));
+
+    // Move key 5 from W to the scancode of Up on most keyboards.
+    bindings.bind(0x48, 0x5);
+    assert_eq!(profiles.map(0x48, &bindings), Some(0x5));
+    assert_eq!(profiles.map(W, &bindings), None);
+
+    // The ROM profile still wins over the global bindings.
+    profiles.bind(0x48, 0x2);
+    assert_eq!(profiles.map(0x48, &bindings), Some(0x2));
+}
+
+#[test]
+fn global_bindings_round_trip_through_a_file() {
+    let path = std::env::temp_dir().join(format!("chip8_keybindings_{}.json", std::process::id()));
+    let mut bindings = KeyBindings::default();
+    bindings.bind(0x48, 0x5);
+    bindings.save(&path).unwrap();
+    assert_eq!(KeyBindings::load(&path).unwrap(), bindings);
+    std::fs::remove_file(&path).unwrap();
+
+    // A missing file gives the defaults.
+    assert_eq!(KeyBindings::load(&path).unwrap(), KeyBindings::default());
+}
+
+#[test]
+fn profiles_save_back_to_the_file_they_were_loaded_from() {
+    let path = std::env::temp_dir().join(format!("chip8_keymaps_{}.json", std::process::id()));
+    let mut profiles = KeyProfiles::load(&path).unwrap();
+    assert!(profiles.profiles.is_empty());
+    profiles.sync_rom(Some("game"));
+    profiles.bind(W, 0x2);
+    profiles.save().unwrap();
+
+    let loaded = KeyProfiles::load(&path).unwrap();
+    assert_eq!(loaded.profiles, profiles.profiles);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use chip_8_emulator::emulator::Emulator;
+
+#[test]
+fn skips_see_every_held_key() {
+    // V1 = 1 if key 3 is held, V2 = 1 if key 7 is held.
+    let code = [0x6003, 0xE0A1, 0x6101, 0x6007, 0xE0A1, 0x6201, 0x120C];
+    let mut emulator = common::load(&code);
+    emulator.press_key(0x3);
+    emulator.press_key(0x7);
+    emulator.press_key(0x9);
+    emulator.release_key(0x9);
+    assert!(emulator.run_until(|emulator| emulator.pc() == 0x20C, 100));
+    assert_eq!(emulator.regs()[1], 1);
+    assert_eq!(emulator.regs()[2], 1);
+}
+
+#[test]
+fn releasing_one_key_keeps_the_others() {
+    let mut emulator = Emulator::new();
+    emulator.press_key(0x1);
+    emulator.press_key(0x2);
+    emulator.release_key(0x1);
+    assert!(!emulator.is_key_down(0x1));
+    assert!(emulator.is_key_down(0x2));
+}
+
+#[test]
+fn get_key_waits_for_release() {
+    let code = [0xF50A, 0x1202];
+    let mut emulator = common::load(&code);
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0x200);
+
+    emulator.press_key(0xA);
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0x200);
+
+    emulator.release_key(0xA);
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0x202);
+    assert_eq!(emulator.regs()[5], 0xA);
+}
+
+#[test]
+fn get_key_catches_taps_between_instructions() {
+    let code = [0xF50A, 0x1202];
+    let mut emulator = common::load(&code);
+    emulator.step_instruction();
+    emulator.press_key(0x4);
+    emulator.release_key(0x4);
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0x202);
+    assert_eq!(emulator.regs()[5], 0x4);
+}
+
+#[test]
+fn get_key_ignores_releases_before_it_ran() {
+    let code = [0xF50A, 0x1202];
+    let mut emulator = common::load(&code);
+    emulator.press_key(0x4);
+    emulator.release_key(0x4);
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0x200);
+}
+
+#[test]
+fn waiting_for_a_key_at_the_end_of_memory_stays_put() {
+    let mut emulator = common::load(&[0x1200]);
+    emulator.poke(0xFFFE, 0xF0).unwrap();
+    emulator.poke(0xFFFF, 0x0A).unwrap();
+    emulator.set_pc(0xFFFE).unwrap();
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0xFFFE);
+    assert!(emulator.halted().is_none());
+}
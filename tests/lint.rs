@@ -0,0 +1,68 @@
+use chip_8_emulator::lint::{lint_rom, LintKind, LintWarning};
+
+const OFFSET: u16 = 0x200;
+
+#[test]
+fn ibm_logo_is_clean() {
+    let rom = std::fs::read(format!(
+        "{}/resources/roms/IBM Logo.ch8",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+    .unwrap();
+    assert_eq!(lint_rom(&rom, OFFSET), Vec::new());
+}
+
+#[test]
+fn unknown_opcode_is_reported() {
+    // 0x200: 8XYF (unknown), 0x202: jump to self.
+    let rom = [0x81, 0x2F, 0x12, 0x02];
+    assert_eq!(
+        lint_rom(&rom, OFFSET),
+        vec![LintWarning {
+            address: 0x200,
+            kind: LintKind::UnknownOpcode(0x812F),
+        }]
+    );
+}
+
+#[test]
+fn unreachable_data_is_ignored() {
+    // Jump over a sprite that would decode as unknown opcodes.
+    let rom = [0x12, 0x04, 0xFF, 0xFF, 0x12, 0x04];
+    assert_eq!(lint_rom(&rom, OFFSET), Vec::new());
+}
+
+#[test]
+fn out_of_range_jump_is_reported() {
+    let rom = [0x1F, 0x00];
+    assert_eq!(
+        lint_rom(&rom, OFFSET),
+        vec![LintWarning {
+            address: 0x200,
+            kind: LintKind::TargetOutOfRange(0xF00),
+        }]
+    );
+}
+
+#[test]
+fn running_off_the_end_is_reported() {
+    let rom = [0x60, 0x01];
+    assert_eq!(
+        lint_rom(&rom, OFFSET),
+        vec![LintWarning {
+            address: 0x202,
+            kind: LintKind::RunsOffEnd,
+        }]
+    );
+}
+
+#[test]
+fn overlapping_code_is_reported() {
+    // 0x200 skips to 0x204 or falls to 0x202, which jumps into the middle at 0x205.
+    let rom = [0x30, 0x00, 0x12, 0x05, 0x12, 0x12, 0x04, 0x00];
+    let warnings = lint_rom(&rom, OFFSET);
+    assert!(warnings.contains(&LintWarning {
+        address: 0x204,
+        kind: LintKind::OverlappingCode,
+    }));
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use chip_8_emulator::{
+    emulator::{Emulator, ETI660_LOAD_OFFSET},
+    error::EmulatorError,
+    machine::MachineProfile,
+    memory_map::Region,
+    quirks::QuirkPreset,
+};
+
+/// I = 0x0FFF, V0 = mem[0xFFF], V1 = mem[0x1000] on 64 KiB machines.
+const READ_PAST_4K: [u16; 2] = [0xAFFF, 0xF165];
+
+use common::rom;
+
+#[test]
+fn eti660_roms_load_at_0x600() {
+    let mut emulator = Emulator::new();
+    emulator.load_rom_data("game.eti", &rom(&[0x1600])).unwrap();
+    assert_eq!(emulator.profile, MachineProfile::Eti660);
+    assert_eq!(emulator.load_offset, ETI660_LOAD_OFFSET);
+    assert_eq!(emulator.pc(), ETI660_LOAD_OFFSET);
+    assert_eq!(emulator.memory()[0x600..0x602], [0x16, 0x00]);
+    assert_eq!(QuirkPreset::detect(&emulator), Some(QuirkPreset::CosmacVip));
+
+    // A regular ROM afterwards goes back to the generic layout.
+    emulator.load_rom_data("game.ch8", &rom(&[0x1200])).unwrap();
+    assert_eq!(emulator.profile, MachineProfile::Generic);
+    assert_eq!(emulator.pc(), 0x200);
+}
+
+#[test]
+fn profiles_keep_roms_out_of_reserved_memory() {
+    let mut emulator = Emulator::new();
+    MachineProfile::CosmacVip.apply(&mut emulator);
+    assert_eq!(MachineProfile::CosmacVip.max_rom_size(0x200), 0xCA0);
+    let fits = vec![0; 0xCA0];
+    emulator.load_rom_data("fits.ch8", &fits).unwrap();
+    let too_large = vec![0; 0xCA1];
+    assert!(matches!(
+        emulator.load_rom_data("large.ch8", &too_large),
+        Err(EmulatorError::RomTooLarge { max: 0xCA0, .. })
+    ));
+
+    assert_eq!(Region::of(&emulator, 0xF10), Some(Region::Reserved));
+    assert_eq!(Region::of(&emulator, 0x10), Some(Region::Reserved));
+    assert_eq!(MachineProfile::Eti660.max_rom_size(0x600), 0x8A0);
+    assert_eq!(MachineProfile::XoChip.max_rom_size(0x200), 0xFE00);
+}
+
+#[test]
+fn memory_size_follows_the_profile() {
+    let mut emulator = Emulator::new();
+    emulator
+        .load_rom_data("read.ch8", &rom(&READ_PAST_4K))
+        .unwrap();
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert!(emulator.halted().is_none());
+
+    MachineProfile::SuperChip.apply(&mut emulator);
+    emulator
+        .load_rom_data("read.ch8", &rom(&READ_PAST_4K))
+        .unwrap();
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert!(emulator.halted().is_some());
+    assert_eq!(emulator.pc(), 0x202);
+}
+
+#[test]
+fn applying_a_profile_sets_its_quirks() {
+    let mut emulator = Emulator::new();
+    for profile in MachineProfile::ALL {
+        profile.apply(&mut emulator);
+        assert_eq!(emulator.quirks, profile.quirks());
+        assert_eq!(emulator.load_offset, profile.load_offset());
+    }
+}
+
+#[test]
+fn profiles_parse_from_their_ids() {
+    for profile in MachineProfile::ALL {
+        assert_eq!(profile.id().parse(), Ok(profile));
+    }
+    assert_eq!("SCHIP".parse(), Ok(MachineProfile::SuperChip));
+    let err = "chip48".parse::<MachineProfile>().unwrap_err();
+    assert!(err.contains("generic, vip, eti660, schip, xochip"));
+}
@@ -0,0 +1,308 @@
+mod common;
+
+use chip_8_emulator::{
+    buzzer::BuzzerQueue,
+    emulator::Emulator,
+    machine::MachineProfile,
+    megachip::{BlendMode, MegaChip, Sample, MEGA_DISPLAY_SIZE},
+};
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+use common::rom;
+
+fn emulator(path: &str, data: &[u8]) -> Emulator {
+    let mut emulator = Emulator::new();
+    emulator.load_rom_data(path, data).unwrap();
+    emulator.pause();
+    emulator
+}
+
+fn pixel(x: usize, y: usize) -> usize {
+    y * MEGA_DISPLAY_SIZE.0 + x
+}
+
+#[test]
+fn blend_modes_mix_channels() {
+    let (source, target) = ([200, 0, 100, 255], [0, 100, 100, 255]);
+    assert_eq!(BlendMode::Normal.blend(source, target), source);
+    assert_eq!(
+        BlendMode::Alpha50.blend(source, target),
+        [100, 50, 100, 255]
+    );
+    assert_eq!(BlendMode::Alpha25.blend(source, target), [50, 75, 100, 255]);
+    assert_eq!(BlendMode::Add.blend(source, target), [200, 100, 200, 255]);
+    assert_eq!(
+        BlendMode::Multiply.blend([255, 128, 0, 255], [100, 100, 100, 255]),
+        [100, 50, 0, 255]
+    );
+    assert_eq!(BlendMode::from_operand(5), Some(BlendMode::Multiply));
+    assert_eq!(BlendMode::from_operand(6), None);
+}
+
+#[test]
+fn sprites_draw_palette_indices_and_collide() {
+    let mut megachip = MegaChip::default();
+    megachip.load_palette(&[0xFF, 10, 20, 30, 0x80, 1, 2, 3]);
+    assert_eq!(megachip.palette[1], [10, 20, 30, 0xFF]);
+    assert_eq!(megachip.palette[2], [1, 2, 3, 0x80]);
+    megachip.sprite_width = 2;
+    megachip.sprite_height = 1;
+    megachip.collision_color = 1;
+
+    assert!(!megachip.draw_sprite(10, 5, &[1, 0]));
+    assert_eq!(megachip.back_buffer()[pixel(10, 5)], [10, 20, 30, 255]);
+    // Index 0 leaves what's under it.
+    assert!(!megachip.draw_sprite(9, 5, &[2, 0]));
+    assert_eq!(megachip.back_buffer()[pixel(10, 5)], [10, 20, 30, 255]);
+    assert!(megachip.draw_sprite(9, 5, &[0, 2]));
+    assert_eq!(megachip.back_buffer()[pixel(10, 5)], [1, 2, 3, 255]);
+
+    // Nothing shows until it's presented.
+    assert_eq!(megachip.frame()[pixel(10, 5)], BLACK);
+}
+
+#[test]
+fn sprites_clip_at_the_edges() {
+    let mut megachip = MegaChip::default();
+    megachip.sprite_width = 4;
+    megachip.sprite_height = 1;
+    megachip.draw_sprite(254, 191, &[255; 4]);
+    megachip.present();
+    let lit = megachip
+        .frame()
+        .iter()
+        .filter(|color| **color == WHITE)
+        .count();
+    assert_eq!(lit, 2);
+    assert_eq!(megachip.frame()[pixel(255, 191)], WHITE);
+}
+
+#[test]
+fn present_fades_by_the_screen_alpha_and_clears() {
+    let mut megachip = MegaChip::default();
+    megachip.sprite_width = 1;
+    megachip.sprite_height = 1;
+    megachip.alpha = 0x80;
+    megachip.draw_sprite(0, 0, &[255]);
+    megachip.present();
+    assert_eq!(megachip.frame()[0], [128, 128, 128, 255]);
+    assert_eq!(megachip.back_buffer()[0], BLACK);
+
+    megachip.present();
+    assert_eq!(megachip.frame()[0], BLACK);
+}
+
+#[test]
+fn samples_play_at_their_rate() {
+    // 120 Hz plays two samples a tick.
+    let memory = [0x00, 0x78, 0x00, 0x00, 0x03, 0x80, 0xFF, 0x00];
+    let sample = Sample::read(0, false, |address| memory[address as usize]);
+    assert_eq!(sample.rate, 120);
+    assert_eq!(sample.data.len(), 3);
+
+    let mut megachip = MegaChip::default();
+    megachip.sample = Some(sample.clone());
+    assert_eq!(*megachip.next_sample_tick().unwrap(), [0.0, 127.0 / 128.0]);
+    assert_eq!(*megachip.next_sample_tick().unwrap(), [-1.0]);
+    assert_eq!(megachip.sample, None);
+    assert_eq!(megachip.next_sample_tick(), None);
+
+    let mut looping = sample;
+    looping.looping = true;
+    looping.next_tick();
+    assert_eq!(looping.next_tick(), [-1.0, 0.0]);
+    assert!(!looping.is_over());
+}
+
+#[test]
+fn roms_switch_mode_and_draw() {
+    let mut data = rom(&[
+        0x0011, // 200: MegaChip mode on
+        0x0100, 0x0220, // 202: I = 0x000220
+        0x0201, // 206: load 1 color
+        0x0100, 0x0224, // 208: I = 0x000224
+        0x0302, // 20C: sprite width 2
+        0x0401, // 20E: sprite height 1
+        0x6004, // 210: V0 = 4
+        0x6103, // 212: V1 = 3
+        0xD010, // 214: draw at (V0, V1)
+        0x00E0, // 216: present
+        0x0010, // 218: MegaChip mode off
+        0x121A, // 21A: loop
+    ]);
+    data.resize(0x20, 0);
+    data.extend([0xFF, 0x11, 0x22, 0x33, 0x01, 0x01]);
+    let mut emulator = emulator("test.mc8", &data);
+
+    emulator.step_instruction();
+    assert!(emulator.megachip().is_some());
+    for _ in 0..9 {
+        emulator.step_instruction();
+    }
+    let megachip = emulator.megachip().unwrap();
+    assert_eq!(megachip.palette[1], [0x11, 0x22, 0x33, 0xFF]);
+    let frame = megachip.frame();
+    assert_eq!(frame[pixel(4, 3)], [0x11, 0x22, 0x33, 255]);
+    assert_eq!(frame[pixel(5, 3)], [0x11, 0x22, 0x33, 255]);
+    assert_eq!(frame[pixel(6, 3)], BLACK);
+    assert_eq!(emulator.registers().v[0xF], 0);
+
+    emulator.step_instruction();
+    assert!(emulator.megachip().is_none());
+}
+
+#[test]
+fn long_i_skips_as_one_instruction() {
+    let data = rom(&[
+        0x0011, // 200: MegaChip mode on
+        0x3000, // 202: V0 == 0, skip
+        0x0100, 0x0300, // 204: I = 0x000300
+        0xA123, // 208: I = 0x123
+    ]);
+    let mut emulator = emulator("test.mc8", &data);
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0x208);
+}
+
+#[test]
+fn mc8_roms_reach_past_64k() {
+    let mut data = rom(&[
+        0x0011, // 200: MegaChip mode on
+        0x0101, 0x0000, // 202: I = 0x010000
+        0x0201, // 206: load 1 color from past the end of memory
+        0x0101, 0x0004, // 208: I = 0x010004
+        0x0601, // 20C: play the sample there once
+        0x120E, // 20E: loop
+    ]);
+    data.resize(0x10000 - 0x200, 0);
+    data.extend([0xFF, 1, 2, 3]);
+    data.extend([0x00, 0x78, 0x00, 0x00, 0x02, 0xFF, 0xFF]);
+    let mut emulator = emulator("game.mc8", &data);
+    assert_eq!(emulator.profile, MachineProfile::MegaChip);
+    assert_eq!(emulator.memory().len(), 0x10000);
+
+    for _ in 0..3 {
+        emulator.step_instruction();
+    }
+    assert_eq!(emulator.megachip().unwrap().palette[1], [1, 2, 3, 0xFF]);
+
+    emulator.step_instruction();
+    emulator.step_instruction();
+    let sample = emulator.megachip().unwrap().sample.clone().unwrap();
+    assert_eq!(sample.rate, 120);
+    assert_eq!(sample.data.len(), 2);
+    assert!(!sample.looping);
+}
+
+#[test]
+fn samples_reach_the_buzzer() {
+    let mut data = rom(&[
+        0x0011, // 200: MegaChip mode on
+        0x0100, 0x0210, // 202: I = 0x000210
+        0x0600, // 206: loop the sample
+        0x1208, // 208: loop
+    ]);
+    data.resize(0x10, 0);
+    data.extend([0x00, 0x78, 0x00, 0x00, 0x01, 0xFF]);
+    let mut emulator = emulator("test.mc8", &data);
+    let queue = BuzzerQueue::default();
+    emulator.buzzer = Some(queue.clone());
+    for _ in 0..3 {
+        emulator.step_instruction();
+    }
+    emulator.step_frame();
+    let tick = queue.pop().unwrap();
+    assert!(!tick.active);
+    assert_eq!(*tick.sample.unwrap(), [127.0 / 128.0; 2]);
+}
+
+#[test]
+fn only_the_megachip_profile_switches_modes() {
+    let mut emulator = emulator("test.ch8", &rom(&[0x0011]));
+    emulator.step_instruction();
+    assert!(emulator.megachip().is_none());
+    assert!(emulator.halted().is_some());
+}
+
+#[test]
+fn switching_modes_can_be_undone() {
+    let mut emulator = emulator("test.mc8", &rom(&[0x0011, 0x0010, 0x1204]));
+    emulator.step_instruction();
+    assert!(emulator.megachip().is_some());
+    emulator.step_instruction();
+    assert!(emulator.megachip().is_none());
+
+    assert!(emulator.step_back());
+    assert!(emulator.megachip().is_some());
+    assert!(emulator.step_back());
+    assert!(emulator.megachip().is_none());
+}
+
+#[test]
+fn restoring_a_state_leaves_megachip_mode() {
+    let mut emulator = emulator("test.mc8", &rom(&[0x0011, 0x1202]));
+    let state = emulator.snapshot();
+    emulator.step_instruction();
+    assert!(emulator.megachip().is_some());
+    emulator.restore(&state);
+    assert!(emulator.megachip().is_none());
+    assert_eq!(emulator.pc(), 0x200);
+}
+
+#[test]
+fn presenting_a_frame_reports_a_display_change() {
+    let mut emulator = emulator("test.mc8", &rom(&[0x0011, 0x0100, 0x0300, 0x00E0]));
+    emulator.step_instruction();
+    assert!(emulator.display_changed_since_last_read());
+    emulator.step_instruction();
+    assert!(!emulator.display_changed_since_last_read());
+    emulator.step_instruction();
+    assert!(emulator.display_changed_since_last_read());
+}
+
+#[test]
+fn stepping_back_undoes_drawing_and_presenting() {
+    let mut data = rom(&[
+        0x0011, // 200: MegaChip mode on
+        0x0100, 0x0220, // 202: I = 0x000220
+        0x0201, // 206: load 1 color
+        0x0100, 0x0224, // 208: I = 0x000224
+        0x0302, // 20C: sprite width 2
+        0x0401, // 20E: sprite height 1
+        0x6004, // 210: V0 = 4
+        0x6103, // 212: V1 = 3
+        0xD010, // 214: draw at (V0, V1)
+        0x00E0, // 216: present
+        0x0500, // 218: screen alpha 0
+        0xD010, // 21A: draw at (V0, V1)
+        0x00E0, // 21C: present
+    ]);
+    data.resize(0x20, 0);
+    data.extend([0xFF, 0x11, 0x22, 0x33, 0x01, 0x01]);
+    let mut emulator = emulator("test.mc8", &data);
+    for _ in 0..8 {
+        emulator.step_instruction();
+    }
+    let before = emulator.megachip().unwrap().clone();
+    emulator.step_instruction();
+    emulator.step_instruction();
+    let drawn = emulator.megachip().unwrap().clone();
+    for _ in 0..3 {
+        emulator.step_instruction();
+    }
+    assert_eq!(emulator.megachip().unwrap().frame()[pixel(4, 3)], BLACK);
+
+    for _ in 0..3 {
+        assert!(emulator.step_back());
+    }
+    assert_eq!(emulator.megachip().unwrap(), &drawn);
+    assert!(emulator.step_back());
+    assert!(emulator.step_back());
+    let megachip = emulator.megachip().unwrap();
+    assert_eq!(megachip.frame(), before.frame());
+    assert_eq!(megachip.back_buffer(), before.back_buffer());
+    assert_eq!(megachip, &before);
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use chip_8_emulator::memory_map::{self, Region};
+
+#[test]
+fn regions_follow_the_machine_state() {
+    // Store V0..V1 at 0x300, then point I at 0x400.
+    let mut emulator = common::load(&[0xA300, 0xF155, 0xA400, 0x1206]);
+    for _ in 0..3 {
+        emulator.step_instruction();
+    }
+    assert_eq!(Region::of(&emulator, 0x050), Some(Region::Font));
+    assert_eq!(Region::of(&emulator, 0x200), Some(Region::Rom));
+    assert_eq!(Region::of(&emulator, 0x206), Some(Region::ProgramCounter));
+    assert_eq!(Region::of(&emulator, 0x207), Some(Region::ProgramCounter));
+    assert_eq!(Region::of(&emulator, 0x300), Some(Region::Written));
+    assert_eq!(Region::of(&emulator, 0x301), Some(Region::Written));
+    assert_eq!(Region::of(&emulator, 0x302), None);
+    assert_eq!(Region::of(&emulator, 0x400), Some(Region::Index));
+}
+
+#[test]
+fn pokes_and_resets_forget_written_bytes() {
+    let mut emulator = common::load(&[0xA300, 0xF055]);
+    emulator.poke(0x400, 1).unwrap();
+    assert!(!emulator.was_written(0x400));
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert!(emulator.was_written(0x300));
+    emulator.reset();
+    assert!(!emulator.was_written(0x300));
+}
+
+#[test]
+fn parses_hex_byte_patterns() {
+    assert_eq!(memory_map::parse_bytes("A2 1e"), Some(vec![0xA2, 0x1E]));
+    assert_eq!(memory_map::parse_bytes("a21e"), Some(vec![0xA2, 0x1E]));
+    assert_eq!(memory_map::parse_bytes("A2 1"), None);
+    assert_eq!(memory_map::parse_bytes("+1"), None);
+    assert_eq!(memory_map::parse_bytes(""), None);
+}
+
+#[test]
+fn find_wraps_around() {
+    let memory = [0x12, 0x34, 0x00, 0x12, 0x34];
+    assert_eq!(memory_map::find(&memory, &[0x12, 0x34], 0), Some(0));
+    assert_eq!(memory_map::find(&memory, &[0x12, 0x34], 1), Some(3));
+    assert_eq!(memory_map::find(&memory, &[0x12, 0x34], 4), Some(0));
+    assert_eq!(memory_map::find(&memory, &[0x56], 0), None);
+}
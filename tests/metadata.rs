@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use chip_8_emulator::{
+    metadata::{extension_metadata, is_rom_file, OctoOptions, RomDatabase},
+    quirks::{IndexIncrement, QuirkPreset},
+};
+
+const DATABASE: &str = r#"[
+    {
+        "title": "Br8kout",
+        "authors": ["SharpenedSpoon"],
+        "release": "2014",
+        "roms": {
+            "ABCDEF0123": { "file": "br8kout.ch8", "platforms": ["superchip", "xochip"], "tickrate": 30 }
+        },
+        "origin": { "type": "gamejam" }
+    },
+    { "title": "No ROMs" }
+]"#;
+
+#[test]
+fn database_entries_are_found_by_hash() {
+    let database = RomDatabase::from_json(DATABASE).unwrap();
+    assert_eq!(database.len(), 1);
+    let rom = database.get("abcdef0123").unwrap();
+    assert_eq!(rom.title.as_deref(), Some("Br8kout"));
+    assert_eq!(rom.authors, ["SharpenedSpoon"]);
+    assert_eq!(rom.platform.as_deref(), Some("superchip"));
+    assert_eq!(rom.quirks, Some(QuirkPreset::SuperChip.quirks()));
+
+    let settings = rom.settings().unwrap();
+    assert_eq!(settings.cpu_hz, Some(30.0 * 60.0));
+}
+
+#[test]
+fn octo_options_map_to_quirks() {
+    let json = r#"{"program": ": main", "options": {"tickrate": 20, "shiftQuirks": true,
+        "loadStoreQuirks": true, "clipQuirks": true, "vBlankQuirks": false}}"#;
+    let options = OctoOptions::from_json(json).unwrap();
+    assert_eq!(options.tickrate, Some(20));
+    let quirks = options.quirks();
+    assert!(!quirks.shift_swap);
+    assert_eq!(quirks.index_increment, IndexIncrement::None);
+    assert!(quirks.clipping);
+    assert!(!quirks.display_wait);
+    // Flags Octo didn't set keep its XO-CHIP defaults.
+    assert_eq!(
+        quirks.complex_jump,
+        QuirkPreset::XoChip.quirks().complex_jump
+    );
+
+    let bare = OctoOptions::from_json(r#"{"tickrate": 7}"#).unwrap();
+    assert_eq!(bare.tickrate, Some(7));
+}
+
+#[test]
+fn metadata_sources_fill_each_other_in() {
+    let database = RomDatabase::from_json(DATABASE).unwrap();
+    let octo = OctoOptions::from_json(r#"{"tickrate": 200}"#).unwrap();
+    let metadata = octo
+        .metadata()
+        .or(database.get("abcdef0123").cloned().unwrap());
+    assert_eq!(metadata.title.as_deref(), Some("Br8kout"));
+    assert_eq!(metadata.tickrate, Some(200));
+}
+
+#[test]
+fn extensions_hint_at_the_platform() {
+    let superchip = extension_metadata(Path::new("roms/car.sc8"));
+    assert_eq!(superchip.quirks, Some(QuirkPreset::SuperChip.quirks()));
+    assert_eq!(extension_metadata(Path::new("pong.ch8")).quirks, None);
+    assert!(is_rom_file(Path::new("games/Snake.CH8")));
+    assert!(is_rom_file(Path::new("games/t8nks.xo8")));
+    assert!(!is_rom_file(Path::new("games/readme.txt")));
+}
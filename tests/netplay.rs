@@ -0,0 +1,157 @@
+mod common;
+
+use std::{
+    io::{BufRead, BufReader},
+    net::TcpStream,
+    thread,
+    time::Duration,
+};
+
+use chip_8_emulator::{
+    emulator::Emulator,
+    netplay::{Netplay, NetplayHost, NetplayRole},
+};
+
+/// V0 = random, V2 counts the loops key 0 was held in, V3 the ones key 1 was.
+const TWO_PLAYERS: [u16; 9] = [
+    0x6601, 0xC0FF, 0xE59E, 0x120A, 0x7201, 0xE69E, 0x1202, 0x7301, 0x1202,
+];
+
+fn load(code: &[u16]) -> Emulator {
+    let mut emulator = common::load(code);
+    emulator.cpf = 10;
+    emulator
+}
+
+/// Connects a host and a guest on localhost, both running their ROM in lockstep.
+fn connect(host_code: &[u16], guest_code: &'static [u16]) -> (Emulator, Emulator) {
+    let mut host = load(host_code);
+    let listener = NetplayHost::listen(0, 2).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let joining = thread::spawn(move || {
+        let mut guest = load(guest_code);
+        let netplay = Netplay::join(("127.0.0.1", port), &guest)?;
+        assert_eq!(netplay.role(), NetplayRole::Guest);
+        guest.start_netplay(netplay).unwrap();
+        Ok::<_, std::io::Error>(guest)
+    });
+    let netplay = loop {
+        if let Some(netplay) = listener.accept(&host).unwrap() {
+            break netplay;
+        }
+        thread::sleep(Duration::from_millis(1));
+    };
+    host.start_netplay(netplay).unwrap();
+    let guest = joining.join().unwrap().unwrap();
+    (host, guest)
+}
+
+/// Steps both sides until they ran `frames` frames.
+fn run(host: &mut Emulator, guest: &mut Emulator, frames: u128) {
+    while host.frame_count() < frames || guest.frame_count() < frames {
+        if host.frame_count() < frames {
+            host.step_frame();
+        }
+        if guest.frame_count() < frames {
+            guest.step_frame();
+        }
+    }
+}
+
+#[test]
+fn both_sides_see_the_same_game() {
+    let (mut host, mut guest) = connect(&TWO_PLAYERS, &TWO_PLAYERS);
+    assert_eq!(host.rng_seed(), guest.rng_seed());
+    host.press_key(0x0);
+    guest.press_key(0x1);
+    run(&mut host, &mut guest, 5);
+    host.release_key(0x0);
+    run(&mut host, &mut guest, 20);
+
+    assert_eq!(host.regs(), guest.regs());
+    assert_eq!(host.pc(), guest.pc());
+    assert_ne!(host.regs()[2], 0);
+    assert_ne!(host.regs()[3], 0);
+    // The player's own key is held locally but only reaches the game after the delay.
+    assert!(guest.is_key_down(0x1));
+}
+
+#[test]
+fn guest_with_another_rom_is_refused() {
+    let host = load(&TWO_PLAYERS);
+    let listener = NetplayHost::listen(0, 2).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let joining = thread::spawn(move || {
+        let guest = load(&[0x1200]);
+        Netplay::join(("127.0.0.1", port), &guest).map(|_| ())
+    });
+    while listener.accept(&host).unwrap().is_none() {
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert!(joining.join().unwrap().is_err());
+}
+
+#[test]
+fn leaving_ends_the_session_on_the_other_side() {
+    let (mut host, mut guest) = connect(&TWO_PLAYERS, &TWO_PLAYERS);
+    run(&mut host, &mut guest, 5);
+    guest.stop_netplay();
+    for _ in 0..1000 {
+        host.step_frame();
+        if host.netplay().is_none() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert!(host.netplay().is_none());
+    assert!(host
+        .break_reason
+        .as_deref()
+        .is_some_and(|reason| reason.starts_with("Netplay ended")));
+}
+
+#[test]
+fn keys_queued_past_the_socket_buffers_arrive_whole() {
+    // Megabytes of keys sent while the other side doesn't read, so the socket stops
+    // taking them partway through a message.
+    const DELAY: u64 = 300_000;
+    let emulator = load(&TWO_PLAYERS);
+    let listener = NetplayHost::listen(0, DELAY as u32).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let guest = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    let mut host = loop {
+        if let Some(netplay) = listener.accept(&emulator).unwrap() {
+            break netplay;
+        }
+        thread::sleep(Duration::from_millis(1));
+    };
+    let mut guest = BufReader::new(guest);
+    let mut hello = String::new();
+    guest.read_line(&mut hello).unwrap();
+    assert!(hello.starts_with(r#"{"Hello":"#));
+
+    // Nobody presses anything during the first frames, the host runs through them while
+    // sending its keys for the frames after.
+    for frame in 0..DELAY {
+        assert_eq!(host.next_frame(frame as u16 & 1).unwrap(), Some(0));
+    }
+    let reader = thread::spawn(move || {
+        let mut keys = Vec::new();
+        for line in (&mut guest).lines() {
+            let message: serde_json::Value = serde_json::from_str(&line.unwrap()).unwrap();
+            assert_eq!(message["Input"]["frame"], DELAY + keys.len() as u64);
+            keys.push(message["Input"]["keys"].as_u64().unwrap());
+            if keys.len() as u64 == DELAY {
+                break;
+            }
+        }
+        // Hung up only once the host is done, it would see a disconnect otherwise.
+        (keys, guest)
+    });
+    // The host is waiting for the guest's keys now, but still sends what's queued.
+    while !reader.is_finished() {
+        assert_eq!(host.next_frame(0).unwrap(), None);
+    }
+    let (keys, _guest) = reader.join().unwrap();
+    assert!(keys.iter().zip(0..).all(|(keys, frame)| *keys == frame & 1));
+}
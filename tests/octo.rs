@@ -0,0 +1,151 @@
+use chip_8_emulator::{
+    emulator::Emulator,
+    error::EmulatorError,
+    octo::{assemble, OctoError},
+};
+
+fn words(rom: &[u8]) -> Vec<u16> {
+    rom.chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], *word.get(1).unwrap_or(&0)]))
+        .collect()
+}
+
+fn run(source: &str, steps: usize) -> Emulator {
+    let mut emulator = Emulator::new();
+    emulator.load_font();
+    emulator
+        .load_rom_data("test.8o", source.as_bytes())
+        .unwrap();
+    for _ in 0..steps {
+        emulator.step_instruction();
+    }
+    emulator
+}
+
+#[test]
+fn statements_assemble_to_their_opcodes() {
+    let source = "
+        : main
+            clear
+            v0 := 5          # 6XNN
+            v1 := v0
+            v1 += 3
+            v1 -= 1
+            v2 := random 0xF
+            v3 := key
+            delay := v0
+            i := hex v1
+            sprite v0 v1 5
+            bcd v2
+            save v2
+            load v2
+            jump main
+    ";
+    assert_eq!(
+        words(&assemble(source).unwrap()),
+        [
+            0x00E0, 0x6005, 0x8100, 0x7103, 0x71FF, 0xC20F, 0xF30A, 0xF015, 0xF129, 0xD015, 0xF233,
+            0xF255, 0xF265, 0x1200
+        ]
+    );
+}
+
+#[test]
+fn main_after_other_code_gets_a_jump() {
+    let source = "
+        : sub
+            v0 := 1
+            return
+        : main
+            sub
+            loop again
+    ";
+    assert_eq!(
+        words(&assemble(source).unwrap()),
+        [0x1206, 0x6001, 0x00EE, 0x2202, 0x1208]
+    );
+}
+
+#[test]
+fn constants_labels_and_data() {
+    let source = "
+        :const SPEED 3
+        :alias x v4
+        : main
+            x := SPEED
+            i := ball
+            :call later
+        : ball
+            :byte 0b11000000
+            0xC0 -1
+        : later
+            ;
+    ";
+    assert_eq!(
+        words(&assemble(source).unwrap()),
+        [0x6403, 0xA206, 0x2209, 0xC0C0, 0xFF00, 0xEE00]
+    );
+}
+
+#[test]
+fn control_flow_runs_as_written() {
+    let source = "
+        : main
+            v1 := 0
+            loop
+                v1 += 1
+                while v1 != 10
+            again
+            if v1 == 10 then v2 := 1
+            if v1 != 10 begin
+                v3 := 1
+            else
+                v3 := 2
+            end
+            : done
+            jump done
+    ";
+    let emulator = run(source, 100);
+    assert_eq!(emulator.regs()[1], 10);
+    assert_eq!(emulator.regs()[2], 1);
+    assert_eq!(emulator.regs()[3], 2);
+}
+
+#[test]
+fn sources_load_from_disk_and_reload() {
+    let path = std::env::temp_dir().join(format!("chip8_octo_{}.8o", std::process::id()));
+    std::fs::write(&path, ": main v5 := 42 : spin jump spin").unwrap();
+    let mut emulator = Emulator::new();
+    emulator
+        .load_rom(path.to_string_lossy().into_owned())
+        .unwrap();
+    emulator.step_instruction();
+    assert_eq!(emulator.regs()[5], 42);
+
+    emulator.reload_rom().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(emulator.regs()[5], 0);
+    emulator.step_instruction();
+    assert_eq!(emulator.regs()[5], 42);
+}
+
+#[test]
+fn errors_name_the_line() {
+    let error = |source: &str| assemble(source).unwrap_err();
+    assert_eq!(
+        error(": main\n  v0 := 300\n"),
+        OctoError {
+            line: 2,
+            message: "300 is out of range (-128 to 255)".to_string()
+        }
+    );
+    assert_eq!(error(": main\n\n  jump nowhere").line, 3);
+    assert_eq!(error("v0 := 1").line, 0);
+    assert_eq!(error(": main\n  loop\n  v0 += 1").line, 2);
+
+    let mut emulator = Emulator::new();
+    assert!(matches!(
+        emulator.load_rom_data("broken.8o", b": main\n  sprite v0"),
+        Err(EmulatorError::Assembly(OctoError { line: 2, .. }))
+    ));
+}
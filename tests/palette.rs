@@ -0,0 +1,37 @@
+use chip_8_emulator::renderer::{Palette, PostEffects, Theme};
+
+#[test]
+fn themes_are_detected() {
+    for theme in Theme::ALL {
+        assert_eq!(Theme::detect(&theme.palette()), Some(theme));
+    }
+    let mut palette = Theme::Amber.palette();
+    palette.foreground = [1, 2, 3, 255];
+    assert_eq!(Theme::detect(&palette), None);
+    assert_eq!(Theme::detect(&Palette::default()), Some(Theme::Classic));
+}
+
+#[test]
+fn palette_round_trips_through_the_config_file() {
+    let path = std::env::temp_dir().join(format!("chip8_palette_{}.json", std::process::id()));
+    let palette = Theme::GreenPhosphor.palette();
+    palette.save(&path).unwrap();
+    let loaded = Palette::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, palette);
+}
+
+#[test]
+fn palette_config_fills_in_missing_colors() {
+    let palette: Palette = serde_json::from_str(r#"{ "foreground": [255, 176, 0, 255] }"#).unwrap();
+    assert_eq!(palette.foreground, [255, 176, 0, 255]);
+    assert_eq!(palette.background, Palette::default().background);
+}
+
+#[test]
+fn effects_are_off_unless_enabled() {
+    let effects: PostEffects = serde_json::from_str(r#"{ "scanlines": 0.8 }"#).unwrap();
+    assert!(!effects.enabled);
+    assert_eq!(effects.scanlines, 0.8);
+    assert_eq!(effects.glow, PostEffects::default().glow);
+}
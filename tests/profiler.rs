@@ -0,0 +1,65 @@
+mod common;
+
+use chip_8_emulator::profiler::opcode_class;
+
+/// Sets V0 once, then loops adding to V1.
+const LOOP: [u16; 3] = [0x6000, 0x7101, 0x1202];
+
+#[test]
+fn counts_nothing_until_enabled() {
+    let mut emulator = common::load(&LOOP);
+    emulator.step_frame();
+    assert_eq!(emulator.profiler.instructions(), 0);
+    assert!(emulator.profiler.hotspots(10).is_empty());
+}
+
+#[test]
+fn counts_addresses_and_classes() {
+    let mut emulator = common::load(&LOOP);
+    emulator.profiler.set_enabled(true);
+    for _ in 0..9 {
+        emulator.step_instruction();
+    }
+    assert_eq!(emulator.profiler.instructions(), 9);
+    assert_eq!(emulator.profiler.hotspots(2), [(0x202, 4), (0x204, 4)]);
+    assert_eq!(emulator.profiler.count(0x200), 1);
+    assert_eq!(
+        emulator.profiler.classes(),
+        [("1NNN", 4), ("7XNN", 4), ("6XNN", 1)]
+    );
+    assert_eq!(emulator.profiler.heat(0x202), 1.0);
+    assert!(emulator.profiler.heat(0x200) < 1.0);
+    assert_eq!(emulator.profiler.heat(0x206), 0.0);
+}
+
+#[test]
+fn instructions_per_frame_follow_cpf() {
+    let mut emulator = common::load(&LOOP);
+    emulator.profiler.set_enabled(true);
+    emulator.cpf = 12;
+    for _ in 0..5 {
+        emulator.step_frame();
+    }
+    assert_eq!(emulator.profiler.frames(), 5);
+    assert_eq!(emulator.profiler.instructions_per_frame(), 12.0);
+}
+
+#[test]
+fn reset_keeps_recording() {
+    let mut emulator = common::load(&LOOP);
+    emulator.profiler.set_enabled(true);
+    emulator.step_instruction();
+    emulator.profiler.reset();
+    assert_eq!(emulator.profiler.instructions(), 0);
+    emulator.step_instruction();
+    assert_eq!(emulator.profiler.instructions(), 1);
+}
+
+#[test]
+fn classes_use_opcode_patterns() {
+    assert_eq!(opcode_class(0x00E0), "00E0");
+    assert_eq!(opcode_class(0x8AB4), "8XY4");
+    assert_eq!(opcode_class(0xD125), "DXYN");
+    assert_eq!(opcode_class(0xF365), "FX65");
+    assert_eq!(opcode_class(0x5121), "DATA");
+}
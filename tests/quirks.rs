@@ -0,0 +1,101 @@
+mod common;
+
+use chip_8_emulator::{
+    emulator::Emulator,
+    quirks::{IndexIncrement, QuirkPreset, Quirks},
+    session::SessionSettings,
+};
+
+fn load(code: &[u16], preset: QuirkPreset) -> Emulator {
+    let mut emulator = Emulator::new();
+    preset.apply(&mut emulator);
+    common::load_with(emulator, code)
+}
+
+#[test]
+fn vf_reset_on_logic_instructions() {
+    // VF = 5, then V0 |= V1.
+    let code = [0x6F05, 0x8011, 0x1204];
+    let mut emulator = load(&code, QuirkPreset::CosmacVip);
+    emulator.run_until(|emulator| emulator.pc() == 0x204, 10);
+    assert_eq!(emulator.regs()[15], 0);
+
+    let mut emulator = load(&code, QuirkPreset::Modern);
+    emulator.run_until(|emulator| emulator.pc() == 0x204, 10);
+    assert_eq!(emulator.regs()[15], 5);
+}
+
+#[test]
+fn sprites_clip_or_wrap_at_the_edge() {
+    // Draw the top row of "0" (0xF0) at x = 62, half of it past the right edge.
+    let code = [0x603E, 0x6100, 0xA050, 0xD011, 0x1208];
+    let mut emulator = load(&code, QuirkPreset::Modern);
+    emulator.run_until(|emulator| emulator.pc() == 0x208, 10);
+    assert_eq!(emulator.display[63][0], 1);
+    assert_eq!(emulator.display[0][0], 0);
+
+    let mut emulator = load(&code, QuirkPreset::XoChip);
+    emulator.run_until(|emulator| emulator.pc() == 0x208, 10);
+    assert_eq!(emulator.display[63][0], 1);
+    assert_eq!(emulator.display[0][0], 1);
+    assert_eq!(emulator.display[1][0], 1);
+}
+
+#[test]
+fn display_wait_draws_once_per_frame() {
+    let code = [0xA050, 0xD005, 0xD005, 0xD005, 0x1208];
+    let mut emulator = load(&code, QuirkPreset::CosmacVip);
+    emulator.step_frame();
+    assert_eq!(emulator.pc(), 0x204);
+    emulator.step_frame();
+    assert_eq!(emulator.pc(), 0x206);
+
+    let mut emulator = load(&code, QuirkPreset::Modern);
+    emulator.step_frame();
+    assert_eq!(emulator.pc(), 0x208);
+}
+
+#[test]
+fn presets_are_detected() {
+    let mut emulator = Emulator::new();
+    assert_eq!(QuirkPreset::detect(&emulator), Some(QuirkPreset::Modern));
+    for preset in QuirkPreset::ALL {
+        preset.apply(&mut emulator);
+        assert_eq!(QuirkPreset::detect(&emulator), Some(preset));
+    }
+    emulator.quirks.vf_reset = !emulator.quirks.vf_reset;
+    assert_eq!(QuirkPreset::detect(&emulator), None);
+}
+
+#[test]
+fn quirks_config_fills_in_missing_fields() {
+    let quirks: Quirks = serde_json::from_str(r#"{ "vf_reset": true }"#).unwrap();
+    assert!(quirks.vf_reset);
+    assert!(quirks.clipping);
+    assert_eq!(quirks.index_increment, IndexIncrement::None);
+}
+
+#[test]
+fn old_session_settings_still_load() {
+    let json = r#"{ "max_fps": 60, "cpf": 10, "shift_swap": true, "complex_jump": false }"#;
+    let settings: SessionSettings = serde_json::from_str(json).unwrap();
+    assert!(settings.quirks.shift_swap);
+    assert!(!settings.quirks.display_wait);
+}
+
+#[test]
+fn display_wait_is_part_of_the_machine_state() {
+    let code = [0xA050, 0xD005, 0x1204];
+    let mut emulator = load(&code, QuirkPreset::CosmacVip);
+    emulator.step_instruction();
+    emulator.step_instruction();
+    let waiting = emulator.snapshot();
+    assert!(waiting.waiting_vblank);
+
+    assert!(emulator.step_back());
+    assert!(!emulator.snapshot().waiting_vblank);
+    emulator.restore(&waiting);
+    assert!(emulator.snapshot().waiting_vblank);
+    emulator.reset();
+    assert!(!emulator.snapshot().waiting_vblank);
+}
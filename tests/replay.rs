@@ -0,0 +1,89 @@
+mod common;
+
+use chip_8_emulator::{
+    emulator::Emulator,
+    replay::{Replay, ReplayStatus},
+};
+
+/// V0 = random, V2 counts the loops key 0 was held in.
+const COUNTER: [u16; 5] = [0xC0FF, 0xE19E, 0x1200, 0x7201, 0x1200];
+
+fn load(code: &[u16]) -> Emulator {
+    let mut emulator = common::load(code);
+    emulator.cpf = 7;
+    emulator
+}
+
+/// Records 10 frames with key 0 held in frames 3 to 5.
+fn record(emulator: &mut Emulator) -> Replay {
+    emulator.start_input_recording().unwrap();
+    for frame in 0..10 {
+        match frame {
+            3 => emulator.press_key(0x0),
+            6 => emulator.release_key(0x0),
+            _ => {}
+        }
+        emulator.step_frame();
+    }
+    emulator.stop_input_recording().unwrap()
+}
+
+fn play(emulator: &mut Emulator, replay: Replay) {
+    emulator.play_replay(replay).unwrap();
+    while emulator.replay_status() != ReplayStatus::Off {
+        emulator.step_frame();
+    }
+}
+
+#[test]
+fn replay_reproduces_keys_and_random_numbers() {
+    let mut recorded = load(&COUNTER);
+    let replay = record(&mut recorded);
+    assert_eq!(replay.frames.len(), 11);
+    assert_ne!(recorded.regs()[2], 0);
+
+    let mut replayed = load(&COUNTER);
+    play(&mut replayed, replay);
+    assert_eq!(replayed.regs(), recorded.regs());
+    assert_eq!(replayed.pc(), recorded.pc());
+    assert_eq!(replayed.frame_count(), recorded.frame_count());
+    assert_eq!(replayed.break_reason.as_deref(), Some("Replay finished."));
+}
+
+#[test]
+fn keys_held_during_playback_are_ignored() {
+    let mut recorded = load(&COUNTER);
+    let replay = record(&mut recorded);
+
+    let mut replayed = load(&COUNTER);
+    replayed.play_replay(replay).unwrap();
+    replayed.press_key(0x0);
+    while replayed.replay_status() != ReplayStatus::Off {
+        replayed.step_frame();
+    }
+    assert_eq!(replayed.regs()[2], recorded.regs()[2]);
+    // The player's keys come back once the replay is over.
+    assert!(replayed.is_key_down(0x0));
+}
+
+#[test]
+fn replay_survives_a_round_trip_through_disk() {
+    let mut emulator = load(&COUNTER);
+    let replay = record(&mut emulator);
+
+    let path = std::env::temp_dir().join(format!("chip8_replay_{}.c8rep", std::process::id()));
+    replay.save(&path).unwrap();
+    let loaded = Replay::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, replay);
+}
+
+#[test]
+fn replay_of_another_rom_is_rejected() {
+    let mut emulator = load(&COUNTER);
+    let replay = record(&mut emulator);
+
+    let mut other = load(&[0x1200]);
+    assert!(other.play_replay(replay).is_err());
+    assert_eq!(other.replay_status(), ReplayStatus::Off);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+/// Draws "0" at (V0, 0), stores V0 at 0x300 through a subroutine and moves right.
+const PROGRAM: [u16; 8] = [
+    0xA050, 0xD015, 0x220A, 0x7008, 0x1200, 0xA300, 0xF055, 0x00EE,
+];
+
+#[test]
+fn step_back_restores_registers_memory_and_display() {
+    let mut emulator = common::load(&PROGRAM);
+    let start = emulator.snapshot();
+    assert!(emulator.run_until(|emulator| emulator.regs()[0] == 16, 1000));
+    assert_eq!(emulator.memory()[0x300], 8);
+    assert_eq!(emulator.display[8][0], 1);
+
+    while emulator.step_back() {}
+    let rewound = emulator.snapshot();
+    assert_eq!(rewound.pc, start.pc);
+    assert_eq!(rewound.regs, start.regs);
+    assert_eq!(rewound.reg_i, start.reg_i);
+    assert_eq!(rewound.stack, start.stack);
+    assert_eq!(rewound.mem, start.mem);
+    assert_eq!(rewound.display, start.display);
+}
+
+#[test]
+fn step_back_undoes_one_instruction() {
+    let mut emulator = common::load(&PROGRAM);
+    emulator.step_instruction();
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0x20A);
+    assert_eq!(emulator.stack(), &[0x206]);
+
+    assert!(emulator.step_back());
+    assert_eq!(emulator.pc(), 0x204);
+    assert!(emulator.stack().is_empty());
+    assert_eq!(emulator.display[0][0], 1);
+
+    assert!(emulator.step_back());
+    assert_eq!(emulator.display[0][0], 0);
+}
+
+#[test]
+fn history_depth_limits_steps_back() {
+    let mut emulator = common::load(&PROGRAM);
+    emulator.history.set_depth(4);
+    assert!(!emulator.run_until(|_| false, 20));
+    assert_eq!(emulator.history.len(), 4);
+    for _ in 0..4 {
+        assert!(emulator.step_back());
+    }
+    assert!(!emulator.step_back());
+
+    emulator.history.set_depth(0);
+    emulator.step_instruction();
+    assert!(emulator.history.is_empty());
+}
+
+#[test]
+fn step_back_restores_rpl_flags() {
+    let mut emulator = common::load(&[0x6011, 0xF075, 0x6022, 0xF075, 0xF085]);
+    for _ in 0..4 {
+        emulator.step_instruction();
+    }
+    assert!(emulator.step_back());
+    emulator.set_pc(0x208).unwrap();
+    emulator.step_instruction();
+    assert_eq!(emulator.regs()[0], 0x11);
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use chip_8_emulator::{
+    emulator::{Emulator, VIP_RNG_PAGE},
+    quirks::RngMode,
+};
+
+/// V0 to V7 = random bytes.
+const RANDOM: [u16; 9] = [
+    0xC0FF, 0xC1FF, 0xC2FF, 0xC3FF, 0xC4FF, 0xC5FF, 0xC6FF, 0xC7FF, 0x1210,
+];
+
+fn load(emulator: Emulator) -> Emulator {
+    common::load_with(emulator, &RANDOM)
+}
+
+fn random_bytes(emulator: &mut Emulator) -> [u8; 8] {
+    assert!(emulator.run_until(|emulator| emulator.pc() == 0x210, 100));
+    emulator.regs()[..8].try_into().unwrap()
+}
+
+#[test]
+fn same_seed_gives_the_same_numbers() {
+    let mut first = load(Emulator::with_seed(42));
+    let mut second = load(Emulator::with_seed(42));
+    let mut other = load(Emulator::with_seed(43));
+    let bytes = random_bytes(&mut first);
+    assert_eq!(random_bytes(&mut second), bytes);
+    assert_ne!(random_bytes(&mut other), bytes);
+}
+
+#[test]
+fn locked_seed_survives_a_reset() {
+    let mut emulator = load(Emulator::with_seed(7));
+    let bytes = random_bytes(&mut emulator);
+    emulator.reload_rom().unwrap();
+    assert_eq!(emulator.rng_seed(), 7);
+    assert_eq!(random_bytes(&mut emulator), bytes);
+}
+
+#[test]
+fn set_seed_restarts_the_sequence() {
+    let mut emulator = load(Emulator::new());
+    emulator.set_seed(1234);
+    let bytes = random_bytes(&mut emulator);
+
+    let mut seeded = load(Emulator::new());
+    seeded.set_seed(1234);
+    assert_eq!(random_bytes(&mut seeded), bytes);
+}
+
+#[test]
+fn restored_state_continues_the_sequence() {
+    let mut emulator = load(Emulator::with_seed(99));
+    emulator.step_instruction();
+    let state = emulator.snapshot();
+    let bytes = random_bytes(&mut emulator);
+
+    let mut restored = load(Emulator::new());
+    restored.restore(&state);
+    assert_eq!(random_bytes(&mut restored), bytes);
+}
+
+#[test]
+fn vip_routine_reproduces_its_sequence() {
+    let mut emulator = Emulator::new();
+    emulator.rng_mode = RngMode::Vip;
+    let mut emulator = load(emulator);
+    // R9 starts at 0, so the first eight numbers read the table from VIP_RNG_PAGE + 1.
+    let table = [0x12, 0x34, 0xF0, 0x00, 0xFF, 0x80, 0x01, 0x7F];
+    for (offset, value) in (1..).zip(table) {
+        emulator.poke(VIP_RNG_PAGE + offset, value).unwrap();
+    }
+    for _ in 0..8 {
+        emulator.step_instruction();
+    }
+    assert_eq!(
+        emulator.regs()[..8],
+        [0x1B, 0x76, 0x19, 0x25, 0xB6, 0xD1, 0x3B, 0x17]
+    );
+}
+
+#[test]
+fn vip_routine_does_not_depend_on_the_seed() {
+    let mut first = Emulator::with_seed(1);
+    first.rng_mode = RngMode::Vip;
+    let mut second = Emulator::with_seed(2);
+    second.rng_mode = RngMode::Vip;
+    let mut first = load(first);
+    let mut second = load(second);
+    assert_eq!(random_bytes(&mut first), random_bytes(&mut second));
+}
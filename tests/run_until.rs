@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use chip_8_emulator::emulator::Emulator;
+
+fn ibm_logo() -> Emulator {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("resources")
+        .join("roms")
+        .join("IBM Logo.ch8");
+    let mut emulator = Emulator::new();
+    emulator.load_font();
+    emulator
+        .load_rom(path.to_string_lossy().into_owned())
+        .unwrap();
+    emulator
+}
+
+fn lit_pixels(emulator: &Emulator) -> usize {
+    emulator
+        .display
+        .iter()
+        .flatten()
+        .filter(|pixel| **pixel == 1)
+        .count()
+}
+
+#[test]
+fn stops_when_pc_is_reached() {
+    let mut emulator = ibm_logo();
+
+    // 0x208 holds the first DXYN, nothing has been drawn before it runs.
+    assert!(emulator.run_until(|emulator| emulator.pc() == 0x208, 100));
+    assert_eq!(emulator.pc(), 0x208);
+    assert_eq!(lit_pixels(&emulator), 0);
+}
+
+#[test]
+fn stops_when_display_changes() {
+    let mut emulator = ibm_logo();
+
+    assert!(emulator.run_until(|emulator| lit_pixels(emulator) > 0, 100));
+    assert_eq!(emulator.pc(), 0x20A);
+}
+
+#[test]
+fn gives_up_after_max_cycles() {
+    let mut emulator = ibm_logo();
+
+    assert!(!emulator.run_until(|_| false, 5));
+    assert_eq!(emulator.pc(), 0x20A);
+}
+
+#[test]
+fn already_true_predicate_runs_nothing() {
+    let mut emulator = ibm_logo();
+
+    assert!(emulator.run_until(|_| true, 100));
+    assert_eq!(emulator.pc(), 0x200);
+}
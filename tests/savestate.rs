@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use chip_8_emulator::emulator::{Emulator, RunState, SaveState};
+
+fn ibm_logo() -> Emulator {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("resources")
+        .join("roms")
+        .join("IBM Logo.ch8");
+    let mut emulator = Emulator::new();
+    emulator.load_font();
+    emulator
+        .load_rom(path.to_string_lossy().into_owned())
+        .unwrap();
+    emulator
+}
+
+#[test]
+fn state_survives_a_round_trip_through_disk() {
+    let mut emulator = ibm_logo();
+    emulator.step_frame();
+    let state = emulator.snapshot();
+
+    let path = std::env::temp_dir().join(format!("chip8_savestate_{}.json", std::process::id()));
+    state.save(&path).unwrap();
+    let loaded = SaveState::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut restored = ibm_logo();
+    restored.restore(&loaded);
+    assert!(matches!(restored.state, RunState::Paused));
+    assert_eq!(restored.pc(), emulator.pc());
+    assert_eq!(restored.memory(), emulator.memory());
+    assert_eq!(restored.display, emulator.display);
+    assert_eq!(restored.frame_count(), emulator.frame_count());
+}
+
+#[test]
+fn restored_state_continues_identically() {
+    let mut emulator = ibm_logo();
+    let state = emulator.snapshot();
+    for _ in 0..3 {
+        emulator.step_frame();
+    }
+
+    let mut restored = ibm_logo();
+    restored.restore(&state);
+    for _ in 0..3 {
+        restored.step_frame();
+    }
+    assert_eq!(restored.display, emulator.display);
+    assert_eq!(restored.pc(), emulator.pc());
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use chip_8_emulator::emulator::{Emulator, RunState, HIRES_DISPLAY_SIZE};
+
+/// Loads a program assembled from `code` and runs it until it reaches the final
+/// self-jump, which every test program ends with.
+fn run(code: &[u16]) -> Emulator {
+    let mut emulator = common::load(code);
+    let end = 0x200 + 2 * (code.len() as u16 - 1);
+    assert!(emulator.run_until(|emulator| emulator.pc() == end, 1000));
+    emulator
+}
+
+#[test]
+fn hires_mode_switches_resolution() {
+    let emulator = run(&[0x00FF, 0x6070, 0x6130, 0xA050, 0xD015, 0x120A]);
+    assert!(emulator.display.hires);
+    assert_eq!(emulator.display.size(), HIRES_DISPLAY_SIZE);
+    // Font "0" drawn at (112, 48), past the low resolution area.
+    assert_eq!(emulator.display[112][48], 1);
+    assert_eq!(emulator.display[116][48], 0);
+
+    let emulator = run(&[0x00FF, 0x00FE, 0x1204]);
+    assert!(!emulator.display.hires);
+}
+
+#[test]
+fn big_font_and_16x16_sprites() {
+    // Big "1" starts with 0x18, the 16x16 sprite is the big font read two bytes a row.
+    let emulator = run(&[0x00FF, 0x6101, 0xF130, 0xD000, 0x1208]);
+    assert_eq!(emulator.display[3][0], 1);
+    assert_eq!(emulator.display[2][0], 0);
+    // Second byte of the first row is 0x78.
+    assert_eq!(emulator.display[9][0], 1);
+    assert_eq!(emulator.display[8][0], 0);
+    assert_eq!(emulator.regs()[15], 0);
+}
+
+#[test]
+fn scrolling_moves_the_display() {
+    // Draw the top row of the small "0" (0xF0) at (8, 0), then scroll.
+    let emulator = run(&[
+        0x00FF, 0x6008, 0x6100, 0xA050, 0xD011, 0x00C3, 0x00FB, 0x00FC, 0x00FC, 0x1212,
+    ]);
+    assert_eq!(emulator.display[4][3], 1);
+    assert_eq!(emulator.display[7][3], 1);
+    assert_eq!(emulator.display[8][3], 0);
+    assert_eq!(emulator.display[4][0], 0);
+}
+
+#[test]
+fn rpl_flags_round_trip() {
+    let emulator = run(&[
+        0x6011, 0x6122, 0x6233, 0xF275, 0x6000, 0x6100, 0x6200, 0xF185, 0x1210,
+    ]);
+    assert_eq!(&emulator.regs()[..3], &[0x11, 0x22, 0x00]);
+}
+
+#[test]
+fn exit_stops_execution() {
+    let mut emulator = common::load(&[0x00FD, 0x1202]);
+    assert!(!emulator.run_until(|emulator| emulator.pc() != 0x200, 10));
+    assert!(matches!(emulator.state, RunState::Paused));
+}
+
+#[test]
+fn exit_ends_the_frame() {
+    let mut emulator = common::load(&[0x00FD]);
+    emulator.step_frame();
+    assert_eq!(emulator.history.len(), 1);
+    assert_eq!(emulator.pc(), 0x200);
+    assert_eq!(
+        emulator.break_reason.as_deref(),
+        Some("Program exited (00FD)")
+    );
+}
+
+#[test]
+fn exit_at_the_end_of_memory_stays_put() {
+    let mut emulator = common::load(&[0x1200]);
+    emulator.poke(0xFFFE, 0x00).unwrap();
+    emulator.poke(0xFFFF, 0xFD).unwrap();
+    emulator.set_pc(0xFFFE).unwrap();
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0xFFFE);
+    assert!(matches!(emulator.state, RunState::Paused));
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use chip_8_emulator::{emulator::Emulator, script::Script};
+
+/// V0 counts up, I = 0x300, FX55 stores V0 there and loops.
+const COUNTER: [u16; 4] = [0xA300, 0x7001, 0xF055, 0x1202];
+
+fn load(script: &str) -> Emulator {
+    let mut emulator = common::load(&COUNTER);
+    emulator.cpf = 3;
+    emulator.set_hooks(Some(Box::new(Script::new(script).unwrap())));
+    emulator
+}
+
+#[test]
+fn script_freezes_a_register() {
+    let mut emulator = load("fn on_instruction(pc) { if pc == 0x202 { set_reg(0, 9); } }");
+    for _ in 0..10 {
+        emulator.step_frame();
+    }
+    assert_eq!(emulator.memory()[0x300], 10);
+}
+
+#[test]
+fn script_sees_memory_writes_and_frames() {
+    let mut emulator = load(
+        "fn on_memory_write(address, value) { poke(address + 1, value * 2); }
+         fn on_frame() { if frame() == 3 { press(5); } }",
+    );
+    for _ in 0..5 {
+        emulator.step_frame();
+    }
+    let stored = emulator.memory()[0x300];
+    assert_eq!(emulator.memory()[0x301], stored * 2);
+    assert!(emulator.is_key_down(5));
+}
+
+#[test]
+fn script_errors_pause_emulation() {
+    let mut emulator = load("fn on_frame() { throw \"game over\"; }");
+    emulator.resume();
+    emulator.step_frame();
+    assert!(emulator
+        .break_reason
+        .as_deref()
+        .is_some_and(|reason| reason.contains("game over")));
+    assert!(Script::new("fn on_frame( {").is_err());
+}
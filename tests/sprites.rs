@@ -0,0 +1,56 @@
+mod common;
+
+use chip_8_emulator::{
+    emulator::{big_font_address, font_address, Emulator, BIG_FONT_HEIGHT, FONT_HEIGHT},
+    sprites::{sprite_rows, sprite_size},
+};
+
+fn font_loaded() -> Emulator {
+    let mut emulator = Emulator::new();
+    emulator.load_font();
+    emulator
+}
+
+#[test]
+fn font_glyphs_read_as_sprites() {
+    let emulator = font_loaded();
+    let zero = sprite_rows(emulator.memory(), font_address(0), FONT_HEIGHT, false);
+    assert_eq!(zero, [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+    let one = sprite_rows(
+        emulator.memory(),
+        big_font_address(1),
+        BIG_FONT_HEIGHT,
+        false,
+    );
+    assert_eq!(one.len(), BIG_FONT_HEIGHT);
+    assert!(one.iter().any(|row| *row != 0));
+}
+
+#[test]
+fn font_address_matches_fx29() {
+    // V0 = 0xA, I = glyph of V0.
+    let code: [u16; 2] = [0x600A, 0xF029];
+    let mut emulator = font_loaded();
+    emulator
+        .load_rom_data("font.ch8", &common::rom(&code))
+        .unwrap();
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert_eq!(emulator.reg_i(), font_address(0xA));
+    // Only the low nibble picks the glyph.
+    assert_eq!(font_address(0x1A), font_address(0xA));
+}
+
+#[test]
+fn wide_rows_take_two_bytes() {
+    let memory = [0x12, 0x34, 0x56, 0x78];
+    assert_eq!(sprite_rows(&memory, 0, 2, true), [0x1234, 0x5678]);
+    assert_eq!(sprite_size(2, true), 4);
+    assert_eq!(sprite_size(2, false), 2);
+}
+
+#[test]
+fn rows_past_memory_are_blank() {
+    let memory = [0xFF, 0xFF];
+    assert_eq!(sprite_rows(&memory, 1, 3, false), [0xFF, 0, 0]);
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use std::time::Duration;
+
+use chip_8_emulator::emulator::Emulator;
+
+fn emulator(code: &[u16]) -> Emulator {
+    let mut emulator = common::load(code);
+    emulator.pause();
+    emulator
+}
+
+#[test]
+fn records_what_each_step_changed() {
+    let mut emulator = emulator(&[
+        0x6005, // V0 = 5
+        0xA300, // I = 0x300
+        0xF055, // mem[0x300] = V0
+        0x2210, // call 0x210
+        0x1208, // loop
+        0x0000, 0x0000, 0x0000, //
+        0xD011, // draw mem[0x300] at (V0, V1)
+        0x00EE, // return
+    ]);
+    assert!(emulator.last_step().is_none());
+
+    emulator.step_instruction();
+    let step = emulator.last_step().unwrap();
+    assert_eq!(step.pc, 0x200);
+    assert!(step.regs[0]);
+    assert_eq!(step.to_string(), "V0 changed");
+
+    emulator.step_instruction();
+    assert!(emulator.last_step().unwrap().reg_i);
+
+    emulator.step_instruction();
+    let step = emulator.last_step().unwrap();
+    assert_eq!(step.memory, [0x300]);
+    assert!(step.memory_changed(0x300));
+    assert!(!step.memory_changed(0x301));
+
+    emulator.step_instruction();
+    assert_eq!(emulator.last_step().unwrap().pushed, [0x208]);
+
+    emulator.step_instruction();
+    let step = emulator.last_step().unwrap();
+    assert_eq!(step.rows, [0]);
+    assert!(step.row_changed(0));
+    assert_eq!(step.to_string(), "drew rows 0");
+
+    emulator.step_instruction();
+    let step = emulator.last_step().unwrap();
+    assert_eq!(step.popped, [0x208]);
+    assert_eq!(step.to_string(), "popped 0x208");
+
+    // Stepping back undoes the return, which pushes the address again.
+    assert!(emulator.step_back());
+    assert_eq!(emulator.last_step().unwrap().pushed, [0x208]);
+}
+
+#[test]
+fn summary_joins_ranges() {
+    let mut emulator = emulator(&[
+        0x6001, // V0 = 1
+        0x6102, // V1 = 2
+        0xA300, // I = 0x300
+        0xF155, // mem[0x300..=0x301] = V0, V1
+        0x1208, // loop
+    ]);
+    emulator.cpf = 4;
+    emulator.step_frame();
+    assert_eq!(
+        emulator.last_step().unwrap().to_string(),
+        "V0, V1, I changed; wrote 0x300-0x301"
+    );
+}
+
+#[test]
+fn running_on_clears_the_last_step() {
+    let mut emulator = emulator(&[0x7001, 0x1200]);
+    emulator.step_instruction();
+    assert!(emulator.last_step().is_some());
+    emulator.resume();
+    emulator.run_for(Duration::from_millis(20));
+    assert!(emulator.last_step().is_none());
+}
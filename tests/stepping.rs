@@ -0,0 +1,127 @@
+mod common;
+
+use chip_8_emulator::emulator::{Emulator, RunState};
+
+fn load(code: &[u16]) -> Emulator {
+    let mut emulator = common::load(code);
+    emulator.pause();
+    emulator
+}
+
+/// Counts up in V0 a few times, then draws the font digit in V0 twice.
+const DRAWS: [u16; 8] = [
+    0x7001, 0x7001, 0x7001, 0xF029, 0xD125, 0x7001, 0xD125, 0x1200,
+];
+
+#[test]
+fn step_instruction_runs_one_instruction() {
+    let mut emulator = load(&DRAWS);
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0x202);
+    assert_eq!(emulator.regs()[0], 1);
+    assert_eq!(emulator.frame_count(), 0);
+}
+
+#[test]
+fn step_frame_runs_a_frame_of_instructions() {
+    let mut emulator = load(&[0x7001, 0x1200]);
+    emulator.cpf = 10;
+    emulator.step_frame();
+    assert_eq!(emulator.regs()[0], 5);
+    assert_eq!(emulator.frame_count(), 1);
+}
+
+#[test]
+fn run_to_draw_stops_after_each_dxyn() {
+    let mut emulator = load(&DRAWS);
+    emulator.run_to_draw();
+    assert!(matches!(emulator.state, RunState::Running));
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Paused));
+    assert_eq!(emulator.pc(), 0x20A);
+    assert_eq!(emulator.regs()[0], 3);
+
+    emulator.run_to_draw();
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Paused));
+    assert_eq!(emulator.pc(), 0x20E);
+    assert_eq!(emulator.regs()[0], 4);
+}
+
+/// Calls A, which calls B, then loops.
+const CALLS: [u16; 8] = [
+    0x2206, // 0x200: call A
+    0x6101, // 0x202: V1 = 1
+    0x1204, // 0x204: loop
+    0x220C, // 0x206: A: call B
+    0x7201, // 0x208: V2 += 1
+    0x00EE, // 0x20A: return
+    0x7301, // 0x20C: B: V3 += 1
+    0x00EE, // 0x20E: return
+];
+
+#[test]
+fn step_over_runs_nested_calls_until_they_return() {
+    let mut emulator = load(&CALLS);
+    emulator.cpf = 100;
+    emulator.step_over();
+    assert!(matches!(emulator.state, RunState::Running));
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Paused));
+    assert_eq!(emulator.pc(), 0x202);
+    assert!(emulator.stack().is_empty());
+    assert_eq!(emulator.regs()[2..4], [1, 1]);
+
+    // Anything but a call is a single step.
+    emulator.step_over();
+    assert!(matches!(emulator.state, RunState::Paused));
+    assert_eq!(emulator.pc(), 0x204);
+    assert_eq!(emulator.regs()[1], 1);
+}
+
+#[test]
+fn step_over_inside_a_subroutine_stops_at_the_same_depth() {
+    let mut emulator = load(&CALLS);
+    emulator.cpf = 100;
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0x206);
+    emulator.step_over();
+    emulator.step_frame();
+    assert_eq!(emulator.pc(), 0x208);
+    assert_eq!(emulator.stack().len(), 1);
+    assert_eq!(emulator.regs()[2..4], [0, 1]);
+}
+
+#[test]
+fn step_out_returns_to_the_caller() {
+    let mut emulator = load(&CALLS);
+    emulator.cpf = 100;
+    emulator.step_instruction();
+    emulator.step_instruction();
+    assert_eq!(emulator.pc(), 0x20C);
+    assert_eq!(emulator.stack().len(), 2);
+    emulator.step_out();
+    emulator.step_frame();
+    assert!(matches!(emulator.state, RunState::Paused));
+    assert_eq!(emulator.pc(), 0x208);
+    assert_eq!(emulator.stack().len(), 1);
+    assert_eq!(emulator.regs()[2..4], [0, 1]);
+}
+
+#[test]
+fn step_out_at_the_top_level_does_nothing() {
+    let mut emulator = load(&CALLS);
+    emulator.step_out();
+    assert!(matches!(emulator.state, RunState::Paused));
+    assert_eq!(emulator.pc(), 0x200);
+}
+
+#[test]
+fn step_over_a_call_at_the_end_of_memory() {
+    let mut emulator = load(&CALLS);
+    emulator.poke(0xFFFE, 0x22).unwrap();
+    emulator.poke(0xFFFF, 0x0C).unwrap();
+    emulator.set_pc(0xFFFE).unwrap();
+    emulator.step_over();
+    assert!(matches!(emulator.state, RunState::Running));
+}
@@ -0,0 +1,74 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use chip_8_emulator::symbols::{self, SymbolFormat};
+
+#[test]
+fn parses_both_orders() {
+    let text = "\
+# Octo style
+main 0x200
+:const draw_player 0x2A4
+score = $300
+
+# Plain text
+2B0 add
+0x2C0 loop # comment
+";
+    let labels = symbols::parse(text).unwrap();
+    let expected = BTreeMap::from([
+        (0x200, String::from("main")),
+        (0x2A4, String::from("draw_player")),
+        (0x2B0, String::from("add")),
+        (0x2C0, String::from("loop")),
+        (0x300, String::from("score")),
+    ]);
+    assert_eq!(labels, expected);
+}
+
+#[test]
+fn reports_bad_lines() {
+    let err = symbols::parse("main 0x200\nmain\n").unwrap_err();
+    assert_eq!(err.line, 2);
+    let err = symbols::parse("main loop\n").unwrap_err();
+    assert_eq!(err.line, 1);
+    assert!(err.message.contains("no address"));
+}
+
+#[test]
+fn written_symbols_read_back() {
+    let labels = BTreeMap::from([
+        (0x200, String::from("main")),
+        (0x2A4, String::from("draw player")),
+        (0xABC, String::from("add")),
+    ]);
+    for format in [SymbolFormat::Octo, SymbolFormat::Text] {
+        let text = symbols::write(&labels, format);
+        let read = symbols::parse(&text).unwrap();
+        assert_eq!(read[&0x2A4], "draw_player");
+        assert_eq!(read[&0xABC], "add");
+        assert_eq!(read.len(), 3);
+    }
+    assert_eq!(
+        symbols::write(&labels, SymbolFormat::Octo).lines().next(),
+        Some("main 0x200")
+    );
+    assert_eq!(
+        SymbolFormat::for_path(Path::new("game.SYM")),
+        SymbolFormat::Octo
+    );
+    assert_eq!(
+        SymbolFormat::for_path(Path::new("game.txt")),
+        SymbolFormat::Text
+    );
+}
+
+#[test]
+fn finds_the_file_next_to_a_rom() {
+    let dir = std::env::temp_dir().join("chip8_symbols_test");
+    fs::create_dir_all(&dir).unwrap();
+    let rom = dir.join("game.ch8");
+    assert_eq!(symbols::sidecar(&rom), None);
+    fs::write(dir.join("game.sym"), "main 0x200\n").unwrap();
+    assert_eq!(symbols::sidecar(&rom), Some(dir.join("game.sym")));
+    fs::remove_dir_all(&dir).unwrap();
+}
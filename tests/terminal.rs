@@ -0,0 +1,29 @@
+use chip_8_emulator::{emulator::Display, terminal};
+
+#[test]
+fn two_pixel_rows_per_line() {
+    let mut display = Display::new(false);
+    display[0][0] = 1;
+    display[1][1] = 1;
+    display[2][0] = 1;
+    display[2][1] = 2;
+    display[3][31] = 1;
+    let lines = terminal::half_blocks(&display);
+    assert_eq!(lines.len(), 16);
+    assert!(lines.iter().all(|line| line.chars().count() == 64));
+    assert!(lines[0].starts_with("▀▄█ "));
+    assert!(lines[15].starts_with("   ▄"));
+
+    let lines = terminal::half_blocks(&Display::new(true));
+    assert_eq!(lines.len(), 32);
+    assert_eq!(lines[0], " ".repeat(128));
+}
+
+#[test]
+fn keypad_follows_the_usual_layout() {
+    assert_eq!(terminal::keypad_key('1'), Some(0x1));
+    assert_eq!(terminal::keypad_key('4'), Some(0xC));
+    assert_eq!(terminal::keypad_key('x'), Some(0x0));
+    assert_eq!(terminal::keypad_key('V'), Some(0xF));
+    assert_eq!(terminal::keypad_key('5'), None);
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use std::time::Duration;
+
+use chip_8_emulator::timing::{Clock, Tick};
+
+/// Runs the clock for one emulated second in 100 ms slices, counting the ticks.
+fn count_ticks(clock: &mut Clock) -> (u32, u32) {
+    let (mut instructions, mut timers) = (0, 0);
+    for _ in 0..10 {
+        clock.advance(Duration::from_millis(100));
+        while let Some(tick) = clock.next_tick() {
+            match tick {
+                Tick::Instruction => instructions += 1,
+                Tick::Timer => timers += 1,
+            }
+        }
+    }
+    (instructions, timers)
+}
+
+#[test]
+fn clock_runs_cpu_and_timers_at_their_rates() {
+    let mut clock = Clock::default();
+    clock.cpu_hz = 1000.0;
+    let (instructions, timers) = count_ticks(&mut clock);
+    assert!((999..=1000).contains(&instructions), "{instructions}");
+    assert!((59..=60).contains(&timers), "{timers}");
+}
+
+#[test]
+fn speed_scales_both_clocks() {
+    let mut clock = Clock::default();
+    clock.cpu_hz = 600.0;
+    clock.speed = 0.5;
+    let (instructions, timers) = count_ticks(&mut clock);
+    assert!((299..=300).contains(&instructions), "{instructions}");
+    assert!((29..=30).contains(&timers), "{timers}");
+}
+
+#[test]
+fn speed_override_wins_until_cleared() {
+    let mut clock = Clock::default();
+    clock.cpu_hz = 600.0;
+    clock.speed = 0.5;
+    clock.speed_override = Some(2.0);
+    assert_eq!(clock.effective_speed(), 2.0);
+    let (instructions, timers) = count_ticks(&mut clock);
+    assert!((1199..=1200).contains(&instructions), "{instructions}");
+    assert!((119..=120).contains(&timers), "{timers}");
+
+    clock.speed_override = None;
+    assert_eq!(clock.effective_speed(), 0.5);
+}
+
+#[test]
+fn timer_ticks_are_spread_between_instructions() {
+    let mut clock = Clock::default();
+    clock.cpu_hz = 600.0;
+    clock.advance(Duration::from_millis(100));
+    let ticks: Vec<Tick> = std::iter::from_fn(|| clock.next_tick()).collect();
+    let timers: Vec<usize> = ticks
+        .iter()
+        .enumerate()
+        .filter(|(_, tick)| **tick == Tick::Timer)
+        .map(|(i, _)| i)
+        .collect();
+    // Around 10 instructions between two timer ticks, not all the ticks in a burst.
+    for pair in timers.windows(2) {
+        assert!((10..=12).contains(&(pair[1] - pair[0])), "{timers:?}");
+    }
+}
+
+#[test]
+fn long_stalls_are_not_caught_up() {
+    let mut clock = Clock::default();
+    clock.advance(Duration::from_secs(10));
+    let ticks = std::iter::from_fn(|| clock.next_tick()).count();
+    assert!(ticks < 200, "{ticks}");
+}
+
+#[test]
+fn run_for_ticks_timers_independently_of_cpu_speed() {
+    // Set the delay timer to 60, then spin.
+    let code = [0x603C, 0xF015, 0x1204];
+    for cpu_hz in [200.0, 2000.0] {
+        let mut emulator = common::load(&code);
+        emulator.clock.cpu_hz = cpu_hz;
+        emulator.run_for(Duration::from_millis(50));
+        assert_eq!(emulator.pc(), 0x204);
+        for _ in 0..4 {
+            emulator.run_for(Duration::from_millis(250));
+        }
+        assert!(emulator.delay_timer() <= 1, "{}", emulator.delay_timer());
+    }
+}
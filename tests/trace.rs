@@ -0,0 +1,72 @@
+mod common;
+
+use chip_8_emulator::trace::{Register, RegisterChange};
+
+#[test]
+fn nothing_is_recorded_until_enabled() {
+    let mut emulator = common::load(&[0x6001, 0x1200]);
+    emulator.step_instruction();
+    assert!(emulator.trace.is_empty());
+}
+
+#[test]
+fn records_instructions_and_register_changes() {
+    let mut emulator = common::load(&[0x6005, 0xA123, 0x6005, 0x1200]);
+    emulator.trace.set_enabled(true);
+    for _ in 0..3 {
+        emulator.step_instruction();
+    }
+    let entries: Vec<_> = emulator.trace.entries().iter().collect();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].instruction.address, 0x200);
+    assert_eq!(entries[0].instruction.mnemonic, "LD V0, 0x05");
+    assert_eq!(
+        entries[0].changes,
+        [RegisterChange {
+            register: Register::V(0),
+            old: 0,
+            new: 5
+        }]
+    );
+    assert_eq!(entries[1].changes_text(), "I: 0000 -> 0123");
+    // Loading the same value changes nothing.
+    assert!(entries[2].changes.is_empty());
+}
+
+#[test]
+fn capacity_drops_the_oldest_instructions() {
+    let mut emulator = common::load(&[0x7001, 0x1200]);
+    emulator.trace.set_enabled(true);
+    emulator.trace.set_capacity(4);
+    for _ in 0..10 {
+        emulator.step_instruction();
+    }
+    assert_eq!(emulator.trace.len(), 4);
+    assert_eq!(emulator.trace.entries()[0].instruction.address, 0x200);
+    assert_eq!(emulator.regs()[0], 5);
+}
+
+#[test]
+fn exports_text_and_csv() {
+    let mut emulator = common::load(&[0x6A02, 0x3A02]);
+    emulator.trace.set_enabled(true);
+    emulator.step_instruction();
+    emulator.step_instruction();
+
+    let text = emulator.trace.to_text();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("6A02  LD VA, 0x02"));
+    assert!(lines[0].ends_with("VA: 00 -> 02"));
+
+    let csv = emulator.trace.to_csv();
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows[0], "frame,pc,opcode,operand,mnemonic,changes");
+    assert_eq!(rows[2], "0,0202,3A02,,\"SE VA, 0x02\",\"\"");
+
+    let path = std::env::temp_dir().join(format!("chip8_trace_{}.csv", std::process::id()));
+    emulator.trace.export(&path).unwrap();
+    let exported = std::fs::read_to_string(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(exported.unwrap(), csv);
+}
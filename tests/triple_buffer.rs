@@ -0,0 +1,37 @@
+use std::thread;
+
+use chip_8_emulator::triple_buffer::triple_buffer;
+
+#[test]
+fn reader_sees_latest_write() {
+    let (mut writer, mut reader) = triple_buffer(0u32);
+    assert_eq!(*reader.read(), 0);
+
+    writer.write(1);
+    writer.write(2);
+    assert!(reader.has_new());
+    assert_eq!(*reader.read(), 2);
+    assert!(!reader.has_new());
+    assert_eq!(*reader.read(), 2);
+}
+
+#[test]
+fn frames_are_never_torn() {
+    const FRAMES: u32 = 20_000;
+    let (mut writer, mut reader) = triple_buffer([0u32; 64]);
+
+    let producer = thread::spawn(move || {
+        for frame in 1..=FRAMES {
+            writer.write([frame; 64]);
+        }
+    });
+
+    let mut last = 0;
+    while last < FRAMES {
+        let frame = reader.read();
+        assert!(frame.iter().all(|value| *value == frame[0]), "torn frame");
+        assert!(frame[0] >= last, "frames went backwards");
+        last = frame[0];
+    }
+    producer.join().unwrap();
+}
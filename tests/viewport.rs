@@ -0,0 +1,69 @@
+use chip_8_emulator::{
+    megachip::MEGA_DISPLAY_SIZE,
+    renderer::{Rotation, Scaling, Viewport},
+};
+
+#[test]
+fn fit_letterboxes_to_two_by_one() {
+    let viewport = Viewport::new((1000, 1000), Rotation::None, Scaling::Fit);
+    assert_eq!(
+        viewport,
+        Viewport {
+            x: 0.0,
+            y: 250.0,
+            width: 1000.0,
+            height: 500.0,
+        }
+    );
+
+    let viewport = Viewport::new((1000, 300), Rotation::None, Scaling::Fit);
+    assert_eq!(
+        (viewport.x, viewport.width, viewport.height),
+        (200.0, 600.0, 300.0)
+    );
+}
+
+#[test]
+fn rotated_display_is_taller_than_wide() {
+    let viewport = Viewport::new((1000, 1000), Rotation::Cw90, Scaling::Fit);
+    assert_eq!(
+        (viewport.x, viewport.width, viewport.height),
+        (250.0, 500.0, 1000.0)
+    );
+}
+
+#[test]
+fn integer_scaling_uses_whole_multiples() {
+    let viewport = Viewport::new((1000, 700), Rotation::None, Scaling::Integer);
+    assert_eq!((viewport.width, viewport.height), (896.0, 448.0));
+    assert_eq!((viewport.x, viewport.y), (52.0, 126.0));
+
+    // Smaller than the display, fit instead of disappearing.
+    let viewport = Viewport::new((64, 64), Rotation::None, Scaling::Integer);
+    assert_eq!((viewport.width, viewport.height), (64.0, 32.0));
+}
+
+#[test]
+fn stretch_fills_the_surface() {
+    let viewport = Viewport::new((1280, 720), Rotation::Cw90, Scaling::Stretch);
+    assert_eq!(viewport, Viewport::full((1280, 720)));
+}
+
+#[test]
+fn megachip_screen_is_four_by_three() {
+    let viewport = Viewport::for_display(
+        (800, 600),
+        MEGA_DISPLAY_SIZE,
+        Rotation::None,
+        Scaling::Integer,
+    );
+    assert_eq!(
+        viewport,
+        Viewport {
+            x: 16.0,
+            y: 12.0,
+            width: 768.0,
+            height: 576.0,
+        }
+    );
+}
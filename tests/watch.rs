@@ -0,0 +1,72 @@
+mod common;
+
+use chip_8_emulator::{
+    emulator::{Emulator, RunState},
+    watch::{Expression, Watch},
+};
+
+/// V1 = 3, V2 = 7, I = 0x300, mem[0x300..0x302] = V0, V1, then V3 counts up forever.
+const SETUP: [u16; 7] = [0x6103, 0x6207, 0xA300, 0xF155, 0xA300, 0x7301, 0x120A];
+
+fn emulator() -> Emulator {
+    let mut emulator = common::load(&SETUP);
+    for _ in 0..5 {
+        emulator.step_instruction();
+    }
+    emulator
+}
+
+fn eval(emulator: &Emulator, text: &str) -> Option<i64> {
+    Expression::parse(text).unwrap().evaluate(emulator)
+}
+
+#[test]
+fn evaluates_against_the_machine_state() {
+    let emulator = emulator();
+    assert_eq!(eval(&emulator, "V1"), Some(3));
+    assert_eq!(eval(&emulator, "v1*10+V2"), Some(37));
+    assert_eq!(eval(&emulator, "I"), Some(0x300));
+    assert_eq!(eval(&emulator, "[I+1]"), Some(3));
+    assert_eq!(eval(&emulator, "mem[0x301]"), Some(3));
+    assert_eq!(eval(&emulator, "PC"), Some(0x20A));
+    assert_eq!(eval(&emulator, "(V1 + 1) * 2 - -1"), Some(9));
+    assert_eq!(eval(&emulator, "1 + 2 << 1 == 6 & V2 > V1"), Some(1));
+    assert_eq!(eval(&emulator, "V2 % V1 | 8"), Some(9));
+}
+
+#[test]
+fn evaluation_errors_give_no_value() {
+    let emulator = emulator();
+    assert_eq!(eval(&emulator, "V1 / V0"), None);
+    assert_eq!(eval(&emulator, "[0x10000]"), None);
+    assert_eq!(eval(&emulator, "[-1]"), None);
+}
+
+#[test]
+fn parse_errors_point_at_the_problem() {
+    let err = Expression::parse("V1 +").unwrap_err();
+    assert_eq!(err.column, 5);
+    let err = Expression::parse("V1 + VG").unwrap_err();
+    assert_eq!((err.column, err.message.as_str()), (6, "unknown name `VG`"));
+    assert_eq!(Expression::parse("[I").unwrap_err().column, 3);
+    assert_eq!(Expression::parse("V1 V2").unwrap_err().column, 4);
+    assert!(Expression::parse("0xZZ").is_err());
+}
+
+#[test]
+fn breaks_when_a_watched_expression_changes() {
+    let mut emulator = emulator();
+    let mut watch = Watch::new(Expression::parse("V3 / 2").unwrap());
+    watch.break_on_change = true;
+    emulator.watches.push(watch);
+    emulator
+        .watches
+        .push(Watch::new(Expression::parse("V3").unwrap()));
+    emulator.step_frame();
+    assert_eq!(emulator.state, RunState::Paused);
+    assert_eq!(emulator.regs()[3], 2);
+    assert!(emulator
+        .break_reason
+        .as_deref()
+        .is_some_and(|reason| reason.starts_with("V3 / 2 changed 0 -> 1")));
+}
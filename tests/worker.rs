@@ -0,0 +1,106 @@
+mod common;
+
+use std::{path::PathBuf, thread, time::Duration};
+
+use chip_8_emulator::{
+    emulator::{Emulator, RunState},
+    worker::{EmulationThread, EmulatorCommand},
+};
+
+/// Draws the font digit 0 in the top left corner, then loops.
+fn draw_rom() -> Vec<u8> {
+    common::rom(&[0xF029, 0xD125, 0x1204])
+}
+
+fn lit_pixels(emulator: &mut EmulationThread) -> usize {
+    emulator
+        .latest_frame()
+        .iter()
+        .flatten()
+        .filter(|pixel| **pixel != 0)
+        .count()
+}
+
+#[test]
+fn inline_mode_applies_commands_as_they_are_sent() {
+    let emulator = EmulationThread::inline(Emulator::new());
+    emulator.send(EmulatorCommand::LoadRom {
+        name: PathBuf::from("draw.ch8"),
+        data: draw_rom(),
+    });
+    let emulator = emulator.lock();
+    assert!(matches!(emulator.state, RunState::Running));
+    assert_eq!(
+        emulator.rom.as_ref().unwrap().path,
+        PathBuf::from("draw.ch8")
+    );
+    assert_eq!(emulator.pc(), 0x200);
+}
+
+#[test]
+fn inline_mode_runs_when_a_frame_is_read() {
+    let mut emulator = EmulationThread::inline(Emulator::new());
+    emulator.send(EmulatorCommand::LoadRom {
+        name: PathBuf::from("draw.ch8"),
+        data: draw_rom(),
+    });
+    assert_eq!(lit_pixels(&mut emulator), 0);
+    thread::sleep(Duration::from_millis(50));
+    assert!(lit_pixels(&mut emulator) > 0);
+}
+
+#[test]
+fn thread_applies_commands_before_sync_returns() {
+    let emulator = EmulationThread::spawn(Emulator::new());
+    emulator.send(EmulatorCommand::LoadRom {
+        name: PathBuf::from("draw.ch8"),
+        data: draw_rom(),
+    });
+    emulator.sync();
+    assert!(emulator.lock().rom.is_some());
+}
+
+#[test]
+fn rom_loaded_from_memory_reloads_without_a_file() {
+    let mut emulator = Emulator::new();
+    emulator.load_font();
+    emulator.load_rom_data("draw.ch8", &draw_rom()).unwrap();
+    emulator.step_instruction();
+    emulator.step_instruction();
+    emulator.reload_rom().unwrap();
+    assert_eq!(emulator.pc(), 0x200);
+    assert_eq!(emulator.memory()[0x200..0x206], draw_rom()[..]);
+    assert!(emulator.display.iter().flatten().all(|pixel| *pixel == 0));
+}
+
+#[test]
+fn handle_controls_the_thread_and_reads_its_status() {
+    let emulator = EmulationThread::spawn(Emulator::new());
+    assert!(EmulationThread::inline(Emulator::new()).handle().is_none());
+    let handle = emulator.handle().unwrap();
+    assert_eq!(handle.status().state, RunState::NoROM);
+
+    let remote = handle.clone();
+    thread::spawn(move || {
+        remote.send(EmulatorCommand::LoadRom {
+            name: PathBuf::from("draw.ch8"),
+            data: draw_rom(),
+        });
+        remote.send(EmulatorCommand::Pause);
+        remote.send(EmulatorCommand::SetSpeed(4.0));
+        remote.sync();
+    })
+    .join()
+    .unwrap();
+
+    let status = emulator.status();
+    assert_eq!(status.state, RunState::Paused);
+    assert_eq!(status.rom, Some(PathBuf::from("draw.ch8")));
+    assert_eq!(status.speed, 4.0);
+    assert!(!status.halted);
+
+    handle.send(EmulatorCommand::Reset);
+    handle.send(EmulatorCommand::Resume);
+    handle.sync();
+    assert_eq!(handle.status().state, RunState::Running);
+}
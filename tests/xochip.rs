@@ -0,0 +1,76 @@
+mod common;
+
+use chip_8_emulator::emulator::{Emulator, DEFAULT_PITCH};
+
+/// Loads a program assembled from `code` and runs it until it reaches the final
+/// self-jump, which every test program ends with.
+fn run(code: &[u16]) -> Emulator {
+    let mut emulator = common::load(code);
+    let end = 0x200 + 2 * (code.len() as u16 - 1);
+    assert!(emulator.run_until(|emulator| emulator.pc() == end, 1000));
+    emulator
+}
+
+#[test]
+fn sprites_draw_on_the_selected_planes() {
+    // Draw the top row of "0" (0xF0) on plane 2, then again on both planes. The second
+    // plane's data follows the first, so it reads the top row of "0"'s next row (0x90).
+    let emulator = run(&[
+        0xF201, 0x6000, 0x6100, 0xA050, 0xD011, 0xF301, 0xD011, 0x120E,
+    ]);
+    // Both planes toggled: plane 2 was set and is cleared again, plane 1 is set.
+    assert_eq!(emulator.display[0][0], 1);
+    // Only plane 1 toggled on top of the first plane 2 pixel.
+    assert_eq!(emulator.display[1][0], 3);
+    assert_eq!(emulator.display[4][0], 0);
+    assert_eq!(emulator.regs()[15], 1);
+}
+
+#[test]
+fn long_load_reaches_extended_memory() {
+    let emulator = run(&[0xF000, 0x1234, 0x1204]);
+    assert_eq!(emulator.reg_i(), 0x1234);
+    assert_eq!(emulator.memory().len(), 0x10000);
+}
+
+#[test]
+fn skips_step_over_long_load() {
+    let emulator = run(&[0x6005, 0x3005, 0xF000, 0xABCD, 0x6107, 0x120A]);
+    assert_eq!(emulator.reg_i(), 0);
+    assert_eq!(emulator.regs()[1], 7);
+}
+
+#[test]
+fn register_ranges_leave_i_unchanged() {
+    let emulator = run(&[
+        0x6011, 0x6122, 0x6233, 0xA300, 0x5022, 0x6000, 0x6100, 0x6200, 0x5203, 0x1212,
+    ]);
+    assert_eq!(&emulator.memory()[0x300..0x303], &[0x11, 0x22, 0x33]);
+    // Loaded back in reverse order.
+    assert_eq!(&emulator.regs()[..3], &[0x33, 0x22, 0x11]);
+    assert_eq!(emulator.reg_i(), 0x300);
+}
+
+#[test]
+fn audio_pattern_and_pitch() {
+    let code = [0xA200, 0xF002, 0x6050, 0xF03A, 0x1208];
+    let emulator = common::load(&code);
+    assert_eq!(emulator.audio_pattern(), None);
+
+    let emulator = run(&code);
+    let pattern = emulator.audio_pattern().unwrap();
+    // The pattern is the 16 bytes at I, here the program itself.
+    assert_eq!(&pattern.bits, &emulator.memory()[0x200..0x210]);
+    assert_eq!(pattern.pitch, 0x50);
+    assert!(pattern.bit(0));
+    assert!(!pattern.bit(1));
+    assert!(pattern.rate() > 4000.0);
+    assert_ne!(pattern.pitch, DEFAULT_PITCH);
+}
+
+#[test]
+fn scroll_up_moves_the_display() {
+    let emulator = run(&[0x6000, 0x6105, 0xA050, 0xD011, 0x00D2, 0x120A]);
+    assert_eq!(emulator.display[0][3], 1);
+    assert_eq!(emulator.display[0][5], 0);
+}